@@ -0,0 +1,38 @@
+//! Rebuilding a view with many children when only its own attributes
+//! change, which shares the children with the last build instead of
+//! copying them.
+//!
+//! Run with `cargo bench --bench build`.
+
+#![feature(test)]
+
+extern crate test;
+
+use moxie::runtime::Runtime;
+use moxie_native::dom::{Node, View};
+use moxie_native::mox_impl::{attr, elt};
+use test::Bencher;
+
+const CHILDREN: usize = 1000;
+
+#[bench]
+fn parent_attribute_change(bencher: &mut Bencher) {
+    let mut runtime = Runtime::new();
+    let mut frame = 0;
+    let mut last: Option<Node<View>> = None;
+    bencher.iter(|| {
+        frame += 1;
+        let title = frame.to_string();
+        let view = runtime.run_once(|| {
+            let mut view = elt::view().set_attr(attr::title(), title);
+            for index in 0..CHILDREN {
+                view = view.add_child(elt::span().add_child(index.to_string()).build());
+            }
+            view.build()
+        });
+        if let Some(last) = last.replace(view.clone()) {
+            assert_eq!(last.children().as_ptr(), view.children().as_ptr());
+        }
+        view
+    });
+}
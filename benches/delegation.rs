@@ -0,0 +1,106 @@
+//! Rebuilding a list of buttons which each have their own click
+//! handler, against the same list with a single `on_child_click`
+//! handler on the parent, along with a test comparing how many
+//! allocations a rebuild makes either way.
+//!
+//! Run with `cargo bench --bench delegation`.
+
+#![feature(test)]
+
+extern crate test;
+
+use moxie::runtime::Runtime;
+use moxie_native::dom::{Node, View};
+use moxie_native::mox_impl::{elt, event};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use test::Bencher;
+
+const ITEMS: usize = 1000;
+
+/// Counts the allocations made on each thread, so that tests running
+/// at the same time don't count each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn per_item_handlers(runtime: &mut Runtime, selected: &Arc<AtomicUsize>) -> Node<View> {
+    runtime.run_once(|| {
+        let mut list = elt::view();
+        for index in 0..ITEMS {
+            let selected = selected.clone();
+            list = list.add_child(
+                elt::button()
+                    .on_event(event::on_click(), move |_| {
+                        selected.store(index, Ordering::Relaxed)
+                    })
+                    .build(),
+            );
+        }
+        list.build()
+    })
+}
+
+fn delegated_handler(runtime: &mut Runtime, selected: &Arc<AtomicUsize>) -> Node<View> {
+    runtime.run_once(|| {
+        let selected = selected.clone();
+        let mut list = elt::view().on_event(event::on_child_click(), move |event| {
+            selected.store(event.index, Ordering::Relaxed)
+        });
+        for _ in 0..ITEMS {
+            list = list.add_child(elt::button().build());
+        }
+        list.build()
+    })
+}
+
+/// The allocations made by a rebuild, after a first build which
+/// creates the nodes.
+fn rebuild_allocations(build: fn(&mut Runtime, &Arc<AtomicUsize>) -> Node<View>) -> usize {
+    let mut runtime = Runtime::new();
+    let selected = Arc::new(AtomicUsize::new(0));
+    drop(build(&mut runtime, &selected));
+    let before = ALLOCATIONS.with(Cell::get);
+    drop(build(&mut runtime, &selected));
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn delegation_allocates_less() {
+    let per_item = rebuild_allocations(per_item_handlers);
+    let delegated = rebuild_allocations(delegated_handler);
+    // Each item's handler is boxed on every rebuild.
+    assert!(per_item >= delegated + ITEMS);
+}
+
+#[bench]
+fn rebuild_per_item_handlers(bencher: &mut Bencher) {
+    let mut runtime = Runtime::new();
+    let selected = Arc::new(AtomicUsize::new(0));
+    bencher.iter(|| per_item_handlers(&mut runtime, &selected));
+}
+
+#[bench]
+fn rebuild_delegated_handler(bencher: &mut Bencher) {
+    let mut runtime = Runtime::new();
+    let selected = Arc::new(AtomicUsize::new(0));
+    bencher.iter(|| delegated_handler(&mut runtime, &selected));
+}
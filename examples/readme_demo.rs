@@ -58,7 +58,7 @@ fn my_app() -> Node<App> {
 }
 
 fn main() {
-    // The entrypoint to the application is creating a runtime and starting it.
-    let runtime = moxie_native::Runtime::new(my_app);
-    runtime.start();
+    // The entrypoint to the application runs its root component until
+    // it exits. `moxie_native::Runtime` has more options.
+    App::run(my_app).unwrap();
 }
@@ -35,6 +35,11 @@ define_style! {
         height: 150 px,
     };
 
+    static TEXT_AREA_STYLE = {
+        width: 200 px,
+        height: 60 px,
+    };
+
     static SQUARE_STYLE = {
         background_color: rgb(0, 0, 0),
         display: block,
@@ -51,6 +56,13 @@ fn foo() -> Node<App> {
         click_count.update(|count| Some(count + 1));
     };
 
+    let (current_text, text) = state(|| TextValue::new("Edit me".to_owned()));
+
+    let on_change = move |event: &ChangeEvent| {
+        let value = event.value.clone();
+        text.update(move |_| Some(value));
+    };
+
     mox! {
         <app>
             <window>
@@ -67,6 +79,7 @@ fn foo() -> Node<App> {
                             "Clicked " {% "{}", current_count} " times)"
                         </span>
                     </button>
+                    <text_area style={TEXT_AREA_STYLE} value={(*current_text).clone()} on_change={on_change} />
                     <view style={VIEW1_STYLE}></view>
                     <view style={VIEW2_STYLE}></view>
                 </view>
@@ -125,9 +125,13 @@ fn tag_to_tokens(
 ) {
     // this needs to be nested within other token groups, must be accumulated separately from stream
     let mut contents = quote!();
+    let mut key = None;
 
     for attr in attributes {
-        attr.to_tokens(&mut contents);
+        match attr {
+            MoxAttr::Key { value } => key = Some(value),
+            attr => attr.to_tokens(&mut contents),
+        }
     }
 
     if let Some(items) = children {
@@ -168,6 +172,13 @@ fn tag_to_tokens(
         quote!(mox_impl::elt::#name() #contents .build())
     };
 
+    // `key` is never an attribute of the element, it gives the whole
+    // subtree its identity instead.
+    let invocation = match key {
+        Some(key) => quote!(mox_impl::keyed(&(#key), || #invocation)),
+        None => invocation,
+    };
+
     stream.extend(invocation);
 }
 
@@ -220,6 +231,7 @@ enum MoxAttr {
     Simple { name: Ident, value: TokenTree },
     Handler { name: Ident, value: TokenTree },
     Data { name: Ident, value: TokenTree },
+    Key { value: TokenTree },
 }
 
 impl ToTokens for MoxAttr {
@@ -232,6 +244,7 @@ impl ToTokens for MoxAttr {
                 literal.set_span(name.span());
                 quote!(.set_data(#literal, #value))
             }
+            MoxAttr::Key { .. } => unreachable!("keys are handled by the tag"),
         };
 
         tokens.extend(stream);
@@ -248,6 +261,8 @@ impl From<SnaxAttribute> for MoxAttr {
                         name.span(),
                         "anonymous attributes are only allowed in the first position"
                     );
+                } else if name_str == "key" {
+                    MoxAttr::Key { value }
                 } else if name_str.starts_with("data_") {
                     MoxAttr::Data { name, value }
                 } else if name_str.starts_with("on_") {
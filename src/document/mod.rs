@@ -1,43 +1,206 @@
-use crate::dom::element::{DynamicNode, ElementStates};
-use crate::dom::input::{InputEvent, State};
+use crate::dom::element::{DynamicNode, ElementState, ElementStates};
+use crate::dom::input::{
+    InputEvent, InputModality, Key, KeyboardState, PointerId, State, TouchPhase,
+};
 use crate::dom::node::{AnyNode, NodeRef};
-use crate::dom::{Node, Window};
-use crate::layout::{LayoutEngine, LayoutTreeNode, LogicalSize};
-use crate::style::ComputedValues;
+use crate::dom::{
+    ColorScheme, GesturePhase, ImageSource, Node, ResizeEvent, Window, WindowMetrics, WindowState,
+};
+use crate::layout::{
+    from_local_space, to_local_space, LayoutEngine, LayoutTree, LayoutTreeNode, LogicalPoint,
+    LogicalRect, LogicalSize, LogicalTransform, RectChange, RenderData,
+};
+use crate::render::renderer::{caret_scroll, is_portal};
+use crate::render::TextRendering;
+use crate::runtime::{Gesture, Images, MotionState, RecordedInput, TransformRecognizer};
+use crate::style::{Animator, ComputedValues, RootTextSize, Style, Stylesheet, Visibility};
 use crate::util::equal_rc::EqualRc;
 use moxie::runtime::Runtime;
 use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Instant;
 
 struct NodeState {
     node: AnyNode,
     states: ElementStates,
     computed_values: Option<ComputedValues>,
+    /// The parent's id, and this node's index within its children.
+    parent: Option<(u64, usize)>,
     live: bool,
 }
 
 pub(crate) struct DocumentState {
     states: HashMap<u64, NodeState>,
-    pub window: Node<Window>,
+    /// The root of the tree, which is normally the window.
+    pub root: AnyNode,
     pub content_size: LogicalSize,
+    pub sheet: Stylesheet,
+    pub keyboard: KeyboardState,
+    pub images: Images,
+    modality: InputModality,
+    /// The element under the mouse. Touches don't hover.
     hovered_node: Option<u64>,
-    pressed_node: Option<u64>,
+    /// The element under the mouse, even while the mouse is down on
+    /// another one and `hovered_node` stays, which decides whether the
+    /// release clicks.
+    mouse_over: Option<u64>,
+    /// The element each pointer which is down went down on, which gets
+    /// its release.
+    pressed: HashMap<PointerId, u64>,
+    focused_node: Option<u64>,
+    /// Visual lines of the focused node's text from the last layout.
+    focused_lines: Vec<Range<usize>>,
+    animator: Animator,
+    /// Style transitions show every change at once while motion is
+    /// reduced.
+    motion: MotionState,
 }
 
 impl DocumentState {
-    pub fn walk_children(&mut self, node: NodeRef) {
+    pub fn new(root: NodeRef, content_size: LogicalSize) -> DocumentState {
+        let mut state = DocumentState {
+            root: root.to_owned(),
+            content_size,
+            sheet: Stylesheet::default(),
+            keyboard: KeyboardState::default(),
+            images: Images::default(),
+            modality: InputModality::Pointer,
+            states: HashMap::new(),
+            hovered_node: None,
+            mouse_over: None,
+            pressed: HashMap::new(),
+            focused_node: None,
+            focused_lines: vec![],
+            animator: Animator::default(),
+            motion: MotionState::default(),
+        };
+        state.walk_children(root, None);
+        state
+    }
+
+    /// Computes the styles of every node in the tree, with properties
+    /// which are transitioning where they are at `now`.
+    pub fn update_styles(&mut self, runtime: &mut Runtime, now: Instant) {
+        let root = self.root.clone();
+        let size = self.content_size;
+        let root_text_size = RootTextSize(ComputedValues::default().text_size);
+        runtime.run_once(move || {
+            illicit::Layer::new()
+                .offer(size)
+                .offer(root_text_size)
+                .enter(move || {
+                    self.update_style((&root).into(), None, now);
+                })
+        });
+        // Hidden elements can't be typed into, so they lose focus.
+        if let Some(focused) = self.focused_node {
+            if !self.is_shown(focused) {
+                self.set_focus(None);
+            }
+        }
+    }
+
+    /// Whether the node is painted and can be interacted with, which is
+    /// when it isn't hidden, and neither it nor any of its ancestors has
+    /// `display: none`. Nodes whose style hasn't been computed yet are
+    /// considered shown.
+    fn is_shown(&self, id: u64) -> bool {
+        let values = |id| {
+            self.states
+                .get(&id)
+                .and_then(|state| state.computed_values.as_ref())
+        };
+        if values(id).map_or(false, |values| values.visibility == Visibility::Hidden) {
+            return false;
+        }
+        let mut next = Some(id);
+        while let Some(id) = next {
+            if values(id).map_or(false, |values| values.display_none) {
+                return false;
+            }
+            next = self
+                .states
+                .get(&id)
+                .and_then(|state| state.parent)
+                .map(|(parent, _)| parent);
+        }
+        true
+    }
+
+    pub fn walk_children(&mut self, node: NodeRef, parent: Option<(u64, usize)>) {
         let entry = self.states.entry(node.id()).or_insert_with(|| NodeState {
             node: node.to_owned(),
             states: ElementStates::default(),
             computed_values: None,
+            parent: None,
             live: false,
         });
         entry.live = true;
+        entry.parent = parent;
 
-        for child in node.children() {
-            if let DynamicNode::Node(node) = child {
-                self.walk_children(node)
+        for (index, child) in node.children().enumerate() {
+            if let DynamicNode::Node(child) = child {
+                self.walk_children(child, Some((node.id(), index)))
+            }
+        }
+    }
+
+    /// Offers a click on the given node to each of its ancestors in
+    /// turn, until one of them handles it.
+    fn bubble_click(&mut self, target: u64) {
+        let mut current = target;
+        while let Some((parent, index)) = self.states.get(&current).and_then(|state| state.parent) {
+            if let Some(state) = self.states.get_mut(&parent) {
+                let (sink, states) = state
+                    .node
+                    .dispatch(state.states, &InputEvent::ChildClicked { index, target });
+                state.states = states;
+                if sink {
+                    return;
+                }
+            }
+            current = parent;
+        }
+    }
+
+    /// Finds the topmost element at `position` which would be hit
+    /// tested by the renderer, which is those with a background, that
+    /// are interactive, that are drag regions, or that have a `title`.
+    /// Portals are added to `portals` to be tested after everything
+    /// else, since they're painted on top.
+    fn hit_test<'a>(
+        &self,
+        layout: &'a LayoutTreeNode,
+        origin: LogicalPoint,
+        position: LogicalPoint,
+        portals: &mut Vec<(LogicalPoint, &'a LayoutTreeNode)>,
+    ) -> Option<u64> {
+        let mut hit = None;
+        if let RenderData::Node(ref node) = layout.render {
+            let values = self.computed_values(node.id());
+            let paint = node.paint(values);
+            let visible = paint.background_color.alpha > 0
+                || node.interactive()
+                || node.drag_region()
+                || node.title().is_some();
+            if visible
+                && values.visibility == Visibility::Visible
+                && LogicalRect::new(origin, layout.size).contains(position)
+            {
+                hit = Some(node.id());
+            }
+        }
+        for child in &layout.children {
+            let origin = origin + child.position.to_vector();
+            if is_portal(&child.layout) {
+                portals.push((origin, &child.layout));
+                continue;
+            }
+            if let Some(id) = self.hit_test(&child.layout, origin, position, portals) {
+                hit = Some(id);
             }
         }
+        hit
     }
 
     pub fn computed_values(&self, id: u64) -> &ComputedValues {
@@ -54,11 +217,215 @@ impl DocumentState {
     }
 
     fn set_root(&mut self, window: Node<Window>) {
-        self.window = window.clone();
+        self.root = window.into();
 
-        self.walk_children((&window).into());
+        let root = self.root.clone();
+        self.walk_children((&root).into(), None);
         self.states
             .retain(|_id, state| std::mem::replace(&mut state.live, false));
+        let states = &self.states;
+        self.animator.retain(|id| states.contains_key(&id));
+
+        if let Some(focused) = self.focused_node {
+            let focusable = self
+                .states
+                .get(&focused)
+                .map_or(false, |state| state.node.focusable());
+            if !focusable {
+                self.set_focus(None);
+            }
+        }
+        // The focused node may have moved to other ancestors.
+        self.update_focus_within();
+    }
+
+    pub fn focused(&self) -> Option<u64> {
+        self.focused_node
+    }
+
+    /// Whether the node or one of its descendants is focused.
+    pub fn focus_within(&self, id: u64) -> bool {
+        self.focused_node == Some(id)
+            || self.states.get(&id).map_or(false, |state| {
+                state.states.contains(ElementState::FocusWithin)
+            })
+    }
+
+    /// Moves keyboard focus to the given node, or clears it.
+    pub fn set_focus(&mut self, focused: Option<u64>) -> bool {
+        if focused == self.focused_node {
+            return false;
+        }
+
+        if let Some(old) = self.focused_node.take() {
+            if let Some(state) = self.states.get_mut(&old) {
+                state.states = state.node.process(
+                    state.states - ElementState::Focus - ElementState::FocusVisible,
+                    &InputEvent::Focused { state: State::End },
+                );
+            }
+        }
+        self.focused_lines.clear();
+
+        if let Some(new) = focused {
+            let mut states = ElementState::Focus.into();
+            if self.modality == InputModality::Keyboard {
+                states |= ElementState::FocusVisible;
+            }
+            if let Some(state) = self.states.get_mut(&new) {
+                self.focused_node = Some(new);
+                state.states = state.node.process(
+                    state.states | states,
+                    &InputEvent::Focused {
+                        state: State::Begin,
+                    },
+                );
+            }
+        }
+        self.update_focus_within();
+
+        true
+    }
+
+    /// Sets `ElementState::FocusWithin` on the ancestors of the focused
+    /// node, and clears it from every other node.
+    fn update_focus_within(&mut self) {
+        for state in self.states.values_mut() {
+            state.states -= ElementState::FocusWithin;
+        }
+        let mut next = self
+            .focused_node
+            .and_then(|id| self.states.get(&id))
+            .and_then(|state| state.parent);
+        while let Some(state) = next.and_then(|(id, _)| self.states.get_mut(&id)) {
+            state.states |= ElementState::FocusWithin;
+            next = state.parent;
+        }
+    }
+
+    pub fn modality(&self) -> InputModality {
+        self.modality
+    }
+
+    /// Switches the input modality, showing or hiding the focus of the
+    /// focused node to match. Returns whether this changed anything.
+    fn set_modality(&mut self, modality: InputModality) -> bool {
+        if modality == self.modality {
+            return false;
+        }
+        self.modality = modality;
+        if let Some(state) = self
+            .focused_node
+            .and_then(|focused| self.states.get_mut(&focused))
+        {
+            match modality {
+                InputModality::Keyboard => state.states |= ElementState::FocusVisible,
+                InputModality::Pointer => state.states -= ElementState::FocusVisible,
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Moves focus to the next focusable node in tab order after the
+    /// focused one, wrapping around to the first. The order is that of
+    /// `Element::tab_index()`: positive indices first, then the rest in
+    /// tree order, leaving out negative indices.
+    fn focus_next(&mut self) -> bool {
+        let mut focusable = vec![];
+        collect_focusable((&self.root).into(), &mut focusable);
+        focusable.retain(|&(id, index)| index >= 0 && self.is_shown(id));
+        // The sort is stable, so tree order is kept among equal indices.
+        focusable.sort_by_key(|&(_, index)| (index == 0, index));
+        let focusable: Vec<u64> = focusable.into_iter().map(|(id, _)| id).collect();
+        let next = match self
+            .focused_node
+            .and_then(|focused| focusable.iter().position(|&id| id == focused))
+        {
+            Some(index) => focusable.get(index + 1).or_else(|| focusable.first()),
+            None => focusable.first(),
+        };
+        match next {
+            Some(&next) => self.set_focus(Some(next)),
+            None => false,
+        }
+    }
+
+    /// The first shown node in tree order which `key` activates from
+    /// anywhere in the window, see `Element::window_key()`.
+    fn window_key_target(&self, key: Key) -> Option<u64> {
+        let mut targets = vec![];
+        collect_window_key_targets((&self.root).into(), key, &mut targets);
+        targets.into_iter().find(|&id| self.is_shown(id))
+    }
+
+    /// Tab moves focus to the next focusable node. Other keys go to the
+    /// focused node, unless they activate a default or cancel button
+    /// which the focused node doesn't take them from.
+    pub fn key_pressed(&mut self, key: Key) -> bool {
+        let changed = self.set_modality(InputModality::Keyboard);
+        let repeat = !self.keyboard.press(key);
+        if key == Key::Tab {
+            return self.focus_next() | changed;
+        }
+        let focused = self
+            .focused_node
+            .and_then(|focused| self.states.get(&focused));
+        let captured = focused.map_or(false, |state| state.node.captures_key(key));
+        if !captured {
+            if let Some(target) = self.window_key_target(key) {
+                if repeat {
+                    return changed;
+                }
+                return self.send(target, &InputEvent::Activated) | changed;
+            }
+        }
+        let ignored = focused.map_or(false, |state| repeat && !state.node.key_repeat());
+        if ignored {
+            return changed;
+        }
+        let lines = self.focused_lines.clone();
+        self.send_focused(&InputEvent::KeyPressed { key, lines }) | changed
+    }
+
+    pub fn key_released(&mut self, key: Key) {
+        self.keyboard.release(key);
+    }
+
+    /// The window lost focus, so it won't see any more key releases.
+    pub fn blur(&mut self) {
+        self.keyboard.clear();
+    }
+
+    pub fn character(&mut self, character: char) -> bool {
+        if character.is_control() {
+            return false;
+        }
+        self.send_focused(&InputEvent::Character { character })
+    }
+
+    fn send_focused(&mut self, event: &InputEvent) -> bool {
+        match self.focused_node {
+            Some(focused) => self.send(focused, event),
+            None => false,
+        }
+    }
+
+    fn send(&mut self, id: u64, event: &InputEvent) -> bool {
+        if let Some(state) = self.states.get_mut(&id) {
+            state.states = state.node.process(state.states, event);
+            return true;
+        }
+        false
+    }
+
+    /// Records the lines the focused node's text was broken into, which
+    /// is needed for moving the caret between lines.
+    fn update_focused_lines(&mut self, layout: &LayoutTreeNode) {
+        if let Some(focused) = self.focused_node {
+            self.focused_lines.clear();
+            collect_lines(focused, layout, &mut self.focused_lines);
+        }
     }
 
     pub fn set_size(&mut self, size: LogicalSize) {
@@ -66,7 +433,8 @@ impl DocumentState {
     }
 
     pub fn mouse_move(&mut self, hovered: Option<u64>) -> bool {
-        if self.pressed_node.is_some() {
+        self.mouse_over = hovered;
+        if self.pressed.contains_key(&PointerId::MOUSE) {
             return false;
         }
 
@@ -98,72 +466,303 @@ impl DocumentState {
         }
     }
 
+    /// Releasing the mouse clicks the element it went down on only if
+    /// it's still over that element or inside of it. Anywhere else, the
+    /// press is cancelled instead.
     pub fn mouse_button1(&mut self, pressed: bool) -> bool {
-        if let Some(node) = self.pressed_node {
-            if let Some(state) = self.states.get_mut(&node) {
-                if !pressed {
-                    self.pressed_node = None;
-                }
-                state.states = state.node.process(
-                    state.states,
-                    &InputEvent::MouseLeft {
-                        state: if pressed { State::Begin } else { State::End },
-                    },
-                );
+        if pressed {
+            return self.pointer_down(PointerId::MOUSE, self.hovered_node);
+        }
+        let over = self.mouse_over;
+        let state = match self.pressed.get(&PointerId::MOUSE) {
+            Some(&node) if !over.map_or(false, |over| self.is_within(over, node)) => State::Cancel,
+            _ => State::End,
+        };
+        self.pointer_up(PointerId::MOUSE, over, state)
+    }
+
+    /// Moves of a touch don't change anything, since touches don't
+    /// hover and are released on the element they went down on.
+    pub fn touch(&mut self, pointer: PointerId, phase: TouchPhase, target: Option<u64>) -> bool {
+        match phase {
+            TouchPhase::Started => self.pointer_down(pointer, target),
+            TouchPhase::Moved => false,
+            TouchPhase::Ended => self.pointer_up(pointer, None, State::End),
+            TouchPhase::Cancelled => self.pointer_up(pointer, None, State::Cancel),
+        }
+    }
+
+    /// The pointer went down over `target`, which captures it until it's
+    /// released. Other pointers can go down on other elements meanwhile.
+    fn pointer_down(&mut self, pointer: PointerId, target: Option<u64>) -> bool {
+        if let Some(&node) = self.pressed.get(&pointer) {
+            return self.send_pointer(node, pointer, State::Begin);
+        }
+
+        let mut changed = self.set_modality(InputModality::Pointer);
+        let focusable = target
+            .and_then(|target| self.states.get(&target))
+            .filter(|state| state.node.focusable())
+            .map(|state| state.node.id());
+        changed |= self.set_focus(focusable);
+
+        if let Some(target) = target {
+            if self.send_pointer(target, pointer, State::Begin) {
+                self.pressed.insert(pointer, target);
                 return true;
             }
         }
+        changed
+    }
 
-        if let Some(hovered) = self.hovered_node {
-            if let Some(state) = self.states.get_mut(&hovered) {
-                if pressed {
-                    self.pressed_node = Some(hovered);
+    /// The pointer was released, or cancelled, which goes to the element
+    /// it went down on, and clicks it. Without one, it goes to `over`.
+    fn pointer_up(&mut self, pointer: PointerId, over: Option<u64>, state: State) -> bool {
+        if let Some(node) = self.pressed.remove(&pointer) {
+            if self.send_pointer(node, pointer, state) {
+                if let State::End = state {
+                    self.bubble_click(node);
                 }
-                state.states = state.node.process(
-                    state.states,
-                    &InputEvent::MouseLeft {
-                        state: if pressed { State::Begin } else { State::End },
-                    },
-                );
                 return true;
             }
         }
+        match over {
+            Some(over) => self.send_pointer(over, pointer, state),
+            None => false,
+        }
+    }
 
+    /// Whether `id` is `ancestor` or one of its descendants.
+    fn is_within(&self, id: u64, ancestor: u64) -> bool {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self
+                .states
+                .get(&id)
+                .and_then(|state| state.parent)
+                .map(|(parent, _)| parent);
+        }
         false
     }
 
+    fn send_pointer(&mut self, id: u64, pointer: PointerId, state: State) -> bool {
+        match self.states.get_mut(&id) {
+            Some(node) => {
+                node.states = node
+                    .node
+                    .process(node.states, &InputEvent::MouseLeft { state, pointer });
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn close_requested(&mut self) -> bool {
-        if let Some(state) = self.states.get_mut(&self.window.id()) {
-            state.states = state
-                .node
-                .process(state.states, &InputEvent::CloseRequested);
+        self.send_root(&InputEvent::CloseRequested)
+    }
+
+    pub fn window_state(&mut self, window_state: WindowState) -> bool {
+        self.send_root(&InputEvent::WindowState {
+            state: window_state,
+        })
+    }
+
+    pub fn color_scheme(&mut self, scheme: ColorScheme) -> bool {
+        self.send_root(&InputEvent::ColorScheme { scheme })
+    }
+
+    pub fn metrics(&mut self, metrics: WindowMetrics) -> bool {
+        self.send_root(&InputEvent::Metrics { metrics })
+    }
+
+    /// Offers a recognized gesture to the element it started on, then
+    /// to each of its ancestors in turn, until one of them handles it.
+    pub fn gesture(&mut self, target: u64, gesture: Gesture) -> bool {
+        let event = match gesture {
+            Gesture::LongPress => InputEvent::LongPress,
+            Gesture::Swipe(direction) => InputEvent::Swipe { direction },
+            Gesture::Pinch(pinch) => InputEvent::Pinch(pinch),
+            Gesture::Rotate(rotate) => InputEvent::Rotate(rotate),
+        };
+        let mut current = Some(target);
+        while let Some(state) = current.and_then(|id| self.states.get_mut(&id)) {
+            let (sink, states) = state.node.dispatch(state.states, &event);
+            state.states = states;
+            if sink {
+                return true;
+            }
+            current = state.parent.map(|(parent, _)| parent);
+        }
+        false
+    }
+
+    /// Sends a resize to the node, without bubbling it, returning
+    /// whether the node handled it.
+    pub fn resized(&mut self, id: u64, resize: ResizeEvent) -> bool {
+        match self.states.get_mut(&id) {
+            Some(state) => {
+                let (sink, states) = state
+                    .node
+                    .dispatch(state.states, &InputEvent::Resized(resize));
+                state.states = states;
+                sink
+            }
+            None => false,
+        }
+    }
+
+    /// The outermost event sink containing the node, which gets the
+    /// pointer input that lands on it, or else the node itself.
+    pub fn event_target(&self, id: u64) -> u64 {
+        let mut target = id;
+        let mut current = Some(id);
+        while let Some(state) = current.and_then(|id| self.states.get(&id)) {
+            if state.node.event_sink() {
+                target = state.node.id();
+            }
+            current = state.parent.map(|(parent, _)| parent);
+        }
+        target
+    }
+
+    /// Whether the node is a drag region or is inside one. Interactive
+    /// elements end the search, so a button in a title bar can still be
+    /// clicked.
+    pub fn in_drag_region(&self, id: u64) -> bool {
+        let mut current = id;
+        while let Some(state) = self.states.get(&current) {
+            if state.node.drag_region() {
+                return true;
+            }
+            if state.node.interactive() {
+                return false;
+            }
+            match state.parent {
+                Some((parent, _)) => current = parent,
+                None => break,
+            }
+        }
+        false
+    }
+
+    /// The `title` of the hovered element, or of its nearest ancestor
+    /// with one, which is shown in the native tooltip.
+    pub fn tooltip(&self) -> Option<&str> {
+        let mut current = self.hovered_node;
+        while let Some(state) = current.and_then(|id| self.states.get(&id)) {
+            if let Some(title) = state.node.title() {
+                return Some(title);
+            }
+            current = state.parent.map(|(parent, _)| parent);
+        }
+        None
+    }
+
+    fn send_root(&mut self, event: &InputEvent) -> bool {
+        if let Some(state) = self.states.get_mut(&self.root.id()) {
+            state.states = state.node.process(state.states, event);
             return true;
         }
         false
     }
 }
 
+fn find_by_id(node: NodeRef, id: &str) -> Option<u64> {
+    if node.element_id() == Some(id) {
+        return Some(node.id());
+    }
+    node.children()
+        .filter_map(|child| child.node())
+        .find_map(|child| find_by_id(child, id))
+}
+
+fn query_all(node: NodeRef, predicate: &impl Fn(NodeRef) -> bool, ids: &mut Vec<u64>) {
+    if predicate(node) {
+        ids.push(node.id());
+    }
+    for child in node.children().filter_map(|child| child.node()) {
+        query_all(child, predicate, ids);
+    }
+}
+
+/// Collects the focusable nodes in tree order, with their tab indices.
+fn collect_focusable(node: NodeRef, ids: &mut Vec<(u64, i32)>) {
+    if node.focusable() {
+        ids.push((node.id(), node.tab_index().unwrap_or(0)));
+    }
+    // Focus inside an event sink stays on the sink.
+    if node.event_sink() {
+        return;
+    }
+    for child in node.children().filter_map(|child| child.node()) {
+        collect_focusable(child, ids);
+    }
+}
+
+fn collect_window_key_targets(node: NodeRef, key: Key, ids: &mut Vec<u64>) {
+    if node.window_key(key) {
+        ids.push(node.id());
+    }
+    for child in node.children().filter_map(|child| child.node()) {
+        collect_window_key_targets(child, key, ids);
+    }
+}
+
+fn collect_lines(id: u64, layout: &LayoutTreeNode, lines: &mut Vec<Range<usize>>) {
+    if let RenderData::Text {
+        ref text,
+        ref parent,
+    } = layout.render
+    {
+        if parent.id() == id {
+            lines.push(text.range.clone());
+        }
+    }
+    for child in &layout.children {
+        collect_lines(id, &child.layout, lines);
+    }
+}
+
 pub struct Document {
     state: DocumentState,
     style_runtime: Runtime,
     layout_engine: LayoutEngine,
+    layout: Option<LayoutTree>,
+    /// The rects of each node when layout changes were last taken.
+    reported_rects: HashMap<u64, LogicalRect>,
+    /// The sizes of each node when resizes were last delivered.
+    resized_sizes: HashMap<u64, LogicalSize>,
+    /// Set by each layout until resizes are delivered.
+    resizes_due: bool,
+    /// The latest cursor position which hasn't been hit tested yet.
+    pending_cursor: Option<LogicalPoint>,
+    transforms: TransformRecognizer,
+    /// The element getting the current pinch and rotation.
+    transform_target: Option<u64>,
+    text_rendering: TextRendering,
+    /// Whether the caret is in the shown phase of blinking.
+    caret_visible: bool,
 }
 
 impl Document {
     pub fn new(window: Node<Window>, content_size: LogicalSize) -> Document {
-        let mut doc = Document {
-            state: DocumentState {
-                window,
-                content_size,
-                states: HashMap::new(),
-                hovered_node: None,
-                pressed_node: None,
-            },
+        Document {
+            state: DocumentState::new((&window).into(), content_size),
             style_runtime: Runtime::new(),
             layout_engine: LayoutEngine::new(),
-        };
-        doc.state.walk_children((&doc.state.window.clone()).into());
-        doc
+            layout: None,
+            reported_rects: HashMap::new(),
+            resized_sizes: HashMap::new(),
+            resizes_due: false,
+            pending_cursor: None,
+            transforms: TransformRecognizer::default(),
+            transform_target: None,
+            text_rendering: TextRendering::deterministic(),
+            caret_visible: true,
+        }
     }
 
     pub fn computed_values(&self, id: u64) -> &ComputedValues {
@@ -178,16 +777,338 @@ impl Document {
         self.state.set_size(size);
     }
 
+    /// Sets a stylesheet which overrides the default look of elements,
+    /// usually through element selectors. Each element's own style
+    /// still takes precedence over it.
+    pub fn set_theme(&mut self, theme: Option<Style>) {
+        self.state.sheet.theme = theme;
+    }
+
+    /// Sets the style used by elements which list `class` in their
+    /// `class` attribute. Class styles are applied after the theme, in
+    /// the order the element lists them, and before the element's own
+    /// style.
+    pub fn set_class(&mut self, class: &str, style: Option<Style>) {
+        match style {
+            Some(style) => self.state.sheet.classes.insert(class.to_owned(), style),
+            None => self.state.sheet.classes.remove(class),
+        };
+    }
+
+    pub(crate) fn set_stylesheet(&mut self, sheet: Stylesheet) {
+        self.state.sheet = sheet;
+    }
+
+    /// Shares the held keys with the other windows.
+    pub(crate) fn set_keyboard(&mut self, keyboard: KeyboardState) {
+        self.state.keyboard = keyboard;
+    }
+
+    pub fn keyboard(&self) -> &KeyboardState {
+        &self.state.keyboard
+    }
+
+    /// Sets how the document's text is rasterized. Documents start out
+    /// with `TextRendering::deterministic()`, and windows set the
+    /// runtime's mode.
+    pub(crate) fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        self.text_rendering = text_rendering;
+    }
+
+    pub fn text_rendering(&self) -> TextRendering {
+        self.text_rendering
+    }
+
+    /// Whether the caret of the focused element is painted.
+    pub fn caret_visible(&self) -> bool {
+        self.caret_visible
+    }
+
+    /// Shows or hides the caret as it blinks, returning whether this
+    /// changed anything.
+    pub fn set_caret_visible(&mut self, visible: bool) -> bool {
+        std::mem::replace(&mut self.caret_visible, visible) != visible
+    }
+
+    /// Shares the decoded images with the other windows.
+    pub(crate) fn set_images(&mut self, images: Images) {
+        self.state.images = images;
+    }
+
+    pub(crate) fn images(&self) -> &Images {
+        &self.state.images
+    }
+
+    /// The sources of the images shown by the elements in the tree.
+    pub(crate) fn image_sources(&self) -> impl Iterator<Item = &ImageSource> {
+        self.state
+            .states
+            .values()
+            .filter_map(|state| state.node.image_source())
+    }
+
+    pub fn content_size(&self) -> LogicalSize {
+        self.state.content_size
+    }
+
     pub fn get_layout(&mut self) -> EqualRc<LayoutTreeNode> {
-        let state = &mut self.state;
-        let window = state.window.clone();
-        let size = state.content_size;
-        self.style_runtime.run_once(move || {
-            illicit::Layer::new().offer(size).enter(move || {
-                state.update_style((&window).into(), None);
-            })
+        self.get_layout_at(Instant::now())
+    }
+
+    /// Lays out the document with style transitions as they are at
+    /// `now`, for tests which step through them.
+    pub(crate) fn get_layout_at(&mut self, now: Instant) -> EqualRc<LayoutTreeNode> {
+        trace_span!(span = "layout", elements = tracing::field::Empty);
+        self.state.update_styles(&mut self.style_runtime, now);
+        let layout = self.layout_engine.layout(&mut self.state);
+        self.state.update_focused_lines(&layout);
+        let tree = LayoutTree::new(layout.clone());
+        trace_record!(span, "elements", tree.rects().len());
+        self.layout = Some(tree);
+        self.resizes_due = true;
+        layout
+    }
+
+    /// Whether any style transition is still running, so the document
+    /// has to be laid out and painted again for the next frame.
+    pub fn animating(&self) -> bool {
+        self.state.animator.animating(Instant::now())
+    }
+
+    pub(crate) fn set_motion(&mut self, motion: MotionState) {
+        self.state.motion = motion;
+    }
+
+    /// Whether there's been a layout since resizes were last delivered.
+    pub fn resizes_due(&self) -> bool {
+        self.resizes_due
+    }
+
+    /// Sends a `ResizeEvent` to each node whose size changed since the
+    /// last call, as of the last layout, in order of id. Returns whether
+    /// any node handled one.
+    pub fn deliver_resizes(&mut self) -> bool {
+        let layout = match self.layout {
+            Some(ref layout) if self.resizes_due => layout,
+            _ => return false,
+        };
+        self.resizes_due = false;
+        let mut resizes = layout
+            .rects()
+            .iter()
+            .map(|(&id, rect)| (id, rect.size))
+            .filter(|(id, size)| self.resized_sizes.get(id) != Some(size))
+            .collect::<Vec<_>>();
+        resizes.sort_by_key(|(id, _)| *id);
+        self.resized_sizes
+            .retain(|id, _| layout.rects().contains_key(id));
+        let mut handled = false;
+        for (id, size) in resizes {
+            let old = self.resized_sizes.insert(id, size);
+            handled |= self.state.resized(id, ResizeEvent { old, new: size });
+        }
+        handled
+    }
+
+    /// Returns the nodes whose rects changed since the last call, as of
+    /// the last layout. Layouts in between are coalesced, so each node
+    /// is reported at most once with its rect from before the first of
+    /// them and after the last.
+    pub fn take_layout_changes(&mut self) -> Vec<RectChange> {
+        let layout = match self.layout {
+            Some(ref layout) => layout,
+            None => return vec![],
+        };
+        let changes = layout.changes_since(&self.reported_rects);
+        if !changes.is_empty() {
+            self.reported_rects = layout.rects().clone();
+        }
+        changes
+    }
+
+    /// Finds the first node in tree order whose `id` attribute matches.
+    pub fn find_by_id(&self, id: &str) -> Option<u64> {
+        find_by_id((&self.state.root).into(), id)
+    }
+
+    /// The ids of every node for which `predicate` returns true, in
+    /// tree order.
+    pub fn query_all(&self, predicate: impl Fn(NodeRef) -> bool) -> Vec<u64> {
+        let mut ids = vec![];
+        query_all((&self.state.root).into(), &predicate, &mut ids);
+        ids
+    }
+
+    /// The border box of a node in the last layout.
+    pub fn rect(&self, id: u64) -> Option<LogicalRect> {
+        self.layout.as_ref()?.rect(id)
+    }
+
+    /// Maps a point in the window into the space of a node's border
+    /// box, where its top left corner is the origin, using the last
+    /// layout. This goes through the position of each ancestor, and the
+    /// scrolling of any editable text the node is inside of. Returns
+    /// `None` if the node wasn't laid out, or if the point can't be
+    /// mapped because a transform along the way can't be inverted.
+    pub fn to_local(&self, id: u64, point: LogicalPoint) -> Option<LogicalPoint> {
+        let mut chain = vec![];
+        self.local_chain(id, &self.layout.as_ref()?.root, &mut chain)?;
+        to_local_space(&chain, point)
+    }
+
+    /// The caret of the focused element in the window, as of the last
+    /// layout, for placing the candidate window of an input method next
+    /// to it. Returns `None` when the focused element isn't editable.
+    pub fn caret_rect(&self) -> Option<LogicalRect> {
+        let id = self.focused()?;
+        let mut chain = vec![];
+        let layout = self.local_chain(id, &self.layout.as_ref()?.root, &mut chain)?;
+        let caret = match layout.render {
+            RenderData::Node(ref node) => node.caret()?,
+            RenderData::Text { .. } => return None,
+        };
+        let values = self.state.computed_values(id);
+        let (caret_rect, scroll) = caret_scroll(id, caret, values, layout);
+        let caret_rect = caret_rect.translate(euclid::vec2(0.0, -scroll));
+        Some(from_local_space(&chain, caret_rect))
+    }
+
+    /// Collects the transforms from `layout` down to the node, returning
+    /// its layout if it was found.
+    fn local_chain<'a>(
+        &self,
+        id: u64,
+        layout: &'a LayoutTreeNode,
+        chain: &mut Vec<LogicalTransform>,
+    ) -> Option<&'a LayoutTreeNode> {
+        let mut scroll = 0.0;
+        if let RenderData::Node(ref node) = layout.render {
+            if node.id() == id {
+                return Some(layout);
+            }
+            if let Some(caret) = node.caret() {
+                let values = self.state.computed_values(node.id());
+                scroll = caret_scroll(node.id(), caret, values, layout).1;
+            }
+        }
+        for child in &layout.children {
+            let offset = child.position.to_vector() - euclid::vec2(0.0, scroll);
+            chain.push(LogicalTransform::translation(offset.x, offset.y));
+            if let Some(found) = self.local_chain(id, &child.layout, chain) {
+                return Some(found);
+            }
+            chain.pop();
+        }
+        None
+    }
+
+    /// Finds the element at the given position using the last layout.
+    /// Like the renderer, the root element itself is never hit, and
+    /// hits inside an event sink go to the sink.
+    pub fn element_at(&self, position: LogicalPoint) -> Option<u64> {
+        let layout = &self.layout.as_ref()?.root;
+        let mut portals = vec![];
+        let mut hit = layout.children.iter().fold(None, |hit, child| {
+            self.state
+                .hit_test(&child.layout, child.position, position, &mut portals)
+                .or(hit)
         });
-        self.layout_engine.layout(&mut self.state)
+        let mut index = 0;
+        while let Some(&(origin, portal)) = portals.get(index) {
+            hit = self
+                .state
+                .hit_test(portal, origin, position, &mut portals)
+                .or(hit);
+            index += 1;
+        }
+        hit.map(|id| self.event_target(id))
+    }
+
+    /// The element which gets pointer input landing on `id`, see
+    /// `Element::event_sink()`.
+    pub fn event_target(&self, id: u64) -> u64 {
+        self.state.event_target(id)
+    }
+
+    /// Applies a recorded input. Cursor positions are hit tested
+    /// against the last layout, so this doesn't need a window, and
+    /// works on a best-effort basis when the tree has changed since
+    /// the input was recorded. Like live input, cursor moves are
+    /// coalesced until the next `flush_cursor()` or other input.
+    pub fn input(&mut self, input: &RecordedInput) -> bool {
+        trace_span!(span = "input", input = ?input);
+        match *input {
+            RecordedInput::CursorMoved(position) => {
+                self.cursor_moved(position);
+                false
+            }
+            RecordedInput::CursorLeft => {
+                self.pending_cursor = None;
+                self.mouse_move(None)
+            }
+            RecordedInput::MouseButton1(pressed) => {
+                self.flush_cursor() | self.mouse_button1(pressed)
+            }
+            RecordedInput::Touch(pointer, phase, position) => {
+                self.flush_cursor() | self.touch(pointer, phase, position)
+            }
+            RecordedInput::Key(key) => self.flush_cursor() | self.key_pressed(key),
+            RecordedInput::KeyReleased(key) => {
+                self.key_released(key);
+                false
+            }
+            RecordedInput::Blur => {
+                self.blur();
+                false
+            }
+            RecordedInput::Character(character) => self.flush_cursor() | self.character(character),
+            RecordedInput::CloseRequested => self.flush_cursor() | self.close_requested(),
+        }
+    }
+
+    /// Records that the cursor moved, without hit testing. The OS can
+    /// report many moves each frame, so only the latest position is
+    /// kept, and it's delivered once per frame by `take_cursor_move()`.
+    /// Hover follows the final position, so elements the cursor passed
+    /// over in between don't see it enter or leave.
+    pub fn cursor_moved(&mut self, position: LogicalPoint) {
+        self.pending_cursor = Some(position);
+    }
+
+    /// Takes the latest cursor position since the last call, to be hit
+    /// tested and passed to `mouse_move()`. This should be called at
+    /// the end of each frame's events, and before delivering any other
+    /// input, so that clicks and leaves see where the cursor is.
+    pub fn take_cursor_move(&mut self) -> Option<LogicalPoint> {
+        self.pending_cursor.take()
+    }
+
+    /// Delivers the pending cursor move, hit testing against the last
+    /// layout.
+    pub fn flush_cursor(&mut self) -> bool {
+        match self.take_cursor_move() {
+            Some(position) => {
+                let hovered = self.element_at(position);
+                self.mouse_move(hovered)
+            }
+            None => false,
+        }
+    }
+
+    pub fn focused(&self) -> Option<u64> {
+        self.state.focused()
+    }
+
+    pub(crate) fn focus_within(&self, id: u64) -> bool {
+        self.state.focus_within(id)
+    }
+
+    pub fn in_drag_region(&self, id: u64) -> bool {
+        self.state.in_drag_region(id)
+    }
+
+    pub fn modality(&self) -> InputModality {
+        self.state.modality()
     }
 
     pub fn mouse_move(&mut self, hovered: Option<u64>) -> bool {
@@ -198,9 +1119,931 @@ impl Document {
         self.state.mouse_button1(pressed)
     }
 
+    /// Delivers a touch, hit testing where it starts against the last
+    /// layout. Each touch is followed separately from the mouse and
+    /// from other touches. Two touches also pinch and rotate the
+    /// element under the point midway between them.
+    pub fn touch(&mut self, pointer: PointerId, phase: TouchPhase, position: LogicalPoint) -> bool {
+        let target = match phase {
+            TouchPhase::Started => self.element_at(position),
+            _ => None,
+        };
+        let mut changed = self.state.touch(pointer, phase, target);
+        if let Some((pinch, rotate)) = self.transforms.touch(pointer, phase, position) {
+            if pinch.phase == GesturePhase::Began {
+                self.transform_target = self.element_at(pinch.centroid);
+            }
+            if let Some(target) = self.transform_target {
+                changed |= self.state.gesture(target, Gesture::Pinch(pinch));
+                changed |= self.state.gesture(target, Gesture::Rotate(rotate));
+            }
+        }
+        changed
+    }
+
     pub fn close_requested(&mut self) -> bool {
         self.state.close_requested()
     }
+
+    pub fn window_state(&mut self, state: WindowState) -> bool {
+        self.state.window_state(state)
+    }
+
+    pub fn color_scheme(&mut self, scheme: ColorScheme) -> bool {
+        self.state.color_scheme(scheme)
+    }
+
+    pub fn metrics(&mut self, metrics: WindowMetrics) -> bool {
+        self.state.metrics(metrics)
+    }
+
+    pub(crate) fn gesture(&mut self, target: u64, gesture: Gesture) -> bool {
+        self.state.gesture(target, gesture)
+    }
+
+    pub fn tooltip(&self) -> Option<&str> {
+        self.state.tooltip()
+    }
+
+    pub fn key_pressed(&mut self, key: Key) -> bool {
+        self.state.key_pressed(key)
+    }
+
+    pub fn key_released(&mut self, key: Key) {
+        self.state.key_released(key)
+    }
+
+    pub fn blur(&mut self) {
+        self.state.blur()
+    }
+
+    pub fn character(&mut self, character: char) -> bool {
+        self.state.character(character)
+    }
 }
 
 mod styling;
+
+#[cfg(test)]
+mod test {
+    use super::Document;
+    use crate::dom::element::ElementState;
+    use crate::dom::input::{InputModality, Key, PointerId, TouchPhase};
+    use crate::dom::Button;
+    use crate::layout::{LogicalLength, RectChange};
+    use crate::mox_impl::{attr, elt, event};
+    use crate::runtime::{Gesture, RecordedInput};
+    use crate::style::{
+        sized_box, test_style, AnimatedProperty, Attributes, ComputedValues, Corners, DisplayType,
+        NodeSelect, PropertyTransition, Style, StyleData, SubStyle, Visibility,
+    };
+    use crate::Color;
+    use euclid::{point2, rect, size2};
+    use moxie::runtime::Runtime;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn moved_element_is_reported() {
+        let short = sized_box("short", 20.0, 10.0);
+        let tall = sized_box("tall", 20.0, 30.0);
+
+        let mut runtime = Runtime::new();
+        let mut build = |style: Style| {
+            runtime.run_once(|| {
+                let first = elt::view().set_attr(attr::style(), style).build();
+                let second = elt::view().set_attr(attr::style(), short).build();
+                let window = elt::window()
+                    .add_child(first)
+                    .add_child(second.clone())
+                    .build();
+                (window, second)
+            })
+        };
+
+        let (window, second) = build(short);
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        assert!(document.take_layout_changes().contains(&RectChange {
+            id: second.id(),
+            old: None,
+            new: Some(rect(0.0, 10.0, 20.0, 10.0)),
+        }));
+        document.get_layout();
+        assert_eq!(document.take_layout_changes(), vec![]);
+
+        let (window, moved) = build(tall);
+        assert_eq!(moved, second);
+        document.set_root(window);
+        document.get_layout();
+        document.get_layout();
+        let changes = document.take_layout_changes();
+        assert!(changes.contains(&RectChange {
+            id: second.id(),
+            old: Some(rect(0.0, 10.0, 20.0, 10.0)),
+            new: Some(rect(0.0, 30.0, 20.0, 10.0)),
+        }));
+        assert_eq!(document.take_layout_changes(), vec![]);
+    }
+
+    #[test]
+    fn find_by_id() {
+        let square = sized_box("square", 20.0, 10.0);
+
+        let (window, second) = Runtime::new().run_once(|| {
+            let first = elt::view().set_attr(attr::style(), square).build();
+            let second = elt::view()
+                .set_attr(attr::id(), "target")
+                .set_attr(attr::style(), square)
+                .build();
+            let third = elt::view()
+                .set_attr(attr::id(), "target")
+                .set_attr(attr::style(), square)
+                .build();
+            let window = elt::window()
+                .add_child(first)
+                .add_child(second.clone())
+                .add_child(third)
+                .build();
+            (window, second)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let found = document.find_by_id("target");
+        assert_eq!(found, Some(second.id()));
+        assert_eq!(
+            found.and_then(|id| document.rect(id)),
+            Some(rect(0.0, 10.0, 20.0, 10.0))
+        );
+        assert_eq!(document.find_by_id("missing"), None);
+    }
+
+    #[test]
+    fn width_transitions() {
+        fn sized(values: &mut ComputedValues, width: f32) {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(width).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+            values.transitions.set(PropertyTransition {
+                property: AnimatedProperty::Width,
+                duration: Duration::from_millis(200),
+            });
+        }
+        let narrow = test_style("narrow", |values| sized(values, 100.0));
+        let wide = test_style("wide", |values| sized(values, 200.0));
+        let mut runtime = Runtime::new();
+        let mut build = |style: Style| {
+            runtime.run_once(|| {
+                let view = elt::view().set_attr(attr::style(), style).build();
+                let id = view.id();
+                (elt::window().add_child(view).build(), id)
+            })
+        };
+
+        let (window, id) = build(narrow);
+        let mut document = Document::new(window, size2(300.0, 100.0));
+        let start = Instant::now();
+        document.get_layout_at(start);
+        let width = |document: &Document| document.rect(id).unwrap().size.width;
+        assert_eq!(width(&document), 100.0);
+
+        let (window, resized) = build(wide);
+        assert_eq!(resized, id);
+        document.set_root(window);
+        document.get_layout_at(start + Duration::from_millis(1000));
+        assert_eq!(width(&document), 100.0);
+        document.get_layout_at(start + Duration::from_millis(1100));
+        assert!((width(&document) - 150.0).abs() < 0.5);
+        document.get_layout_at(start + Duration::from_millis(1200));
+        assert_eq!(width(&document), 200.0);
+    }
+
+    #[test]
+    fn query_all_in_tree_order() {
+        let (window, buttons) = Runtime::new().run_once(|| {
+            let first = elt::button().build();
+            let second = elt::button().set_attr(attr::class(), "primary").build();
+            let third = elt::button().build();
+            let root = elt::view()
+                .add_child(first.clone())
+                .add_child(
+                    elt::view()
+                        .add_child(second.clone())
+                        .add_child(elt::span().add_child("label").build())
+                        .build(),
+                )
+                .add_child(third.clone())
+                .build();
+            let window = elt::window().add_child(root).build();
+            (window, vec![first.id(), second.id(), third.id()])
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        assert_eq!(document.query_all(|node| node.is::<Button>()), buttons);
+        assert_eq!(
+            document.query_all(|node| node.class() == Some("primary")),
+            vec![buttons[1]]
+        );
+        assert!(document.query_all(|_| false).is_empty());
+    }
+
+    #[test]
+    fn cursor_moves_are_coalesced() {
+        let square = sized_box("square", 20.0, 10.0);
+        let (window, first, second) = Runtime::new().run_once(|| {
+            let first = elt::view().set_attr(attr::style(), square).build();
+            let second = elt::view().set_attr(attr::style(), square).build();
+            let window = elt::window()
+                .add_child(first.clone())
+                .add_child(second.clone())
+                .build();
+            (window, first, second)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+
+        // The cursor wiggles between the two views within one frame.
+        for step in 0..10 {
+            let y = if step % 2 == 0 { 15.0 } else { 5.0 };
+            let changed = document.input(&RecordedInput::CursorMoved(point2(5.0, y)));
+            assert!(!changed);
+        }
+        assert_eq!(document.take_cursor_move(), Some(point2(5.0, 5.0)));
+        assert_eq!(document.take_cursor_move(), None);
+
+        // A cursor which comes to rest is still delivered.
+        document.cursor_moved(point2(5.0, 5.0));
+        assert!(document.flush_cursor());
+        assert_eq!(document.state.hovered_node, Some(first.id()));
+        assert!(!document.flush_cursor());
+
+        // Other input sees the latest position first.
+        document.cursor_moved(point2(5.0, 15.0));
+        document.input(&RecordedInput::MouseButton1(true));
+        assert_eq!(
+            document.state.pressed.get(&PointerId::MOUSE),
+            Some(&second.id())
+        );
+    }
+
+    #[test]
+    fn held_keys() {
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        let (window, text_area) = Runtime::new().run_once(|| {
+            let text_area = elt::text_area()
+                .set_attr(attr::value(), "abc".into())
+                .set_attr(attr::key_repeat(), false)
+                .on_event(event::on_change(), move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .build();
+            (
+                elt::window().add_child(text_area.clone()).build(),
+                text_area,
+            )
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.state.set_focus(Some(text_area.id()));
+        let keyboard = document.keyboard().clone();
+
+        document.key_pressed(Key::Char('w'));
+        assert!(keyboard.is_held(Key::Char('w')));
+        document.key_released(Key::Char('w'));
+        assert!(!keyboard.is_held(Key::Char('w')));
+
+        // Holding Backspace only deletes once, as repeats are ignored.
+        document.key_pressed(Key::Backspace);
+        document.key_pressed(Key::Backspace);
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+        assert_eq!(keyboard.held(), vec![Key::Backspace]);
+
+        document.blur();
+        assert_eq!(keyboard.held(), vec![]);
+    }
+
+    #[test]
+    fn tab_index_orders_focus() {
+        let (window, buttons) = Runtime::new().run_once(|| {
+            let buttons: Vec<_> = [None, Some(2), Some(0), Some(1), Some(-1)]
+                .iter()
+                .map(|&index| {
+                    elt::button()
+                        .set_attr(attr::tab_index(), index)
+                        .add_child(elt::span().add_child("button").build())
+                        .build()
+                })
+                .collect();
+            let mut root = elt::view();
+            for button in &buttons {
+                root = root.add_child(button.clone());
+            }
+            let window = elt::window().add_child(root.build()).build();
+            (window, buttons)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+
+        let mut visited = vec![];
+        for _ in 0..5 {
+            document.key_pressed(Key::Tab);
+            visited.push(document.focused().unwrap());
+        }
+        let id = |index: usize| buttons[index].id();
+        assert_eq!(visited, vec![id(3), id(1), id(0), id(2), id(3)]);
+
+        // A negative index is skipped by Tab, but can still be focused.
+        document.state.set_focus(Some(id(4)));
+        assert_eq!(document.focused(), Some(id(4)));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(id(3)));
+    }
+
+    #[test]
+    fn focus_visible_after_keyboard() {
+        let field = sized_box("field", 50.0, 10.0);
+        let (window, first, second) = Runtime::new().run_once(|| {
+            let first = elt::text_area().set_attr(attr::style(), field).build();
+            let second = elt::text_area().set_attr(attr::style(), field).build();
+            let window = elt::window()
+                .add_child(first.clone())
+                .add_child(second.clone())
+                .build();
+            (window, first, second)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let states = |document: &Document, id| document.state.node_states(id);
+
+        // Tabbing shows the focus.
+        assert!(document.key_pressed(Key::Tab));
+        assert_eq!(document.focused(), Some(first.id()));
+        assert_eq!(document.modality(), InputModality::Keyboard);
+        assert_eq!(
+            states(&document, first.id()),
+            ElementState::Focus | ElementState::FocusVisible
+        );
+
+        // Clicking doesn't.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 15.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(document.focused(), Some(second.id()));
+        assert_eq!(document.modality(), InputModality::Pointer);
+        assert!(states(&document, first.id()).is_empty());
+        assert!(states(&document, second.id()).contains(ElementState::Focus));
+        assert!(!states(&document, second.id()).contains(ElementState::FocusVisible));
+
+        // Typing into the clicked field shows its focus, and Tab wraps
+        // around.
+        document.key_pressed(Key::Char('a'));
+        assert!(states(&document, second.id()).contains(ElementState::FocusVisible));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(first.id()));
+    }
+
+    #[test]
+    fn focus_within_ancestors() {
+        let field = sized_box("field", 50.0, 10.0);
+        let mut runtime = Runtime::new();
+        let mut render = |grouped: bool| {
+            runtime.run_once(|| {
+                let inner = elt::text_area().set_attr(attr::style(), field).build();
+                let outer = elt::text_area().set_attr(attr::style(), field).build();
+                let mut group = elt::view();
+                if grouped {
+                    group = group.add_child(inner.clone());
+                }
+                let group = group.build();
+                let window = elt::window()
+                    .add_child(group.clone())
+                    .add_child(outer.clone())
+                    .build();
+                (window, group, inner, outer)
+            })
+        };
+        let (window, group, inner, outer) = render(true);
+        let mut document = Document::new(window.clone(), size2(100.0, 100.0));
+        document.get_layout();
+        let within = |document: &Document, id| {
+            document
+                .state
+                .node_states(id)
+                .contains(ElementState::FocusWithin)
+        };
+
+        document.state.set_focus(Some(inner.id()));
+        assert!(within(&document, group.id()));
+        assert!(within(&document, window.id()));
+        // Not the focused element itself, or its siblings.
+        assert!(!within(&document, inner.id()));
+        assert!(!within(&document, outer.id()));
+
+        // Moving focus out of the group clears it there.
+        document.state.set_focus(Some(outer.id()));
+        assert!(!within(&document, group.id()));
+        assert!(within(&document, window.id()));
+
+        // So does removing the focused element.
+        document.state.set_focus(Some(inner.id()));
+        let (window, group, _, _) = render(false);
+        document.set_root(window.clone());
+        assert_eq!(document.focused(), None);
+        assert!(!within(&document, group.id()));
+        assert!(!within(&document, window.id()));
+    }
+
+    #[test]
+    fn readonly_and_disabled() {
+        let changes = Arc::new(AtomicUsize::new(0));
+        let carets = Arc::new(Mutex::new(vec![]));
+        let field = sized_box("field", 50.0, 10.0);
+        let (change_counter, caret_log) = (changes.clone(), carets.clone());
+        let (window, readonly, disabled) = Runtime::new().run_once(|| {
+            let counter = change_counter.clone();
+            let caret_log = caret_log.clone();
+            let readonly = elt::text_area()
+                .set_attr(attr::style(), field)
+                .set_attr(attr::value(), "abc".into())
+                .set_attr(attr::readonly(), true)
+                .on_event(event::on_change(), move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .on_event(event::on_select(), move |event| {
+                    caret_log.lock().unwrap().push(event.caret);
+                })
+                .build();
+            let counter = change_counter.clone();
+            let disabled = elt::text_area()
+                .set_attr(attr::style(), field)
+                .set_attr(attr::value(), "abc".into())
+                .set_attr(attr::disabled(), true)
+                .on_event(event::on_change(), move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .build();
+            let window = elt::window()
+                .add_child(readonly.clone())
+                .add_child(disabled.clone())
+                .build();
+            (window, readonly, disabled)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+
+        // The readonly field can be focused and navigated, but not
+        // edited.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 5.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(document.focused(), Some(readonly.id()));
+        document.key_pressed(Key::Left);
+        document.key_pressed(Key::Backspace);
+        document.character('x');
+        assert_eq!(*carets.lock().unwrap(), vec![2]);
+
+        // The disabled field can't be focused by clicking or tabbing.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 15.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(document.focused(), None);
+        document.key_pressed(Key::Tab);
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(readonly.id()));
+        assert_eq!(changes.load(Ordering::SeqCst), 0);
+        assert!(!document
+            .state
+            .node_states(disabled.id())
+            .contains(ElementState::Focus));
+    }
+
+    #[test]
+    fn buttons_from_keyboard() {
+        let clicks = Arc::new(Mutex::new(vec![]));
+        let square = sized_box("square", 10.0, 10.0);
+        let log = clicks.clone();
+        let window = Runtime::new().run_once(|| {
+            let button = |name: &'static str| {
+                let log = log.clone();
+                elt::button()
+                    .set_attr(attr::style(), square)
+                    .on_event(event::on_click(), move |_| log.lock().unwrap().push(name))
+            };
+            elt::window()
+                .add_child(elt::text_area().set_attr(attr::style(), square).build())
+                .add_child(button("plain").build())
+                .add_child(button("ok").set_attr(attr::default(), true).build())
+                .add_child(button("cancel").set_attr(attr::cancel(), true).build())
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let take = || std::mem::take(&mut *clicks.lock().unwrap());
+
+        // Without focus, Enter clicks the default button once, however
+        // long it's held.
+        document.key_pressed(Key::Enter);
+        document.key_pressed(Key::Enter);
+        document.key_released(Key::Enter);
+        assert_eq!(take(), vec!["ok"]);
+
+        let press = |document: &mut Document, key| {
+            document.key_pressed(key);
+            document.key_released(key);
+        };
+
+        // The focused text area takes Enter, but not Escape.
+        press(&mut document, Key::Tab);
+        press(&mut document, Key::Enter);
+        press(&mut document, Key::Escape);
+        assert_eq!(take(), vec!["cancel"]);
+
+        // A focused button clicks itself on Space and Enter.
+        press(&mut document, Key::Tab);
+        press(&mut document, Key::Space);
+        press(&mut document, Key::Enter);
+        assert_eq!(take(), vec!["plain", "plain"]);
+    }
+
+    #[test]
+    fn release_outside_cancels_click() {
+        let square = sized_box("square", 10.0, 10.0);
+        let clicks = Arc::new(Mutex::new(vec![]));
+        let (button_log, list_log) = (clicks.clone(), clicks.clone());
+        let (window, button) = Runtime::new().run_once(|| {
+            let button = elt::button()
+                .set_attr(attr::style(), square)
+                .on_event(event::on_click(), move |_| {
+                    button_log.lock().unwrap().push("button")
+                })
+                .build();
+            let list = elt::view()
+                .on_event(event::on_child_click(), move |_| {
+                    list_log.lock().unwrap().push("list")
+                })
+                .add_child(button.clone())
+                .add_child(elt::view().set_attr(attr::style(), square).build())
+                .build();
+            (elt::window().add_child(list).build(), button)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let take = || std::mem::take(&mut *clicks.lock().unwrap());
+
+        // Pressed on the button and released over the square below it.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 5.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 15.0)));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(take(), Vec::<&str>::new());
+        assert!(!document
+            .state
+            .node_states(button.id())
+            .contains(ElementState::Press));
+
+        // Moving back onto the button before the release still clicks.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 5.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 15.0)));
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 5.0)));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(take(), vec!["button", "list"]);
+    }
+
+    #[test]
+    fn event_sink_takes_child_clicks() {
+        let canvas = sized_box("canvas", 50.0, 50.0);
+        let square = sized_box("square", 10.0, 10.0);
+        let clicks = Arc::new(Mutex::new(vec![]));
+        let (sink_log, child_log) = (clicks.clone(), clicks.clone());
+        let (window, sink, child) = Runtime::new().run_once(|| {
+            let child = elt::button()
+                .set_attr(attr::style(), square)
+                .on_event(event::on_click(), move |_| {
+                    child_log.lock().unwrap().push("child")
+                })
+                .build();
+            let sink = elt::view()
+                .set_attr(attr::style(), canvas)
+                .set_attr(attr::event_sink(), true)
+                .on_event(event::on_click(), move |_| {
+                    sink_log.lock().unwrap().push("sink")
+                })
+                .add_child(child.clone())
+                .build();
+            (elt::window().add_child(sink.clone()).build(), sink, child)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+
+        assert_eq!(document.element_at(point2(5.0, 5.0)), Some(sink.id()));
+        assert_eq!(document.element_at(point2(40.0, 40.0)), Some(sink.id()));
+        document.cursor_moved(point2(5.0, 5.0));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(*clicks.lock().unwrap(), vec!["sink"]);
+        assert_eq!(document.state.hovered_node, Some(sink.id()));
+        assert_eq!(document.focused(), Some(sink.id()));
+        assert!(!document
+            .state
+            .node_states(child.id())
+            .contains(ElementState::Hover));
+
+        // Tabbing skips over the sink's descendants.
+        document.state.set_focus(None);
+        assert!(document.key_pressed(Key::Tab));
+        assert_eq!(document.focused(), Some(sink.id()));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(sink.id()));
+    }
+
+    #[test]
+    fn resized_container_is_observed() {
+        let narrow = sized_box("narrow", 20.0, 10.0);
+        let wide = sized_box("wide", 60.0, 10.0);
+        let sizes = Arc::new(Mutex::new(vec![]));
+
+        let mut runtime = Runtime::new();
+        let mut build = |style: Style| {
+            let log = sizes.clone();
+            runtime.run_once(|| {
+                let container = elt::view()
+                    .set_attr(attr::style(), style)
+                    .on_event(event::on_resize(), move |event| {
+                        log.lock().unwrap().push((event.old, event.new));
+                    })
+                    .build();
+                elt::window().add_child(container).build()
+            })
+        };
+
+        let mut document = Document::new(build(narrow), size2(100.0, 100.0));
+        assert!(!document.deliver_resizes());
+        document.get_layout();
+        assert!(document.deliver_resizes());
+        assert_eq!(*sizes.lock().unwrap(), vec![(None, size2(20.0, 10.0))]);
+
+        // Layouts in between deliveries are coalesced.
+        sizes.lock().unwrap().clear();
+        document.set_root(build(wide));
+        document.get_layout();
+        document.set_root(build(narrow));
+        document.get_layout();
+        document.set_root(build(wide));
+        document.get_layout();
+        assert!(document.deliver_resizes());
+        assert_eq!(
+            *sizes.lock().unwrap(),
+            vec![(Some(size2(20.0, 10.0)), size2(60.0, 10.0))]
+        );
+
+        sizes.lock().unwrap().clear();
+        document.get_layout();
+        assert!(!document.deliver_resizes());
+        assert!(!document.resizes_due());
+        assert_eq!(*sizes.lock().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn concurrent_pointers() {
+        let clicks = Arc::new(Mutex::new(vec![]));
+        let square = sized_box("square", 10.0, 10.0);
+        let log = clicks.clone();
+        let (window, first, second) = Runtime::new().run_once(|| {
+            let button = |name: &'static str| {
+                let log = log.clone();
+                elt::button()
+                    .set_attr(attr::style(), square)
+                    .on_event(event::on_click(), move |_| log.lock().unwrap().push(name))
+                    .build()
+            };
+            let (first, second) = (button("first"), button("second"));
+            let window = elt::window()
+                .add_child(first.clone())
+                .add_child(second.clone())
+                .build();
+            (window, first, second)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let take = || std::mem::take(&mut *clicks.lock().unwrap());
+        let pressed = |document: &Document, id: u64| {
+            document.state.node_states(id).contains(ElementState::Press)
+        };
+        let finger = PointerId::touch(3);
+
+        // The mouse holds the first button while a finger taps the
+        // second, and each is released on the element it went down on.
+        document.input(&RecordedInput::CursorMoved(point2(5.0, 5.0)));
+        document.input(&RecordedInput::MouseButton1(true));
+        document.touch(finger, TouchPhase::Started, point2(5.0, 15.0));
+        assert!(pressed(&document, first.id()));
+        assert!(pressed(&document, second.id()));
+        document.touch(finger, TouchPhase::Moved, point2(50.0, 50.0));
+        document.touch(finger, TouchPhase::Ended, point2(50.0, 50.0));
+        assert_eq!(take(), vec!["second"]);
+        assert!(pressed(&document, first.id()));
+        document.input(&RecordedInput::MouseButton1(false));
+        assert_eq!(take(), vec!["first"]);
+
+        // Touches don't hover, and a cancelled one doesn't click.
+        document.touch(finger, TouchPhase::Started, point2(5.0, 15.0));
+        assert!(!document
+            .state
+            .node_states(second.id())
+            .contains(ElementState::Hover));
+        document.touch(finger, TouchPhase::Cancelled, point2(5.0, 15.0));
+        assert!(!pressed(&document, second.id()));
+        assert_eq!(take(), Vec::<&str>::new());
+        assert_ne!(finger, PointerId::MOUSE);
+    }
+
+    fn is_button(node: &dyn NodeSelect) -> bool {
+        node.is_type::<Button>()
+    }
+
+    #[test]
+    fn theme_overrides_defaults() {
+        let grey = Color::new(221, 221, 221, 255);
+        let red = Color::new(255, 0, 0, 255);
+        let theme = Style(Box::leak(Box::new(StyleData {
+            attributes: Attributes {
+                apply: |_| {},
+                get_attributes: Vec::new,
+            },
+            css: None,
+            sub_styles: Box::leak(Box::new([SubStyle {
+                selector: is_button,
+                attributes: Attributes {
+                    apply: |values| values.background_color = Color::new(221, 221, 221, 255),
+                    get_attributes: Vec::new,
+                },
+            }])),
+            name: "THEME",
+            file: file!(),
+            line: line!(),
+        })));
+        let red_style = test_style("red", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+        });
+
+        let (window, view, plain, nested, styled) = Runtime::new().run_once(|| {
+            let plain = elt::button().build();
+            let nested = elt::button().build();
+            let styled = elt::button().set_attr(attr::style(), red_style).build();
+            let view = elt::view()
+                .add_child(plain.clone())
+                .add_child(elt::view().add_child(nested.clone()).build())
+                .add_child(styled.clone())
+                .build();
+            let window = elt::window().add_child(view.clone()).build();
+            (window, view, plain, nested, styled)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let background = |document: &Document, id| document.computed_values(id).background_color;
+        assert_eq!(background(&document, plain.id()), Color::clear());
+        assert_eq!(
+            background(&document, view.id()),
+            Color::new(50, 180, 200, 255)
+        );
+
+        document.set_theme(Some(theme));
+        document.get_layout();
+        assert_eq!(background(&document, plain.id()), grey);
+        assert_eq!(background(&document, nested.id()), grey);
+        assert_eq!(background(&document, styled.id()), red);
+        assert_eq!(
+            background(&document, view.id()),
+            Color::new(50, 180, 200, 255)
+        );
+    }
+
+    #[test]
+    fn classes_share_styles() {
+        let card = test_style("card", |values| {
+            values.background_color = Color::new(255, 255, 255, 255);
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(4.0));
+        });
+        let selected = test_style("selected", |values| {
+            values.background_color = Color::new(0, 0, 255, 255);
+        });
+        let red = test_style("red", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+        });
+
+        let (window, first, second, both, inline) = Runtime::new().run_once(|| {
+            let first = elt::view().set_attr(attr::class(), "card").build();
+            let second = elt::view().set_attr(attr::class(), "card").build();
+            let both = elt::view().set_attr(attr::class(), "card selected").build();
+            let inline = elt::view()
+                .set_attr(attr::class(), "selected card")
+                .set_attr(attr::style(), red)
+                .build();
+            let window = elt::window()
+                .add_child(first.clone())
+                .add_child(second.clone())
+                .add_child(both.clone())
+                .add_child(inline.clone())
+                .build();
+            (window, first, second, both, inline)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.set_class("card", Some(card));
+        document.set_class("selected", Some(selected));
+        document.get_layout();
+
+        let values = |id| *document.computed_values(id);
+        assert_eq!(values(first.id()), values(second.id()));
+        assert_eq!(
+            values(first.id()).background_color,
+            Color::new(255, 255, 255, 255)
+        );
+        assert_eq!(
+            values(both.id()).background_color,
+            Color::new(0, 0, 255, 255)
+        );
+        assert_eq!(
+            values(both.id()).corner_radius,
+            Corners::new_all_same(LogicalLength::new(4.0))
+        );
+        assert_eq!(
+            values(inline.id()).background_color,
+            Color::new(255, 0, 0, 255)
+        );
+    }
+
+    #[test]
+    fn hidden_and_collapsed() {
+        let square = sized_box("square", 10.0, 10.0);
+        let hidden = test_style("hidden", |values| {
+            values.visibility = Visibility::Hidden;
+        });
+        let collapsed = test_style("collapsed", |values| {
+            values.display_none = true;
+        });
+
+        let (window, middle, last) = Runtime::new().run_once(|| {
+            let middle = elt::text_area()
+                .set_attr(attr::class(), "middle".to_owned())
+                .set_attr(attr::style(), square)
+                .set_attr(attr::value(), "kept".into())
+                .build();
+            let last = elt::view().set_attr(attr::style(), square).build();
+            let window = elt::window()
+                .add_child(
+                    elt::view()
+                        .add_child(elt::view().set_attr(attr::style(), square).build())
+                        .add_child(middle.clone())
+                        .add_child(last.clone())
+                        .build(),
+                )
+                .build();
+            (window, middle, last)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+
+        // Hidden elements keep their space, but can't be hit or focused.
+        document.set_class("middle", Some(hidden));
+        document.get_layout();
+        assert_eq!(
+            document.rect(middle.id()),
+            Some(rect(0.0, 10.0, 10.0, 10.0))
+        );
+        assert_eq!(document.rect(last.id()), Some(rect(0.0, 20.0, 10.0, 10.0)));
+        assert_ne!(document.element_at(point2(5.0, 15.0)), Some(middle.id()));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), None);
+
+        // Collapsed elements are taken out of the layout.
+        document.set_class("middle", Some(collapsed));
+        document.get_layout();
+        assert_eq!(document.rect(middle.id()), None);
+        assert_eq!(document.rect(last.id()), Some(rect(0.0, 10.0, 10.0, 10.0)));
+        assert_eq!(document.element_at(point2(5.0, 15.0)), Some(last.id()));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), None);
+
+        // Once revealed, the element is back as it was.
+        document.set_class("middle", None);
+        document.get_layout();
+        assert_eq!(
+            document.rect(middle.id()),
+            Some(rect(0.0, 10.0, 10.0, 10.0))
+        );
+        assert_eq!(document.element_at(point2(5.0, 15.0)), Some(middle.id()));
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(middle.id()));
+
+        // Hiding the focused element takes its focus away.
+        document.set_class("middle", Some(hidden));
+        document.get_layout();
+        assert_eq!(document.focused(), None);
+    }
+}
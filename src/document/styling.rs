@@ -1,8 +1,9 @@
 use super::DocumentState;
 use crate::dom::element::{DynamicNode, ElementState};
 use crate::dom::node::NodeRef;
-use crate::style::{ComputedValues, NodeSelect, Style};
+use crate::style::{default_style, ComputedValues, NodeSelect, RootTextSize, Style};
 use std::any::TypeId;
+use std::time::Instant;
 
 struct NodeProxy<'a> {
     node: NodeRef<'a>,
@@ -20,36 +21,67 @@ impl<'a> NodeSelect for NodeProxy<'a> {
 }
 
 impl DocumentState {
-    pub fn update_style(&mut self, node: NodeRef, parent: Option<&ComputedValues>) {
+    pub fn update_style(&mut self, node: NodeRef, parent: Option<&ComputedValues>, now: Instant) {
         let mut computed = node.create_computed_values();
 
         let default_values = ComputedValues::default();
+        let is_root = parent.is_none();
         let parent = parent.unwrap_or(&default_values);
 
         // Default-inherited attributes
         computed.text_color = parent.text_color;
+        computed.text_shadow = parent.text_shadow;
         computed.text_size = parent.text_size;
+        computed.theme_colors = parent.theme_colors;
+        computed.visibility = parent.visibility;
 
         illicit::Layer::new().offer(*parent).enter(|| {
-            let style = node.style();
-            if let Some(Style(style)) = style {
+            let proxy = NodeProxy { node, state: self };
+            // Later styles take precedence over earlier ones.
+            let classes = node
+                .class()
+                .unwrap_or("")
+                .split_whitespace()
+                .filter_map(|class| self.sheet.classes.get(class).copied());
+            let styles = std::iter::once(default_style())
+                .chain(self.sheet.theme)
+                .chain(classes)
+                .chain(node.style());
+            for Style(style) in styles {
                 (style.attributes.apply)(&mut computed);
-
-                let node = NodeProxy { node, state: self };
+                if let Some(css) = style.css {
+                    css.apply(&mut computed);
+                }
                 for sub_style in style.sub_styles {
-                    if (sub_style.selector)(&node) {
+                    if (sub_style.selector)(&proxy) {
                         (sub_style.attributes.apply)(&mut computed);
                     }
                 }
             }
         });
 
+        // Children inherit the values which are shown, so text sizes
+        // animate inside of a transitioning parent.
+        let reduced = self.motion.reduced();
+        self.animator.update(node.id(), &mut computed, now, reduced);
+
         self.states.get_mut(&node.id()).unwrap().computed_values = Some(computed);
 
-        for child in node.children() {
-            if let DynamicNode::Node(node) = child {
-                self.update_style(node, Some(&computed));
+        let mut update_children = || {
+            for child in node.children() {
+                if let DynamicNode::Node(node) = child {
+                    self.update_style(node, Some(&computed), now);
+                }
             }
+        };
+        // The root's own `rem` lengths are relative to the default text
+        // size, and those of everything inside of it to the root's.
+        if is_root {
+            illicit::Layer::new()
+                .offer(RootTextSize(computed.text_size))
+                .enter(update_children);
+        } else {
+            update_children();
         }
     }
 }
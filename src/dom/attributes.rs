@@ -1,4 +1,12 @@
+use crate::dom::element::FromAttributeText;
+use crate::dom::elements::image::ImageSource;
+use crate::dom::elements::svg::SvgImage;
+use crate::dom::elements::text_area::TextValue;
+use crate::dom::elements::view::Portal;
+use crate::dom::elements::window::{Decorations, WindowIcon, WindowLevel};
+use crate::render::painter::PaintOverlay;
 use crate::style::Style;
+use crate::Color;
 
 macro_rules! attribute {
     ($name:ident, $value:ty) => {
@@ -11,5 +19,128 @@ macro_rules! attribute {
     };
 }
 
+attribute!(AttrAfter, String);
+attribute!(AttrBefore, String);
+attribute!(AttrCancel, bool);
+attribute!(AttrClass, String);
+attribute!(AttrDecorations, Decorations);
+attribute!(AttrDefault, bool);
+attribute!(AttrDisabled, bool);
+attribute!(AttrDragRegion, bool);
+attribute!(AttrEventSink, bool);
+attribute!(AttrFill, Option<Color>);
+attribute!(AttrIcon, Option<WindowIcon>);
+attribute!(AttrId, String);
+attribute!(AttrKeyRepeat, bool);
+attribute!(AttrLevel, WindowLevel);
+attribute!(AttrPaintOverlay, Option<PaintOverlay>);
+attribute!(AttrPortal, Option<Portal>);
+attribute!(AttrReadonly, bool);
+attribute!(AttrSource, Option<SvgImage>);
+attribute!(AttrSrc, Option<ImageSource>);
+attribute!(AttrStroke, Option<Color>);
 attribute!(AttrStyle, Option<Style>);
+attribute!(AttrTabIndex, Option<i32>);
 attribute!(AttrTitle, String);
+attribute!(AttrValue, TextValue);
+
+impl FromAttributeText for String {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        Some(text.to_owned())
+    }
+}
+
+impl FromAttributeText for bool {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        text.parse().ok()
+    }
+}
+
+impl FromAttributeText for TextValue {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        Some(text.into())
+    }
+}
+
+impl FromAttributeText for Decorations {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        match text {
+            "full" => Some(Decorations::Full),
+            "none" => Some(Decorations::None),
+            _ => None,
+        }
+    }
+}
+
+impl FromAttributeText for WindowLevel {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        match text {
+            "normal" => Some(WindowLevel::Normal),
+            "always_on_top" => Some(WindowLevel::AlwaysOnTop),
+            _ => None,
+        }
+    }
+}
+
+/// Colors are written as `#rrggbb` or `#rrggbbaa`, and `none` clears
+/// them.
+impl FromAttributeText for Option<Color> {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        if text == "none" {
+            return Some(None);
+        }
+        if !text.starts_with('#') {
+            return None;
+        }
+        Color::from_hex(text).ok().map(Some)
+    }
+}
+
+impl FromAttributeText for Option<Portal> {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        match text {
+            "none" => Some(None),
+            "over" => Some(Some(Portal::Over)),
+            "below" => Some(Some(Portal::Below)),
+            "right" => Some(Some(Portal::Right)),
+            _ => None,
+        }
+    }
+}
+
+/// Tab indices are written as integers, and `none` clears them.
+impl FromAttributeText for Option<i32> {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        if text == "none" {
+            return Some(None);
+        }
+        text.parse().ok().map(Some)
+    }
+}
+
+/// Images are given from text by their path.
+impl FromAttributeText for Option<ImageSource> {
+    fn from_attribute_text(text: &str) -> Option<Self> {
+        Some(Some(ImageSource::Path(text.into())))
+    }
+}
+
+/// Values which can only be given from code.
+macro_rules! no_attribute_text {
+    ($($value:ty),+) => {
+        $(
+            impl FromAttributeText for $value {
+                fn from_attribute_text(_text: &str) -> Option<Self> {
+                    None
+                }
+            }
+        )+
+    };
+}
+
+no_attribute_text!(
+    Option<PaintOverlay>,
+    Option<Style>,
+    Option<SvgImage>,
+    Option<WindowIcon>
+);
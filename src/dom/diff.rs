@@ -0,0 +1,243 @@
+//! Comparing two built trees, for tooling such as design tools and hot
+//! reloading which have to find what changed between two versions of
+//! the UI.
+
+use crate::dom::element::{DynamicNode, Element};
+use crate::dom::node::{AnyNode, Node, NodeRef};
+
+/// A change between two trees, found by `diff()`. Paths are the indices
+/// of the children to follow from the root, like in `children()`, so
+/// the text of a `<span>` is a child too. `Removed` paths are into the
+/// old tree, and all the others are into the new tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Patch {
+    /// A node was added at `path`.
+    Added { path: Vec<usize>, node: AnyNode },
+    /// The child at `path` in the old tree was removed.
+    Removed { path: Vec<usize> },
+    /// An element with an `id` moved from `from` in the old tree to `to`
+    /// in the new tree. It may have changed as well, which is reported
+    /// separately at `to`.
+    Moved { from: Vec<usize>, to: Vec<usize> },
+    /// The attributes of the element at `path` changed. `names` lists
+    /// which ones, and is empty for elements which don't name their
+    /// attributes, see `Element::changed_attributes()`.
+    Attributes {
+        path: Vec<usize>,
+        names: Vec<&'static str>,
+    },
+    /// The text at `path` was added or changed.
+    Text { path: Vec<usize>, text: String },
+}
+
+/// Finds the changes between two versions of a tree.
+///
+/// Children are matched to those of the same parent in the old tree by
+/// their identity. An element with an `id` attribute is identified by
+/// its type and id, wherever it is among its siblings, so it's reported
+/// as moved rather than removed and added again. Any other element, and
+/// text, is identified by its position among its siblings, and matches
+/// the old child at the same index if it's of the same type. Children
+/// which don't match are reported as removed and added.
+///
+/// Nodes which are the same, such as subtrees which were memoized
+/// between renders, aren't looked into.
+pub fn diff<Elt>(old: &Node<Elt>, new: &Node<Elt>) -> Vec<Patch>
+where
+    Elt: Element,
+{
+    let mut patches = vec![];
+    diff_node(old.into(), new.into(), &mut vec![], &mut patches);
+    patches
+}
+
+fn diff_node(old: NodeRef, new: NodeRef, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    if old == new {
+        return;
+    }
+    if let Some(names) = new.changed_attributes(&*old) {
+        patches.push(Patch::Attributes {
+            path: path.clone(),
+            names,
+        });
+    }
+
+    let old_children = old.children().collect::<Vec<_>>();
+    let mut matched = vec![false; old_children.len()];
+    let new_children = new
+        .children()
+        .enumerate()
+        .map(|(index, child)| {
+            let found = (0..old_children.len()).find(|&candidate| {
+                !matched[candidate]
+                    && same_identity(&old_children[candidate], candidate, &child, index)
+            });
+            if let Some(candidate) = found {
+                matched[candidate] = true;
+            }
+            (child, found)
+        })
+        .collect::<Vec<_>>();
+
+    // Removed from the last, so that the paths of ones before stay valid
+    // while they're applied.
+    for (index, _) in matched
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, matched)| !**matched)
+    {
+        patches.push(Patch::Removed {
+            path: child_path(path, index),
+        });
+    }
+
+    for (index, (child, found)) in new_children.into_iter().enumerate() {
+        path.push(index);
+        match (found.map(|old| (old, &old_children[old])), child) {
+            (Some((_, DynamicNode::Text(old))), DynamicNode::Text(new)) => {
+                if *old != new {
+                    patches.push(Patch::Text {
+                        path: path.clone(),
+                        text: new.to_owned(),
+                    });
+                }
+            }
+            (Some((old_index, DynamicNode::Node(old))), DynamicNode::Node(new)) => {
+                if old_index != index {
+                    patches.push(Patch::Moved {
+                        from: child_path(&path[..path.len() - 1], old_index),
+                        to: path.clone(),
+                    });
+                }
+                diff_node(*old, new, path, patches);
+            }
+            (_, DynamicNode::Text(new)) => patches.push(Patch::Text {
+                path: path.clone(),
+                text: new.to_owned(),
+            }),
+            (_, DynamicNode::Node(new)) => patches.push(Patch::Added {
+                path: path.clone(),
+                node: new.to_owned(),
+            }),
+        }
+        path.pop();
+    }
+}
+
+/// Whether the old child at `old_index` is the same node as the new one
+/// at `new_index`, as described on `diff()`.
+fn same_identity(old: &DynamicNode, old_index: usize, new: &DynamicNode, new_index: usize) -> bool {
+    match (old, new) {
+        (DynamicNode::Text(_), DynamicNode::Text(_)) => old_index == new_index,
+        (DynamicNode::Node(old), DynamicNode::Node(new)) => {
+            old.type_id() == new.type_id()
+                && match (old.element_id(), new.element_id()) {
+                    (Some(old), Some(new)) => old == new,
+                    (None, None) => old_index == new_index,
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+fn child_path(parent: &[usize], index: usize) -> Vec<usize> {
+    let mut path = parent.to_vec();
+    path.push(index);
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, Patch};
+    use crate::dom::node::AnyNode;
+    use crate::dom::{Node, View};
+    use crate::mox_impl::{attr, elt};
+    use moxie::runtime::Runtime;
+
+    fn item(id: &str) -> Node<View> {
+        elt::view().set_attr(attr::id(), id.to_owned()).build()
+    }
+
+    #[test]
+    fn keyed_children_added_and_removed() {
+        let old = Runtime::new().run_once(|| {
+            elt::view()
+                .add_child(item("a"))
+                .add_child(item("b"))
+                .build()
+        });
+        let (new, added) = Runtime::new().run_once(|| {
+            let added = item("c");
+            let view = elt::view()
+                .add_child(item("b"))
+                .add_child(added.clone())
+                .build();
+            (view, added)
+        });
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Patch::Removed { path: vec![0] },
+                Patch::Moved {
+                    from: vec![1],
+                    to: vec![0],
+                },
+                Patch::Added {
+                    path: vec![1],
+                    node: AnyNode::from(added),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn positional_children_and_attributes() {
+        let tree = |class: &str, text: &str| {
+            let (class, text) = (class.to_owned(), text.to_owned());
+            Runtime::new().run_once(move || {
+                elt::view()
+                    .add_child(
+                        elt::button()
+                            .set_attr(attr::class(), class.clone())
+                            .add_child(elt::span().add_child(text.clone()).build())
+                            .build(),
+                    )
+                    .add_child(elt::span().build())
+                    .build()
+            })
+        };
+        let old = tree("card", "hello");
+        assert_eq!(diff(&old, &old), vec![]);
+        assert_eq!(diff(&old, &tree("card", "hello")), vec![]);
+        assert_eq!(
+            diff(&old, &tree("card selected", "goodbye")),
+            vec![
+                Patch::Attributes {
+                    path: vec![0],
+                    names: vec!["class"],
+                },
+                Patch::Text {
+                    path: vec![0, 0, 0],
+                    text: "goodbye".to_owned(),
+                },
+            ]
+        );
+
+        // Without ids, a different type of element in the same place is
+        // a different node.
+        let new = Runtime::new().run_once(|| elt::view().add_child(elt::span().build()).build());
+        let patches = diff(&old, &new);
+        assert_eq!(patches.len(), 3);
+        assert_eq!(patches[0], Patch::Removed { path: vec![1] });
+        assert_eq!(patches[1], Patch::Removed { path: vec![0] });
+        match &patches[2] {
+            Patch::Added { path, node } => {
+                assert_eq!(*path, vec![0]);
+                assert_eq!(node.name(), "span");
+            }
+            patch => panic!("unexpected patch {:?}", patch),
+        }
+    }
+}
@@ -1,9 +1,14 @@
-use crate::dom::input::InputEvent;
+use crate::dom::input::{InputEvent, Key};
 use crate::dom::node::{Node, NodeRef};
-use crate::style::{ComputedValues, Style};
-use crate::util::event_handler::EventHandler;
+use crate::dom::{ImageSource, Portal};
+use crate::render::painter::{PaintOverlay, Painter};
+use crate::style::{ComputedValues, PaintDetails, Style};
+pub use crate::util::event_handler::EventHandler;
 use enumset::{EnumSet, EnumSetType};
-use std::fmt::Debug;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{self, Debug, Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 /// Represents the attributes and behavior of a single DOM element.
 pub trait Element: Default + Clone + Debug + PartialEq + 'static {
@@ -22,6 +27,99 @@ pub trait Element: Default + Clone + Debug + PartialEq + 'static {
         false
     }
 
+    /// Determines how the element is painted, based on its computed
+    /// style values.
+    fn paint(&self, values: &ComputedValues) -> PaintDetails {
+        PaintDetails::from_values(values)
+    }
+
+    /// Whether the element can receive keyboard focus.
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    /// Where the element comes in the order Tab moves focus in, set with
+    /// the `tab_index` attribute, which follows HTML's `tabindex`:
+    ///
+    /// - Elements with a positive index come first, from the lowest
+    ///   index to the highest, and in tree order among equal ones.
+    /// - Then come elements with an index of `0`, and focusable elements
+    ///   without one, in tree order.
+    /// - Elements with a negative index are skipped by Tab, but can
+    ///   still be focused by clicking them or from code.
+    ///
+    /// An index makes elements which otherwise aren't focusable, like
+    /// views, focusable. It doesn't make a disabled element focusable.
+    fn tab_index(&self) -> Option<i32> {
+        None
+    }
+
+    /// Whether the element handles `key` itself while it's focused,
+    /// rather than leaving it to the window's default and cancel
+    /// buttons.
+    fn captures_key(&self, _key: Key) -> bool {
+        false
+    }
+
+    /// Whether pressing `key` activates the element wherever focus is
+    /// in its window, like the default and cancel buttons of a dialog.
+    fn window_key(&self, _key: Key) -> bool {
+        false
+    }
+
+    /// Text generated by the element itself, which is laid out before
+    /// any of its children.
+    fn content(&self) -> Option<&str> {
+        None
+    }
+
+    /// Text generated before the element's children, set with the
+    /// `before` attribute, like CSS's `::before`. It's laid out and
+    /// styled like text inside of the element, but it isn't one of its
+    /// children, so it's not in `children()` and doesn't count towards
+    /// the index of a `ChildClickEvent`. It has no node of its own to be
+    /// hit, so it's only hit where the element itself is.
+    fn before(&self) -> Option<&str> {
+        None
+    }
+
+    /// Text generated after the element's children, set with the
+    /// `after` attribute, like CSS's `::after`. See `before()`.
+    fn after(&self) -> Option<&str> {
+        None
+    }
+
+    /// Where the element is placed when it's lifted out of its parent
+    /// and painted on top of the window, see `Portal`. `None` lays it
+    /// out in place.
+    fn portal(&self) -> Option<Portal> {
+        None
+    }
+
+    /// Whether the element receives the repeated presses the OS sends
+    /// while a key is held down, when it's focused.
+    fn key_repeat(&self) -> bool {
+        true
+    }
+
+    /// Whether pressing on the element and dragging moves the window,
+    /// for custom title bars.
+    fn drag_region(&self) -> bool {
+        false
+    }
+
+    /// Whether the element takes the pointer input over all of its
+    /// descendants, which still paint but are never hit themselves.
+    fn event_sink(&self) -> bool {
+        false
+    }
+
+    /// The byte offset of the text caret within `content()`, for
+    /// editable elements.
+    fn caret(&self) -> Option<usize> {
+        None
+    }
+
     fn process(
         &self,
         states: ElementStates,
@@ -34,9 +132,49 @@ pub trait Element: Default + Clone + Debug + PartialEq + 'static {
     /// Returns the list of styles attached to this element.
     fn style(&self) -> Option<Style>;
 
+    /// The `id` attribute, which tags the element for looking it up.
+    fn element_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// The `class` attribute, a space separated list of the shared
+    /// styles which apply to the element.
+    fn class(&self) -> Option<&str> {
+        None
+    }
+
+    /// The `title` attribute, a short description of the element which
+    /// is shown in the OS's tooltip while it's hovered.
+    fn title(&self) -> Option<&str> {
+        None
+    }
+
+    /// The bitmap the element shows, which is decoded while the element
+    /// is in the tree.
+    fn image_source(&self) -> Option<&ImageSource> {
+        None
+    }
+
+    /// Draws content of the element's own, after its background and
+    /// border and before its children.
+    fn draw(&self, _painter: &mut Painter) {}
+
+    /// Painted over the element and its children, see `PaintOverlay`.
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        None
+    }
+
     fn attributes(&self) -> Vec<(&'static str, String)> {
         vec![]
     }
+
+    /// The names of the attributes which differ from `other`'s, for
+    /// `diff()`. The built-in elements return the attributes they list
+    /// in `HasTextAttributes`. By default no names are known, and
+    /// elements which aren't equal are reported as changed without any.
+    fn changed_attributes(&self, _other: &Self) -> Vec<&'static str> {
+        vec![]
+    }
 }
 
 /// The trait representing all events that can be invoked on an element.
@@ -44,7 +182,8 @@ pub trait Event {}
 
 /// Statically defines the relationship between which elements can have
 /// which events listened to, and also provides the mechanism for that
-/// to happen via the set_handler method.
+/// to happen via the set_handler method. Implemented with
+/// `element_handlers!`, which also defines the element's `Handlers`.
 pub trait HasEvent<Ev>: Element
 where
     Ev: Event,
@@ -64,10 +203,57 @@ where
     fn set_attribute(&mut self, value: Attr::Value);
 }
 
+/// Attribute values which can be written as text, for setting attributes
+/// by name with `Builder::attrs()`.
+pub trait FromAttributeText: Sized {
+    /// Returns `None` if the text isn't a valid value, or if values of
+    /// this type can't be written as text at all.
+    fn from_attribute_text(text: &str) -> Option<Self>;
+}
+
+/// Sets attributes by their name, the same as the functions in `attr`.
+/// This is implemented for each element along with its attributes.
+pub trait HasTextAttributes {
+    fn set_text_attribute(&mut self, name: &str, value: &str) -> Result<(), AttributeError>;
+
+    /// The names of the attributes which differ from `other`'s.
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str>;
+}
+
+/// An attribute set by name couldn't be set. The element is unchanged.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeError {
+    /// The element doesn't have an attribute with this name.
+    Unknown { name: String },
+    /// The text isn't a valid value for the attribute.
+    Invalid { name: String, value: String },
+}
+
+impl Display for AttributeError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            AttributeError::Unknown { name } => write!(fmt, "unknown attribute `{}`", name),
+            AttributeError::Invalid { name, value } => {
+                write!(fmt, "invalid value {:?} for attribute `{}`", value, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttributeError {}
+
 #[derive(EnumSetType)]
 pub enum ElementState {
     Hover,
     Press,
+    Focus,
+    /// Set along with `Focus` when the focus should be shown, which is
+    /// when the keyboard was used last. See `InputModality`.
+    FocusVisible,
+    /// Set on each ancestor of the focused element, so that containers
+    /// can be styled while something inside of them has focus. It's
+    /// only for styling: no events are sent when it changes.
+    FocusWithin,
 }
 
 pub type ElementStates = EnumSet<ElementState>;
@@ -128,6 +314,172 @@ impl NodeChild for String {
     }
 }
 
+/// A type-erased child, for when the children of an element come from
+/// data and their types aren't known statically. The usual compile
+/// time checks of which elements can be parented to which don't apply
+/// to dynamic children.
+///
+/// For memoization purposes, dynamic children are compared by their
+/// keys. A node's key is its id and revision, so it changes whenever
+/// the node is rebuilt, and text is its own key. `DynChild::keyed()`
+/// sets the key instead, which should change whenever the child does.
+#[derive(Clone)]
+pub struct DynChild {
+    key: ChildKey,
+    child: Arc<dyn NodeChild + Send + Sync>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ChildKey {
+    Node { id: u64, revision: u64 },
+    Text,
+    Keyed(u64),
+}
+
+impl DynChild {
+    pub fn new(child: impl NodeChild + Send + Sync) -> DynChild {
+        let key = match child.get_node() {
+            DynamicNode::Node(node) => ChildKey::Node {
+                id: node.id(),
+                revision: node.revision(),
+            },
+            DynamicNode::Text(_) => ChildKey::Text,
+        };
+        DynChild {
+            key,
+            child: Arc::new(child),
+        }
+    }
+
+    /// A child compared only by `key`, such as the id and version of the
+    /// data it shows, so that two children with the same key are taken
+    /// to be the same, even if they were built separately.
+    pub fn keyed(key: impl Hash, child: impl NodeChild + Send + Sync) -> DynChild {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        DynChild {
+            key: ChildKey::Keyed(hasher.finish()),
+            child: Arc::new(child),
+        }
+    }
+}
+
+impl<Elt> From<Node<Elt>> for DynChild
+where
+    Elt: Element,
+    Node<Elt>: Send + Sync,
+{
+    fn from(node: Node<Elt>) -> Self {
+        DynChild::new(node)
+    }
+}
+
+impl From<String> for DynChild {
+    fn from(text: String) -> Self {
+        DynChild::new(text)
+    }
+}
+
+impl<'a> From<&'a DynChild> for DynamicNode<'a> {
+    fn from(value: &'a DynChild) -> Self {
+        value.get_node()
+    }
+}
+
+impl NodeChild for DynChild {
+    fn get_node(&self) -> DynamicNode {
+        self.child.get_node()
+    }
+}
+
+impl PartialEq for DynChild {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.key, &other.key) {
+            (ChildKey::Text, ChildKey::Text) => match (self.get_node(), other.get_node()) {
+                (DynamicNode::Text(left), DynamicNode::Text(right)) => left == right,
+                _ => false,
+            },
+            (left, right) => left == right,
+        }
+    }
+}
+
+impl Debug for DynChild {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.get_node() {
+            DynamicNode::Node(node) => f
+                .debug_tuple("DynChild")
+                .field(&node.name())
+                .field(&node.id())
+                .finish(),
+            DynamicNode::Text(text) => f.debug_tuple("DynChild").field(&text).finish(),
+        }
+    }
+}
+
+/// Child type for elements which can't have any children.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoChildren {}
+
+impl NodeChild for NoChildren {
+    fn get_node(&self) -> DynamicNode {
+        match *self {}
+    }
+}
+
 pub trait HandlerList: Default + 'static {}
 
 impl HandlerList for () {}
+
+#[cfg(test)]
+mod test {
+    use super::DynChild;
+    use crate::dom::elements::view::ViewChild;
+    use crate::dom::node::NodeRef;
+    use crate::dom::{Button, Node, View};
+
+    #[test]
+    fn dynamic_children() {
+        let button = Node::new(0, Button::default(), vec![]);
+        let view = Node::new(1, View::default(), vec![]);
+        let children: Vec<DynChild> = vec![button.clone().into(), view.into(), button.into()];
+        let parent = Node::new(
+            2,
+            View::default(),
+            children.into_iter().map(ViewChild::from).collect(),
+        );
+
+        let parent_ref: NodeRef = (&parent).into();
+        let names = parent_ref
+            .children()
+            .map(|child| child.node().unwrap().name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["button", "view", "button"]);
+        assert_eq!(parent.children()[0], parent.children()[2]);
+        assert!(parent.children()[0] != parent.children()[1]);
+    }
+
+    #[test]
+    fn dynamic_children_compare_by_key() {
+        // A node rebuilt with the same id, as when its element changes,
+        // is a different child.
+        let button = Node::new(0, Button::default(), vec![]);
+        let rebuilt = Node::new(0, Button::default(), vec![]);
+        assert_eq!(
+            DynChild::from(button.clone()),
+            DynChild::from(button.clone())
+        );
+        assert!(DynChild::from(button) != DynChild::from(rebuilt));
+        assert_eq!(
+            DynChild::from("a".to_owned()),
+            DynChild::from("a".to_owned())
+        );
+        assert!(DynChild::from("a".to_owned()) != DynChild::from("b".to_owned()));
+
+        // Children built separately are the same if their keys are.
+        let keyed = |key, id| DynChild::keyed(key, Node::new(id, View::default(), vec![]));
+        assert_eq!(keyed(("row", 1), 1), keyed(("row", 1), 2));
+        assert!(keyed(("row", 1), 1) != keyed(("row", 2), 1));
+        assert!(keyed(("row", 1), 1) != DynChild::from(Node::new(1, View::default(), vec![])));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::dom::element::Element;
 use crate::dom::{Node, Window};
+use crate::runtime::{RunError, Runtime};
 use crate::style::Style;
 
 /// Corresponds to <app>. This is the root of the DOM and contains
@@ -13,6 +14,16 @@ impl Default for App {
     }
 }
 
+impl App {
+    /// Runs the app built by `root` with the default options, until it
+    /// shuts down or its last window closes, see `Runtime::run()`. This
+    /// is the same as `Runtime::new(root).run()`, so use `Runtime` to
+    /// set options or to drive the app from another loop.
+    pub fn run(root: impl FnMut() -> Node<App> + 'static + Sync + Send) -> Result<(), RunError> {
+        Runtime::new(root).run()
+    }
+}
+
 impl Element for App {
     type Child = Node<Window>;
     type Handlers = ();
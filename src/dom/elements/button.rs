@@ -1,27 +1,58 @@
-use crate::dom::element::{Element, ElementState, ElementStates, HasEvent};
-use crate::dom::input::{InputEvent, State};
-use crate::dom::{AttrStyle, ClickEvent, Node, Span, View};
+use crate::dom::element::{Element, ElementState, ElementStates, HasTextAttributes};
+use crate::dom::input::{InputEvent, Key, State};
+use crate::dom::{
+    AttrAfter, AttrBefore, AttrCancel, AttrClass, AttrDefault, AttrId, AttrPaintOverlay, AttrStyle,
+    AttrTabIndex, AttrTitle, ClickEvent, Image, Node, Span, Svg, View,
+};
+use crate::render::painter::PaintOverlay;
 use crate::style::Style;
-use crate::util::event_handler::EventHandler;
 
 /// Corresponds to <button>. This element can be hovered and pressed,
-/// resulting in corresponding events.
+/// resulting in corresponding events. It can be focused, and while it
+/// is, Space and Enter click it.
+///
+/// In a dialog, the `default` button is clicked by Enter and the
+/// `cancel` button by Escape, wherever focus is in the window. The
+/// focused element goes first: a focused button clicks itself on Enter,
+/// and a focused text area takes Enter for a line break, since text
+/// areas are always multi-line. If several buttons are marked, the
+/// first shown one in tree order is clicked.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Button {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
     style: Option<Style>,
+    default: bool,
+    cancel: bool,
+    tab_index: Option<i32>,
 }
 
 multiple_children! {
     enum ButtonChild {
         Button(Node<Button>),
+        Image(Node<Image>),
         View(Node<View>),
         Span(Node<Span>),
+        Svg(Node<Svg>),
     }
 }
 
 element_attributes! {
     Button {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        before: AttrBefore,
+        after: AttrAfter,
+        paint_overlay: AttrPaintOverlay,
         style: AttrStyle,
+        default: AttrDefault,
+        cancel: AttrCancel,
+        tab_index: AttrTabIndex,
     }
 }
 
@@ -41,6 +72,30 @@ impl Element for Button {
         true
     }
 
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn tab_index(&self) -> Option<i32> {
+        self.tab_index
+    }
+
+    fn key_repeat(&self) -> bool {
+        false
+    }
+
+    fn captures_key(&self, key: Key) -> bool {
+        key == Key::Enter || key == Key::Space
+    }
+
+    fn window_key(&self, key: Key) -> bool {
+        match key {
+            Key::Enter => self.default,
+            Key::Escape => self.cancel,
+            _ => false,
+        }
+    }
+
     fn process(
         &self,
         states: ElementStates,
@@ -64,6 +119,20 @@ impl Element for Button {
                 handlers.on_click.invoke(&ClickEvent);
                 (true, states.difference(ElementState::Press.into()))
             }
+            InputEvent::MouseLeft {
+                state: State::Cancel,
+                ..
+            } => (true, states.difference(ElementState::Press.into())),
+            InputEvent::KeyPressed {
+                key: Key::Enter, ..
+            }
+            | InputEvent::KeyPressed {
+                key: Key::Space, ..
+            }
+            | InputEvent::Activated => {
+                handlers.on_click.invoke(&ClickEvent);
+                (true, states)
+            }
             _ => (false, states),
         }
     }
@@ -71,4 +140,32 @@ impl Element for Button {
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn before(&self) -> Option<&str> {
+        self.before.as_deref()
+    }
+
+    fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
 }
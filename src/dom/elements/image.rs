@@ -0,0 +1,182 @@
+use crate::dom::element::{Element, HasTextAttributes, NoChildren};
+use crate::dom::{AttrClass, AttrId, AttrPaintOverlay, AttrSrc, AttrStyle, AttrTitle};
+use crate::render::painter::{PaintOverlay, Painter};
+use crate::style::Style;
+use crate::Color;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A bitmap for the `src` attribute of an `<image>`. PNG, ICO and GIF
+/// files can be used, and animated GIFs and PNGs play their frames, as
+/// many times as the file asks for, while they're on screen. Images are
+/// decoded on worker threads the first time they're shown, and then
+/// kept for as long as the application runs, so an image which is shown
+/// in several places, or shown again, is only decoded once. Sources are
+/// compared by their contents, so two `Bytes` with the same contents
+/// share the decoded image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImageSource {
+    /// The contents of an image file.
+    Bytes(ImageBytes),
+    /// An image file to read.
+    Path(PathBuf),
+}
+
+/// The contents of an image file, hashed once when they're made rather
+/// than each time the source is looked up, and compared by address
+/// before comparing the contents.
+#[derive(Clone)]
+pub struct ImageBytes {
+    bytes: Arc<[u8]>,
+    hash: u64,
+}
+
+impl From<Arc<[u8]>> for ImageBytes {
+    fn from(bytes: Arc<[u8]>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        ImageBytes {
+            bytes,
+            hash: hasher.finish(),
+        }
+    }
+}
+
+impl Deref for ImageBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl PartialEq for ImageBytes {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.bytes, &other.bytes)
+            || (self.hash == other.hash && self.bytes == other.bytes)
+    }
+}
+
+impl Eq for ImageBytes {}
+
+impl Hash for ImageBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl fmt::Debug for ImageBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ImageBytes({} bytes)", self.bytes.len())
+    }
+}
+
+impl From<&'static [u8]> for ImageSource {
+    fn from(bytes: &'static [u8]) -> Self {
+        ImageSource::Bytes(Arc::<[u8]>::from(bytes).into())
+    }
+}
+
+impl From<Vec<u8>> for ImageSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        ImageSource::Bytes(Arc::<[u8]>::from(bytes).into())
+    }
+}
+
+impl From<Arc<[u8]>> for ImageSource {
+    fn from(bytes: Arc<[u8]>) -> Self {
+        ImageSource::Bytes(bytes.into())
+    }
+}
+
+impl From<PathBuf> for ImageSource {
+    fn from(path: PathBuf) -> Self {
+        ImageSource::Path(path)
+    }
+}
+
+impl<'a> From<&'a Path> for ImageSource {
+    fn from(path: &'a Path) -> Self {
+        ImageSource::Path(path.to_owned())
+    }
+}
+
+/// Corresponds to <image>. Displays the bitmap set with the `src`
+/// attribute, scaled to fit the element's content box while keeping
+/// its aspect ratio. Like `<svg>`, the element has no size of its own,
+/// so its width and height should be set with a style.
+///
+/// Decoding happens off the main thread, and until it's done, a
+/// translucent gray placeholder fills the content box. The window is
+/// repainted with the image once it's ready. If it can't be decoded, a
+/// warning is logged and the placeholder stays.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Image {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
+    style: Option<Style>,
+    src: Option<ImageSource>,
+}
+
+element_attributes! {
+    Image {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        paint_overlay: AttrPaintOverlay,
+        style: AttrStyle,
+        src: AttrSrc,
+    }
+}
+
+impl Element for Image {
+    type Child = NoChildren;
+    type Handlers = ();
+
+    const ELEMENT_NAME: &'static str = "image";
+
+    fn style(&self) -> Option<Style> {
+        self.style
+    }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn image_source(&self) -> Option<&ImageSource> {
+        self.src.as_ref()
+    }
+
+    fn draw(&self, painter: &mut Painter) {
+        let source = match self.src {
+            Some(ref source) => source,
+            None => return,
+        };
+        let content = painter.content_rect();
+        if !painter.image(content, source) {
+            painter.fill_rect(content, Color::new(128, 128, 128, 64));
+        }
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
+}
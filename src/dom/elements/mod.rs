@@ -1,5 +1,6 @@
 /// Defines an enum over multiple types that implement NodeChild and
-/// then implements NodeChild for that enum.
+/// then implements NodeChild for that enum, along with FromBuiltChild
+/// so that it can be built from a spec.
 macro_rules! multiple_children {
     (enum $name:ident { $( $var_name:ident ( $var_ty:ty ) ),+ $(,)* }) => {
         #[derive(Clone, Debug, PartialEq)]
@@ -26,6 +27,17 @@ macro_rules! multiple_children {
                 }
             }
         }
+
+        impl $crate::mox_impl::spec::FromBuiltChild for $name {
+            fn from_built_child(child: &$crate::mox_impl::spec::BuiltChild) -> Option<Self> {
+                $(
+                    if let Some(elt) = child.get::<$var_ty>() {
+                        return Some($name::$var_name(elt));
+                    }
+                )+
+                None
+            }
+        }
     }
 }
 
@@ -38,26 +50,148 @@ macro_rules! element_attributes {
                 }
             }
         )+
+
+        impl $crate::dom::element::HasTextAttributes for $element {
+            fn set_text_attribute(
+                &mut self,
+                name: &str,
+                value: &str,
+            ) -> Result<(), $crate::dom::element::AttributeError> {
+                use $crate::dom::element::{AttributeError, FromAttributeText, HasAttribute};
+                match name {
+                    $(
+                        stringify!($name) => {
+                            let parsed = FromAttributeText::from_attribute_text(value).ok_or_else(|| {
+                                AttributeError::Invalid {
+                                    name: name.to_owned(),
+                                    value: value.to_owned(),
+                                }
+                            })?;
+                            <Self as HasAttribute<$class>>::set_attribute(self, parsed);
+                            Ok(())
+                        }
+                    )+
+                    _ => Err(AttributeError::Unknown {
+                        name: name.to_owned(),
+                    }),
+                }
+            }
+
+            fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+                let mut changed = vec![];
+                $(
+                    if self.$name != other.$name {
+                        changed.push(stringify!($name));
+                    }
+                )+
+                changed
+            }
+        }
     };
 }
 
+/// Defines the struct which stores the event handlers of an element,
+/// with a field for each event, and implements `HasEvent` for each of
+/// them so they can be registered with `Builder::on_event()`. The
+/// built-in elements use this too, so elements from other crates take
+/// part in events the same way.
+///
+/// An element invokes its handlers from `Element::process()`, in
+/// response to the input it's sent, or they can be invoked from
+/// outside with `Node::emit()`. Events are marked by a function
+/// returning `PhantomData` of the event, like the ones in `event`, to
+/// pass to `on_event()`.
+///
+/// ```
+/// use moxie_native::dom::element::{Element, ElementStates, Event, NoChildren};
+/// use moxie_native::dom::input::{InputEvent, Key};
+/// use moxie_native::mox_impl::Builder;
+/// use moxie_native::style::Style;
+/// use std::marker::PhantomData;
+/// use std::sync::{Arc, Mutex};
+///
+/// /// Fired when the knob is turned, by the arrow keys while it's
+/// /// focused.
+/// pub struct TurnEvent {
+///     pub steps: i32,
+/// }
+/// impl Event for TurnEvent {}
+///
+/// pub fn on_turn() -> PhantomData<TurnEvent> {
+///     PhantomData
+/// }
+///
+/// #[derive(Default, Clone, Debug, PartialEq)]
+/// pub struct Knob;
+///
+/// moxie_native::element_handlers! {
+///     KnobHandlers for Knob {
+///         on_turn: TurnEvent,
+///     }
+/// }
+///
+/// impl Element for Knob {
+///     type Child = NoChildren;
+///     type Handlers = KnobHandlers;
+///
+///     const ELEMENT_NAME: &'static str = "knob";
+///
+///     fn style(&self) -> Option<Style> {
+///         None
+///     }
+///
+///     fn focusable(&self) -> bool {
+///         true
+///     }
+///
+///     fn process(
+///         &self,
+///         states: ElementStates,
+///         handlers: &mut KnobHandlers,
+///         event: &InputEvent,
+///     ) -> (bool, ElementStates) {
+///         let steps = match event {
+///             InputEvent::KeyPressed { key: Key::Up, .. } => 1,
+///             InputEvent::KeyPressed { key: Key::Down, .. } => -1,
+///             _ => return (false, states),
+///         };
+///         handlers.on_turn.invoke(&TurnEvent { steps });
+///         (true, states)
+///     }
+/// }
+///
+/// let turned = Arc::new(Mutex::new(0));
+/// let log = turned.clone();
+/// let knob = Builder::<Knob>::new()
+///     .on_event(on_turn(), move |event| *log.lock().unwrap() += event.steps)
+///     .build();
+/// knob.emit(&TurnEvent { steps: 3 });
+/// assert_eq!(*turned.lock().unwrap(), 3);
+/// ```
+///
+/// Built-in elements only take other built-in elements as children, so
+/// a custom element is added to a `<view>` as a `DynChild`.
+#[macro_export]
 macro_rules! element_handlers {
     ( $handler_name:ident for $element:ty { $( $(#[$meta:meta])* $name:ident : $class:ty ),+ $(,)* } ) => {
         #[derive(Default)]
         pub struct $handler_name {
             $(
                 $(#[$meta])*
-                $name : EventHandler<$class>
+                $name : $crate::dom::element::EventHandler<$class>
             ),+
         }
 
         $(
-            impl HasEvent<$class> for $element {
-                fn set_handler(list: &mut $handler_name, handler: EventHandler<$class>) {
+            impl $crate::dom::element::HasEvent<$class> for $element {
+                fn set_handler(
+                    list: &mut $handler_name,
+                    handler: $crate::dom::element::EventHandler<$class>,
+                ) {
                     list.$name = handler;
                 }
 
-                fn get_handler(list: &$handler_name) -> &EventHandler<$class> {
+                fn get_handler(list: &$handler_name) -> &$crate::dom::element::EventHandler<$class> {
                     &list.$name
                 }
             }
@@ -69,6 +203,99 @@ macro_rules! element_handlers {
 
 pub mod app;
 pub mod button;
+pub mod image;
+pub mod spacer;
 pub mod span;
+pub mod svg;
+pub mod text_area;
 pub mod view;
 pub mod window;
+
+#[cfg(test)]
+mod test {
+    use crate::document::Document;
+    use crate::dom::element::{DynChild, Element, ElementStates, Event, NoChildren};
+    use crate::dom::input::{InputEvent, Key};
+    use crate::mox_impl::{elt, Builder};
+    use crate::style::Style;
+    use euclid::size2;
+    use moxie::runtime::Runtime;
+    use std::marker::PhantomData;
+    use std::sync::{Arc, Mutex};
+
+    // Defined only through the public API, as another crate would.
+    struct TurnEvent {
+        steps: i32,
+    }
+    impl Event for TurnEvent {}
+
+    fn on_turn() -> PhantomData<TurnEvent> {
+        PhantomData
+    }
+
+    #[derive(Default, Clone, Debug, PartialEq)]
+    struct Knob;
+
+    crate::element_handlers! {
+        KnobHandlers for Knob {
+            on_turn: TurnEvent,
+        }
+    }
+
+    impl Element for Knob {
+        type Child = NoChildren;
+        type Handlers = KnobHandlers;
+
+        const ELEMENT_NAME: &'static str = "knob";
+
+        fn style(&self) -> Option<Style> {
+            None
+        }
+
+        fn focusable(&self) -> bool {
+            true
+        }
+
+        fn process(
+            &self,
+            states: ElementStates,
+            handlers: &mut KnobHandlers,
+            event: &InputEvent,
+        ) -> (bool, ElementStates) {
+            let steps = match event {
+                InputEvent::KeyPressed { key: Key::Up, .. } => 1,
+                InputEvent::KeyPressed { key: Key::Down, .. } => -1,
+                _ => return (false, states),
+            };
+            handlers.on_turn.invoke(&TurnEvent { steps });
+            (true, states)
+        }
+    }
+
+    #[test]
+    fn custom_element_receives_events() {
+        let turns = Arc::new(Mutex::new(vec![]));
+        let log = turns.clone();
+        let (window, knob) = Runtime::new().run_once(|| {
+            let knob = Builder::<Knob>::new()
+                .on_event(on_turn(), move |event| {
+                    log.lock().unwrap().push(event.steps)
+                })
+                .build();
+            let view = elt::view().add_child(DynChild::from(knob.clone())).build();
+            (elt::window().add_child(view).build(), knob)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+
+        // Dispatched by the document to the focused knob.
+        document.key_pressed(Key::Tab);
+        assert_eq!(document.focused(), Some(knob.id()));
+        assert!(document.key_pressed(Key::Up));
+        document.key_pressed(Key::Down);
+
+        // Emitted from outside, without any input.
+        knob.emit(&TurnEvent { steps: 5 });
+        assert_eq!(*turns.lock().unwrap(), vec![1, -1, 5]);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::dom::element::{Element, HasTextAttributes, NoChildren};
+use crate::dom::{AttrClass, AttrId, AttrStyle};
+use crate::style::{BlockValues, ComputedValues, DisplayType, PaintDetails, Style};
+
+/// Corresponds to <spacer>. Empty space which grows to fill whatever
+/// is left along its parent's direction, for pushing items apart, like
+/// the ends of a toolbar. It has `flex_grow: 1` by default, so space is
+/// shared evenly between spacers. A `min_width` or `min_height` in its
+/// style keeps it from shrinking below that size.
+///
+/// Space is only handed out when the parent has a fixed size along its
+/// direction. A spacer is never painted, whatever its style.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Spacer {
+    id: Option<String>,
+    class: Option<String>,
+    style: Option<Style>,
+}
+
+element_attributes! {
+    Spacer {
+        id: AttrId,
+        class: AttrClass,
+        style: AttrStyle,
+    }
+}
+
+impl Element for Spacer {
+    type Child = NoChildren;
+    type Handlers = ();
+
+    const ELEMENT_NAME: &'static str = "spacer";
+
+    fn create_computed_values(&self) -> ComputedValues {
+        ComputedValues {
+            display: DisplayType::Block(BlockValues {
+                flex_grow: 1.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn paint(&self, _values: &ComputedValues) -> PaintDetails {
+        PaintDetails::new()
+    }
+
+    fn style(&self) -> Option<Style> {
+        self.style
+    }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
+}
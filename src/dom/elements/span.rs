@@ -1,16 +1,33 @@
-use crate::dom::element::Element;
-use crate::dom::{AttrStyle, Button, Node, View};
+use crate::dom::element::{DynChild, Element, HasTextAttributes};
+use crate::dom::{
+    AttrAfter, AttrBefore, AttrClass, AttrId, AttrPaintOverlay, AttrStyle, AttrTitle, Button, Node,
+    View,
+};
+use crate::render::painter::PaintOverlay;
 use crate::style::{ComputedValues, DisplayType, InlineValues, Style};
 
 /// Corresponds to <span>. This element is typically used for inline
-/// layout of text.
+/// layout of text. Newlines in the text break it into lines, like they
+/// do in a `text_area`.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Span {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
     style: Option<Style>,
 }
 
 element_attributes! {
     Span {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        before: AttrBefore,
+        after: AttrAfter,
+        paint_overlay: AttrPaintOverlay,
         style: AttrStyle,
     }
 }
@@ -21,6 +38,7 @@ multiple_children! {
         Button(Node<Button>),
         View(Node<View>),
         Span(Node<Span>),
+        Dynamic(DynChild),
     }
 }
 
@@ -40,4 +58,32 @@ impl Element for Span {
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn before(&self) -> Option<&str> {
+        self.before.as_deref()
+    }
+
+    fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
 }
@@ -0,0 +1,1035 @@
+use crate::dom::element::{Element, HasTextAttributes, NoChildren};
+use crate::dom::{
+    AttrClass, AttrFill, AttrId, AttrPaintOverlay, AttrSource, AttrStroke, AttrStyle, AttrTitle,
+};
+use crate::layout::{LogicalPixel, LogicalPoint, LogicalRect, LogicalSize};
+use crate::render::painter::{PaintOverlay, Painter};
+use crate::runtime::DecodedImage;
+use crate::style::{Border, BorderStyle, Style};
+use crate::Color;
+use euclid::{point2, size2, vec2, Vector2D};
+use log::warn;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+type LogicalVector = Vector2D<f32, LogicalPixel>;
+
+/// Returned when an SVG document can't be parsed. `offset` is the byte
+/// offset of the tag which couldn't be read.
+#[derive(Debug, PartialEq)]
+pub struct ParseSvgError {
+    pub offset: usize,
+}
+
+impl fmt::Display for ParseSvgError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid SVG at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for ParseSvgError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ShapeKind {
+    Rect { rect: LogicalRect, radius: f32 },
+    Circle { center: LogicalPoint, radius: f32 },
+    Path(Arc<PathData>),
+}
+
+/// What a path's raster depends on besides the path itself.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct RasterKey {
+    scale: f32,
+    offset: LogicalVector,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+}
+
+type Raster = Option<(LogicalRect, Arc<DecodedImage>)>;
+
+/// A `<path>`, with its curves flattened into straight segments. There
+/// are no path primitives to draw it with, so it's rasterized into an
+/// image, which is kept until the path is drawn at another size or in
+/// other colors.
+#[derive(Debug)]
+struct PathData {
+    /// The points of each subpath, and whether it was closed with `Z`.
+    subpaths: Vec<(Vec<LogicalPoint>, bool)>,
+    even_odd: bool,
+    raster: Mutex<Option<(RasterKey, Raster)>>,
+}
+
+impl PartialEq for PathData {
+    fn eq(&self, other: &Self) -> bool {
+        self.subpaths == other.subpaths && self.even_odd == other.even_odd
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Shape {
+    kind: ShapeKind,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_width: f32,
+}
+
+#[derive(Debug, PartialEq)]
+struct SvgData {
+    view_box: Option<LogicalRect>,
+    shapes: Vec<Shape>,
+}
+
+/// A parsed SVG image, for displaying with the `<svg>` element.
+///
+/// Only a small subset of SVG is supported, which covers simple icons:
+/// `<rect>` (including `rx`), `<circle>` and `<path>`, with the `fill`,
+/// `stroke` and `stroke-width` attributes set on each shape, and
+/// `fill-rule` on paths. Path data can use the `M`, `L`, `H`, `V`, `C`,
+/// `Q` and `Z` commands, and their relative forms. `<g>` is allowed,
+/// but its attributes aren't inherited. Colors can be named, `#rgb`,
+/// `#rrggbb` or `rgb(r, g, b)`. Arcs and smooth curves in paths, other
+/// shapes, transforms, gradients, text and the `style` attribute aren't
+/// supported, and unsupported elements are skipped with a warning.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgImage(Arc<SvgData>);
+
+impl SvgImage {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<SvgImage> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    offset: usize,
+}
+
+impl<'a> Tag<'a> {
+    fn get(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    }
+
+    fn number(&self, name: &str) -> Result<f32, ParseSvgError> {
+        match self.get(name) {
+            Some(value) => parse_number(value).ok_or(ParseSvgError {
+                offset: self.offset,
+            }),
+            None => Ok(0.0),
+        }
+    }
+
+    fn color(&self, name: &str, default: Option<Color>) -> Result<Option<Color>, ParseSvgError> {
+        match self.get(name) {
+            Some(value) => parse_color(value).ok_or(ParseSvgError {
+                offset: self.offset,
+            }),
+            None => Ok(default),
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    let value = value.trim();
+    value.strip_suffix("px").unwrap_or(value).parse().ok()
+}
+
+/// Parses a paint color, where `none` is `Some(None)`.
+fn parse_color(value: &str) -> Option<Option<Color>> {
+    let value = value.trim();
+    let color = match value {
+        "none" => return Some(None),
+        "black" => Color::black(),
+        "white" => Color::white(),
+        "red" => Color::new(255, 0, 0, 255),
+        "green" => Color::new(0, 128, 0, 255),
+        "blue" => Color::new(0, 0, 255, 255),
+        "gray" | "grey" => Color::new(128, 128, 128, 255),
+        "transparent" => Color::clear(),
+        _ if value.starts_with('#') => {
+            let digits = &value[1..];
+            let channel = |index: usize, width: usize| {
+                let digits = digits.get(index * width..(index + 1) * width)?;
+                let value = u8::from_str_radix(digits, 16).ok()?;
+                Some(if width == 1 { value * 17 } else { value })
+            };
+            let width = match digits.len() {
+                3 => 1,
+                6 => 2,
+                _ => return None,
+            };
+            Color::new(
+                channel(0, width)?,
+                channel(1, width)?,
+                channel(2, width)?,
+                255,
+            )
+        }
+        _ if value.starts_with("rgb(") && value.ends_with(')') => {
+            let channels = value[4..value.len() - 1]
+                .split(',')
+                .map(|channel| channel.trim().parse().ok())
+                .collect::<Option<Vec<u8>>>()
+                .filter(|channels| channels.len() == 3)?;
+            Color::new(channels[0], channels[1], channels[2], 255)
+        }
+        _ => return None,
+    };
+    Some(Some(color))
+}
+
+/// Reads the numbers and commands of a path's `d` attribute, where
+/// numbers can be separated by spaces, commas or just their signs and
+/// decimal points, as in `1-2.5.5`.
+struct PathTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> PathTokens<'a> {
+    fn skip_separators(&mut self) {
+        self.rest = self
+            .rest
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.rest.is_empty()
+    }
+
+    fn command(&mut self) -> Option<char> {
+        self.skip_separators();
+        let command = self.rest.chars().next().filter(char::is_ascii_alphabetic)?;
+        self.rest = &self.rest[1..];
+        Some(command)
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let bytes = self.rest.as_bytes();
+        let digits = |mut end: usize| {
+            while bytes.get(end).map_or(false, u8::is_ascii_digit) {
+                end += 1;
+            }
+            end
+        };
+        let mut end = match bytes.first() {
+            Some(b'+') | Some(b'-') => 1,
+            _ => 0,
+        };
+        end = digits(end);
+        if bytes.get(end) == Some(&b'.') {
+            end = digits(end + 1);
+        }
+        if let Some(b'e') | Some(b'E') = bytes.get(end) {
+            let mut exponent = end + 1;
+            if let Some(b'+') | Some(b'-') = bytes.get(exponent) {
+                exponent += 1;
+            }
+            if bytes.get(exponent).map_or(false, u8::is_ascii_digit) {
+                end = digits(exponent);
+            }
+        }
+        let number = self.rest[..end].parse().ok()?;
+        self.rest = &self.rest[end..];
+        Some(number)
+    }
+
+    fn point(&mut self) -> Option<LogicalPoint> {
+        Some(point2(self.number()?, self.number()?))
+    }
+}
+
+/// How many straight segments each curve in a path is flattened into.
+const CURVE_SEGMENTS: usize = 16;
+
+/// Parses the `d` attribute of a `<path>` into its subpaths.
+fn parse_path(data: &str) -> Option<Vec<(Vec<LogicalPoint>, bool)>> {
+    fn line_to(points: &mut Vec<LogicalPoint>, from: LogicalPoint, to: LogicalPoint) {
+        // A subpath which is continued after `Z` starts again from
+        // where the closed one started.
+        if points.is_empty() {
+            points.push(from);
+        }
+        points.push(to);
+    }
+
+    let mut tokens = PathTokens { rest: data };
+    let mut subpaths = vec![];
+    let mut points = vec![];
+    let mut position = LogicalPoint::zero();
+    let mut start = position;
+    let mut command = None;
+    loop {
+        let next = match tokens.command() {
+            Some(next) => next,
+            None if tokens.at_end() => break,
+            // More numbers repeat the last command, except that the
+            // ones after a move are lines.
+            None => match command? {
+                'M' => 'L',
+                'm' => 'l',
+                'Z' | 'z' => return None,
+                other => other,
+            },
+        };
+        if command.is_none() && next != 'M' && next != 'm' {
+            return None;
+        }
+        command = Some(next);
+
+        let origin = if next.is_ascii_lowercase() {
+            position.to_vector()
+        } else {
+            LogicalVector::zero()
+        };
+        match next.to_ascii_uppercase() {
+            'M' => {
+                if points.len() > 1 {
+                    subpaths.push((std::mem::take(&mut points), false));
+                }
+                position = tokens.point()? + origin;
+                start = position;
+                points = vec![position];
+            }
+            'L' => {
+                let to = tokens.point()? + origin;
+                line_to(&mut points, start, to);
+                position = to;
+            }
+            'H' => {
+                let to = point2(tokens.number()? + origin.x, position.y);
+                line_to(&mut points, start, to);
+                position = to;
+            }
+            'V' => {
+                let to = point2(position.x, tokens.number()? + origin.y);
+                line_to(&mut points, start, to);
+                position = to;
+            }
+            'C' => {
+                let (first, second) = (tokens.point()? + origin, tokens.point()? + origin);
+                let to = tokens.point()? + origin;
+                let from = position;
+                for step in 1..=CURVE_SEGMENTS {
+                    let t = step as f32 / CURVE_SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    let point = from.to_vector() * (u * u * u)
+                        + first.to_vector() * (3.0 * u * u * t)
+                        + second.to_vector() * (3.0 * u * t * t)
+                        + to.to_vector() * (t * t * t);
+                    line_to(&mut points, start, point.to_point());
+                }
+                position = to;
+            }
+            'Q' => {
+                let (control, to) = (tokens.point()? + origin, tokens.point()? + origin);
+                let from = position;
+                for step in 1..=CURVE_SEGMENTS {
+                    let t = step as f32 / CURVE_SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    let point = from.to_vector() * (u * u)
+                        + control.to_vector() * (2.0 * u * t)
+                        + to.to_vector() * (t * t);
+                    line_to(&mut points, start, point.to_point());
+                }
+                position = to;
+            }
+            'Z' => {
+                if points.len() > 1 {
+                    subpaths.push((std::mem::take(&mut points), true));
+                }
+                points.clear();
+                position = start;
+            }
+            _ => return None,
+        }
+    }
+    if points.len() > 1 {
+        subpaths.push((points, false));
+    }
+    Some(subpaths)
+}
+
+fn parse_tags(source: &str) -> Result<Vec<Tag>, ParseSvgError> {
+    let mut tags = vec![];
+    let mut offset = 0;
+    while let Some(start) = source[offset..].find('<') {
+        offset += start;
+        let rest = &source[offset..];
+        let error = ParseSvgError { offset };
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").ok_or(error)?;
+            offset += end + 3;
+            continue;
+        }
+        if rest.starts_with("</") || rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>').ok_or(error)?;
+            offset += end + 1;
+            continue;
+        }
+
+        let end = rest.find('>').ok_or(ParseSvgError { offset })?;
+        let mut body = rest[1..end].trim_end();
+        body = body.strip_suffix('/').unwrap_or(body);
+        let name_end = body
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or_else(|| body.len());
+        let name = &body[..name_end];
+        let mut attributes = vec![];
+        let mut remaining = body[name_end..].trim_start();
+        while !remaining.is_empty() {
+            let equals = remaining.find('=').ok_or(ParseSvgError { offset })?;
+            let key = remaining[..equals].trim();
+            let value = remaining[equals + 1..].trim_start();
+            let quote = value.chars().next().ok_or(ParseSvgError { offset })?;
+            if quote != '"' && quote != '\'' {
+                return Err(ParseSvgError { offset });
+            }
+            let close = value[1..].find(quote).ok_or(ParseSvgError { offset })?;
+            attributes.push((key, &value[1..close + 1]));
+            remaining = value[close + 2..].trim_start();
+        }
+        if name.is_empty() {
+            return Err(ParseSvgError { offset });
+        }
+        tags.push(Tag {
+            name,
+            attributes,
+            offset,
+        });
+        offset += end + 1;
+    }
+    Ok(tags)
+}
+
+impl FromStr for SvgImage {
+    type Err = ParseSvgError;
+
+    fn from_str(source: &str) -> Result<SvgImage, ParseSvgError> {
+        let mut view_box = None;
+        let mut shapes = vec![];
+        for tag in parse_tags(source)? {
+            let fill = tag.color("fill", Some(Color::black()))?;
+            let stroke = tag.color("stroke", None)?;
+            let stroke_width = match tag.get("stroke-width") {
+                Some(_) => tag.number("stroke-width")?,
+                None => 1.0,
+            };
+            let kind = match tag.name {
+                "svg" => {
+                    view_box = match tag.get("viewBox") {
+                        Some(value) => {
+                            let numbers = value
+                                .split(|c: char| c == ',' || c.is_whitespace())
+                                .filter(|number| !number.is_empty())
+                                .map(parse_number)
+                                .collect::<Option<Vec<_>>>()
+                                .filter(|numbers| numbers.len() == 4)
+                                .ok_or(ParseSvgError { offset: tag.offset })?;
+                            Some(LogicalRect::new(
+                                point2(numbers[0], numbers[1]),
+                                size2(numbers[2], numbers[3]),
+                            ))
+                        }
+                        None if tag.get("width").is_some() && tag.get("height").is_some() => {
+                            Some(LogicalRect::new(
+                                LogicalPoint::zero(),
+                                size2(tag.number("width")?, tag.number("height")?),
+                            ))
+                        }
+                        None => None,
+                    };
+                    continue;
+                }
+                "g" => continue,
+                "rect" => ShapeKind::Rect {
+                    rect: LogicalRect::new(
+                        point2(tag.number("x")?, tag.number("y")?),
+                        size2(tag.number("width")?, tag.number("height")?),
+                    ),
+                    radius: tag.number("rx")?,
+                },
+                "circle" => ShapeKind::Circle {
+                    center: point2(tag.number("cx")?, tag.number("cy")?),
+                    radius: tag.number("r")?,
+                },
+                "path" => {
+                    let error = || ParseSvgError { offset: tag.offset };
+                    let subpaths = parse_path(tag.get("d").unwrap_or("")).ok_or_else(error)?;
+                    let even_odd = match tag.get("fill-rule") {
+                        None | Some("nonzero") => false,
+                        Some("evenodd") => true,
+                        Some(_) => return Err(error()),
+                    };
+                    ShapeKind::Path(Arc::new(PathData {
+                        subpaths,
+                        even_odd,
+                        raster: Mutex::new(None),
+                    }))
+                }
+                name => {
+                    warn!("Skipping unsupported SVG element <{}>", name);
+                    continue;
+                }
+            };
+            shapes.push(Shape {
+                kind,
+                fill,
+                stroke,
+                stroke_width,
+            });
+        }
+        Ok(SvgImage(Arc::new(SvgData { view_box, shapes })))
+    }
+}
+
+/// The most pixels a path is rasterized into, beyond which it isn't
+/// drawn.
+const MAX_RASTER_PIXELS: usize = 4096 * 4096;
+
+/// How many rows are sampled within each row of pixels when filling a
+/// path. Coverage along each row is exact.
+const FILL_SAMPLES: usize = 4;
+
+impl PathData {
+    /// The path's raster for `key`, reusing the last one when it's for
+    /// the same key.
+    fn raster(&self, key: RasterKey, stroke_width: f32) -> Raster {
+        let mut cached = self.raster.lock().unwrap();
+        match *cached {
+            Some((cached_key, ref raster)) if cached_key == key => raster.clone(),
+            _ => {
+                let raster = self.rasterize(key, stroke_width);
+                *cached = Some((key, raster.clone()));
+                raster
+            }
+        }
+    }
+
+    /// Rasterizes the path, scaled and moved as `key` says, into an
+    /// image of whole pixels covering it. This is `None` when nothing
+    /// would be drawn.
+    fn rasterize(&self, key: RasterKey, stroke_width: f32) -> Raster {
+        let half = match key.stroke {
+            Some(_) => stroke_width * key.scale / 2.0,
+            None => 0.0,
+        };
+        let transform =
+            |point: &LogicalPoint| (point.to_vector() * key.scale + key.offset).to_point();
+        let mut min = point2(f32::INFINITY, f32::INFINITY);
+        let mut max = point2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for point in self.subpaths.iter().flat_map(|(points, _)| points) {
+            let point = transform(point);
+            min = min.min(point);
+            max = max.max(point);
+        }
+        if min.x > max.x {
+            return None;
+        }
+        let min = (min - vec2(half, half)).floor();
+        let max = (max + vec2(half, half)).ceil();
+        let (width, height) = ((max.x - min.x) as usize, (max.y - min.y) as usize);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        if width * height > MAX_RASTER_PIXELS {
+            warn!(
+                "Not drawing an SVG path {}x{} pixels in size",
+                width, height
+            );
+            return None;
+        }
+
+        let subpaths = self
+            .subpaths
+            .iter()
+            .map(|(points, closed)| {
+                let points = points
+                    .iter()
+                    .map(|point| transform(point) - min.to_vector());
+                (points.collect(), *closed)
+            })
+            .collect::<Vec<_>>();
+        let mut layers = vec![];
+        if let Some(fill) = key.fill {
+            layers.push((fill, fill_coverage(&subpaths, width, height, self.even_odd)));
+        }
+        if let Some(stroke) = key.stroke {
+            layers.push((stroke, stroke_coverage(&subpaths, width, height, half)));
+        }
+
+        // Strokes are drawn over fills, in premultiplied alpha.
+        let mut rgba = vec![0; width * height * 4];
+        for (index, pixel) in rgba.chunks_mut(4).enumerate() {
+            let mut color = [0.0; 4];
+            for (layer, coverage) in &layers {
+                let alpha = layer.alpha as f32 / 255.0 * coverage[index].min(1.0);
+                let channels = [layer.red, layer.green, layer.blue, 255];
+                for (value, channel) in color.iter_mut().zip(&channels) {
+                    *value = *channel as f32 / 255.0 * alpha + *value * (1.0 - alpha);
+                }
+            }
+            for (byte, value) in pixel.iter_mut().zip(&color) {
+                *byte = (value * 255.0).round() as u8;
+            }
+        }
+        if rgba.chunks(4).all(|pixel| pixel[3] == 0) {
+            return None;
+        }
+        let image = DecodedImage {
+            rgba,
+            width: width as u32,
+            height: height as u32,
+        };
+        Some((
+            LogicalRect::new(min, size2(width as f32, height as f32)),
+            Arc::new(image),
+        ))
+    }
+}
+
+/// The fraction of each pixel inside the subpaths, which are closed
+/// if they aren't already.
+fn fill_coverage(
+    subpaths: &[(Vec<LogicalPoint>, bool)],
+    width: usize,
+    height: usize,
+    even_odd: bool,
+) -> Vec<f32> {
+    let mut coverage = vec![0.0; width * height];
+    let mut crossings = vec![];
+    for row in 0..height * FILL_SAMPLES {
+        let y = (row as f32 + 0.5) / FILL_SAMPLES as f32;
+        crossings.clear();
+        for (points, _) in subpaths {
+            let edges = points
+                .iter()
+                .zip(points.iter().skip(1).chain(points.first()));
+            for (from, to) in edges {
+                if (from.y <= y) != (to.y <= y) {
+                    let x = from.x + (y - from.y) / (to.y - from.y) * (to.x - from.x);
+                    crossings.push((x, if to.y > from.y { 1 } else { -1 }));
+                }
+            }
+        }
+        crossings.sort_by(|a: &(f32, i32), b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let line = &mut coverage[row / FILL_SAMPLES * width..][..width];
+        let mut winding = 0;
+        for (index, &(from, direction)) in crossings.iter().enumerate() {
+            winding += direction;
+            let inside = if even_odd {
+                winding % 2 != 0
+            } else {
+                winding != 0
+            };
+            if let (true, Some(&(to, _))) = (inside, crossings.get(index + 1)) {
+                add_span(line, from, to, 1.0 / FILL_SAMPLES as f32);
+            }
+        }
+    }
+    coverage
+}
+
+/// Adds `weight` to the pixels in `line`, in proportion to how much of
+/// each is between `from` and `to`.
+fn add_span(line: &mut [f32], from: f32, to: f32, weight: f32) {
+    let (from, to) = (from.max(0.0), to.min(line.len() as f32));
+    if from >= to {
+        return;
+    }
+    let (start, end) = (from as usize, to.ceil() as usize);
+    for (x, pixel) in line.iter_mut().enumerate().take(end).skip(start) {
+        let overlap = to.min(x as f32 + 1.0) - from.max(x as f32);
+        *pixel += overlap * weight;
+    }
+}
+
+/// The fraction of each pixel within `half` of the subpaths' segments,
+/// which gives strokes round joins and caps.
+fn stroke_coverage(
+    subpaths: &[(Vec<LogicalPoint>, bool)],
+    width: usize,
+    height: usize,
+    half: f32,
+) -> Vec<f32> {
+    let mut coverage = vec![0.0f32; width * height];
+    let reach = half + 0.5;
+    for (points, closed) in subpaths {
+        let closing = points.last().zip(points.first()).filter(|_| *closed);
+        let segments = points.iter().zip(points.iter().skip(1)).chain(closing);
+        for (from, to) in segments {
+            let start = (from.min(*to) - vec2(reach, reach))
+                .floor()
+                .max(point2(0.0, 0.0));
+            let end = (from.max(*to) + vec2(reach, reach)).ceil();
+            let end_x = (end.x.max(0.0) as usize).min(width);
+            for y in start.y as usize..(end.y.max(0.0) as usize).min(height) {
+                for x in start.x as usize..end_x {
+                    let center = point2(x as f32 + 0.5, y as f32 + 0.5);
+                    let distance = distance_to_segment(center, *from, *to);
+                    let pixel = &mut coverage[y * width + x];
+                    *pixel = pixel.max(reach - distance);
+                }
+            }
+        }
+    }
+    coverage
+}
+
+fn distance_to_segment(point: LogicalPoint, from: LogicalPoint, to: LogicalPoint) -> f32 {
+    let along = to - from;
+    let length = along.square_length();
+    let t = if length > 0.0 {
+        ((point - from).dot(along) / length).max(0.0).min(1.0)
+    } else {
+        0.0
+    };
+    (from + along * t - point).length()
+}
+
+/// Corresponds to <svg>. Displays an `SvgImage`, set with the `source`
+/// attribute, scaled to fit the element's content box while keeping
+/// its aspect ratio. The element has no size of its own, so its width
+/// and height should be set with a style. The `fill` and `stroke`
+/// attributes replace the colors of every shape which is filled or
+/// stroked, for recoloring icons.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Svg {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
+    style: Option<Style>,
+    source: Option<SvgImage>,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+}
+
+element_attributes! {
+    Svg {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        paint_overlay: AttrPaintOverlay,
+        style: AttrStyle,
+        source: AttrSource,
+        fill: AttrFill,
+        stroke: AttrStroke,
+    }
+}
+
+impl Element for Svg {
+    type Child = NoChildren;
+    type Handlers = ();
+
+    const ELEMENT_NAME: &'static str = "svg";
+
+    fn style(&self) -> Option<Style> {
+        self.style
+    }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn draw(&self, painter: &mut Painter) {
+        let image = match self.source {
+            Some(ref image) => &image.0,
+            None => return,
+        };
+        let content = painter.content_rect();
+        let view_box = image
+            .view_box
+            .unwrap_or_else(|| LogicalRect::new(LogicalPoint::zero(), content.size));
+        if view_box.is_empty() {
+            return;
+        }
+        let scale = (content.size.width / view_box.size.width)
+            .min(content.size.height / view_box.size.height);
+        let scaled = view_box.size * scale;
+        let offset = content.origin.to_vector()
+            + vec2(
+                (content.size.width - scaled.width) / 2.0,
+                (content.size.height - scaled.height) / 2.0,
+            )
+            - view_box.origin.to_vector() * scale;
+        let transform = |rect: LogicalRect| {
+            LogicalRect::new(
+                (rect.origin.to_vector() * scale + offset).to_point(),
+                rect.size * scale,
+            )
+        };
+
+        for shape in &image.shapes {
+            let (rect, radius) = match shape.kind {
+                ShapeKind::Rect { rect, radius } => (rect, radius),
+                ShapeKind::Circle { center, radius } => (
+                    LogicalRect::new(
+                        center - vec2(radius, radius),
+                        LogicalSize::new(radius * 2.0, radius * 2.0),
+                    ),
+                    radius,
+                ),
+                ShapeKind::Path(ref path) => {
+                    let key = RasterKey {
+                        scale,
+                        offset,
+                        fill: shape.fill.map(|fill| self.fill.unwrap_or(fill)),
+                        stroke: shape.stroke.map(|stroke| self.stroke.unwrap_or(stroke)),
+                    };
+                    if let Some((rect, image)) = path.raster(key, shape.stroke_width) {
+                        painter.bitmap(rect, image);
+                    }
+                    continue;
+                }
+            };
+            if let Some(fill) = shape.fill {
+                let color = self.fill.unwrap_or(fill);
+                if radius > 0.0 {
+                    painter.fill_rounded_rect(transform(rect), color, radius * scale);
+                } else {
+                    painter.fill_rect(transform(rect), color);
+                }
+            }
+            if let Some(stroke) = shape.stroke {
+                // Strokes are centered on the edge of the shape, while
+                // borders are drawn inside the rect. The outside of a
+                // rounded corner is rounder still, but square corners
+                // stay square.
+                let half = shape.stroke_width / 2.0;
+                let outer_radius = if radius > 0.0 { radius + half } else { 0.0 };
+                painter.border(
+                    transform(rect.inflate(half, half)),
+                    Border {
+                        width: (shape.stroke_width * scale).into(),
+                        style: BorderStyle::Solid,
+                        color: self.stroke.unwrap_or(stroke),
+                    },
+                    outer_radius * scale,
+                );
+            }
+        }
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_path, Svg, SvgImage};
+    use crate::dom::element::Element;
+    use crate::layout::LogicalLength;
+    use crate::render::painter::{PaintCommand, Painter};
+    use crate::style::{Border, BorderStyle, Corners, Edges};
+    use crate::Color;
+    use euclid::default::Rect;
+    use euclid::{point2, rect, size2};
+    use std::sync::Arc;
+
+    const ICON: &str = r##"
+        <?xml version="1.0"?>
+        <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">
+            <!-- A square and a ring -->
+            <rect x="1" y="1" width="4" height="4" fill="#f00" />
+            <circle cx="7" cy="7" r="2" fill="blue" stroke="black" stroke-width="1" />
+            <path d="M 0 0 L 10 10" />
+        </svg>
+    "##;
+
+    fn draw(svg: Svg) -> Vec<PaintCommand> {
+        let mut painter = Painter::new(
+            size2(20.0, 20.0),
+            rect(0.0, 0.0, 20.0, 20.0),
+            Default::default(),
+        );
+        svg.draw(&mut painter);
+        painter.commands
+    }
+
+    #[test]
+    fn shapes() {
+        let image: SvgImage = ICON.parse().unwrap();
+        let svg = Svg {
+            source: Some(image.clone()),
+            ..Default::default()
+        };
+        assert_eq!(
+            draw(svg),
+            vec![
+                PaintCommand::Rect(rect(2.0, 2.0, 8.0, 8.0), Color::new(255, 0, 0, 255)),
+                PaintCommand::RoundedRect(
+                    rect(10.0, 10.0, 8.0, 8.0),
+                    Color::new(0, 0, 255, 255),
+                    Corners::new_all_same(LogicalLength::new(4.0))
+                ),
+                PaintCommand::Border(
+                    rect(9.0, 9.0, 10.0, 10.0),
+                    Edges::new_all_same(Border {
+                        width: LogicalLength::new(2.0),
+                        style: BorderStyle::Solid,
+                        color: Color::black(),
+                    }),
+                    Corners::new_all_same(LogicalLength::new(5.0))
+                ),
+            ]
+        );
+
+        let white = Color::white();
+        let recolored = Svg {
+            source: Some(image),
+            fill: Some(white),
+            ..Default::default()
+        };
+        let fills = draw(recolored)
+            .into_iter()
+            .filter(|command| match command {
+                PaintCommand::Rect(_, color) | PaintCommand::RoundedRect(_, color, _) => {
+                    *color == white
+                }
+                _ => false,
+            })
+            .count();
+        assert_eq!(fills, 2);
+
+        assert!("<rect width=10>".parse::<SvgImage>().is_err());
+    }
+
+    #[test]
+    fn square_strokes() {
+        let image: SvgImage =
+            r#"<svg viewBox="0 0 10 10"><rect width="10" height="10" fill="none" stroke="red" /></svg>"#
+                .parse()
+                .unwrap();
+        let commands = draw(Svg {
+            source: Some(image),
+            ..Default::default()
+        });
+        assert_eq!(
+            commands,
+            vec![PaintCommand::Border(
+                rect(-1.0, -1.0, 22.0, 22.0),
+                Edges::new_all_same(Border {
+                    width: LogicalLength::new(2.0),
+                    style: BorderStyle::Solid,
+                    color: Color::new(255, 0, 0, 255),
+                }),
+                Corners::new_all_same(LogicalLength::new(0.0))
+            )]
+        );
+    }
+
+    #[test]
+    fn path_data() {
+        assert_eq!(
+            parse_path("M1 1h8v8h-8z"),
+            Some(vec![(
+                vec![
+                    point2(1.0, 1.0),
+                    point2(9.0, 1.0),
+                    point2(9.0, 9.0),
+                    point2(1.0, 9.0)
+                ],
+                true
+            )])
+        );
+        assert_eq!(
+            parse_path("M0,0L1-2.5.5.5 m1 1 2 2"),
+            Some(vec![
+                (
+                    vec![point2(0.0, 0.0), point2(1.0, -2.5), point2(0.5, 0.5)],
+                    false
+                ),
+                (vec![point2(1.5, 1.5), point2(3.5, 3.5)], false),
+            ])
+        );
+        let curves = parse_path("M0 0C0 10 10 10 10 0Q5-5 0 0").unwrap();
+        assert_eq!(curves[0].0.len(), 33);
+        assert_eq!(curves[0].0[16], point2(10.0, 0.0));
+        assert_eq!(curves[0].0[8], point2(5.0, 7.5));
+
+        assert_eq!(parse_path("L 1 1"), None);
+        assert_eq!(parse_path("M 0 0 L 1"), None);
+        assert_eq!(parse_path("M 0 0 A 1 1 0 0 0 1 1"), None);
+    }
+
+    /// Draws a `<path>` with `attributes` into the 20x20 painter twice,
+    /// checking that the same image is reused, and gives its rect, its
+    /// pixels and its width.
+    fn path_raster(attributes: &str) -> (Rect<f32>, Vec<u8>, usize) {
+        let source = format!(r#"<svg viewBox="0 0 10 10"><path {} /></svg>"#, attributes);
+        let image: SvgImage = source.parse().unwrap();
+        let svg = Svg {
+            source: Some(image),
+            ..Default::default()
+        };
+        match (&draw(svg.clone())[..], &draw(svg)[..]) {
+            ([PaintCommand::Image(rect, image)], [PaintCommand::Image(_, again)]) => {
+                assert!(Arc::ptr_eq(image, again));
+                (rect.to_untyped(), image.rgba.clone(), image.width as usize)
+            }
+            other => panic!("expected one image, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let (bounds, rgba, width) = path_raster(r#"d="M 1 1 h 8 v 8 h -8 z" fill="blue""#);
+        assert_eq!(bounds, rect(2.0, 2.0, 16.0, 16.0));
+        let pixel = |x: usize, y: usize| &rgba[(y * width + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), &[0, 0, 255, 255]);
+        assert_eq!(pixel(15, 15), &[0, 0, 255, 255]);
+
+        // Half of each pixel along the diagonal is covered.
+        let (_, rgba, width) = path_raster(r#"d="M 0 0 L 10 0 L 10 10 Z""#);
+        assert_eq!(rgba[(5 * width + 5) * 4 + 3], 128);
+        assert_eq!(rgba[(5 * width + 4) * 4 + 3], 0);
+        assert_eq!(rgba[(5 * width + 6) * 4 + 3], 255);
+
+        // The inner square turns the same way, so it's only a hole with
+        // the even-odd rule.
+        let rings = r#"d="M 0 0 h 10 v 10 h -10 z M 3 3 h 4 v 4 h -4 z""#;
+        let (_, rgba, width) = path_raster(rings);
+        assert_eq!(rgba[(10 * width + 10) * 4 + 3], 255);
+        let (_, rgba, width) = path_raster(&format!("{} fill-rule=\"evenodd\"", rings));
+        assert_eq!(rgba[(10 * width + 10) * 4 + 3], 0);
+        assert_eq!(rgba[(2 * width + 2) * 4 + 3], 255);
+
+        // An open line is only drawn when it's stroked, and its round
+        // caps reach past its ends without filling the corners.
+        let (bounds, rgba, width) =
+            path_raster(r#"d="M 2 5 H 8" fill="none" stroke="red" stroke-width="2""#);
+        assert_eq!(bounds, rect(2.0, 8.0, 16.0, 4.0));
+        assert_eq!(&rgba[(2 * width + 8) * 4..][..4], &[255, 0, 0, 255]);
+        assert!(rgba[(2 * width) * 4 + 3] > 200);
+        assert!(rgba[3] < 128);
+
+        assert!(r#"<path d="M 0 0 L 1" />"#.parse::<SvgImage>().is_err());
+        assert!(r#"<path d="M 0 0" fill-rule="odd" />"#.parse::<SvgImage>().is_err());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::dom::element::{Element, ElementState, ElementStates, HasTextAttributes, NoChildren};
+use crate::dom::input::{InputEvent, Key, State};
+use crate::dom::{
+    AttrClass, AttrDisabled, AttrId, AttrKeyRepeat, AttrPaintOverlay, AttrReadonly, AttrStyle,
+    AttrTabIndex, AttrTitle, AttrValue, ChangeEvent, SelectEvent,
+};
+use crate::render::painter::PaintOverlay;
+use crate::style::{ComputedValues, PaintDetails, Style};
+use std::ops::Range;
+
+/// The contents of an editable text element, along with the position
+/// of the caret as a byte offset into `text`. A caret past the end of
+/// the text, or inside of a character, is treated as if it were at the
+/// character boundary before it.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct TextValue {
+    pub text: String,
+    pub caret: usize,
+}
+
+impl From<String> for TextValue {
+    fn from(text: String) -> Self {
+        TextValue::new(text)
+    }
+}
+
+impl<'a> From<&'a str> for TextValue {
+    fn from(text: &'a str) -> Self {
+        TextValue::new(text.to_owned())
+    }
+}
+
+impl TextValue {
+    /// Creates a value with the caret placed at the end of the text.
+    pub fn new(text: String) -> TextValue {
+        let caret = text.len();
+        TextValue { text, caret }
+    }
+
+    /// The number of lines, as separated by newlines.
+    pub fn line_count(&self) -> usize {
+        self.text.split('\n').count()
+    }
+
+    /// The caret, moved back to the nearest character boundary within
+    /// the text.
+    fn clamped_caret(&self) -> usize {
+        let mut caret = self.caret.min(self.text.len());
+        while !self.text.is_char_boundary(caret) {
+            caret -= 1;
+        }
+        caret
+    }
+
+    fn clamp_caret(&mut self) {
+        self.caret = self.clamped_caret();
+    }
+
+    pub fn insert(&mut self, character: char) {
+        self.clamp_caret();
+        self.text.insert(self.caret, character);
+        self.caret += character.len_utf8();
+    }
+
+    pub fn backspace(&mut self) {
+        self.clamp_caret();
+        if let Some((index, _)) = self.text[..self.caret].char_indices().last() {
+            self.text.remove(index);
+            self.caret = index;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        self.clamp_caret();
+        if self.caret < self.text.len() {
+            self.text.remove(self.caret);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.clamp_caret();
+        if let Some((index, _)) = self.text[..self.caret].char_indices().last() {
+            self.caret = index;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.clamp_caret();
+        if let Some(ch) = self.text[self.caret..].chars().next() {
+            self.caret += ch.len_utf8();
+        }
+    }
+
+    /// Moves the caret to the start of its line. `lines` are the visual
+    /// lines as reported by layout, an empty slice falls back to the
+    /// lines separated by newlines.
+    pub fn move_home(&mut self, lines: &[Range<usize>]) {
+        self.clamp_caret();
+        let lines = self.resolve_lines(lines);
+        self.caret = lines[self.line_index(&lines)].start;
+    }
+
+    /// Moves the caret to the end of its line.
+    pub fn move_end(&mut self, lines: &[Range<usize>]) {
+        self.clamp_caret();
+        let lines = self.resolve_lines(lines);
+        self.caret = lines[self.line_index(&lines)].end;
+    }
+
+    /// Moves the caret to the previous line, keeping the same column
+    /// where possible.
+    pub fn move_up(&mut self, lines: &[Range<usize>]) {
+        self.clamp_caret();
+        let lines = self.resolve_lines(lines);
+        let index = self.line_index(&lines);
+        if index == 0 {
+            self.caret = 0;
+        } else {
+            self.move_to_line(&lines, index, index - 1);
+        }
+    }
+
+    /// Moves the caret to the next line, keeping the same column where
+    /// possible.
+    pub fn move_down(&mut self, lines: &[Range<usize>]) {
+        self.clamp_caret();
+        let lines = self.resolve_lines(lines);
+        let index = self.line_index(&lines);
+        if index + 1 >= lines.len() {
+            self.caret = self.text.len();
+        } else {
+            self.move_to_line(&lines, index, index + 1);
+        }
+    }
+
+    fn resolve_lines(&self, lines: &[Range<usize>]) -> Vec<Range<usize>> {
+        let valid = !lines.is_empty()
+            && lines
+                .iter()
+                .all(|line| line.start <= line.end && line.end <= self.text.len());
+        if valid {
+            lines.to_vec()
+        } else {
+            let mut lines = vec![];
+            let mut start = 0;
+            for (index, _) in self.text.match_indices('\n') {
+                lines.push(start..index);
+                start = index + 1;
+            }
+            lines.push(start..self.text.len());
+            lines
+        }
+    }
+
+    fn line_index(&self, lines: &[Range<usize>]) -> usize {
+        lines
+            .iter()
+            .rposition(|line| line.start <= self.caret)
+            .unwrap_or(0)
+    }
+
+    fn move_to_line(&mut self, lines: &[Range<usize>], from: usize, to: usize) {
+        let from = &lines[from];
+        let column = self.text[from.start..self.caret.min(from.end)]
+            .chars()
+            .count();
+        let to = &lines[to];
+        self.caret = self.text[to.clone()]
+            .char_indices()
+            .nth(column)
+            .map(|(index, _)| to.start + index)
+            .unwrap_or(to.end);
+    }
+}
+
+/// Corresponds to <text_area>. An editable box of text which wraps and
+/// can contain multiple lines. The element is controlled: edits are
+/// reported through `on_change` and only take effect once the new
+/// value is passed back in through the `value` attribute.
+///
+/// When the text is taller than the box, it scrolls to keep the caret
+/// in view. It only follows the caret: the mouse wheel doesn't scroll
+/// it, so lines above the caret are reached by moving the caret up.
+///
+/// Setting `key_repeat` to false ignores the presses repeated while a
+/// key is held, so holding Backspace deletes a single character.
+///
+/// A `readonly` text area can still be focused and its caret moved,
+/// which is reported through `on_select`, but it can't be edited. A
+/// `disabled` one ignores all input, can't be focused, and is painted
+/// faded. `on_change` never fires in either case.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct TextArea {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
+    style: Option<Style>,
+    value: TextValue,
+    key_repeat: Option<bool>,
+    readonly: bool,
+    disabled: bool,
+    tab_index: Option<i32>,
+}
+
+element_attributes! {
+    TextArea {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        paint_overlay: AttrPaintOverlay,
+        style: AttrStyle,
+        value: AttrValue,
+        key_repeat: AttrKeyRepeat,
+        readonly: AttrReadonly,
+        disabled: AttrDisabled,
+        tab_index: AttrTabIndex,
+    }
+}
+
+element_handlers! {
+    TextAreaHandlers for TextArea {
+        on_change: ChangeEvent,
+        on_select: SelectEvent,
+    }
+}
+
+impl Element for TextArea {
+    type Child = NoChildren;
+    type Handlers = TextAreaHandlers;
+
+    const ELEMENT_NAME: &'static str = "text_area";
+
+    fn interactive(&self) -> bool {
+        true
+    }
+
+    fn focusable(&self) -> bool {
+        !self.disabled
+    }
+
+    fn tab_index(&self) -> Option<i32> {
+        self.tab_index
+    }
+
+    fn paint(&self, values: &ComputedValues) -> PaintDetails {
+        let paint = PaintDetails::from_values(values);
+        if !self.disabled {
+            return paint;
+        }
+        let mut text_color = paint.text_color;
+        text_color.alpha /= 2;
+        let mut background_color = paint.background_color;
+        background_color.alpha /= 2;
+        paint
+            .text_color(text_color)
+            .background_color(background_color)
+            .build()
+    }
+
+    fn key_repeat(&self) -> bool {
+        self.key_repeat.unwrap_or(true)
+    }
+
+    /// Text areas are always multi-line, so Enter adds a line break
+    /// instead of activating the default button.
+    fn captures_key(&self, key: Key) -> bool {
+        key == Key::Enter
+    }
+
+    fn content(&self) -> Option<&str> {
+        Some(&self.value.text[..])
+    }
+
+    fn caret(&self) -> Option<usize> {
+        Some(self.value.clamped_caret())
+    }
+
+    fn process(
+        &self,
+        states: ElementStates,
+        handlers: &mut Self::Handlers,
+        event: &InputEvent,
+    ) -> (bool, ElementStates) {
+        if self.disabled {
+            // It may have been disabled while hovered or pressed, and
+            // can't take part in those any more.
+            let interactions = ElementState::Hover | ElementState::Press;
+            return (
+                !states.is_disjoint(interactions),
+                states.difference(interactions),
+            );
+        }
+        let mut value = self.value.clone();
+        match event {
+            InputEvent::Hovered {
+                state: State::Begin,
+            } => return (true, states | ElementState::Hover),
+            InputEvent::Hovered { state: State::End } => {
+                return (true, states.difference(ElementState::Hover.into()))
+            }
+            InputEvent::Character { character } => value.insert(*character),
+            InputEvent::KeyPressed { key, lines } => match key {
+                Key::Enter => value.insert('\n'),
+                Key::Backspace => value.backspace(),
+                Key::Delete => value.delete(),
+                Key::Left => value.move_left(),
+                Key::Right => value.move_right(),
+                Key::Up => value.move_up(lines),
+                Key::Down => value.move_down(lines),
+                Key::Home => value.move_home(lines),
+                Key::End => value.move_end(lines),
+                _ => return (false, states),
+            },
+            _ => return (false, states),
+        }
+        if value.text == self.value.text {
+            if value.caret != self.value.clamped_caret() {
+                handlers
+                    .on_select
+                    .invoke(&SelectEvent { caret: value.caret });
+                if !self.readonly {
+                    handlers.on_change.invoke(&ChangeEvent { value });
+                }
+            }
+        } else if self.readonly {
+            return (false, states);
+        } else {
+            handlers.on_change.invoke(&ChangeEvent { value });
+        }
+        (true, states)
+    }
+
+    fn style(&self) -> Option<Style> {
+        self.style
+    }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        vec![("value", format!("{:?}", self.value.text))]
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TextArea, TextAreaHandlers, TextValue};
+    use crate::dom::element::{Element, ElementState, ElementStates};
+    use crate::dom::input::{InputEvent, Key, State};
+
+    #[test]
+    fn enter_adds_line() {
+        let mut value = TextValue::new("foo bar".to_owned());
+        value.insert('\n');
+        assert_eq!(value.line_count(), 2);
+        assert_eq!(value.caret, 8);
+        value.insert('x');
+        assert_eq!(value.text, "foo bar\nx");
+    }
+
+    #[test]
+    fn hard_lines() {
+        let mut value = TextValue::new("foo\nbarbaz".to_owned());
+        value.caret = 2;
+        value.move_down(&[]);
+        assert_eq!(value.caret, 6);
+        value.move_end(&[]);
+        assert_eq!(value.caret, 10);
+        value.move_up(&[]);
+        assert_eq!(value.caret, 3);
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        // "foo bar baz" wrapped after each word.
+        let lines = [0..3, 4..7, 8..11];
+        let mut value = TextValue::new("foo bar baz".to_owned());
+        value.caret = 1;
+        value.move_down(&lines);
+        assert_eq!(value.caret, 5);
+        value.move_down(&lines);
+        assert_eq!(value.caret, 9);
+        value.move_down(&lines);
+        assert_eq!(value.caret, 11);
+        value.move_home(&lines);
+        assert_eq!(value.caret, 8);
+    }
+
+    #[test]
+    fn backspace_multibyte() {
+        let mut value = TextValue::new("añ".to_owned());
+        value.backspace();
+        assert_eq!(value.text, "a");
+        assert_eq!(value.caret, 1);
+    }
+
+    #[test]
+    fn invalid_caret() {
+        // Past the end of the text.
+        let mut value = TextValue {
+            text: "añ".to_owned(),
+            caret: 10,
+        };
+        value.insert('x');
+        assert_eq!(value.text, "añx");
+        assert_eq!(value.caret, 4);
+
+        // Inside of the "ñ", which is treated as before it.
+        let mut value = TextValue {
+            text: "añ".to_owned(),
+            caret: 2,
+        };
+        value.move_right();
+        assert_eq!(value.caret, 3);
+        value.caret = 2;
+        value.backspace();
+        assert_eq!(value.text, "ñ");
+        assert_eq!(value.caret, 0);
+
+        let text_area = TextArea {
+            value: TextValue {
+                text: "añ".to_owned(),
+                caret: 10,
+            },
+            ..TextArea::default()
+        };
+        assert_eq!(text_area.caret(), Some(3));
+    }
+
+    #[test]
+    fn disabled_clears_interactions() {
+        let text_area = TextArea {
+            disabled: true,
+            ..TextArea::default()
+        };
+        let mut handlers = TextAreaHandlers::default();
+
+        // Disabled while hovered and pressed, so the next event it gets
+        // drops both, and keeps the others.
+        let states = ElementState::Hover | ElementState::Press | ElementState::FocusWithin;
+        let key = InputEvent::KeyPressed {
+            key: Key::Backspace,
+            lines: vec![],
+        };
+        let (changed, states) = text_area.process(states, &mut handlers, &key);
+        assert!(changed);
+        assert_eq!(states, ElementStates::only(ElementState::FocusWithin));
+
+        let hover = InputEvent::Hovered {
+            state: State::Begin,
+        };
+        let (changed, states) = text_area.process(states, &mut handlers, &hover);
+        assert!(!changed);
+        assert_eq!(states, ElementStates::only(ElementState::FocusWithin));
+    }
+}
@@ -1,42 +1,245 @@
-use crate::dom::element::Element;
-use crate::dom::{AttrStyle, Button, Node, Span};
-use crate::style::{ComputedValues, Style};
-use crate::Color;
+use crate::dom::element::ElementState;
+use crate::dom::element::{DynChild, Element, ElementStates, HasTextAttributes};
+use crate::dom::input::{InputEvent, State};
+use crate::dom::{
+    AttrAfter, AttrBefore, AttrClass, AttrDragRegion, AttrEventSink, AttrId, AttrPaintOverlay,
+    AttrPortal, AttrStyle, AttrTabIndex, AttrTitle, Button, ChildClickEvent, ClickEvent, Image,
+    LongPressEvent, Node, PinchEvent, ResizeEvent, RotateEvent, Span, Svg, SwipeEvent, TextArea,
+};
+use crate::render::painter::PaintOverlay;
+use crate::style::Style;
 
 /// Corresponds to <view>. Generic frame for layout purposes.
+///
+/// Setting `drag_region` makes the view move its window when it's
+/// dragged, and toggle maximizing it when it's double clicked, like a
+/// title bar. Buttons and other interactive elements inside it still
+/// work as usual.
+///
+/// Setting `event_sink` makes the view take all pointer input within
+/// its bounds, for surfaces like an editor canvas which hit test their
+/// own content. Its descendants still paint, but are never hovered,
+/// pressed or clicked, and input that lands on them goes to the view
+/// instead, which gets `on_click` and the hover and press states like
+/// a button. The view is focusable, and its descendants aren't, so
+/// clicking inside it or tabbing to it focuses the view itself, and
+/// keyboard input inside it goes to the view. Nested sinks are part of
+/// the outermost one.
+///
+/// Setting `tab_index` makes the view focusable, so keyboard input can
+/// go to it, see `Element::tab_index()` for how it orders focus.
+///
+/// Setting `portal` lifts the view out of its parent, for dropdowns and
+/// popups anchored to elements deep inside of the window, see `Portal`.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct View {
+    id: Option<String>,
+    class: Option<String>,
+    title: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
     style: Option<Style>,
+    drag_region: bool,
+    event_sink: bool,
+    tab_index: Option<i32>,
+    portal: Option<Portal>,
+}
+
+/// Where a view with the `portal` attribute is placed, relative to the
+/// border box of its parent, the anchor, and then moved by the view's
+/// left and top margins.
+///
+/// A portal is still a child of its anchor as far as the tree is
+/// concerned: it inherits text styles from it, its events bubble up
+/// through it, and it's built by the component that declares it, with
+/// that component's context. It's removed along with the anchor, or
+/// when the component stops building it. But it takes no space in the
+/// anchor, and it's painted after everything else in the window, on
+/// top, where the `overflow`, `clip` and `clip_path` of its ancestors
+/// don't cut it off. Percentages in its size are of the window's size.
+/// Portals only leave block parents: one inside of a span is laid out
+/// in place.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Portal {
+    /// Over the anchor, with their top left corners together.
+    Over,
+    /// Just below the anchor, with their left edges lined up, as for a
+    /// dropdown.
+    Below,
+    /// Just to the right of the anchor, with their top edges lined up,
+    /// as for a submenu.
+    Right,
 }
 
 multiple_children! {
     enum ViewChild {
         Button(Node<Button>),
+        Image(Node<Image>),
         View(Node<View>),
+        Spacer(Node<Spacer>),
         Span(Node<Span>),
+        Svg(Node<Svg>),
+        TextArea(Node<TextArea>),
+        Dynamic(DynChild),
     }
 }
 
 element_attributes! {
     View {
+        id: AttrId,
+        class: AttrClass,
+        title: AttrTitle,
+        before: AttrBefore,
+        after: AttrAfter,
+        paint_overlay: AttrPaintOverlay,
         style: AttrStyle,
+        drag_region: AttrDragRegion,
+        event_sink: AttrEventSink,
+        tab_index: AttrTabIndex,
+        portal: AttrPortal,
+    }
+}
+
+element_handlers! {
+    ViewHandlers for View {
+        /// Handle clicks on any of the view's descendants. Useful for
+        /// long lists, where the index of the clicked child can be
+        /// read from the event instead of giving each item its own
+        /// handler.
+        on_child_click: ChildClickEvent,
+        on_long_press: LongPressEvent,
+        on_swipe: SwipeEvent,
+        on_pinch: PinchEvent,
+        on_rotate: RotateEvent,
+        /// Called after layout when the view's size changes, for
+        /// content which depends on it.
+        on_resize: ResizeEvent,
+        /// Called when the view is clicked, which only happens when it's
+        /// an `event_sink`.
+        on_click: ClickEvent,
     }
 }
 
 impl Element for View {
     type Child = ViewChild;
-    type Handlers = ();
+    type Handlers = ViewHandlers;
 
     const ELEMENT_NAME: &'static str = "view";
 
-    fn create_computed_values(&self) -> ComputedValues {
-        ComputedValues {
-            background_color: Color::new(50, 180, 200, 255),
-            ..Default::default()
+    fn interactive(&self) -> bool {
+        self.event_sink
+    }
+
+    fn focusable(&self) -> bool {
+        self.event_sink || self.tab_index.is_some()
+    }
+
+    fn tab_index(&self) -> Option<i32> {
+        self.tab_index
+    }
+
+    fn process(
+        &self,
+        states: ElementStates,
+        handlers: &mut Self::Handlers,
+        event: &InputEvent,
+    ) -> (bool, ElementStates) {
+        match event {
+            InputEvent::Hovered {
+                state: State::Begin,
+            } if self.event_sink => (true, states | ElementState::Hover),
+            InputEvent::Hovered { state: State::End } if self.event_sink => {
+                (true, states.difference(ElementState::Hover.into()))
+            }
+            InputEvent::MouseLeft {
+                state: State::Begin,
+                ..
+            } if self.event_sink => (true, states | ElementState::Press),
+            InputEvent::MouseLeft {
+                state: State::End, ..
+            } if states.contains(ElementState::Press) => {
+                handlers.on_click.invoke(&ClickEvent);
+                (true, states.difference(ElementState::Press.into()))
+            }
+            InputEvent::MouseLeft {
+                state: State::Cancel,
+                ..
+            } if self.event_sink => (true, states.difference(ElementState::Press.into())),
+            InputEvent::ChildClicked { index, target } if handlers.on_child_click.present() => {
+                handlers.on_child_click.invoke(&ChildClickEvent {
+                    index: *index,
+                    target: *target,
+                });
+                (true, states)
+            }
+            InputEvent::LongPress if handlers.on_long_press.present() => {
+                handlers.on_long_press.invoke(&LongPressEvent {});
+                (true, states)
+            }
+            InputEvent::Swipe { direction } if handlers.on_swipe.present() => {
+                handlers.on_swipe.invoke(&SwipeEvent {
+                    direction: *direction,
+                });
+                (true, states)
+            }
+            InputEvent::Pinch(pinch) if handlers.on_pinch.present() => {
+                handlers.on_pinch.invoke(pinch);
+                (true, states)
+            }
+            InputEvent::Rotate(rotate) if handlers.on_rotate.present() => {
+                handlers.on_rotate.invoke(rotate);
+                (true, states)
+            }
+            InputEvent::Resized(resize) if handlers.on_resize.present() => {
+                handlers.on_resize.invoke(resize);
+                (true, states)
+            }
+            _ => (false, states),
         }
     }
 
     fn style(&self) -> Option<Style> {
         self.style
     }
+
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn before(&self) -> Option<&str> {
+        self.before.as_deref()
+    }
+
+    fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+
+    fn portal(&self) -> Option<Portal> {
+        self.portal
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
+    fn drag_region(&self) -> bool {
+        self.drag_region
+    }
+
+    fn event_sink(&self) -> bool {
+        self.event_sink
+    }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
 }
@@ -1,32 +1,184 @@
-use crate::dom::element::{Element, ElementStates, HasEvent};
-use crate::dom::events::CloseRequestedEvent;
+use crate::dom::element::{Element, ElementStates, HasTextAttributes};
+use crate::dom::events::{
+    CloseRequestedEvent, ColorSchemeChangeEvent, MetricsChangeEvent, WindowStateEvent,
+};
 use crate::dom::input::InputEvent;
-use crate::dom::{AttrStyle, AttrTitle, Node, View};
+use crate::dom::{
+    AttrClass, AttrDecorations, AttrIcon, AttrId, AttrLevel, AttrPaintOverlay, AttrStyle,
+    AttrTitle, Node, View,
+};
+use crate::render::painter::PaintOverlay;
 use crate::style::Style;
-use crate::util::event_handler::EventHandler;
 use crate::Runtime;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An image for the `icon` attribute of a window, which is shown in its
+/// title bar and in the taskbar. PNG and ICO files can be used, and
+/// they're decoded when the icon is set on the window. If that fails, a
+/// warning is logged and the window keeps its previous icon.
+///
+/// Windows and X11 show the icon. On macOS the icon comes from the
+/// application bundle instead, and on Wayland it's set by the desktop
+/// entry, so this is ignored on both.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowIcon {
+    /// The contents of an image file.
+    Bytes(Arc<[u8]>),
+    /// An image file to read.
+    Path(PathBuf),
+    /// Unencoded 8-bit RGBA pixels, row by row from the top left.
+    Rgba {
+        pixels: Arc<[u8]>,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl From<&'static [u8]> for WindowIcon {
+    fn from(bytes: &'static [u8]) -> Self {
+        WindowIcon::Bytes(bytes.into())
+    }
+}
+
+impl From<Vec<u8>> for WindowIcon {
+    fn from(bytes: Vec<u8>) -> Self {
+        WindowIcon::Bytes(bytes.into())
+    }
+}
+
+impl From<PathBuf> for WindowIcon {
+    fn from(path: PathBuf) -> Self {
+        WindowIcon::Path(path)
+    }
+}
+
+impl<'a> From<&'a Path> for WindowIcon {
+    fn from(path: &'a Path) -> Self {
+        WindowIcon::Path(path.to_owned())
+    }
+}
+
+/// A decoded `WindowIcon`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct IconImage {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowIcon {
+    pub(crate) fn decode(&self) -> Result<IconImage, String> {
+        let image = match self {
+            WindowIcon::Rgba {
+                pixels,
+                width,
+                height,
+            } => {
+                if pixels.len() as u64 != *width as u64 * *height as u64 * 4 {
+                    return Err(format!(
+                        "{} bytes of pixels for a {}x{} icon",
+                        pixels.len(),
+                        width,
+                        height
+                    ));
+                }
+                return Ok(IconImage {
+                    rgba: pixels.to_vec(),
+                    width: *width,
+                    height: *height,
+                });
+            }
+            WindowIcon::Bytes(bytes) => image::load_from_memory(bytes),
+            WindowIcon::Path(path) => image::open(path),
+        };
+        let image = image.map_err(|err| err.to_string())?.into_rgba();
+        let (width, height) = image.dimensions();
+        Ok(IconImage {
+            rgba: image.into_raw(),
+            width,
+            height,
+        })
+    }
+}
+
+/// Whether the OS draws a window's title bar and borders, for the
+/// `decorations` attribute. Windows without them can use views with
+/// `drag_region` set as their own title bar, and `WindowControl` for
+/// its buttons.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Decorations {
+    Full,
+    None,
+}
+
+impl Default for Decorations {
+    fn default() -> Self {
+        Decorations::Full
+    }
+}
+
+/// Where a window is stacked among the other windows on the screen, for
+/// the `level` attribute.
+///
+/// An `AlwaysOnTop` window stays above every normal window, including
+/// those of other apps while they're focused, as is wanted for floating
+/// tool palettes. Several always-on-top windows are stacked among
+/// themselves as normal windows are, with the one focused last on top.
+/// Windows, macOS and X11 support this, though on X11 it's up to the
+/// window manager to honor it. Wayland has no way to ask for it, so it's
+/// ignored there.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowLevel {
+    Normal,
+    AlwaysOnTop,
+}
+
+impl Default for WindowLevel {
+    fn default() -> Self {
+        WindowLevel::Normal
+    }
+}
 
 /// Corresponds to <window>. This is the top-level container for UI and
 /// corresponds to an OS window.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Window {
+    id: Option<String>,
+    class: Option<String>,
+    paint_overlay: Option<PaintOverlay>,
     style: Option<Style>,
     pub title: String,
+    pub icon: Option<WindowIcon>,
+    pub decorations: Decorations,
+    pub level: WindowLevel,
 }
 
 impl Default for Window {
     fn default() -> Self {
         Window {
+            id: None,
+            class: None,
+            paint_overlay: None,
             style: None,
             title: "Untitled Window".to_owned(),
+            icon: None,
+            decorations: Decorations::Full,
+            level: WindowLevel::Normal,
         }
     }
 }
 
 element_attributes! {
     Window {
+        id: AttrId,
+        class: AttrClass,
+        paint_overlay: AttrPaintOverlay,
         style: AttrStyle,
         title: AttrTitle,
+        icon: AttrIcon,
+        decorations: AttrDecorations,
+        level: AttrLevel,
     }
 }
 
@@ -37,6 +189,9 @@ element_handlers! {
         /// `Runtime::shutdown()` which will stop the event loop and
         /// cause the application to exit.
         on_close: CloseRequestedEvent,
+        on_state_change: WindowStateEvent,
+        on_color_scheme_change: ColorSchemeChangeEvent,
+        on_metrics_change: MetricsChangeEvent,
     }
 }
 
@@ -50,6 +205,18 @@ impl Element for Window {
         self.style
     }
 
+    fn element_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.paint_overlay.as_ref()
+    }
+
     fn attributes(&self) -> Vec<(&'static str, String)> {
         vec![("title", format!("{:?}", self.title))]
     }
@@ -69,7 +236,29 @@ impl Element for Window {
                 }
                 (true, states)
             }
+            InputEvent::WindowState { state } => {
+                handlers
+                    .on_state_change
+                    .invoke(&WindowStateEvent { state: *state });
+                (true, states)
+            }
+            InputEvent::ColorScheme { scheme } => {
+                handlers
+                    .on_color_scheme_change
+                    .invoke(&ColorSchemeChangeEvent { scheme: *scheme });
+                (true, states)
+            }
+            InputEvent::Metrics { metrics } => {
+                handlers
+                    .on_metrics_change
+                    .invoke(&MetricsChangeEvent { metrics: *metrics });
+                (true, states)
+            }
             _ => (false, states),
         }
     }
+
+    fn changed_attributes(&self, other: &Self) -> Vec<&'static str> {
+        HasTextAttributes::changed_attributes(self, other)
+    }
 }
@@ -1,4 +1,6 @@
 use super::element::Event;
+use super::elements::text_area::TextValue;
+use crate::layout::{LogicalPoint, LogicalSize};
 
 /// The element associated with this event was activated by the user.
 pub struct ClickEvent;
@@ -8,3 +10,181 @@ impl Event for ClickEvent {}
 /// pressing the close button, or pressing alt+f4).
 pub struct CloseRequestedEvent {}
 impl Event for CloseRequestedEvent {}
+
+/// Whether a window is shown normally, minimized or maximized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowState {
+    Normal,
+    Minimized,
+    Maximized,
+}
+
+/// Whether the OS is set to a light or dark interface.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// Platforms which don't report a preference are considered light.
+impl Default for ColorScheme {
+    fn default() -> Self {
+        ColorScheme::Light
+    }
+}
+
+/// Fired on every window when the user switches between light and dark
+/// mode. The current scheme can also be read with
+/// `Runtime::color_scheme()`.
+pub struct ColorSchemeChangeEvent {
+    pub scheme: ColorScheme,
+}
+impl Event for ColorSchemeChangeEvent {}
+
+/// The device scale and logical size of a window's content, which are
+/// also readable with `Runtime::window_metrics()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindowMetrics {
+    /// Physical pixels per logical pixel, such as 2 on most high DPI
+    /// screens, for picking between assets at different resolutions.
+    pub scale_factor: f32,
+    pub size: LogicalSize,
+}
+
+/// Fired on a window once after it opens, and then when its scale
+/// factor or size changes, such as when it's resized or moved onto a
+/// monitor with a different scale. Changes are delivered at most once
+/// per frame, after the frame's events, so a window which is being
+/// dragged to a new size gets one event per frame.
+pub struct MetricsChangeEvent {
+    pub metrics: WindowMetrics,
+}
+impl Event for MetricsChangeEvent {}
+
+/// Fired when a window is minimized, maximized or restored, either by
+/// the user or through `WindowControl`.
+pub struct WindowStateEvent {
+    pub state: WindowState,
+}
+impl Event for WindowStateEvent {}
+
+/// The user edited the value of an element such as `<text_area>`. The
+/// new value has to be passed back to the element for the edit to
+/// take effect.
+pub struct ChangeEvent {
+    pub value: TextValue,
+}
+impl Event for ChangeEvent {}
+
+/// The caret of an element such as `<text_area>` moved without the text
+/// changing. A readonly element only reports caret moves through this,
+/// so `caret` has to be passed back in its value for the caret to move.
+pub struct SelectEvent {
+    pub caret: usize,
+}
+impl Event for SelectEvent {}
+
+/// A descendant of the element was clicked. This lets a single handler
+/// on a parent respond to clicks on any number of children, instead of
+/// attaching a handler to each of them.
+///
+/// ```rs
+/// let on_child_click = move |event: &ChildClickEvent| {
+///     selected.update(|_| Some(event.index));
+/// };
+///
+/// mox! {
+///     <view on_child_click={on_child_click}>
+///         {items.iter().map(|item| mox! {
+///             <button><span>{item.clone()}</span></button>
+///         }).collect::<Vec<_>>()}
+///     </view>
+/// }
+/// ```
+pub struct ChildClickEvent {
+    /// The index of the direct child that the click happened within.
+    pub index: usize,
+    /// The id of the node that was clicked, which may be nested deeper
+    /// within that child.
+    pub target: u64,
+}
+impl Event for ChildClickEvent {}
+
+/// The pointer was held still on the element, or one of its
+/// descendants, for a while. How long is set with
+/// `Runtime::gestures()`.
+pub struct LongPressEvent {}
+impl Event for LongPressEvent {}
+
+/// Which way the pointer travelled in a swipe.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// The pointer was pressed on the element, or one of its descendants,
+/// and quickly dragged away before being released. The element which
+/// was under the pointer when it was pressed gets the swipe, wherever
+/// it's released.
+pub struct SwipeEvent {
+    pub direction: SwipeDirection,
+}
+impl Event for SwipeEvent {}
+
+/// Where a two-finger gesture is. It begins when a second finger
+/// touches the window, and ends when either of the two lifts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GesturePhase {
+    Began,
+    Changed,
+    Ended,
+}
+
+/// Two fingers moved apart or together over the element, or one of its
+/// descendants. The element under the point midway between the fingers
+/// when the second one touched gets every event of the gesture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PinchEvent {
+    pub phase: GesturePhase,
+    /// The point midway between the fingers, relative to the window.
+    pub centroid: LogicalPoint,
+    /// The distance between the fingers relative to when the gesture
+    /// began, so above 1 when they spread apart.
+    pub scale: f32,
+    /// How much `scale` changed since the previous event, as a factor.
+    pub scale_delta: f32,
+}
+impl Event for PinchEvent {}
+
+/// Two fingers turned around each other over the element, or one of its
+/// descendants. This is delivered alongside `PinchEvent`, to the same
+/// element.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RotateEvent {
+    pub phase: GesturePhase,
+    /// The point midway between the fingers, relative to the window.
+    pub centroid: LogicalPoint,
+    /// How far the fingers turned since the gesture began, in radians,
+    /// clockwise on screen. This keeps counting past a full turn.
+    pub angle: f32,
+    /// How much `angle` changed since the previous event.
+    pub angle_delta: f32,
+}
+impl Event for RotateEvent {}
+
+/// The element's border box changed size, or the element was laid out
+/// for the first time. This is sent after the frame is laid out, at
+/// most once per frame with the size from the frame's last layout, so a
+/// size which changes back and forth within a frame isn't reported.
+/// Handlers can change the element's size, such as by laying out a
+/// chart to fit, and the new size is reported in the next frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResizeEvent {
+    /// The size last reported, or `None` the first time.
+    pub old: Option<LogicalSize>,
+    pub new: LogicalSize,
+}
+impl Event for ResizeEvent {}
@@ -1,7 +1,119 @@
+use crate::dom::{
+    ColorScheme, PinchEvent, ResizeEvent, RotateEvent, SwipeDirection, WindowMetrics, WindowState,
+};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
 pub enum InputEvent {
-    Hovered { state: State },
-    MouseLeft { state: State },
+    Hovered {
+        state: State,
+    },
+    /// The left mouse button or a touch went down on (`Begin`) or up
+    /// from (`End`) the element. Once a pointer goes down on an element,
+    /// the element gets that pointer's release even if it's no longer
+    /// over it; `Cancel` is sent instead of `End` when the OS cancels a
+    /// touch.
+    MouseLeft {
+        state: State,
+        pointer: PointerId,
+    },
     CloseRequested,
+    /// The window was minimized, maximized or restored.
+    WindowState {
+        state: WindowState,
+    },
+    /// The system switched between light and dark mode.
+    ColorScheme {
+        scheme: ColorScheme,
+    },
+    /// The window's scale factor or size changed.
+    Metrics {
+        metrics: WindowMetrics,
+    },
+    /// Keyboard focus moved onto (`Begin`) or away from (`End`) the
+    /// element.
+    Focused {
+        state: State,
+    },
+    /// A key was pressed while the element had focus. `lines` contains
+    /// the byte ranges of the element's text from the last layout, one
+    /// per visual line.
+    KeyPressed {
+        key: Key,
+        lines: Vec<Range<usize>>,
+    },
+    /// A printable character was typed while the element had focus.
+    Character {
+        character: char,
+    },
+    /// A descendant of the element was clicked. `index` is the index of
+    /// the direct child containing `target`.
+    ChildClicked {
+        index: usize,
+        target: u64,
+    },
+    /// The element was activated from the keyboard, as a default or
+    /// cancel button. See `Element::window_key()`.
+    Activated,
+    /// The element or one of its descendants was long pressed.
+    LongPress,
+    /// The element or one of its descendants was swiped.
+    Swipe {
+        direction: SwipeDirection,
+    },
+    /// The element or one of its descendants was pinched with two
+    /// fingers.
+    Pinch(PinchEvent),
+    /// Two fingers turned around each other over the element or one of
+    /// its descendants.
+    Rotate(RotateEvent),
+    /// The element's size changed in the last layout.
+    Resized(ResizeEvent),
+}
+
+/// Whether the user last interacted with a window through the keyboard
+/// or a pointer, which decides whether focus is shown.
+///
+/// Pressing a key switches to `Keyboard`, and pressing a mouse button
+/// switches to `Pointer`. Moving the cursor doesn't change it. While it's
+/// `Keyboard`, the focused element has `ElementState::FocusVisible`, so
+/// focus moved with Tab is shown, and so is focus given by a click once
+/// a key is pressed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputModality {
+    Pointer,
+    Keyboard,
+}
+
+/// Identifies one of the pointers over a window, so that several
+/// touches can be followed at once. The mouse is always `MOUSE`, and
+/// each touch gets its own id from when it starts until it ends. Touch
+/// ids can be reused for later touches.
+///
+/// Only the mouse hovers: touches go down on the element under them and
+/// up from it, without `Hovered` events in between.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerId(pub u64);
+
+impl PointerId {
+    pub const MOUSE: PointerId = PointerId(0);
+
+    /// The pointer for a touch with the id the OS gave it, which is
+    /// offset so that it never clashes with the mouse.
+    pub fn touch(id: u64) -> PointerId {
+        PointerId(id.wrapping_add(1))
+    }
+}
+
+/// Where a touch is in its lifetime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    /// The OS took the touch away, such as for a system gesture.
+    Cancelled,
 }
 
 #[derive(Copy, Clone)]
@@ -12,6 +124,62 @@ pub enum State {
     Cancel,
 }
 
+/// Keys which elements can respond to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Tab,
+    Escape,
+    Space,
+    /// A letter or digit key, as the lowercase character printed on it.
+    /// This identifies the physical key for things like WASD movement,
+    /// use `InputEvent::Character` for text input.
+    Char(char),
+}
+
+/// The keys which are currently held down, shared by every window of
+/// the application. Get it with `Runtime::keyboard()` from components
+/// and event handlers.
+///
+/// A key is held from when it's pressed in a window until it's
+/// released. Releases aren't reported once a window loses focus, so
+/// all keys are considered released when any window does.
+#[derive(Clone, Debug, Default)]
+pub struct KeyboardState(Arc<Mutex<HashSet<Key>>>);
+
+impl KeyboardState {
+    pub fn is_held(&self, key: Key) -> bool {
+        self.0.lock().unwrap().contains(&key)
+    }
+
+    /// All of the held keys, in no particular order.
+    pub fn held(&self) -> Vec<Key> {
+        self.0.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Returns whether the key wasn't already held, meaning this isn't
+    /// a repeat.
+    pub(crate) fn press(&self, key: Key) -> bool {
+        self.0.lock().unwrap().insert(key)
+    }
+
+    pub(crate) fn release(&self, key: Key) {
+        self.0.lock().unwrap().remove(&key);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
 impl InputEvent {
     pub fn get_position(&self) -> Option<(f32, f32)> {
         None
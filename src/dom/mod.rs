@@ -4,6 +4,7 @@
 
 pub mod attributes;
 pub mod devtools;
+pub mod diff;
 pub mod element;
 pub mod elements;
 pub mod events;
@@ -11,6 +12,18 @@ pub mod input;
 pub mod node;
 
 pub use attributes::*;
-pub use elements::{app::App, button::Button, span::Span, view::View, window::Window};
+pub use diff::{diff, Patch};
+pub use elements::{
+    app::App,
+    button::Button,
+    image::{Image, ImageBytes, ImageSource},
+    spacer::Spacer,
+    span::Span,
+    svg::{ParseSvgError, Svg, SvgImage},
+    text_area::{TextArea, TextValue},
+    view::{Portal, View},
+    window::{Decorations, Window, WindowIcon, WindowLevel},
+};
 pub use events::*;
+pub use input::{InputModality, KeyboardState, PointerId, TouchPhase};
 pub use node::Node;
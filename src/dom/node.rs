@@ -1,20 +1,29 @@
-use crate::dom::element::{DynamicNode, Element, ElementStates, NodeChild};
-use crate::dom::input::InputEvent;
-use crate::style::{ComputedValues, Style};
-use std::any::{type_name, TypeId};
+use crate::dom::element::{DynamicNode, Element, ElementStates, Event, HasEvent, NodeChild};
+use crate::dom::input::{InputEvent, Key};
+use crate::dom::{ImageSource, Portal};
+use crate::render::painter::{PaintOverlay, Painter};
+use crate::style::{ComputedValues, PaintDetails, Style};
+use std::any::{type_name, Any, TypeId};
 use std::fmt::{Debug, Formatter, Result as FmtResult};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Counts the nodes built, for `NodeData::revision()`.
+static REVISIONS: AtomicU64 = AtomicU64::new(0);
+
 pub struct NodeData<Elt>
 where
     Elt: Element,
 {
     id: u64,
+    revision: u64,
     element: Elt,
     handlers: Mutex<Elt::Handlers>,
-    children: Vec<Elt::Child>,
+    /// Shared with the other nodes built with the same children, so
+    /// changing only the element doesn't copy them.
+    children: Arc<[Elt::Child]>,
 }
 
 impl<Elt> Debug for NodeData<Elt>
@@ -36,9 +45,10 @@ impl<Elt> NodeData<Elt>
 where
     Elt: Element,
 {
-    fn new(id: u64, element: Elt, children: Vec<Elt::Child>) -> NodeData<Elt> {
+    fn new(id: u64, element: Elt, children: Arc<[Elt::Child]>) -> NodeData<Elt> {
         NodeData {
             id,
+            revision: REVISIONS.fetch_add(1, Ordering::Relaxed),
             element,
             children,
             handlers: Mutex::new(Default::default()),
@@ -59,9 +69,30 @@ where
         &self.handlers
     }
 
+    /// Invokes the element's handler for `event`, if it has one, for
+    /// events which don't come from input, such as ones synthesized by
+    /// the app or by tests.
+    pub fn emit<Ev>(&self, event: &Ev)
+    where
+        Ev: Event,
+        Elt: HasEvent<Ev>,
+    {
+        // The lock is released first, so the handler can emit events on
+        // the node itself.
+        let handler = Elt::get_handler(&self.handlers.lock().unwrap()).clone();
+        handler.invoke(event);
+    }
+
     pub fn id(&self) -> u64 {
         self.id
     }
+
+    /// Tells apart the nodes built for the same element as it changes.
+    /// Unlike the id, it's different for every node built, and unlike
+    /// the node's address, it's never reused.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
 }
 
 pub struct NodeDataChildrenIter<'a> {
@@ -83,13 +114,41 @@ pub trait AnyNodeData: Debug {
     fn get_child(&self, index: usize) -> Option<DynamicNode>;
     fn children(&self) -> NodeDataChildrenIter;
     fn process(&self, states: ElementStates, event: &InputEvent) -> ElementStates;
+    /// Like `process`, but also returns whether the element handled the
+    /// event.
+    fn dispatch(&self, states: ElementStates, event: &InputEvent) -> (bool, ElementStates);
     fn create_computed_values(&self) -> ComputedValues;
+    fn paint(&self, values: &ComputedValues) -> PaintDetails;
     fn style(&self) -> Option<Style>;
+    fn element_id(&self) -> Option<&str>;
+    fn class(&self) -> Option<&str>;
+    fn title(&self) -> Option<&str>;
+    fn before(&self) -> Option<&str>;
+    fn after(&self) -> Option<&str>;
+    fn portal(&self) -> Option<Portal>;
+    fn image_source(&self) -> Option<&ImageSource>;
+    fn draw(&self, painter: &mut Painter);
+    fn paint_overlay(&self) -> Option<&PaintOverlay>;
     fn type_id(&self) -> TypeId;
     fn attributes(&self) -> Vec<(&'static str, String)>;
     fn name(&self) -> &'static str;
     fn id(&self) -> u64;
+    fn revision(&self) -> u64;
     fn interactive(&self) -> bool;
+    fn focusable(&self) -> bool;
+    fn tab_index(&self) -> Option<i32>;
+    fn captures_key(&self, key: Key) -> bool;
+    fn window_key(&self, key: Key) -> bool;
+    fn key_repeat(&self) -> bool;
+    fn drag_region(&self) -> bool;
+    fn event_sink(&self) -> bool;
+    fn caret(&self) -> Option<usize>;
+    fn as_any(&self) -> &dyn Any;
+    /// Compares the element with `other`'s, returning `None` when
+    /// they're equal, or when `other` is a different type of element.
+    /// Otherwise, returns the names of the attributes which changed,
+    /// see `Element::changed_attributes()`.
+    fn changed_attributes(&self, other: &dyn AnyNodeData) -> Option<Vec<&'static str>>;
 }
 
 impl<Elt> AnyNodeData for NodeData<Elt>
@@ -97,6 +156,12 @@ where
     Elt: Element,
 {
     fn get_child(&self, index: usize) -> Option<DynamicNode> {
+        if let Some(content) = self.element.content() {
+            if index == 0 {
+                return Some(DynamicNode::Text(content));
+            }
+            return self.children.get(index - 1).map(|child| child.get_node());
+        }
         self.children.get(index).map(|child| child.get_node())
     }
 
@@ -108,19 +173,63 @@ where
     }
 
     fn process(&self, states: ElementStates, event: &InputEvent) -> ElementStates {
-        let mut handlers = self.handlers.lock().unwrap();
-        let (_sink, new_states) = self.element.process(states, &mut *handlers, event);
+        let (_sink, new_states) = self.dispatch(states, event);
         new_states
     }
 
+    fn dispatch(&self, states: ElementStates, event: &InputEvent) -> (bool, ElementStates) {
+        let mut handlers = self.handlers.lock().unwrap();
+        self.element.process(states, &mut *handlers, event)
+    }
+
     fn create_computed_values(&self) -> ComputedValues {
         self.element.create_computed_values()
     }
 
+    fn paint(&self, values: &ComputedValues) -> PaintDetails {
+        self.element.paint(values)
+    }
+
     fn style(&self) -> Option<Style> {
         self.element.style()
     }
 
+    fn element_id(&self) -> Option<&str> {
+        self.element.element_id()
+    }
+
+    fn class(&self) -> Option<&str> {
+        self.element.class()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.element.title()
+    }
+
+    fn before(&self) -> Option<&str> {
+        self.element.before()
+    }
+
+    fn portal(&self) -> Option<Portal> {
+        self.element.portal()
+    }
+
+    fn after(&self) -> Option<&str> {
+        self.element.after()
+    }
+
+    fn image_source(&self) -> Option<&ImageSource> {
+        self.element.image_source()
+    }
+
+    fn draw(&self, painter: &mut Painter) {
+        self.element.draw(painter)
+    }
+
+    fn paint_overlay(&self) -> Option<&PaintOverlay> {
+        self.element.paint_overlay()
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<Elt>()
     }
@@ -137,9 +246,57 @@ where
         self.id
     }
 
+    fn revision(&self) -> u64 {
+        self.revision
+    }
+
     fn interactive(&self) -> bool {
         self.element.interactive()
     }
+
+    fn focusable(&self) -> bool {
+        self.element.focusable()
+    }
+
+    fn tab_index(&self) -> Option<i32> {
+        self.element.tab_index()
+    }
+
+    fn captures_key(&self, key: Key) -> bool {
+        self.element.captures_key(key)
+    }
+
+    fn window_key(&self, key: Key) -> bool {
+        self.element.window_key(key)
+    }
+
+    fn key_repeat(&self) -> bool {
+        self.element.key_repeat()
+    }
+
+    fn drag_region(&self) -> bool {
+        self.element.drag_region()
+    }
+
+    fn event_sink(&self) -> bool {
+        self.element.event_sink()
+    }
+
+    fn caret(&self) -> Option<usize> {
+        self.element.caret()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn changed_attributes(&self, other: &dyn AnyNodeData) -> Option<Vec<&'static str>> {
+        let other = other.as_any().downcast_ref::<NodeData<Elt>>()?;
+        if self.element == other.element {
+            return None;
+        }
+        Some(self.element.changed_attributes(&other.element))
+    }
 }
 
 /// Typed handle to a DOM node.
@@ -152,8 +309,16 @@ where
 {
     /// Create a new DOM node from the given element and children vector.
     pub fn new(id: u64, element: Elt, children: Vec<Elt::Child>) -> Node<Elt> {
-        let data = Arc::new(NodeData::new(id, element, children));
-        Node(data)
+        Node::with_shared_children(id, element, children.into())
+    }
+
+    /// Create a node whose children are shared with other nodes.
+    pub(crate) fn with_shared_children(
+        id: u64,
+        element: Elt,
+        children: Arc<[Elt::Child]>,
+    ) -> Node<Elt> {
+        Node(Arc::new(NodeData::new(id, element, children)))
     }
 }
 
@@ -254,6 +419,11 @@ impl<'a> NodeRef<'a> {
     pub fn to_owned(&self) -> AnyNode {
         self.0.to_owned()
     }
+
+    /// Whether the node is of the element type `Elt`, such as `Button`.
+    pub fn is<Elt: Element>(&self) -> bool {
+        self.type_id() == TypeId::of::<Elt>()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1,25 +1,193 @@
-use super::{inline, LayoutChild, LayoutTreeNode, LogicalSize, RenderData};
+use super::{
+    first_baseline, inline, laid_out_children, next_generation, LayoutChild, LayoutTreeNode,
+    LogicalSize, RenderData,
+};
 use crate::document::DocumentState;
-use crate::dom::{element::DynamicNode, node::AnyNode, node::NodeRef};
-use crate::style::{BlockValues, ComputedValues, Direction, DisplayType};
+use crate::dom::{element::DynamicNode, node::AnyNode, node::NodeRef, Portal};
+use crate::style::{Alignment, BlockValues, ComputedValues, Dimension, Direction, DisplayType};
 use crate::util::equal_rc::EqualRc;
 use euclid::{point2, size2, vec2};
 
-fn calc_max_size(values: &BlockValues, parent_size: LogicalSize) -> LogicalSize {
+/// The sizes set on a box, in pixels, with percentages resolved against
+/// the size of its parent's content box. Sizes which resolve to less
+/// than zero, such as `calc(50% - 20px)` in a parent of unbounded size,
+/// where the percentage is left out, are zero.
+#[derive(Copy, Clone, Default, PartialEq)]
+struct Sizes {
+    width: Option<f32>,
+    height: Option<f32>,
+    min_width: Option<f32>,
+    min_height: Option<f32>,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+}
+
+impl Sizes {
+    fn resolve(values: &BlockValues, parent: LogicalSize) -> Sizes {
+        let width = |dimension: Option<Dimension>| {
+            dimension.map(|dimension| dimension.resolve(parent.width).get().max(0.0))
+        };
+        let height = |dimension: Option<Dimension>| {
+            dimension.map(|dimension| dimension.resolve(parent.height).get().max(0.0))
+        };
+        Sizes {
+            width: width(values.width),
+            height: height(values.height),
+            min_width: width(values.min_width),
+            min_height: height(values.min_height),
+            max_width: width(values.max_width),
+            max_height: height(values.max_height),
+        }
+    }
+}
+
+fn calc_max_size(values: &BlockValues, sizes: &Sizes, parent_size: LogicalSize) -> LogicalSize {
     let mut outer = parent_size;
-    if let Some(width) = values.width {
-        outer.width = width.get();
+    if let Some(width) = sizes.width {
+        outer.width = width;
+    }
+    if let Some(height) = sizes.height {
+        outer.height = height;
     }
-    if let Some(height) = values.height {
-        outer.height = height.get();
+    if let Some(max_width) = sizes.max_width {
+        outer.width = outer.width.min(max_width);
+    }
+    if let Some(max_height) = sizes.max_height {
+        outer.height = outer.height.min(max_height);
     }
     outer - size2(values.padding.horizontal(), values.padding.vertical())
 }
 
+/// How a child of a block takes part in handing out its free space.
+#[derive(Copy, Clone, Default, PartialEq)]
+struct Flex {
+    grow: f32,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+}
+
+impl Flex {
+    fn new(block: &BlockValues, sizes: &Sizes) -> Flex {
+        Flex {
+            grow: block.flex_grow,
+            max_width: sizes.max_width,
+            max_height: sizes.max_height,
+        }
+    }
+}
+
+/// Copies a laid out element with a larger size, for growing it into
+/// free space. Its contents stay where they were.
+fn grown(layout: &LayoutTreeNode, extra: LogicalSize) -> EqualRc<LayoutTreeNode> {
+    let render = match layout.render {
+        RenderData::Node(ref node) => RenderData::Node(node.clone()),
+        RenderData::Text { .. } => unreachable!("text doesn't grow"),
+    };
+    EqualRc::new(LayoutTreeNode {
+        size: layout.size + extra,
+        margin: layout.margin,
+        baseline: layout.baseline,
+        render,
+        children: layout.children.clone(),
+        generation: next_generation(),
+    })
+}
+
+/// Hands out the space left over along the main axis to the children
+/// which grow, in proportion to their `flex_grow`, moving the children
+/// after them along. This only happens when the box has a fixed size
+/// along its direction, since otherwise it's sized to its children.
+///
+/// Children don't grow past their maximum size, and the share they
+/// can't take is left empty rather than handed to the others. Nothing
+/// shrinks when there isn't enough space: children are laid out within
+/// the space their parent has to begin with, so text in them wraps
+/// sooner, and what doesn't fit overflows.
+fn distribute_free_space(
+    block_values: &BlockValues,
+    main_size: f32,
+    used: f32,
+    flex: &[Flex],
+    children: &mut [LayoutChild],
+) {
+    let free = main_size - used;
+    let total: f32 = flex.iter().map(|flex| flex.grow).sum();
+    if free <= 0.0 || total <= 0.0 {
+        return;
+    }
+    let mut offset = 0.0;
+    for (child, flex) in children.iter_mut().zip(flex) {
+        let (size, max) = match block_values.direction {
+            Direction::Horizontal => (child.layout.size.width, flex.max_width),
+            Direction::Vertical => (child.layout.size.height, flex.max_height),
+        };
+        let mut extra = free * flex.grow / total;
+        if let Some(max) = max {
+            extra = extra.min((max - size).max(0.0));
+        }
+        let (shift, extra_size) = match block_values.direction {
+            Direction::Horizontal => (vec2(offset, 0.0), size2(extra, 0.0)),
+            Direction::Vertical => (vec2(0.0, offset), size2(0.0, extra)),
+        };
+        child.position += shift;
+        if extra > 0.0 {
+            child.layout = grown(&child.layout, extra_size);
+        }
+        offset += extra;
+    }
+}
+
+/// Shifts the children of a row which are aligned by their baselines
+/// down, so that their baselines are as low as the lowest of them,
+/// returning the height of the row with them shifted. Children without
+/// a baseline use the bottom edge of their margin.
+fn align_baselines(
+    align_items: Alignment,
+    align_self: &[Option<Alignment>],
+    children: &mut [LayoutChild],
+    height: f32,
+) -> f32 {
+    let baseline = |child: &LayoutChild| {
+        let layout = &child.layout;
+        layout
+            .baseline
+            .unwrap_or(layout.size.height + layout.margin.vertical())
+    };
+    let aligned = |index: usize| align_self[index].unwrap_or(align_items) == Alignment::Baseline;
+    let shared = children
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| aligned(*index))
+        .map(|(_, child)| baseline(child))
+        .fold(None, |lowest: Option<f32>, baseline| {
+            Some(lowest.map_or(baseline, |lowest| lowest.max(baseline)))
+        });
+    let shared = match shared {
+        Some(shared) => shared,
+        None => return height,
+    };
+    let mut height = height;
+    for (index, child) in children.iter_mut().enumerate() {
+        if aligned(index) {
+            let shift = shared - baseline(child);
+            child.position.y += shift;
+            let size = child.layout.size.height + child.layout.margin.vertical();
+            height = height.max(shift + size);
+        }
+    }
+    height
+}
+
 fn calc_block_layout(
-    input: &(ComputedValues, Vec<EqualRc<LayoutTreeNode>>, AnyNode),
+    input: &(
+        ComputedValues,
+        Sizes,
+        Vec<(EqualRc<LayoutTreeNode>, Flex, Option<Alignment>)>,
+        Vec<(EqualRc<LayoutTreeNode>, Portal)>,
+        AnyNode,
+    ),
 ) -> EqualRc<LayoutTreeNode> {
-    let (values, children, node) = input;
+    let (values, sizes, children, portals, node) = input;
 
     let block_values = if let DisplayType::Block(block) = values.display {
         block
@@ -35,7 +203,7 @@ fn calc_block_layout(
     let mut width = 0.0f32;
     let mut height = 0.0f32;
     let mut child_positions = vec![];
-    for child in children {
+    for (child, _, _) in children {
         let child = child.clone();
         let size = child.size + size2(child.margin.horizontal(), child.margin.vertical());
         if block_values.direction == Direction::Vertical {
@@ -55,6 +223,19 @@ fn calc_block_layout(
         }
     }
 
+    if block_values.direction == Direction::Horizontal {
+        let align_self = children
+            .iter()
+            .map(|(_, _, align)| *align)
+            .collect::<Vec<_>>();
+        height = align_baselines(
+            block_values.align_items,
+            &align_self,
+            &mut child_positions,
+            height,
+        );
+    }
+
     let size = size2(width, height);
     let padding = size2(
         block_values.padding.horizontal(),
@@ -67,11 +248,63 @@ fn calc_block_layout(
 
     let mut size = size + padding + border;
 
-    if let Some(width) = block_values.width {
-        size.width = width.get();
+    if let Some(width) = sizes.width {
+        size.width = width;
+    }
+    if let Some(height) = sizes.height {
+        size.height = height;
     }
-    if let Some(height) = block_values.height {
-        size.height = height.get();
+
+    let (main_size, used) = match block_values.direction {
+        Direction::Horizontal => (
+            sizes
+                .width
+                .map(|_| size.width - padding.width - border.width),
+            width,
+        ),
+        Direction::Vertical => (
+            sizes
+                .height
+                .map(|_| size.height - padding.height - border.height),
+            height,
+        ),
+    };
+    if let Some(main_size) = main_size {
+        let flex = children
+            .iter()
+            .map(|(_, flex, _)| *flex)
+            .collect::<Vec<_>>();
+        distribute_free_space(&block_values, main_size, used, &flex, &mut child_positions);
+    }
+
+    // The minimum size wins when it's larger than the maximum, like in
+    // CSS.
+    if let Some(max_width) = sizes.max_width {
+        size.width = size.width.min(max_width);
+    }
+    if let Some(max_height) = sizes.max_height {
+        size.height = size.height.min(max_height);
+    }
+    if let Some(min_width) = sizes.min_width {
+        size.width = size.width.max(min_width);
+    }
+    if let Some(min_height) = sizes.min_height {
+        size.height = size.height.max(min_height);
+    }
+
+    // Portals come after the children in flow, which don't make room for
+    // them, at their place next to this box's border box.
+    let baseline = first_baseline(&child_positions);
+    for (portal, placement) in portals {
+        let anchor = match placement {
+            Portal::Over => vec2(0.0, 0.0),
+            Portal::Below => vec2(0.0, size.height),
+            Portal::Right => vec2(size.width, 0.0),
+        };
+        child_positions.push(LayoutChild {
+            position: point2(portal.margin.left, portal.margin.top) + anchor,
+            layout: portal.clone(),
+        });
     }
 
     let margin = block_values.margin;
@@ -79,44 +312,103 @@ fn calc_block_layout(
     EqualRc::new(LayoutTreeNode {
         size,
         margin,
+        baseline,
         children: child_positions,
         render: RenderData::Node(node.clone()),
+        generation: next_generation(),
     })
 }
 
+/// Lays out a box within the space its parent has for it. Percentages
+/// in its sizes are of `parent_size`, the size of the parent's content
+/// box, which is larger than `parent_max_size` after the first child of
+/// a row.
 pub(crate) fn layout_block(
     state: &mut DocumentState,
     node: NodeRef,
     values: &ComputedValues,
     block_values: &BlockValues,
     parent_max_size: LogicalSize,
+    parent_size: LogicalSize,
 ) -> EqualRc<LayoutTreeNode> {
-    let max_size = calc_max_size(block_values, parent_max_size);
+    let sizes = Sizes::resolve(block_values, parent_size);
+    let content_size = calc_max_size(block_values, &sizes, parent_size);
+    let max_size = calc_max_size(block_values, &sizes, parent_max_size);
 
+    // In a row, each child only gets the width its earlier siblings
+    // left, so that text in it wraps to fit, and the row grows to its
+    // wrapped height.
+    let mut used_width = 0.0f32;
     let mut children = vec![];
-    for child in node.children() {
+    let mut portals = vec![];
+    for child in laid_out_children(&node) {
+        let max_size = match block_values.direction {
+            Direction::Horizontal => size2((max_size.width - used_width).max(0.0), max_size.height),
+            Direction::Vertical => max_size,
+        };
+        let previous = children.len();
         topo::call(|| match child {
             DynamicNode::Node(node) => {
                 let values = *state.computed_values(node.id());
+                if values.display_none {
+                    return;
+                }
                 match values.display {
+                    DisplayType::Block(ref block) if node.portal().is_some() => {
+                        let window = state.content_size;
+                        let layout = layout_block(state, node, &values, block, window, window);
+                        portals.push((layout, node.portal().unwrap()));
+                    }
                     DisplayType::Block(ref block) => {
-                        children.push(layout_block(state, node, &values, block, max_size));
+                        let layout =
+                            layout_block(state, node, &values, block, max_size, content_size);
+                        let sizes = Sizes::resolve(block, content_size);
+                        children.push((layout, Flex::new(block, &sizes), values.align_self));
                     }
                     DisplayType::Inline(_) => {
-                        children.push(inline::layout_inline(state, node, &values, max_size));
+                        let layout = inline::layout_inline(state, node, &values, max_size);
+                        children.push((layout, Flex::default(), values.align_self));
                     }
                 }
             }
             DynamicNode::Text(text) => {
-                children.push(inline::layout_text(
-                    node.to_owned(),
-                    text,
-                    max_size.width,
-                    values,
-                ));
+                let layout = inline::layout_text(node.to_owned(), text, max_size.width, values);
+                children.push((layout, Flex::default(), None));
             }
-        })
+        });
+        if let Some((layout, _, _)) = children.get(previous) {
+            used_width += layout.size.width + layout.margin.horizontal();
+        }
     }
 
-    moxie::cache(&(*values, children, node.to_owned()), calc_block_layout)
+    moxie::cache(
+        &(*values, sizes, children, portals, node.to_owned()),
+        calc_block_layout,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sizes;
+    use crate::style::{BlockValues, Dimension};
+    use euclid::size2;
+
+    #[test]
+    fn negative_sizes_are_zero() {
+        let calc = Dimension::parse("calc(50% - 20px)");
+        let values = BlockValues {
+            width: calc,
+            height: calc,
+            min_width: calc,
+            max_height: calc,
+            ..BlockValues::default()
+        };
+        let sizes = Sizes::resolve(&values, size2(std::f32::INFINITY, 30.0));
+        assert_eq!(sizes.width, Some(0.0));
+        assert_eq!(sizes.min_width, Some(0.0));
+        assert_eq!(sizes.height, Some(0.0));
+        assert_eq!(sizes.max_height, Some(0.0));
+        let sizes = Sizes::resolve(&values, size2(100.0, 100.0));
+        assert_eq!(sizes.width, Some(30.0));
+    }
 }
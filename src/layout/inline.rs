@@ -1,5 +1,5 @@
 use super::{
-    block,
+    block, first_baseline, laid_out_children, next_generation,
     text::{TextLayoutInfo, TextState},
     LayoutChild, LayoutText, LayoutTreeNode, LogicalSideOffsets, LogicalSize, RenderData,
 };
@@ -94,12 +94,16 @@ impl LineState {
                         text: LayoutText {
                             fragments: line.fragments,
                             size: line.text_size,
+                            range: line.range,
+                            boundaries: line.boundaries,
                         },
                         parent,
                     },
                     size: size2(line.width, line.height),
                     margin: LogicalSideOffsets::default(),
+                    baseline: Some(line.ascender),
                     children: vec![],
+                    generation: next_generation(),
                 }),
             });
 
@@ -121,13 +125,17 @@ fn collect_inline_items(
     max_size: LogicalSize,
     items: &mut Vec<InlineLayoutItem>,
 ) {
-    for child in node.children() {
+    for child in laid_out_children(&node) {
         topo::call(|| match child {
             DynamicNode::Node(node) => {
                 let values = *state.computed_values(node.id());
+                if values.display_none {
+                    return;
+                }
                 match values.display {
                     DisplayType::Block(ref block) => {
-                        let layout = block::layout_block(state, node, &values, block, max_size);
+                        let layout =
+                            block::layout_block(state, node, &values, block, max_size, max_size);
                         items.push(InlineLayoutItem::Block(layout));
                     }
                     DisplayType::Inline(_) => {
@@ -190,8 +198,10 @@ fn calc_inline_layout(
     EqualRc::new(LayoutTreeNode {
         render: RenderData::Node(node),
         margin: LogicalSideOffsets::default(),
+        baseline: first_baseline(&children),
         size,
         children,
+        generation: next_generation(),
     })
 }
 
@@ -2,39 +2,72 @@
 //! arranging elements and performing text layout.
 
 use crate::document::DocumentState;
-use crate::dom::node::AnyNode;
+use crate::dom::element::{DynamicNode, Element};
+use crate::dom::node::{AnyNode, AnyNodeData, Node, NodeRef};
 use crate::style::DisplayType;
 use crate::util::equal_rc::EqualRc;
-use euclid::{Length, Point2D, SideOffsets2D, Size2D};
+use euclid::{Length, Point2D, Rect, SideOffsets2D, Size2D, Transform2D};
 use font_kit::family_name::FamilyName;
 use font_kit::properties::Properties;
 use font_kit::source::SystemSource;
 use moxie::runtime::Runtime;
 use skribo::{FontCollection, FontFamily, FontRef};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 
 mod block;
 mod inline;
 mod text;
 
+pub use text::text_hit_test;
+
 pub struct LogicalPixel;
 pub type LogicalPoint = Point2D<f32, LogicalPixel>;
 pub type LogicalSize = Size2D<f32, LogicalPixel>;
+pub type LogicalRect = Rect<f32, LogicalPixel>;
 pub type LogicalLength = Length<f32, LogicalPixel>;
 pub type LogicalSideOffsets = SideOffsets2D<f32, LogicalPixel>;
+pub type LogicalTransform = Transform2D<f32, LogicalPixel, LogicalPixel>;
+
+/// Maps a point through a chain of transforms, from the outermost space
+/// to the innermost. Each transform maps points from its own space into
+/// the one before it, the way an element's transform maps its contents
+/// into its parent. Returns `None` if any transform can't be inverted,
+/// such as a scale by zero, since points can't be mapped back through
+/// it.
+pub fn to_local_space(chain: &[LogicalTransform], point: LogicalPoint) -> Option<LogicalPoint> {
+    chain.iter().try_fold(point, |point, transform| {
+        Some(transform.inverse()?.transform_point(point))
+    })
+}
+
+/// Maps a rect through a chain of transforms the other way around, from
+/// the innermost space out to the outermost one.
+pub fn from_local_space(chain: &[LogicalTransform], rect: LogicalRect) -> LogicalRect {
+    chain
+        .iter()
+        .rev()
+        .fold(rect, |rect, transform| transform.transform_rect(&rect))
+}
 
 /// Each edge of the layout tree contains information on the positions
 /// of the child elements, since elements are positioned relative to
 /// their parents, and the position is assigned by the parent.
+#[derive(Clone)]
 pub struct LayoutChild {
     pub position: LogicalPoint,
     pub layout: EqualRc<LayoutTreeNode>,
 }
 
+/// A glyph of a `TextFragment`, offset from the position of its line.
 pub struct Glyph {
     pub index: u32,
     pub offset: LogicalPoint,
 }
 
+/// A run of glyphs of a line of text in the same font.
 pub struct TextFragment {
     pub font: FontRef,
     pub glyphs: Vec<Glyph>,
@@ -45,6 +78,22 @@ pub struct LayoutText {
     pub fragments: Vec<TextFragment>,
     /// The text size of the text.
     pub size: f32,
+    /// The byte range of the source text covered by this line.
+    pub range: Range<usize>,
+    /// The x offset of each character boundary in `range`, used for
+    /// placing carets.
+    pub boundaries: Vec<(usize, f32)>,
+}
+
+impl LayoutText {
+    /// Returns the x offset of the caret at the given byte offset, if
+    /// the offset falls on this line.
+    pub fn caret_position(&self, offset: usize) -> Option<f32> {
+        self.boundaries
+            .iter()
+            .find(|(index, _)| *index == offset)
+            .map(|(_, x)| *x)
+    }
 }
 
 pub enum RenderData {
@@ -57,8 +106,42 @@ pub struct LayoutTreeNode {
     /// The computed size of the node.
     pub size: LogicalSize,
     pub margin: LogicalSideOffsets,
+    /// How far below the top of the node the baseline of its first line
+    /// of text is, or `None` if there's no text in it.
+    pub baseline: Option<f32>,
     pub render: RenderData,
     pub children: Vec<LayoutChild>,
+    /// Unique to this layout of the subtree. Layouts are reused while
+    /// nothing they're made from changes, including the computed values
+    /// and the nodes themselves, so a subtree with the same generation
+    /// as last frame lays out and paints the same.
+    pub generation: u64,
+}
+
+/// A fresh `LayoutTreeNode::generation`.
+fn next_generation() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The children of a node in the order they're laid out, with the text
+/// of its `before` and `after` attributes around them.
+fn laid_out_children<'a>(node: &'a NodeRef<'a>) -> impl Iterator<Item = DynamicNode<'a>> + 'a {
+    let node: &'a dyn AnyNodeData = &**node;
+    let before = node.before().map(DynamicNode::Text);
+    let after = node.after().map(DynamicNode::Text);
+    before.into_iter().chain(node.children()).chain(after)
+}
+
+/// The baseline of the first of `children` which has one, relative to
+/// their parent.
+fn first_baseline(children: &[LayoutChild]) -> Option<f32> {
+    children.iter().find_map(|child| {
+        child
+            .layout
+            .baseline
+            .map(|baseline| child.position.y + baseline)
+    })
 }
 
 /// Used to build the layout tree, with internal caching for
@@ -92,7 +175,7 @@ impl LayoutEngine {
             .offer(self.collection.clone())
             .enter(move || {
                 self.runtime.run_once(move || {
-                    let node = state.window.clone();
+                    let node = state.root.clone();
                     let values = *state.computed_values(node.id());
                     match values.display {
                         DisplayType::Block(ref block) => block::layout_block(
@@ -101,6 +184,7 @@ impl LayoutEngine {
                             &values,
                             block,
                             state.content_size,
+                            state.content_size,
                         ),
                         DisplayType::Inline(_) => inline::layout_inline(
                             state,
@@ -113,3 +197,587 @@ impl LayoutEngine {
             })
     }
 }
+
+/// The result of `compute_layout`, with the rect of every node in the
+/// tree.
+pub struct LayoutTree {
+    pub(crate) root: EqualRc<LayoutTreeNode>,
+    rects: HashMap<u64, LogicalRect>,
+}
+
+/// How a node's rect changed between two layouts. `old` is `None` for
+/// nodes that weren't laid out before, and `new` is `None` for nodes
+/// that were removed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RectChange {
+    pub id: u64,
+    pub old: Option<LogicalRect>,
+    pub new: Option<LogicalRect>,
+}
+
+impl LayoutTree {
+    pub(crate) fn new(root: EqualRc<LayoutTreeNode>) -> LayoutTree {
+        let mut rects = HashMap::new();
+        collect_rects(&root, LogicalPoint::zero(), &mut rects);
+        LayoutTree { root, rects }
+    }
+
+    /// The border box of the node with the given id, relative to the
+    /// root of the tree.
+    pub fn rect(&self, id: u64) -> Option<LogicalRect> {
+        self.rects.get(&id).copied()
+    }
+
+    pub(crate) fn rects(&self) -> &HashMap<u64, LogicalRect> {
+        &self.rects
+    }
+
+    /// Lists the nodes whose rects differ from `previous`, ordered by
+    /// id.
+    pub fn changes_since(&self, previous: &HashMap<u64, LogicalRect>) -> Vec<RectChange> {
+        let mut changes = self
+            .rects
+            .iter()
+            .filter(|(id, rect)| previous.get(id) != Some(rect))
+            .map(|(&id, &rect)| RectChange {
+                id,
+                old: previous.get(&id).copied(),
+                new: Some(rect),
+            })
+            .chain(
+                previous
+                    .iter()
+                    .filter(|(id, _)| !self.rects.contains_key(id))
+                    .map(|(&id, &rect)| RectChange {
+                        id,
+                        old: Some(rect),
+                        new: None,
+                    }),
+            )
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|change| change.id);
+        changes
+    }
+}
+
+fn collect_rects(
+    layout: &LayoutTreeNode,
+    position: LogicalPoint,
+    rects: &mut HashMap<u64, LogicalRect>,
+) {
+    if let RenderData::Node(ref node) = layout.render {
+        rects.insert(node.id(), LogicalRect::new(position, layout.size));
+    }
+    for child in &layout.children {
+        collect_rects(&child.layout, position + child.position.to_vector(), rects);
+    }
+}
+
+/// Runs styling and layout over a tree, without needing a window or
+/// a renderer. The result only depends on the tree, the available
+/// size and the system's default font.
+pub fn compute_layout<Elt>(root: &Node<Elt>, available: LogicalSize) -> LayoutTree
+where
+    Elt: Element,
+{
+    let mut state = DocumentState::new(NodeRef::from(root), available);
+    state.update_styles(&mut Runtime::new(), Instant::now());
+    LayoutTree::new(LayoutEngine::new().layout(&mut state))
+}
+
+/// Measures the natural size of a tree's border box when given up to
+/// `available` space, without a window or a document, for sizing
+/// things such as popups to their content before placing them. Like
+/// `compute_layout`, the tree is styled on its own, so it doesn't
+/// inherit text styles from where it will be placed.
+///
+/// Elements shrink to fit their content, so this returns the width of
+/// the content with text wrapped to fit within `available`, unless the
+/// style sets a size. An infinite width gives the max-content size,
+/// where text only breaks at newlines, and a width of zero gives the
+/// min-content size, where text breaks at every opportunity.
+pub fn measure_intrinsic<Elt>(root: &Node<Elt>, available: LogicalSize) -> LogicalSize
+where
+    Elt: Element,
+{
+    compute_layout(root, available).root.size
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        compute_layout, measure_intrinsic, text_hit_test, to_local_space, LayoutEngine, LayoutTree,
+        LayoutTreeNode, LogicalLength, LogicalPoint, LogicalRect, LogicalSideOffsets,
+        LogicalTransform, RenderData,
+    };
+    use crate::document::DocumentState;
+    use crate::dom::node::NodeRef;
+    use crate::mox_impl::{attr, elt};
+    use crate::style::{sized_box, test_style, Alignment, Direction, DisplayType, Style};
+    use crate::style_impl::{apply, attribute::width, func::calc, func::fit_content, pixels, rems};
+    use euclid::{point2, rect, size2};
+    use moxie::runtime::Runtime;
+    use std::time::Instant;
+
+    #[test]
+    fn nested_blocks() {
+        let padded = test_style("padded", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.padding = LogicalSideOffsets::new_all_same(10.0);
+            }
+        });
+        let square = sized_box("square", 20.0, 30.0);
+
+        let (root, first, second) = Runtime::new().run_once(|| {
+            let first = elt::view().set_attr(attr::style(), square).build();
+            let second = elt::view().set_attr(attr::style(), square).build();
+            let root = elt::view()
+                .set_attr(attr::style(), padded)
+                .add_child(first.clone())
+                .add_child(second.clone())
+                .build();
+            (root, first, second)
+        });
+
+        let tree = compute_layout(&root, size2(100.0, 100.0));
+        let expected: LogicalRect = rect(0.0, 0.0, 40.0, 80.0);
+        assert_eq!(tree.rect(root.id()), Some(expected));
+        assert_eq!(tree.rect(first.id()), Some(rect(10.0, 10.0, 20.0, 30.0)));
+        assert_eq!(tree.rect(second.id()), Some(rect(10.0, 40.0, 20.0, 30.0)));
+    }
+
+    #[test]
+    fn intrinsic_text_size() {
+        let root = Runtime::new().run_once(|| {
+            elt::span()
+                .add_child("measured without wrapping".to_owned())
+                .build()
+        });
+
+        let max_content = measure_intrinsic(&root, size2(std::f32::INFINITY, std::f32::INFINITY));
+        let roomy = compute_layout(&root, size2(10_000.0, 10_000.0));
+        assert_eq!(
+            roomy.rect(root.id()).map(|rect| rect.size),
+            Some(max_content)
+        );
+
+        let min_content = measure_intrinsic(&root, size2(0.0, std::f32::INFINITY));
+        assert!(min_content.width < max_content.width);
+        assert!(min_content.height > max_content.height);
+    }
+
+    #[test]
+    fn point_through_transforms() {
+        // A canvas 10px in, drawn at twice the size.
+        let chain = [
+            LogicalTransform::translation(10.0, 20.0),
+            LogicalTransform::scale(2.0, 2.0),
+        ];
+        assert_eq!(
+            to_local_space(&chain, point2(30.0, 30.0)),
+            Some(point2(10.0, 5.0))
+        );
+        assert_eq!(
+            to_local_space(&[], point2(1.0, 2.0)),
+            Some(point2(1.0, 2.0))
+        );
+
+        let flattened = [LogicalTransform::scale(1.0, 0.0)];
+        assert_eq!(to_local_space(&flattened, point2(1.0, 2.0)), None);
+    }
+
+    #[test]
+    fn spacer_pushes_apart() {
+        let toolbar = test_style("toolbar", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.direction = Direction::Horizontal;
+                block.width = Some(LogicalLength::new(100.0).into());
+            }
+        });
+        let item = sized_box("item", 20.0, 10.0);
+        let wide = test_style("wide", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.min_width = Some(LogicalLength::new(70.0).into());
+            }
+        });
+
+        let (root, first, spacer, second) = Runtime::new().run_once(|| {
+            let first = elt::view().set_attr(attr::style(), item).build();
+            let spacer = elt::spacer().build();
+            let second = elt::view().set_attr(attr::style(), item).build();
+            let root = elt::view()
+                .set_attr(attr::style(), toolbar)
+                .add_child(first.clone())
+                .add_child(spacer.clone())
+                .add_child(second.clone())
+                .build();
+            (root, first, spacer, second)
+        });
+        let tree = compute_layout(&root, size2(200.0, 100.0));
+        assert_eq!(tree.rect(first.id()), Some(rect(0.0, 0.0, 20.0, 10.0)));
+        assert_eq!(tree.rect(spacer.id()), Some(rect(20.0, 0.0, 60.0, 0.0)));
+        assert_eq!(tree.rect(second.id()), Some(rect(80.0, 0.0, 20.0, 10.0)));
+
+        // The minimum size wins over the space available.
+        let (root, second) = Runtime::new().run_once(|| {
+            let second = elt::view().set_attr(attr::style(), item).build();
+            let root = elt::view()
+                .set_attr(attr::style(), toolbar)
+                .add_child(elt::view().set_attr(attr::style(), item).build())
+                .add_child(elt::spacer().set_attr(attr::style(), wide).build())
+                .add_child(second.clone())
+                .build();
+            (root, second)
+        });
+        let tree = compute_layout(&root, size2(200.0, 100.0));
+        assert_eq!(tree.rect(second.id()), Some(rect(90.0, 0.0, 20.0, 10.0)));
+    }
+
+    #[test]
+    fn fit_content_clamps_and_wraps() {
+        let bubble = test_style("bubble", |values| {
+            apply(values, width(), fit_content(pixels(100.0)));
+        });
+
+        let (root, short, long) = Runtime::new().run_once(|| {
+            let bubble = |text: &str| {
+                elt::view()
+                    .set_attr(attr::style(), bubble)
+                    .add_child(elt::span().add_child(text.to_owned()).build())
+                    .build()
+            };
+            let short = bubble("hi");
+            let long = bubble("a much longer message which can't fit on one line");
+            let root = elt::view()
+                .add_child(short.clone())
+                .add_child(long.clone())
+                .build();
+            (root, short, long)
+        });
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let short = tree.rect(short.id()).unwrap().size;
+        let long = tree.rect(long.id()).unwrap().size;
+        assert!(short.width > 0.0 && short.width < 100.0);
+        assert!(long.width > short.width && long.width <= 100.0);
+        assert!(long.height > short.height);
+    }
+
+    #[test]
+    fn wrapped_text_grows_row() {
+        let row = test_style("row", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.direction = Direction::Horizontal;
+                block.width = Some(LogicalLength::new(100.0).into());
+            }
+        });
+        let icon = sized_box("icon", 40.0, 10.0);
+
+        let (root, label) = Runtime::new().run_once(|| {
+            let label = elt::span()
+                .add_child("a long label which wraps onto several lines".to_owned())
+                .build();
+            let root = elt::view()
+                .set_attr(attr::style(), row)
+                .add_child(elt::view().set_attr(attr::style(), icon).build())
+                .add_child(label.clone())
+                .build();
+            (root, label)
+        });
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let label_rect = tree.rect(label.id()).unwrap();
+        let lines = &tree.root.children[1].layout.children;
+        let line_height = lines[0].layout.size.height;
+
+        // The label wraps within the 60px left after the icon.
+        assert!(lines.len() > 1);
+        assert!(label_rect.size.width <= 60.0);
+        assert_eq!(label_rect.origin.x, 40.0);
+        assert_eq!(label_rect.size.height, lines.len() as f32 * line_height);
+        assert_eq!(
+            tree.rect(root.id()).map(|rect| rect.size.height),
+            Some(label_rect.size.height)
+        );
+    }
+
+    #[test]
+    fn calc_width_of_parent() {
+        let parent = test_style("parent", |values| {
+            apply(values, width(), pixels(200.0));
+        });
+        let inset = test_style("inset", |values| {
+            apply(values, width(), calc("100% - 20px"));
+        });
+        let invalid = test_style("invalid", |values| {
+            apply(values, width(), pixels(50.0));
+            apply(values, width(), calc("100% * 20px"));
+        });
+
+        let (root, inset, invalid) = Runtime::new().run_once(|| {
+            let inset = elt::view().set_attr(attr::style(), inset).build();
+            let invalid = elt::view().set_attr(attr::style(), invalid).build();
+            let root = elt::view()
+                .set_attr(attr::style(), parent)
+                .add_child(inset.clone())
+                .add_child(invalid.clone())
+                .build();
+            (root, inset, invalid)
+        });
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let width = |id| tree.rect(id).map(|rect| rect.size.width);
+        assert_eq!(width(inset.id()), Some(180.0));
+        // The invalid expression is ignored.
+        assert_eq!(width(invalid.id()), Some(50.0));
+    }
+
+    #[test]
+    fn text_hit_test_lines() {
+        let root = Runtime::new().run_once(|| elt::span().add_child("ab\ncd".to_owned()).build());
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let lines = tree
+            .root
+            .children
+            .iter()
+            .map(|child| match child.layout.render {
+                RenderData::Text { ref text, .. } => {
+                    (child.position, child.layout.size, text.boundaries.clone())
+                }
+                _ => panic!("expected a line of text"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let (first, first_size, ref boundaries) = lines[0];
+        let (second, second_size, _) = lines[1];
+        assert_eq!(
+            boundaries
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        // The advances add up to the width of the line.
+        assert!((boundaries[2].1 - first_size.width).abs() < 0.5);
+        let middle = first.y + first_size.height / 2.0;
+
+        // Boundaries and points close to them.
+        for &(index, x) in boundaries {
+            assert_eq!(text_hit_test(&tree.root, point2(x, middle)), index);
+            assert_eq!(text_hit_test(&tree.root, point2(x + 0.5, middle)), index);
+        }
+        // Before the first glyph and after the last one.
+        assert_eq!(text_hit_test(&tree.root, point2(-20.0, middle)), 0);
+        assert_eq!(text_hit_test(&tree.root, point2(250.0, middle)), 2);
+        assert_eq!(text_hit_test(&tree.root, point2(250.0, -20.0)), 2);
+
+        // The second line starts after the newline.
+        let middle = second.y + second_size.height / 2.0;
+        assert_eq!(text_hit_test(&tree.root, point2(0.0, middle)), 3);
+        assert_eq!(text_hit_test(&tree.root, point2(250.0, middle)), 5);
+        assert_eq!(text_hit_test(&tree.root, point2(0.0, 250.0)), 3);
+    }
+
+    #[test]
+    fn newlines_break_text() {
+        // Not only in text areas: newlines in any text start new lines,
+        // and blank lines and a trailing newline are kept.
+        let root =
+            Runtime::new().run_once(|| elt::span().add_child("ab\n\ncd\n".to_owned()).build());
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let ranges = tree
+            .root
+            .children
+            .iter()
+            .map(|child| match child.layout.render {
+                RenderData::Text { ref text, .. } => text.range.clone(),
+                _ => panic!("expected a line of text"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![0..2, 3..3, 4..6, 7..7]);
+    }
+
+    #[test]
+    fn caret_boundaries_follow_glyphs() {
+        // Kerned pairs, which are narrower together than apart.
+        let root = Runtime::new().run_once(|| elt::span().add_child("AVATAR".to_owned()).build());
+        let tree = compute_layout(&root, size2(300.0, 300.0));
+        let (size, text) = match tree.root.children[0].layout.render {
+            RenderData::Text { ref text, .. } => (tree.root.children[0].layout.size, text),
+            _ => panic!("expected a line of text"),
+        };
+        let offsets = text
+            .fragments
+            .iter()
+            .flat_map(|fragment| fragment.glyphs.iter().map(|glyph| glyph.offset.x))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets.len(), 6);
+        for (&(index, x), offset) in text.boundaries.iter().zip(&offsets) {
+            assert!((x - offset).abs() < 0.01, "boundary {} at {}", index, x);
+        }
+        assert_eq!(text.boundaries.last().map(|(index, _)| *index), Some(6));
+        assert!((text.boundaries[6].1 - size.width).abs() < 0.5);
+    }
+
+    #[test]
+    fn baseline_alignment() {
+        let row = test_style("row", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.direction = Direction::Horizontal;
+                block.align_items = Alignment::Baseline;
+            }
+        });
+        let large = test_style("large", |values| {
+            values.text_size = LogicalLength::new(32.0);
+        });
+        let small = test_style("small", |values| {
+            values.text_size = LogicalLength::new(12.0);
+        });
+        let square = sized_box("square", 10.0, 10.0);
+
+        let (root, square) = Runtime::new().run_once(|| {
+            let square = elt::view().set_attr(attr::style(), square).build();
+            let root = elt::view()
+                .set_attr(attr::style(), row)
+                .add_child(
+                    elt::span()
+                        .set_attr(attr::style(), large)
+                        .add_child("Large".to_owned())
+                        .build(),
+                )
+                .add_child(
+                    elt::span()
+                        .set_attr(attr::style(), small)
+                        .add_child("small".to_owned())
+                        .build(),
+                )
+                .add_child(square.clone())
+                .build();
+            (root, square)
+        });
+
+        let tree = compute_layout(&root, size2(500.0, 100.0));
+        let mut baselines = vec![];
+        collect_baselines(&tree.root, 0.0, &mut baselines);
+        assert_eq!(baselines.len(), 2);
+        assert!((baselines[0] - baselines[1]).abs() < 0.01);
+        assert!(tree
+            .root
+            .baseline
+            .map_or(false, |root| (root - baselines[0]).abs() < 0.01));
+
+        // Without text, the square sits on the baseline.
+        let square = tree.rect(square.id()).unwrap();
+        assert!(square.min_y() > 0.0);
+        assert!((square.max_y() - baselines[0]).abs() < 0.01);
+    }
+
+    /// The baselines of each line of text, relative to the root.
+    fn collect_baselines(layout: &LayoutTreeNode, y: f32, baselines: &mut Vec<f32>) {
+        if let (RenderData::Text { .. }, Some(baseline)) = (&layout.render, layout.baseline) {
+            baselines.push(y + baseline);
+        }
+        for child in &layout.children {
+            collect_baselines(&child.layout, y + child.position.y, baselines);
+        }
+    }
+
+    #[test]
+    fn generated_content() {
+        let span = Runtime::new().run_once(|| {
+            elt::span()
+                .set_attr(attr::after(), "★".to_owned())
+                .add_child("Rating".to_owned())
+                .build()
+        });
+        assert_eq!(span.children().len(), 1);
+
+        let tree = compute_layout(&span, size2(500.0, 100.0));
+        let mut lines = vec![];
+        collect_text(&tree.root, point2(0.0, 0.0), &mut lines);
+        assert_eq!(lines.len(), 2);
+        let (rating, star) = (&lines[0], &lines[1]);
+        assert_eq!(rating.1, 0..6);
+        assert_eq!(star.1, 0.."★".len());
+        // The star trails the text, on the same line.
+        assert_eq!(star.0.min_y(), rating.0.min_y());
+        assert!(star.0.min_x() >= rating.0.max_x() - 0.01);
+        assert!(tree.rect(span.id()).unwrap().max_x() >= star.0.max_x() - 0.01);
+    }
+
+    /// The rect and source range of each line of text, relative to the
+    /// root.
+    fn collect_text(
+        layout: &LayoutTreeNode,
+        origin: LogicalPoint,
+        lines: &mut Vec<(LogicalRect, std::ops::Range<usize>)>,
+    ) {
+        if let RenderData::Text { ref text, .. } = layout.render {
+            lines.push((LogicalRect::new(origin, layout.size), text.range.clone()));
+        }
+        for child in &layout.children {
+            collect_text(&child.layout, origin + child.position.to_vector(), lines);
+        }
+    }
+
+    #[test]
+    fn rem_lengths() {
+        let large_root = test_style("large_root", |values| {
+            values.text_size = LogicalLength::new(20.0);
+        });
+        let small_text = test_style("small_text", |values| {
+            values.text_size = LogicalLength::new(8.0);
+        });
+        let large_text = test_style("large_text", |values| {
+            values.text_size = LogicalLength::new(30.0);
+        });
+        let from_code = test_style("from_code", |values| {
+            apply(values, width(), rems(2.0));
+        });
+        let from_css =
+            Style::from_css_string("width: 2rem; height: calc(1rem + 2px); text_size: 2rem");
+
+        let render = |root_style| {
+            Runtime::new().run_once(|| {
+                let css = elt::view().set_attr(attr::style(), from_css).build();
+                let code = elt::view().set_attr(attr::style(), from_code).build();
+                let nested = elt::view()
+                    .set_attr(attr::style(), small_text)
+                    .add_child(
+                        elt::view()
+                            .set_attr(attr::style(), large_text)
+                            .add_child(css.clone())
+                            .add_child(code.clone())
+                            .build(),
+                    )
+                    .build();
+                let root = elt::view()
+                    .set_attr(attr::style(), root_style)
+                    .add_child(nested)
+                    .build();
+                (root, css, code)
+            })
+        };
+        let check = |root_style, root_size: f32| {
+            let (root, css, code) = render(root_style);
+            let mut state = DocumentState::new(NodeRef::from(&root), size2(500.0, 500.0));
+            state.update_styles(&mut Runtime::new(), Instant::now());
+            assert_eq!(
+                state.computed_values(css.id()).text_size.get(),
+                2.0 * root_size
+            );
+            let tree = LayoutTree::new(LayoutEngine::new().layout(&mut state));
+            let size = |id| tree.rect(id).map(|rect| rect.size);
+            assert_eq!(
+                size(css.id()),
+                Some(size2(2.0 * root_size, root_size + 2.0))
+            );
+            assert_eq!(
+                size(code.id()).map(|size| size.width),
+                Some(2.0 * root_size)
+            );
+        };
+
+        // However deeply they're nested, and whatever the text sizes in
+        // between, they're relative to the root's text size.
+        check(None, 16.0);
+        check(Some(large_root), 20.0);
+    }
+}
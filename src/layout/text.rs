@@ -1,9 +1,12 @@
-use super::{Glyph, TextFragment};
+use super::{
+    Glyph, LayoutText, LayoutTreeNode, LogicalPoint, LogicalRect, RenderData, TextFragment,
+};
 use crate::util::equal_rc::EqualRc;
 use crate::util::word_break_iter;
 use euclid::point2;
 use skribo::{FontCollection, LayoutSession, TextStyle};
 use std::cell::RefCell;
+use std::ops::Range;
 
 pub struct TextLayoutInfo {
     session: RefCell<LayoutSession<String>>,
@@ -15,10 +18,15 @@ pub struct FilledLine {
     pub ascender: f32,
     pub fragments: Vec<TextFragment>,
     pub text_size: f32,
+    pub range: Range<usize>,
+    pub boundaries: Vec<(usize, f32)>,
 }
 
 pub struct TextState<'a> {
     offset: usize,
+    /// Set when the previous line was ended by a `\n`, so that a
+    /// trailing newline still produces an (empty) final line.
+    hard_break: bool,
     layout: &'a TextLayoutInfo,
 }
 
@@ -33,7 +41,106 @@ impl TextLayoutInfo {
 
 impl<'a> TextState<'a> {
     pub fn new(layout: &'a TextLayoutInfo) -> TextState<'a> {
-        TextState { offset: 0, layout }
+        TextState {
+            offset: 0,
+            hard_break: false,
+            layout,
+        }
+    }
+
+    /// Line height and ascender of the font used at the given offset,
+    /// used for lines that don't contain any glyphs.
+    fn empty_line_metrics(session: &mut LayoutSession<String>, offset: usize) -> (f32, f32) {
+        let size = session.style().size;
+        let text = session.text().to_owned();
+        let start = if offset < text.len() {
+            offset
+        } else {
+            text.char_indices()
+                .last()
+                .map(|(index, _)| index)
+                .unwrap_or(0)
+        };
+        if let Some(run) = session.iter_substr(start..text.len()).next() {
+            let metrics = run.font().font.metrics();
+            let units_per_px = metrics.units_per_em as f32 / size;
+            (
+                (metrics.ascent - metrics.descent) / units_per_px,
+                metrics.ascent / units_per_px,
+            )
+        } else {
+            (size, size)
+        }
+    }
+
+    fn create_line(
+        &self,
+        session: &mut LayoutSession<String>,
+        start: usize,
+        end: usize,
+        width: f32,
+        height: f32,
+        ascender: f32,
+    ) -> FilledLine {
+        let boundaries = Self::boundaries(session, start, end);
+        let (height, ascender) = if start == end {
+            Self::empty_line_metrics(session, start)
+        } else {
+            (height, ascender)
+        };
+        FilledLine {
+            fragments: self.create_fragments(session, start, end),
+            width,
+            height,
+            ascender,
+            text_size: session.style().size,
+            range: start..end,
+            boundaries,
+        }
+    }
+
+    /// The x offset of each character boundary of a line, taken from
+    /// the glyphs the line was shaped into, so that they follow its
+    /// kerning. Each character starts at its first glyph, found by
+    /// counting the glyphs of the characters before it, and one which
+    /// has no glyphs of its own, such as the second character of a
+    /// ligature, shares the boundary before it.
+    fn boundaries(
+        session: &mut LayoutSession<String>,
+        start: usize,
+        end: usize,
+    ) -> Vec<(usize, f32)> {
+        let size = session.style().size;
+        let mut offsets = vec![];
+        let mut width = 0.0f32;
+        for run in session.iter_substr(start..end) {
+            let font = run.font();
+            let units_per_px = font.font.metrics().units_per_em as f32 / size;
+            for glyph in run.glyphs() {
+                let advance = font.font.advance(glyph.glyph_id).unwrap().x() / units_per_px;
+                offsets.push(glyph.offset.x());
+                width = width.max(glyph.offset.x() + advance);
+            }
+        }
+
+        let text = session.text().to_owned();
+        let mut boundaries = Vec::with_capacity(end - start + 1);
+        let mut glyph = 0;
+        let mut x = 0.0f32;
+        for (index, character) in text[start..end].char_indices() {
+            let from = start + index;
+            let glyphs = session
+                .iter_substr(from..from + character.len_utf8())
+                .map(|run| run.glyphs().count())
+                .sum::<usize>();
+            if glyphs > 0 {
+                x = x.max(offsets.get(glyph).copied().unwrap_or(width));
+            }
+            boundaries.push((from, x));
+            glyph += glyphs;
+        }
+        boundaries.push((end, width.max(x)));
+        boundaries
     }
 
     fn create_fragments(
@@ -64,7 +171,7 @@ impl<'a> TextState<'a> {
     }
 
     pub fn finished(&self) -> bool {
-        self.offset == self.layout.session.borrow().text().len()
+        self.offset == self.layout.session.borrow().text().len() && !self.hard_break
     }
 
     pub fn fill_line(&mut self, width: f32, is_new_line: bool) -> Option<FilledLine> {
@@ -80,8 +187,12 @@ impl<'a> TextState<'a> {
         let size = session.style().size;
         let text = session.text().to_owned();
 
+        let after_break = std::mem::replace(&mut self.hard_break, false);
+
         if is_new_line {
-            let trimmed = text[self.offset..].trim_start();
+            // Newlines are kept so that blank lines aren't collapsed.
+            let trimmed =
+                text[self.offset..].trim_start_matches(|ch: char| ch.is_whitespace() && ch != '\n');
             self.offset = trimmed.as_ptr() as usize - text.as_ptr() as usize;
         };
 
@@ -89,6 +200,22 @@ impl<'a> TextState<'a> {
             let start = word.as_ptr() as usize - text.as_ptr() as usize;
             let end = start + word.len();
 
+            if let Some(newline) = word.find('\n') {
+                // hard break, the newline itself is consumed
+                let line_start = self.offset;
+                let line_end = self.offset + last_word_end;
+                self.offset = start + newline + 1;
+                self.hard_break = true;
+                return Some(self.create_line(
+                    &mut *session,
+                    line_start,
+                    line_end,
+                    last_word_x,
+                    last_word_height,
+                    last_word_ascender,
+                ));
+            }
+
             for run in session.iter_substr(start..end) {
                 let font = run.font();
                 let metrics = font.font.metrics();
@@ -105,13 +232,14 @@ impl<'a> TextState<'a> {
                         self.offset += last_word_end;
                         if last_word_end > 0 {
                             // soft break
-                            return Some(FilledLine {
-                                fragments: self.create_fragments(&mut *session, start, self.offset),
-                                width: last_word_x,
-                                height: last_word_height,
-                                ascender: last_word_ascender,
-                                text_size: size,
-                            });
+                            return Some(self.create_line(
+                                &mut *session,
+                                start,
+                                self.offset,
+                                last_word_x,
+                                last_word_height,
+                                last_word_ascender,
+                            ));
                         } else {
                             // todo: force progress by hard breaking if is_new_line is true
                             return None;
@@ -130,16 +258,73 @@ impl<'a> TextState<'a> {
 
         let start = self.offset;
         self.offset += last_word_end;
-        if last_word_end > 0 {
-            Some(FilledLine {
-                fragments: self.create_fragments(&mut *session, start, self.offset),
-                width: last_word_x,
-                height: last_word_height,
-                ascender: last_word_ascender,
-                text_size: size,
-            })
+        if last_word_end > 0 || after_break {
+            Some(self.create_line(
+                &mut *session,
+                start,
+                self.offset,
+                last_word_x,
+                last_word_height,
+                last_word_ascender,
+            ))
         } else {
             None
         }
     }
 }
+
+/// Finds the character boundary nearest to `point`, for placing the
+/// caret where text is clicked or dragged over. `point` is relative to
+/// the border box of the element laid out as `layout`, and the text is
+/// that of any of its descendants.
+///
+/// The line nearest to the point is used, so points above the first
+/// line land on it, and points below the last line land on that. Within
+/// the line, points before its first glyph give the start of the line,
+/// and points after its last glyph give the end. The result is a byte
+/// offset into the text the line is from, or 0 if there's no text.
+pub fn text_hit_test(layout: &LayoutTreeNode, point: LogicalPoint) -> usize {
+    let mut lines = vec![];
+    collect_text_lines(layout, LogicalPoint::zero(), &mut lines);
+    let distance = |rect: &LogicalRect| {
+        let outside = |value: f32, start: f32, end: f32| (start - value).max(value - end).max(0.0);
+        (
+            outside(point.y, rect.min_y(), rect.max_y()),
+            outside(point.x, rect.min_x(), rect.max_x()),
+        )
+    };
+    let nearest = lines
+        .into_iter()
+        .fold(None, |nearest, (rect, text)| match nearest {
+            Some((ref best, _)) if distance(best) <= distance(&rect) => nearest,
+            _ => Some((rect, text)),
+        });
+    let (rect, text) = match nearest {
+        Some(line) => line,
+        None => return 0,
+    };
+    let x = point.x - rect.min_x();
+    text.boundaries
+        .iter()
+        .fold(
+            None,
+            |nearest: Option<(usize, f32)>, &(index, boundary)| match nearest {
+                Some((_, best)) if (best - x).abs() <= (boundary - x).abs() => nearest,
+                _ => Some((index, boundary)),
+            },
+        )
+        .map_or(text.range.start, |(index, _)| index)
+}
+
+fn collect_text_lines<'a>(
+    layout: &'a LayoutTreeNode,
+    origin: LogicalPoint,
+    lines: &mut Vec<(LogicalRect, &'a LayoutText)>,
+) {
+    if let RenderData::Text { ref text, .. } = layout.render {
+        lines.push((LogicalRect::new(origin, layout.size), text));
+    }
+    for child in &layout.children {
+        collect_text_lines(&child.layout, origin + child.position.to_vector(), lines);
+    }
+}
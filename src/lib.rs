@@ -37,6 +37,26 @@
 pub use moxie_native_mox::mox;
 pub use moxie_native_style::define_style;
 
+/// Enters a span named `$name` until the end of the scope, when the
+/// `tracing` feature is enabled, and does nothing otherwise. Fields
+/// declared as `tracing::field::Empty` can be filled in later with
+/// `trace_record!()` on `$span`.
+macro_rules! trace_span {
+    ($span:ident = $name:literal $(, $($field:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let $span = tracing::info_span!($name $(, $($field)*)?);
+        #[cfg(feature = "tracing")]
+        let _entered = $span.enter();
+    };
+}
+
+macro_rules! trace_record {
+    ($span:ident, $field:literal, $value:expr) => {
+        #[cfg(feature = "tracing")]
+        $span.record($field, &$value);
+    };
+}
+
 mod document;
 pub mod dom;
 mod layout;
@@ -50,6 +70,20 @@ pub mod style;
 pub mod style_impl;
 mod util;
 
-pub use runtime::Runtime;
+pub use layout::{
+    compute_layout, measure_intrinsic, Glyph, LayoutText, LayoutTree, LogicalLength, LogicalPixel,
+    LogicalPoint, LogicalRect, LogicalSideOffsets, LogicalSize, RectChange, TextFragment,
+};
+pub use render::painter::{PaintOverlay, Painter};
+pub use render::{
+    CachedPaint, DirtyRegion, FrameStats, PresentMode, RenderPath, Renderer, TextAntialiasing,
+    TextHinting, TextRendering,
+};
+#[cfg(feature = "webrender-interop")]
+pub use render::{DisplayListFrame, DisplayListTarget};
+pub use runtime::{
+    CaretBlink, DecodedImage, ElementPath, GestureConfig, HostedRuntime, ReplayTiming, RunError,
+    Runtime, TimerHandle, WindowControl,
+};
 pub use topo;
-pub use util::color::Color;
+pub use util::color::{Color, Gradient};
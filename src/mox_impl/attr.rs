@@ -8,5 +8,27 @@ macro_rules! attribute {
     };
 }
 
+attribute!(after -> AttrAfter);
+attribute!(before -> AttrBefore);
+attribute!(cancel -> AttrCancel);
+attribute!(class -> AttrClass);
+attribute!(decorations -> AttrDecorations);
+attribute!(default -> AttrDefault);
+attribute!(disabled -> AttrDisabled);
+attribute!(drag_region -> AttrDragRegion);
+attribute!(event_sink -> AttrEventSink);
+attribute!(fill -> AttrFill);
+attribute!(icon -> AttrIcon);
+attribute!(id -> AttrId);
+attribute!(key_repeat -> AttrKeyRepeat);
+attribute!(level -> AttrLevel);
+attribute!(paint_overlay -> AttrPaintOverlay);
+attribute!(portal -> AttrPortal);
+attribute!(readonly -> AttrReadonly);
+attribute!(source -> AttrSource);
+attribute!(src -> AttrSrc);
+attribute!(stroke -> AttrStroke);
 attribute!(style -> AttrStyle);
+attribute!(tab_index -> AttrTabIndex);
 attribute!(title -> AttrTitle);
+attribute!(value -> AttrValue);
@@ -1,18 +1,42 @@
-use crate::dom::element::{Attribute, Element, Event, HasAttribute, HasEvent};
+use crate::dom::element::{
+    Attribute, AttributeError, Element, Event, HasAttribute, HasEvent, HasTextAttributes,
+};
 use crate::dom::node::Node;
 use crate::util::event_handler::EventHandler;
+use log::warn;
 use std::marker::PhantomData;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 
 static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+type Callback = Box<dyn FnOnce() + Send>;
+
 /// Builder pattern for creating a DOM node, typically used from the
 /// mox! macro.
 pub struct Builder<Elt: Element> {
     element: Elt,
     handlers: Elt::Handlers,
     children: Vec<Elt::Child>,
+    mount: Option<Callback>,
+    unmount: Option<Callback>,
+}
+
+/// What's kept for a node between builds, for as long as it's mounted.
+/// Moxie drops it once a build no longer reaches the node, which calls
+/// the last `on_unmount()` callback.
+struct Mounted {
+    id: u64,
+    unmount: Mutex<Option<Callback>>,
+}
+
+impl Drop for Mounted {
+    fn drop(&mut self) {
+        if let Some(unmount) = self.unmount.get_mut().unwrap().take() {
+            unmount();
+        }
+    }
 }
 
 pub trait IntoChildren<Elt>
@@ -128,16 +152,29 @@ where
     }
 }
 
+impl<Elt> Default for Builder<Elt>
+where
+    Elt: Element,
+{
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
 impl<Elt> Builder<Elt>
 where
     Elt: Element,
 {
-    /// Creates a new builder.
-    pub(crate) fn new() -> Self {
+    /// Creates a new builder. The built-in elements have a function in
+    /// `elt` for this, and elements defined in other crates are built
+    /// with `Builder::<Elt>::new()`.
+    pub fn new() -> Self {
         Builder {
             element: Elt::default(),
             handlers: Elt::Handlers::default(),
             children: vec![],
+            mount: None,
+            unmount: None,
         }
     }
 
@@ -151,6 +188,53 @@ where
         self
     }
 
+    /// Sets an attribute when `value` is `Some`, for optional data. With
+    /// `None`, the attribute keeps the value it had, whether that's its
+    /// default or one set earlier.
+    pub fn set_attr_opt<Attr, Value>(self, attr: Attr, value: Option<Value>) -> Self
+    where
+        Attr: Attribute,
+        Elt: HasAttribute<Attr>,
+        Value: Into<Attr::Value>,
+    {
+        match value {
+            Some(value) => self.set_attr(attr, value),
+            None => self,
+        }
+    }
+
+    /// Sets attributes by name from text, such as `("class", "card")`,
+    /// for when they're computed from data. Names are the same as the
+    /// functions in `attr`. They're set in order, so when a name is
+    /// given more than once, the last value wins.
+    ///
+    /// Like invalid style values, unknown names and values which can't
+    /// be parsed are logged and ignored, leaving the attribute as it was.
+    /// Values like `style` which can't be written as text can only be
+    /// set with `set_attr()`.
+    pub fn attrs<Name, Value>(mut self, attributes: impl IntoIterator<Item = (Name, Value)>) -> Self
+    where
+        Name: AsRef<str>,
+        Value: AsRef<str>,
+        Elt: HasTextAttributes,
+    {
+        for (name, value) in attributes {
+            if let Err(err) = self.try_attr(name.as_ref(), value.as_ref()) {
+                warn!("Ignoring attribute: {}", err);
+            }
+        }
+        self
+    }
+
+    /// Sets an attribute by name from text, like `attrs()`, but returns
+    /// the error instead of logging it.
+    pub(crate) fn try_attr(&mut self, name: &str, value: &str) -> Result<(), AttributeError>
+    where
+        Elt: HasTextAttributes,
+    {
+        self.element.set_text_attribute(name, value)
+    }
+
     /// Register an event handler on the element. The event type has to
     /// be supported by the element, see `HasEvent`.
     pub fn on_event<Ev>(
@@ -166,6 +250,49 @@ where
         self
     }
 
+    /// Registers an event handler when `func` is `Some`, like
+    /// `set_attr_opt()`. With `None`, any handler registered earlier is
+    /// kept.
+    pub fn on_event_opt<Ev, Func>(self, event: PhantomData<Ev>, func: Option<Func>) -> Self
+    where
+        Ev: Event,
+        Elt: HasEvent<Ev>,
+        Func: FnMut(&Ev) + 'static + Sync + Send,
+    {
+        match func {
+            Some(func) => self.on_event(event, func),
+            None => self,
+        }
+    }
+
+    /// Calls `func` when the node is first built at its place in the
+    /// tree, for setup like starting a subscription. It's called while
+    /// the tree is being built, so before the node is laid out and
+    /// painted, and changes to state it makes show up in the next
+    /// render. If the node is removed and built there again later,
+    /// that's a new mount, and `func` is called again.
+    ///
+    /// Nodes inside of `memo_component()` aren't built when it reuses
+    /// its subtree, so like state, their lifecycle isn't kept there, and
+    /// they shouldn't use this or `on_unmount()`.
+    pub fn on_mount(mut self, func: impl FnOnce() + Send + 'static) -> Self {
+        self.mount = Some(Box::new(func));
+        self
+    }
+
+    /// Calls `func` once the node is no longer built at its place in the
+    /// tree, for teardown like releasing a resource. It's called at the
+    /// end of the first render without the node, before the tree without
+    /// it is laid out and painted, and when the app shuts down for nodes
+    /// which are still mounted. Like event handlers, the callback given
+    /// in the latest build is the one that's called, so it can use the
+    /// latest state, and each mount is followed by exactly one unmount,
+    /// however quickly the node comes and goes.
+    pub fn on_unmount(mut self, func: impl FnOnce() + Send + 'static) -> Self {
+        self.unmount = Some(Box::new(func));
+        self
+    }
+
     /// Adds a child node.
     pub fn add_child(mut self, children: impl IntoChildren<Elt>) -> Self {
         for child in children.into_children() {
@@ -176,19 +303,40 @@ where
 
     /// Build the actual node. This attempts some memoization so that a
     /// node won't necessarily always be created.
+    ///
+    /// The children are memoized on their own, so when only the
+    /// element's attributes change, the new node shares the children of
+    /// the last one instead of copying them.
     #[topo::nested]
     pub fn build(self) -> Node<Elt> {
         let Self {
             element,
             children,
             handlers,
+            mount,
+            unmount,
         } = self;
 
-        let id = moxie::once(|| ID_COUNTER.fetch_add(1, Ordering::Acquire));
-
+        let mounted = moxie::once(move || {
+            if let Some(mount) = mount {
+                mount();
+            }
+            Arc::new(Mounted {
+                id: ID_COUNTER.fetch_add(1, Ordering::Acquire),
+                unmount: Mutex::new(None),
+            })
+        });
+        *mounted.unmount.lock().unwrap() = unmount;
+        let id = mounted.id;
+
+        let children = moxie::cache(&children, |children: &Vec<Elt::Child>| {
+            Arc::<[Elt::Child]>::from(&children[..])
+        });
         let node = moxie::cache(
             &(element, children),
-            |(elt, children): &(Elt, Vec<Elt::Child>)| Node::new(id, elt.clone(), children.clone()),
+            |(elt, children): &(Elt, Arc<[Elt::Child]>)| {
+                Node::with_shared_children(id, elt.clone(), children.clone())
+            },
         );
 
         *node.handlers().lock().unwrap() = handlers;
@@ -21,7 +21,27 @@ pub fn button() -> Builder<Button> {
     Builder::new()
 }
 
+/// Bitmap image, see `ImageSource`.
+pub fn image() -> Builder<Image> {
+    Builder::new()
+}
+
+/// Flexible empty space.
+pub fn spacer() -> Builder<Spacer> {
+    Builder::new()
+}
+
 /// Container for inline text.
 pub fn span() -> Builder<Span> {
     Builder::new()
 }
+
+/// Vector image, see `SvgImage`.
+pub fn svg() -> Builder<Svg> {
+    Builder::new()
+}
+
+/// Multi-line editable text.
+pub fn text_area() -> Builder<TextArea> {
+    Builder::new()
+}
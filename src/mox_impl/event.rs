@@ -1,6 +1,54 @@
-use crate::dom::events::ClickEvent;
+use crate::dom::events::{
+    ChangeEvent, ChildClickEvent, ClickEvent, ColorSchemeChangeEvent, LongPressEvent,
+    MetricsChangeEvent, PinchEvent, ResizeEvent, RotateEvent, SelectEvent, SwipeEvent,
+    WindowStateEvent,
+};
 use std::marker::PhantomData;
 
 pub fn on_click() -> PhantomData<ClickEvent> {
     PhantomData
 }
+
+pub fn on_change() -> PhantomData<ChangeEvent> {
+    PhantomData
+}
+
+pub fn on_select() -> PhantomData<SelectEvent> {
+    PhantomData
+}
+
+pub fn on_child_click() -> PhantomData<ChildClickEvent> {
+    PhantomData
+}
+
+pub fn on_state_change() -> PhantomData<WindowStateEvent> {
+    PhantomData
+}
+
+pub fn on_color_scheme_change() -> PhantomData<ColorSchemeChangeEvent> {
+    PhantomData
+}
+
+pub fn on_metrics_change() -> PhantomData<MetricsChangeEvent> {
+    PhantomData
+}
+
+pub fn on_long_press() -> PhantomData<LongPressEvent> {
+    PhantomData
+}
+
+pub fn on_swipe() -> PhantomData<SwipeEvent> {
+    PhantomData
+}
+
+pub fn on_pinch() -> PhantomData<PinchEvent> {
+    PhantomData
+}
+
+pub fn on_rotate() -> PhantomData<RotateEvent> {
+    PhantomData
+}
+
+pub fn on_resize() -> PhantomData<ResizeEvent> {
+    PhantomData
+}
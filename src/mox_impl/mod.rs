@@ -12,11 +12,24 @@
 //! ```
 
 use crate::dom::element::Element;
+use crate::dom::Node;
+use crate::runtime::{MotionState, TimerHandle, Timers};
+use crate::Runtime;
+use presence::Presences;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 pub mod attr;
 mod builder;
 pub mod elt;
 pub mod event;
+mod presence;
+pub mod spec;
+
+pub use builder::Builder;
+pub use presence::{Phase, Presence, Transition};
+pub use spec::{build_from_spec, ChildSpec, ElementRegistry, ElementSpec, SpecError};
 
 pub fn fragment<Elt>() -> builder::Fragment<Elt>
 where
@@ -24,3 +37,647 @@ where
 {
     builder::Fragment::new()
 }
+
+/// Conditionally creates a child. The child is only built while
+/// `condition` is true, and otherwise occupies no space in layout.
+///
+/// Each element's identity comes from where it was built, so hiding
+/// and showing the child doesn't affect the state of its siblings. The
+/// state of the hidden child itself is dropped while it's hidden.
+///
+/// ```rs
+/// mox! {
+///     <view>
+///         {when(expanded, || mox! { <view style={DETAILS_STYLE} /> })}
+///         <button on_click={toggle}><span>"Toggle"</span></button>
+///     </view>
+/// }
+/// ```
+pub fn when<Child>(condition: bool, child: impl FnOnce() -> Child) -> Option<Child> {
+    if condition {
+        Some(child())
+    } else {
+        None
+    }
+}
+
+/// Creates one child for each item in `items`. Each child is built
+/// under the key returned by `key`, rather than its position in the
+/// list, so when the data is reordered the children keep their state
+/// and items that didn't change aren't rebuilt.
+///
+/// Keys must be unique within the list and stay the same for an item
+/// across renders. Using an index as the key defeats the purpose, as
+/// inserting an item then shifts the identity of everything after it.
+///
+/// ```rs
+/// mox! {
+///     <view>
+///         {list(&todos, |todo| todo.id, |todo| mox! {
+///             <span>{% "{}", todo.title}</span>
+///         })}
+///     </view>
+/// }
+/// ```
+pub fn list<Item, Key, Child>(
+    items: &[Item],
+    key: impl Fn(&Item) -> Key,
+    mut child: impl FnMut(&Item) -> Child,
+) -> Vec<Child>
+where
+    Key: Eq + std::hash::Hash + Send + 'static,
+{
+    items
+        .iter()
+        .map(|item| topo::call_in_slot(&key(item), || child(item)))
+        .collect()
+}
+
+/// Builds `child` under `key` rather than its position among its
+/// siblings, the same as one item of `list()`, so it keeps its state
+/// and node identity when the children around it are reordered. This
+/// is what the mox! macro's reserved `key` attribute turns into, which
+/// is never given to the element itself:
+///
+/// ```rs
+/// mox! {
+///     <view>
+///         <span key="item-3">"Third"</span>
+///     </view>
+/// }
+/// ```
+pub fn keyed<Key, Child>(key: &Key, child: impl FnOnce() -> Child) -> Child
+where
+    Key: Eq + std::hash::Hash + Send + 'static,
+{
+    topo::call_in_slot(key, child)
+}
+
+/// Like `list()`, but items animate in when they're added, and out when
+/// they're removed. `child` is given where the item is in its
+/// transition, to style it from, such as by fading or sliding it with
+/// `progress`. A removed item is still built from its last value, in
+/// the same place among the others, until it finishes exiting, and only
+/// then unmounted.
+///
+/// Items which are in the list when it's first built enter as well.
+/// While anything is animating, the list is rendered again each frame.
+/// When motion is reduced, see `Runtime::reduce_motion()`, items enter
+/// and exit at once instead.
+///
+/// ```rs
+/// let transition = Transition { enter: Duration::from_millis(150), exit: Duration::from_millis(200) };
+/// mox! {
+///     <view>
+///         {animated_list(&todos, |todo| todo.id, transition, |todo, presence| mox! {
+///             <view style={row_style(presence.progress)}>
+///                 <span>{% "{}", todo.title}</span>
+///             </view>
+///         })}
+///     </view>
+/// }
+/// ```
+#[topo::nested]
+pub fn animated_list<Item, Key, Child>(
+    items: &[Item],
+    key: impl Fn(&Item) -> Key,
+    transition: Transition,
+    mut child: impl FnMut(&Item, Presence) -> Child,
+) -> Vec<Child>
+where
+    Item: Clone + 'static,
+    Key: Eq + std::hash::Hash + Clone + Send + 'static,
+{
+    let presences = moxie::once(|| Arc::new(Mutex::new(Presences::<Key, Item>::default())));
+    let frame = moxie::once(|| Arc::new(Mutex::new(None::<TimerHandle>)));
+    let transition = match illicit::get::<MotionState>() {
+        Ok(motion) if motion.reduced() => Transition {
+            enter: Duration::from_secs(0),
+            exit: Duration::from_secs(0),
+        },
+        _ => transition,
+    };
+    let mut presences = presences.lock().unwrap();
+    let shown = presences.update(items, key, transition, Instant::now());
+
+    // The next frame is requested with a timer, which updates the DOM
+    // once it fires.
+    let mut frame = frame.lock().unwrap();
+    if let Some(frame) = frame.take() {
+        frame.cancel();
+    }
+    if presences.animating() {
+        if let Ok(timers) = illicit::get::<Timers>() {
+            *frame = Some(timers.schedule(
+                Instant::now(),
+                Duration::from_millis(16),
+                None,
+                Box::new(|| ()),
+            ));
+        }
+    }
+
+    shown
+        .into_iter()
+        .map(|(key, item, presence)| topo::call_in_slot(&key, || child(&item, presence)))
+        .collect()
+}
+
+/// Builds a pure part of the tree only when `deps` change, reusing the
+/// node built last time otherwise. Unlike the memoization done by each
+/// builder, this skips running `builder` altogether, so nothing inside
+/// of it is re-rendered.
+///
+/// `deps` has to hold everything the subtree is built from, and compare
+/// equal exactly when it would build the same tree: anything left out
+/// won't show up until something in `deps` changes, including values
+/// captured by event handlers inside. Since the subtree usually isn't
+/// rendered, it shouldn't keep state of its own with `moxie::state()`
+/// or `use_future()`, which would be reset when it's next rebuilt.
+///
+/// ```rs
+/// memo_component(row.clone(), |row| mox! {
+///     <view>
+///         <span>{% "{}", row.title}</span>
+///     </view>
+/// })
+/// ```
+#[topo::nested]
+pub fn memo_component<Deps, Elt, Build>(deps: Deps, builder: Build) -> Node<Elt>
+where
+    Deps: PartialEq + Clone + 'static,
+    Elt: Element,
+    Build: FnOnce(&Deps) -> Node<Elt>,
+{
+    moxie::cache(&deps, builder)
+}
+
+/// Makes `value` available to every `use_context()` call made while
+/// building `child`, including in nested components, without passing
+/// it through each of them. Values are looked up by type, so a nested
+/// `provide()` of the same type shadows this one within its children.
+///
+/// The tree is rebuilt on each update, so consumers always see the
+/// value from the current render.
+///
+/// ```rs
+/// provide(Theme { accent: Color::new(40, 120, 220, 255) }, || mox! {
+///     <window>
+///         <toolbar />
+///     </window>
+/// })
+/// ```
+pub fn provide<T, Child>(value: T, child: impl FnOnce() -> Child) -> Child
+where
+    T: std::fmt::Debug + 'static,
+{
+    illicit::Layer::new().offer(value).enter(child)
+}
+
+/// Reads the value of type `T` from the nearest enclosing `provide()`,
+/// or `None` if no ancestor provides one.
+pub fn use_context<T>() -> Option<T>
+where
+    T: Clone + 'static,
+{
+    illicit::get::<T>().ok().map(|value| (*value).clone())
+}
+
+/// Starts the future returned by `init` the first time this is called
+/// at a given place in the tree, returning `None` until it resolves and
+/// its output after. The future runs on the main thread, as described
+/// by `Runtime::spawn()`, and the component is re-rendered once the
+/// output is ready.
+///
+/// ```rs
+/// match use_future(load_profile) {
+///     Some(profile) => mox! { <span>{% "{}", profile.name}</span> },
+///     None => mox! { <span>"Loading..."</span> },
+/// }
+/// ```
+pub fn use_future<Fut, Output>(init: impl FnOnce() -> Fut) -> Option<Output>
+where
+    Fut: Future<Output = Output> + Send + 'static,
+    Output: Clone + Send + 'static,
+{
+    let (result, key) = moxie::state(|| None::<Output>);
+    moxie::once(move || {
+        let future = init();
+        Runtime::spawn(async move {
+            let output = future.await;
+            key.update(move |_| Some(Some(output)));
+        });
+    });
+    (*result).clone()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        animated_list, attr, build_from_spec, elt, event, keyed, list, memo_component, provide,
+        use_context, use_future, when, ElementSpec, Phase, Transition,
+    };
+    use crate::document::Document;
+    use crate::dom::element::{AttributeError, Element, HasTextAttributes, NodeChild};
+    use crate::dom::input::Key;
+    use crate::dom::{ClickEvent, Node, TextArea, View};
+    use crate::layout::LogicalLength;
+    use crate::runtime::{MotionState, Tasks};
+    use crate::style::{test_style, DisplayType, Style};
+    use crate::Color;
+    use euclid::size2;
+    use moxie::runtime::Runtime;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn last_child_id(view: &Node<View>) -> u64 {
+        view.children()
+            .last()
+            .and_then(|child| child.get_node().node().map(|node| node.id()))
+            .unwrap()
+    }
+
+    #[test]
+    fn when_keeps_sibling_identity() {
+        let mut runtime = Runtime::new();
+        let mut render = |show: bool| {
+            runtime.run_once(|| {
+                elt::view()
+                    .add_child(when(show, || elt::button().build()))
+                    .add_child(elt::span().build())
+                    .build()
+            })
+        };
+
+        let shown = render(true);
+        let hidden = render(false);
+        assert_eq!(shown.children().len(), 2);
+        assert_eq!(hidden.children().len(), 1);
+        assert_eq!(last_child_id(&shown), last_child_id(&hidden));
+    }
+
+    #[test]
+    fn mount_and_unmount_once() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut runtime = Runtime::new();
+        let mut render = |show: bool| {
+            let calls = log.clone();
+            runtime.run_once(move || {
+                let button = || {
+                    let (mounted, unmounted) = (calls.clone(), calls.clone());
+                    elt::button()
+                        .on_mount(move || mounted.lock().unwrap().push("mount"))
+                        .on_unmount(move || unmounted.lock().unwrap().push("unmount"))
+                        .build()
+                };
+                elt::view().add_child(when(show, button)).build()
+            });
+            log.lock().unwrap().clone()
+        };
+
+        assert_eq!(render(true), vec!["mount"]);
+        assert_eq!(render(true), vec!["mount"]);
+        assert_eq!(render(false), vec!["mount", "unmount"]);
+        assert_eq!(render(false), vec!["mount", "unmount"]);
+        // Coming back is another mount.
+        assert_eq!(render(true), vec!["mount", "unmount", "mount"]);
+        assert_eq!(render(false), vec!["mount", "unmount", "mount", "unmount"]);
+    }
+
+    #[test]
+    fn list_follows_keys() {
+        let mut runtime = Runtime::new();
+        let mut render = |items: &[(u32, &'static str)]| {
+            runtime.run_once(|| {
+                list(
+                    items,
+                    |(key, _)| *key,
+                    |(_, text)| elt::span().add_child(text.to_string()).build(),
+                )
+            })
+        };
+
+        let before = render(&[(1, "foo"), (2, "bar")]);
+        let after = render(&[(2, "bar"), (1, "foo")]);
+        assert_eq!(after.len(), 2);
+        assert!(before[0] == after[1]);
+        assert!(before[1] == after[0]);
+        assert!(render(&[]).is_empty());
+    }
+
+    #[test]
+    fn key_attribute_follows_keys() {
+        let mut runtime = Runtime::new();
+        // What the mox! macro emits for `<span key={key}>{text}</span>`.
+        let mut render = |items: &[(&'static str, &'static str)]| {
+            runtime.run_once(|| {
+                items
+                    .iter()
+                    .map(|(key, text)| {
+                        keyed(&key.to_string(), || {
+                            elt::span().add_child(text.to_string()).build()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let before = render(&[("item-1", "foo"), ("item-2", "bar")]);
+        let after = render(&[("item-2", "bar"), ("item-1", "foo")]);
+        assert_eq!(before[0].id(), after[1].id());
+        assert_eq!(before[1].id(), after[0].id());
+
+        // Specs reserve `key` the same way.
+        let mut runtime = Runtime::new();
+        let mut render = |keys: &[&str]| {
+            let mut spec = ElementSpec::new("view");
+            for key in keys {
+                spec = spec.child(ElementSpec::new("view").attr("key", *key));
+            }
+            let view = runtime
+                .run_once(|| build_from_spec(&spec))
+                .unwrap()
+                .node::<View>()
+                .unwrap();
+            view.children()
+                .iter()
+                .map(|child| child.get_node().node().unwrap().id())
+                .collect::<Vec<_>>()
+        };
+        let before = render(&["a", "b"]);
+        let after = render(&["b", "a"]);
+        assert_eq!(before, vec![after[1], after[0]]);
+    }
+
+    #[test]
+    fn animated_list_defers_unmount() {
+        let mut runtime = Runtime::new();
+        let mut render = |items: &[u32], exit: Duration| {
+            let transition = Transition {
+                enter: Duration::from_secs(0),
+                exit,
+            };
+            runtime.run_once(|| {
+                animated_list(
+                    items,
+                    |item| *item,
+                    transition,
+                    |item, presence| (*item, presence.phase),
+                )
+            })
+        };
+
+        let hour = Duration::from_secs(3600);
+        assert_eq!(
+            render(&[1, 2], hour),
+            vec![(1, Phase::Shown), (2, Phase::Shown)]
+        );
+        assert_eq!(
+            render(&[2], hour),
+            vec![(1, Phase::Exiting), (2, Phase::Shown)]
+        );
+        assert_eq!(
+            render(&[2], Duration::from_secs(0)),
+            vec![(2, Phase::Shown)]
+        );
+    }
+
+    #[test]
+    fn reduced_motion_skips_transitions() {
+        let motion = MotionState::default();
+        motion.set(true);
+        let mut runtime = Runtime::new();
+        let mut render = |items: &[u32]| {
+            let transition = Transition {
+                enter: Duration::from_secs(3600),
+                exit: Duration::from_secs(3600),
+            };
+            illicit::Layer::new().offer(motion.clone()).enter(|| {
+                runtime.run_once(|| {
+                    animated_list(
+                        items,
+                        |item| *item,
+                        transition,
+                        |item, presence| (*item, presence.phase, presence.progress),
+                    )
+                })
+            })
+        };
+
+        assert_eq!(
+            render(&[1, 2]),
+            vec![(1, Phase::Shown, 1.0), (2, Phase::Shown, 1.0)]
+        );
+        assert_eq!(render(&[2]), vec![(2, Phase::Shown, 1.0)]);
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Theme {
+        accent: Color,
+    }
+
+    #[topo::nested]
+    fn swatch() -> Option<Color> {
+        use_context::<Theme>().map(|theme| theme.accent)
+    }
+
+    #[topo::nested]
+    fn panel() -> Vec<Option<Color>> {
+        vec![swatch(), swatch()]
+    }
+
+    #[test]
+    fn context_from_ancestors() {
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+
+        let mut runtime = Runtime::new();
+        let mut render =
+            |accent: Color| runtime.run_once(|| provide(Theme { accent }, || provide(3u32, panel)));
+        assert_eq!(render(red), vec![Some(red), Some(red)]);
+        assert_eq!(render(blue), vec![Some(blue), Some(blue)]);
+
+        let nested = Runtime::new().run_once(|| {
+            provide(Theme { accent: red }, || {
+                provide(Theme { accent: blue }, swatch)
+            })
+        });
+        assert_eq!(nested, Some(blue));
+        assert_eq!(Runtime::new().run_once(swatch), None);
+    }
+
+    #[test]
+    fn future_output_rerenders() {
+        let tasks = Tasks::default();
+        let mut runtime = Runtime::new();
+        let mut render = || {
+            illicit::Layer::new()
+                .offer(tasks.clone())
+                .enter(|| runtime.run_once(|| use_future(|| async { 42 })))
+        };
+
+        assert_eq!(render(), None);
+        assert!(tasks.run_ready());
+        assert_eq!(render(), Some(42));
+        // The future isn't started again.
+        assert!(!tasks.run_ready());
+        assert_eq!(render(), Some(42));
+    }
+
+    #[test]
+    fn attributes_from_text() {
+        let card = test_style("card", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+        });
+        let mut computed = HashMap::new();
+        computed.insert("class", "card");
+        computed.insert("key_repeat", "false");
+        let (window, text_area) = Runtime::new().run_once(|| {
+            let text_area = elt::text_area()
+                .attrs(computed)
+                .attrs(vec![
+                    ("id", "first"),
+                    ("id", "second"),
+                    ("disabled", "maybe"),
+                    ("colour", "red"),
+                ])
+                .build();
+            (
+                elt::window().add_child(text_area.clone()).build(),
+                text_area,
+            )
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.set_class("card", Some(card));
+        document.get_layout();
+        assert_eq!(document.find_by_id("second"), Some(text_area.id()));
+        assert_eq!(document.find_by_id("first"), None);
+        assert_eq!(
+            document.computed_values(text_area.id()).background_color,
+            Color::new(255, 0, 0, 255)
+        );
+
+        let mut element = TextArea::default();
+        assert_eq!(
+            element.set_text_attribute("style", "card"),
+            Err(AttributeError::Invalid {
+                name: "style".to_owned(),
+                value: "card".to_owned(),
+            })
+        );
+        assert_eq!(
+            element.set_text_attribute("colour", "red"),
+            Err(AttributeError::Unknown {
+                name: "colour".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn optional_attributes() {
+        let wide = test_style("wide", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(80.0).into());
+            }
+        });
+        let clicks = Arc::new(AtomicUsize::new(0));
+        let counter = clicks.clone();
+        let (window, unset, set) = Runtime::new().run_once(|| {
+            let counter = counter.clone();
+            let unset = elt::view()
+                .set_attr_opt(attr::style(), None::<Style>)
+                .set_attr_opt(attr::title(), None::<&str>)
+                .build();
+            let set = elt::button()
+                .set_attr(attr::title(), "kept")
+                .set_attr_opt(attr::title(), None::<String>)
+                .set_attr_opt(attr::style(), Some(wide))
+                .on_event(event::on_click(), move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .on_event_opt(event::on_click(), None::<fn(&ClickEvent)>)
+                .add_child(elt::span().add_child("button").build())
+                .build();
+            let window = elt::window()
+                .add_child(
+                    elt::view()
+                        .add_child(unset.clone())
+                        .add_child(set.clone())
+                        .build(),
+                )
+                .build();
+            (window, unset, set)
+        });
+        assert_eq!(unset.element(), &View::default());
+        assert_eq!(set.element().title(), Some("kept"));
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let width = |document: &Document, id| match document.computed_values(id).display {
+            DisplayType::Block(block) => block.width,
+            DisplayType::Inline(_) => None,
+        };
+        document.get_layout();
+        assert_eq!(width(&document, unset.id()), None);
+        assert_eq!(
+            width(&document, set.id()),
+            Some(LogicalLength::new(80.0).into())
+        );
+
+        // The handler registered before the `None` one still runs.
+        document.key_pressed(Key::Tab);
+        document.key_pressed(Key::Enter);
+        assert_eq!(clicks.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn memo_skips_stable_deps() {
+        static BUILDS: AtomicUsize = AtomicUsize::new(0);
+        let mut runtime = Runtime::new();
+        let mut render = |title: &'static str, count: u32| {
+            runtime.run_once(|| {
+                elt::view()
+                    .add_child(memo_component(title, |title| {
+                        BUILDS.fetch_add(1, Ordering::SeqCst);
+                        elt::view()
+                            .add_child(elt::span().add_child(title.to_string()).build())
+                            .build()
+                    }))
+                    .add_child(elt::span().add_child(count.to_string()).build())
+                    .build()
+            })
+        };
+
+        let first = render("header", 0);
+        let second = render("header", 1);
+        assert_eq!(BUILDS.load(Ordering::SeqCst), 1);
+        assert!(first.children()[0] == second.children()[0]);
+        assert!(first.children()[1] != second.children()[1]);
+
+        let third = render("renamed", 1);
+        assert_eq!(BUILDS.load(Ordering::SeqCst), 2);
+        assert!(second.children()[0] != third.children()[0]);
+    }
+
+    #[test]
+    fn attribute_change_shares_children() {
+        let mut runtime = Runtime::new();
+        let mut render = |title: &str, label: &str| {
+            runtime.run_once(|| {
+                elt::view()
+                    .set_attr(attr::title(), title)
+                    .add_child(elt::span().add_child(label).build())
+                    .build()
+            })
+        };
+
+        let first = render("first", "label");
+        let second = render("second", "label");
+        assert!(first != second);
+        assert_eq!(second.element().title(), Some("second"));
+        assert_eq!(first.children().as_ptr(), second.children().as_ptr());
+
+        let third = render("second", "changed");
+        assert_ne!(second.children().as_ptr(), third.children().as_ptr());
+    }
+}
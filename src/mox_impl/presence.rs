@@ -0,0 +1,299 @@
+//! Following the items of an `animated_list()` as they're added and
+//! removed, so that they can animate in and out.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// How long the items of an `animated_list()` take to animate in and
+/// out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub enter: Duration,
+    pub exit: Duration,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Phase {
+    /// The item was added, and is animating in.
+    Entering,
+    /// The item is fully shown.
+    Shown,
+    /// The item was removed, and is animating out. It's unmounted once
+    /// `progress` reaches 0.
+    Exiting,
+}
+
+/// Where an item of an `animated_list()` is in its transition.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Presence {
+    pub phase: Phase,
+    /// How far the item is shown, from 0 when it's hidden to 1 when it's
+    /// fully shown, for fading it or sliding it in. This rises while it
+    /// enters and falls while it exits.
+    pub progress: f32,
+}
+
+struct Entry<Item> {
+    /// The item as it was last given, which exiting items are still
+    /// built from.
+    item: Item,
+    phase: Phase,
+    /// The progress when the phase started.
+    from: f32,
+    start: Instant,
+}
+
+impl<Item> Entry<Item> {
+    fn progress(&self, transition: Transition, now: Instant) -> f32 {
+        let (duration, direction) = match self.phase {
+            Phase::Entering => (transition.enter, 1.0),
+            Phase::Exiting => (transition.exit, -1.0),
+            Phase::Shown => return 1.0,
+        };
+        if duration == Duration::from_secs(0) {
+            return if direction > 0.0 { 1.0 } else { 0.0 };
+        }
+        let elapsed = now.duration_since(self.start).as_nanos() as f64 / duration.as_nanos() as f64;
+        (self.from + elapsed as f32 * direction).max(0.0).min(1.0)
+    }
+}
+
+/// The state behind an `animated_list()`: the items it last showed, in
+/// order, including those which are exiting.
+pub(crate) struct Presences<Key, Item> {
+    entries: HashMap<Key, Entry<Item>>,
+    order: Vec<Key>,
+}
+
+impl<Key, Item> Default for Presences<Key, Item> {
+    fn default() -> Self {
+        Presences {
+            entries: HashMap::new(),
+            order: vec![],
+        }
+    }
+}
+
+impl<Key, Item> Presences<Key, Item>
+where
+    Key: Eq + Hash + Clone,
+    Item: Clone,
+{
+    /// Takes the current items, returning those to show at `now`, with
+    /// where each is in its transition. Items which were removed stay
+    /// where they were among the others until they finish exiting.
+    ///
+    /// Removing an item which is still entering makes it exit from how
+    /// far it got, and adding back one which is exiting makes it enter
+    /// from there, so interrupted transitions don't jump.
+    pub fn update(
+        &mut self,
+        items: &[Item],
+        key: impl Fn(&Item) -> Key,
+        transition: Transition,
+        now: Instant,
+    ) -> Vec<(Key, Item, Presence)> {
+        let new_keys = items.iter().map(&key).collect::<Vec<_>>();
+
+        for (key, item) in new_keys.iter().zip(items) {
+            match self.entries.get_mut(key) {
+                Some(entry) => {
+                    if entry.phase == Phase::Exiting {
+                        entry.from = entry.progress(transition, now);
+                        entry.phase = Phase::Entering;
+                        entry.start = now;
+                    }
+                    entry.item = item.clone();
+                }
+                None => {
+                    self.entries.insert(
+                        key.clone(),
+                        Entry {
+                            item: item.clone(),
+                            phase: Phase::Entering,
+                            from: 0.0,
+                            start: now,
+                        },
+                    );
+                }
+            }
+        }
+        for key in &self.order {
+            if new_keys.contains(key) {
+                continue;
+            }
+            let entry = self.entries.get_mut(key).unwrap();
+            if entry.phase != Phase::Exiting {
+                entry.from = entry.progress(transition, now);
+                entry.phase = Phase::Exiting;
+                entry.start = now;
+            }
+        }
+
+        // Exiting items follow the item they came after.
+        let mut order = new_keys;
+        let mut previous = None;
+        for key in std::mem::take(&mut self.order) {
+            if !order.contains(&key) {
+                let index = previous
+                    .and_then(|previous| order.iter().position(|key| *key == previous))
+                    .map_or(0, |index| index + 1);
+                order.insert(index, key.clone());
+            }
+            previous = Some(key);
+        }
+
+        let entries = &mut self.entries;
+        order.retain(|key| {
+            let entry = entries.get_mut(key).unwrap();
+            let progress = entry.progress(transition, now);
+            if entry.phase == Phase::Entering && progress == 1.0 {
+                entry.phase = Phase::Shown;
+            }
+            let exited = entry.phase == Phase::Exiting && progress == 0.0;
+            if exited {
+                entries.remove(key);
+            }
+            !exited
+        });
+        self.order = order;
+
+        self.order
+            .iter()
+            .map(|key| {
+                let entry = &self.entries[key];
+                let presence = Presence {
+                    phase: entry.phase,
+                    progress: entry.progress(transition, now),
+                };
+                (key.clone(), entry.item.clone(), presence)
+            })
+            .collect()
+    }
+
+    /// Whether any item is still entering or exiting, so that the list
+    /// has to be rendered again.
+    pub fn animating(&self) -> bool {
+        self.entries
+            .values()
+            .any(|entry| entry.phase != Phase::Shown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Phase, Presence, Presences, Transition};
+    use std::time::{Duration, Instant};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn shown(
+        presences: &mut Presences<u32, u32>,
+        items: &[u32],
+        now: Instant,
+    ) -> Vec<(u32, Presence)> {
+        let transition = Transition {
+            enter: ms(100),
+            exit: ms(200),
+        };
+        presences
+            .update(items, |item| *item, transition, now)
+            .into_iter()
+            .map(|(key, _, presence)| (key, presence))
+            .collect()
+    }
+
+    fn presence(phase: Phase, progress: f32) -> Presence {
+        Presence { phase, progress }
+    }
+
+    #[test]
+    fn removed_item_exits_then_unmounts() {
+        let start = Instant::now();
+        let mut presences = Presences::default();
+        assert_eq!(
+            shown(&mut presences, &[1, 2], start),
+            vec![
+                (1, presence(Phase::Entering, 0.0)),
+                (2, presence(Phase::Entering, 0.0)),
+            ]
+        );
+        assert_eq!(
+            shown(&mut presences, &[1, 2], start + ms(100)),
+            vec![
+                (1, presence(Phase::Shown, 1.0)),
+                (2, presence(Phase::Shown, 1.0)),
+            ]
+        );
+        assert!(!presences.animating());
+
+        // The removed item stays in its place for the exit duration.
+        let removed = start + ms(200);
+        assert_eq!(
+            shown(&mut presences, &[2], removed),
+            vec![
+                (1, presence(Phase::Exiting, 1.0)),
+                (2, presence(Phase::Shown, 1.0)),
+            ]
+        );
+        assert_eq!(
+            shown(&mut presences, &[2], removed + ms(100)),
+            vec![
+                (1, presence(Phase::Exiting, 0.5)),
+                (2, presence(Phase::Shown, 1.0)),
+            ]
+        );
+        assert!(presences.animating());
+        assert_eq!(
+            shown(&mut presences, &[2], removed + ms(200)),
+            vec![(2, presence(Phase::Shown, 1.0))]
+        );
+        assert!(!presences.animating());
+    }
+
+    #[test]
+    fn interrupted_transitions() {
+        let start = Instant::now();
+        let mut presences = Presences::default();
+        shown(&mut presences, &[1], start);
+        shown(&mut presences, &[1], start + ms(100));
+
+        // Added, then removed halfway through entering: it exits from
+        // where it got to, taking half of the exit duration.
+        let added = start + ms(200);
+        shown(&mut presences, &[1, 2], added);
+        assert_eq!(
+            shown(&mut presences, &[1], added + ms(50)),
+            vec![
+                (1, presence(Phase::Shown, 1.0)),
+                (2, presence(Phase::Exiting, 0.5)),
+            ]
+        );
+        assert_eq!(
+            shown(&mut presences, &[1], added + ms(100)),
+            vec![
+                (1, presence(Phase::Shown, 1.0)),
+                (2, presence(Phase::Exiting, 0.25)),
+            ]
+        );
+
+        // Added back while exiting.
+        assert_eq!(
+            shown(&mut presences, &[1, 2], added + ms(125)),
+            vec![
+                (1, presence(Phase::Shown, 1.0)),
+                (2, presence(Phase::Entering, 0.125)),
+            ]
+        );
+        assert_eq!(
+            shown(&mut presences, &[2, 1], added + ms(300)),
+            vec![
+                (2, presence(Phase::Shown, 1.0)),
+                (1, presence(Phase::Shown, 1.0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Building elements from a description which is only known at run
+//! time, such as one loaded from a file or made by an editor, instead
+//! of with the mox! macro.
+
+use super::builder::Builder;
+use crate::dom::element::{AttributeError, DynChild, DynamicNode, Element, HasTextAttributes};
+use crate::dom::element::{NoChildren, NodeChild};
+use crate::dom::{Button, Image, Node, Spacer, Span, Svg, TextArea, View, Window};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+/// An element to build, by the name it's registered with in an
+/// `ElementRegistry`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElementSpec {
+    pub tag: String,
+    /// Set by name from text, the same as with `Builder::attrs()`.
+    /// Like in the mox! macro, `key` is reserved: it's never given to
+    /// the element, and builds it under the key instead of its position,
+    /// see `keyed()`.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<ChildSpec>,
+}
+
+impl ElementSpec {
+    pub fn new(tag: impl Into<String>) -> ElementSpec {
+        ElementSpec {
+            tag: tag.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<String>) -> ElementSpec {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<ChildSpec>) -> ElementSpec {
+        self.children.push(child.into());
+        self
+    }
+
+    /// The last `key` in the attributes.
+    pub fn key(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(name, _)| name == "key")
+            .map(|(_, value)| &value[..])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChildSpec {
+    Element(ElementSpec),
+    Text(String),
+}
+
+impl From<ElementSpec> for ChildSpec {
+    fn from(spec: ElementSpec) -> Self {
+        ChildSpec::Element(spec)
+    }
+}
+
+impl From<String> for ChildSpec {
+    fn from(text: String) -> Self {
+        ChildSpec::Text(text)
+    }
+}
+
+impl<'a> From<&'a str> for ChildSpec {
+    fn from(text: &'a str) -> Self {
+        ChildSpec::Text(text.to_owned())
+    }
+}
+
+/// A spec couldn't be built.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpecError {
+    /// No element is registered with this name.
+    UnknownTag { tag: String },
+    /// An attribute couldn't be set on an element.
+    Attribute { tag: String, error: AttributeError },
+    /// An element was given a child it can't have, such as text
+    /// directly inside of a window. Text is named `text`.
+    Child { parent: String, child: String },
+}
+
+impl Display for SpecError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            SpecError::UnknownTag { tag } => write!(fmt, "unknown element `{}`", tag),
+            SpecError::Attribute { tag, error } => write!(fmt, "{} on `{}`", error, tag),
+            SpecError::Child { parent, child } => {
+                write!(fmt, "`{}` can't be a child of `{}`", child, parent)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// A node or text built from a spec. It can be added as a child of the
+/// elements which take `DynChild`, or turned back into a node of a
+/// known type with `node()`, such as the windows to give to `app`.
+#[derive(Clone)]
+pub struct BuiltChild {
+    value: Arc<dyn Any + Send + Sync>,
+    dynamic: DynChild,
+}
+
+impl BuiltChild {
+    pub fn from_node<Elt>(node: Node<Elt>) -> BuiltChild
+    where
+        Elt: Element,
+        Node<Elt>: Send + Sync,
+    {
+        BuiltChild {
+            value: Arc::new(node.clone()),
+            dynamic: node.into(),
+        }
+    }
+
+    pub fn from_text(text: String) -> BuiltChild {
+        BuiltChild {
+            value: Arc::new(text.clone()),
+            dynamic: text.into(),
+        }
+    }
+
+    pub fn node<Elt>(&self) -> Option<Node<Elt>>
+    where
+        Elt: Element,
+    {
+        self.get()
+    }
+
+    /// The child as `T`, which is either its node or text, or `DynChild`.
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + 'static,
+    {
+        let dynamic: &dyn Any = &self.dynamic;
+        dynamic
+            .downcast_ref::<T>()
+            .or_else(|| self.value.downcast_ref::<T>())
+            .cloned()
+    }
+}
+
+impl NodeChild for BuiltChild {
+    fn get_node(&self) -> DynamicNode {
+        self.dynamic.get_node()
+    }
+}
+
+impl From<BuiltChild> for DynChild {
+    fn from(child: BuiltChild) -> Self {
+        child.dynamic
+    }
+}
+
+/// The child type of an element which is built from a spec, see
+/// `ElementRegistry::register()`. This is implemented for the enums
+/// defined with `multiple_children!`, where the typed variants are
+/// tried in order before a `DynChild` one.
+pub trait FromBuiltChild: Sized {
+    /// Returns `None` if the element can't have this child.
+    fn from_built_child(child: &BuiltChild) -> Option<Self>;
+}
+
+impl<Elt> FromBuiltChild for Node<Elt>
+where
+    Elt: Element,
+{
+    fn from_built_child(child: &BuiltChild) -> Option<Self> {
+        child.node()
+    }
+}
+
+impl FromBuiltChild for String {
+    fn from_built_child(child: &BuiltChild) -> Option<Self> {
+        child.get()
+    }
+}
+
+impl FromBuiltChild for NoChildren {
+    fn from_built_child(_child: &BuiltChild) -> Option<Self> {
+        None
+    }
+}
+
+type BuildFn =
+    dyn Fn(&ElementSpec, &ElementRegistry) -> Result<BuiltChild, SpecError> + Send + Sync;
+
+/// The elements which can be built from a spec, by tag name.
+///
+/// The default registry has every element except for `app`, under the
+/// same names as the functions in `elt`. Custom elements register with
+/// `register()`, and tags which stand for a component, built out of
+/// other elements, with `register_with()`. Registering a tag again
+/// replaces what it built before.
+#[derive(Clone)]
+pub struct ElementRegistry {
+    builders: HashMap<String, Arc<BuildFn>>,
+}
+
+impl ElementRegistry {
+    /// A registry without any elements.
+    pub fn empty() -> ElementRegistry {
+        ElementRegistry {
+            builders: HashMap::new(),
+        }
+    }
+
+    /// Registers `Elt` under its `Element::ELEMENT_NAME`. Its attributes
+    /// are set by name, and its children are built from the registry
+    /// and converted with `FromBuiltChild`.
+    pub fn register<Elt>(&mut self)
+    where
+        Elt: Element + HasTextAttributes,
+        Elt::Child: FromBuiltChild,
+        Node<Elt>: Send + Sync,
+    {
+        self.register_with(Elt::ELEMENT_NAME, build_element::<Elt>);
+    }
+
+    /// Registers `tag` to be built by `build`, which is given the spec
+    /// and the registry to build its children with.
+    pub fn register_with(
+        &mut self,
+        tag: impl Into<String>,
+        build: impl Fn(&ElementSpec, &ElementRegistry) -> Result<BuiltChild, SpecError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.builders.insert(tag.into(), Arc::new(build));
+    }
+
+    /// Builds the element and all of its descendants. Like the mox!
+    /// macro, this has to be called while composing, and each element
+    /// keeps its identity between compositions by where it is in the
+    /// spec.
+    pub fn build(&self, spec: &ElementSpec) -> Result<BuiltChild, SpecError> {
+        let build = self
+            .builders
+            .get(&spec.tag)
+            .ok_or_else(|| SpecError::UnknownTag {
+                tag: spec.tag.clone(),
+            })?;
+        match spec.key() {
+            Some(key) => topo::call_in_slot(&key.to_owned(), || build(spec, self)),
+            None => topo::call(|| build(spec, self)),
+        }
+    }
+}
+
+impl Default for ElementRegistry {
+    fn default() -> Self {
+        let mut registry = ElementRegistry::empty();
+        registry.register::<Window>();
+        registry.register::<View>();
+        registry.register::<Button>();
+        registry.register::<Image>();
+        registry.register::<Spacer>();
+        registry.register::<Span>();
+        registry.register::<Svg>();
+        registry.register::<TextArea>();
+        registry
+    }
+}
+
+fn build_element<Elt>(
+    spec: &ElementSpec,
+    registry: &ElementRegistry,
+) -> Result<BuiltChild, SpecError>
+where
+    Elt: Element + HasTextAttributes,
+    Elt::Child: FromBuiltChild,
+    Node<Elt>: Send + Sync,
+{
+    let mut builder = Builder::<Elt>::new();
+    for (name, value) in spec.attributes.iter().filter(|(name, _)| name != "key") {
+        builder
+            .try_attr(name, value)
+            .map_err(|error| SpecError::Attribute {
+                tag: spec.tag.clone(),
+                error,
+            })?;
+    }
+    let mut children = vec![];
+    for child in &spec.children {
+        let (built, name) = match child {
+            ChildSpec::Element(child) => (registry.build(child)?, &child.tag[..]),
+            ChildSpec::Text(text) => (BuiltChild::from_text(text.clone()), "text"),
+        };
+        let child = Elt::Child::from_built_child(&built).ok_or_else(|| SpecError::Child {
+            parent: spec.tag.clone(),
+            child: name.to_owned(),
+        })?;
+        children.push(child);
+    }
+    Ok(BuiltChild::from_node(builder.add_child(children).build()))
+}
+
+/// Builds `spec` with the default `ElementRegistry`.
+pub fn build_from_spec(spec: &ElementSpec) -> Result<BuiltChild, SpecError> {
+    ElementRegistry::default().build(spec)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_from_spec, ChildSpec, ElementRegistry, ElementSpec, SpecError};
+    use crate::dom::element::{AttributeError, DynChild, DynamicNode, Element, NodeChild};
+    use crate::dom::{Button, View, Window};
+    use crate::mox_impl::elt;
+    use moxie::runtime::Runtime;
+
+    #[test]
+    fn builds_tree_from_spec() {
+        let spec = ElementSpec::new("window").child(
+            ElementSpec::new("view")
+                .attr("class", "toolbar")
+                .child(ElementSpec::new("button").child(ElementSpec::new("span").child("Save")))
+                .child(ElementSpec::new("confirm"))
+                .child(ElementSpec::new("text_area").attr("value", "notes")),
+        );
+        let mut registry = ElementRegistry::default();
+        registry.register_with("confirm", |spec, registry| {
+            let mut button = ElementSpec::new("button").attr("default", "true");
+            button.children = spec.children.clone();
+            registry.build(&button)
+        });
+
+        let window = Runtime::new()
+            .run_once(|| registry.build(&spec))
+            .unwrap()
+            .node::<Window>()
+            .unwrap();
+        let view = &window.children()[0];
+        assert_eq!(view.element().class(), Some("toolbar"));
+        let names: Vec<_> = view
+            .children()
+            .iter()
+            .map(|child| child.get_node().node().unwrap().name())
+            .collect();
+        assert_eq!(names, vec!["button", "button", "text_area"]);
+        let button = view.children()[0].get_node().node().unwrap();
+        let span = button.children().next().unwrap().node().unwrap();
+        assert!(matches!(
+            span.children().next(),
+            Some(DynamicNode::Text("Save"))
+        ));
+
+        // Built nodes can be added to elements which take dynamic
+        // children, and converted back.
+        let built = Runtime::new()
+            .run_once(|| build_from_spec(&ElementSpec::new("button")))
+            .unwrap();
+        assert!(built.node::<Button>().is_some());
+        assert!(built.node::<View>().is_none());
+        let parent = Runtime::new().run_once(|| {
+            elt::span()
+                .add_child(vec![DynChild::from(built.clone())])
+                .build()
+        });
+        assert_eq!(parent.children().len(), 1);
+    }
+
+    #[test]
+    fn spec_errors() {
+        let build = |spec: ElementSpec| Runtime::new().run_once(|| build_from_spec(&spec).err());
+        assert_eq!(
+            build(ElementSpec::new("view").child(ElementSpec::new("marquee"))),
+            Some(SpecError::UnknownTag {
+                tag: "marquee".to_owned()
+            })
+        );
+        assert_eq!(
+            build(ElementSpec::new("window").child(ChildSpec::Text("hi".to_owned()))),
+            Some(SpecError::Child {
+                parent: "window".to_owned(),
+                child: "text".to_owned(),
+            })
+        );
+        assert_eq!(
+            build(ElementSpec::new("view").attr("colour", "red")),
+            Some(SpecError::Attribute {
+                tag: "view".to_owned(),
+                error: AttributeError::Unknown {
+                    name: "colour".to_owned()
+                },
+            })
+        );
+    }
+}
@@ -1,8 +1,15 @@
 // For conveniently naming various types
-pub use crate::dom::{events::*, App, Button, Node, Span, View, Window};
+pub use crate::dom::{
+    events::*, App, Button, Image, ImageSource, Node, Spacer, Span, Svg, SvgImage, TextArea,
+    TextValue, View, Window,
+};
 pub use crate::style::Style;
 // mox!() macro
 pub use crate::mox_impl;
+pub use crate::mox_impl::{
+    animated_list, build_from_spec, keyed, list, provide, use_context, use_future, when,
+    ElementSpec, Phase, Presence, Transition,
+};
 pub use mox;
 // define_style!() macro
 pub use crate::style_impl;
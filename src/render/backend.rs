@@ -0,0 +1,445 @@
+use super::dirty::{DirtyRegion, DirtyTracking, ItemSummary};
+use super::display_list::DisplayListBackend;
+use super::renderer::{self, CachedPaint, Renderer};
+use super::webrender_backend::WebrenderBackend;
+use super::TextRendering;
+use crate::document::Document;
+use crate::dom::WindowMetrics;
+use crate::layout::{
+    LayoutText, LayoutTreeNode, LogicalLength, LogicalPixel, LogicalPoint, LogicalRect,
+};
+use crate::runtime::DecodedImage;
+use crate::style::{Border, Corners, Edges, TextShadow};
+use crate::Color;
+use log::error;
+use std::rc::Rc;
+use std::sync::Arc;
+use webrender::api::{units::DevicePixel, IdNamespace, PipelineId};
+use webrender::euclid::{Scale, Size2D};
+
+/// How a window is being rendered, see `Runtime::render_path()`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderPath {
+    /// Webrender, on the GPU.
+    Webrender,
+    /// The backend set with `Runtime::renderer()`.
+    Custom,
+    /// The GPU couldn't be set up, for the given reason, so the window
+    /// runs headless: nothing is rasterized and the window stays blank,
+    /// but the app keeps running. Components, layout and input work as
+    /// usual, with hit testing done against the layout instead of by
+    /// Webrender. Apps which need pixels without a GPU can draw them
+    /// with a backend of their own, see `Runtime::renderer()`.
+    Headless { reason: String },
+}
+
+/// Creates the backend of each window, see `Runtime::renderer()`.
+pub(crate) type RendererFactory = Rc<dyn Fn(WindowMetrics) -> Box<dyn Renderer<Clip = usize>>>;
+
+/// Wraps a backend set with `Runtime::renderer()`, numbering its clips
+/// so that backends with any type of clip can be stored alike. The
+/// numbers start over with each frame.
+pub(crate) struct NumberedClips<R: Renderer> {
+    renderer: R,
+    clips: Vec<R::Clip>,
+}
+
+impl<R: Renderer> NumberedClips<R> {
+    pub fn new(renderer: R) -> NumberedClips<R> {
+        let clips = vec![renderer.root_clip()];
+        NumberedClips { renderer, clips }
+    }
+
+    fn number(&mut self, clip: R::Clip) -> usize {
+        self.clips.push(clip);
+        self.clips.len() - 1
+    }
+}
+
+impl<R: Renderer> Renderer for NumberedClips<R> {
+    type Clip = usize;
+
+    fn root_clip(&self) -> usize {
+        0
+    }
+
+    fn push_clip(
+        &mut self,
+        parent: usize,
+        rect: LogicalRect,
+        radius: Corners<LogicalLength>,
+    ) -> usize {
+        let clip = self.renderer.push_clip(self.clips[parent], rect, radius);
+        self.number(clip)
+    }
+
+    fn push_rect(&mut self, clip: usize, rect: LogicalRect, color: Color, hit_test: Option<u64>) {
+        self.renderer
+            .push_rect(self.clips[clip], rect, color, hit_test)
+    }
+
+    fn push_backdrop_blur(&mut self, clip: usize, rect: LogicalRect, radius: LogicalLength) {
+        self.renderer
+            .push_backdrop_blur(self.clips[clip], rect, radius)
+    }
+
+    fn push_border(
+        &mut self,
+        clip: usize,
+        rect: LogicalRect,
+        border: &Edges<Border>,
+        radius: Corners<LogicalLength>,
+    ) {
+        self.renderer
+            .push_border(self.clips[clip], rect, border, radius)
+    }
+
+    fn push_image(&mut self, clip: usize, rect: LogicalRect, image: &Arc<DecodedImage>) {
+        self.renderer.push_image(self.clips[clip], rect, image)
+    }
+
+    fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        self.renderer.set_text_rendering(text_rendering)
+    }
+
+    fn push_text(
+        &mut self,
+        clip: usize,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        color: Color,
+    ) {
+        self.renderer
+            .push_text(self.clips[clip], rect, position, text, color)
+    }
+
+    fn push_text_shadow(
+        &mut self,
+        clip: usize,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        shadow: &TextShadow,
+    ) {
+        self.renderer
+            .push_text_shadow(self.clips[clip], rect, position, text, shadow)
+    }
+
+    fn begin_cached(
+        &mut self,
+        clip: usize,
+        id: u64,
+        rect: LogicalRect,
+        hash: u64,
+    ) -> CachedPaint<usize> {
+        match self.renderer.begin_cached(self.clips[clip], id, rect, hash) {
+            CachedPaint::Inline => CachedPaint::Inline,
+            CachedPaint::Reused => CachedPaint::Reused,
+            CachedPaint::Repaint(clip) => CachedPaint::Repaint(self.number(clip)),
+        }
+    }
+
+    fn end_cached(&mut self) {
+        self.renderer.end_cached()
+    }
+
+    fn resize(&mut self, metrics: WindowMetrics) {
+        self.renderer.resize(metrics)
+    }
+
+    fn present(&mut self, dirty: &DirtyRegion) -> bool {
+        let presented = self.renderer.present(dirty);
+        self.clips.clear();
+        self.clips.push(self.renderer.root_clip());
+        presented
+    }
+}
+
+/// What a `Context` paints with.
+pub(crate) enum Backend {
+    Webrender(WebrenderBackend),
+    /// Set with `Runtime::renderer()`, see `NumberedClips`.
+    Custom(Box<dyn Renderer<Clip = usize>>),
+    /// Display lists are still built, so painting goes through the same
+    /// code, but they're dropped instead of being presented.
+    Headless {
+        display_list: DisplayListBackend,
+        reason: String,
+    },
+}
+
+impl Backend {
+    /// Uses Webrender if it was set up, or logs why it couldn't be and
+    /// runs headless.
+    pub fn new(webrender: Result<WebrenderBackend, String>) -> Backend {
+        match webrender {
+            Ok(backend) => Backend::Webrender(backend),
+            Err(reason) => {
+                error!(
+                    "Rendering is unavailable, the window will stay blank: {}",
+                    reason
+                );
+                Backend::Headless {
+                    display_list: DisplayListBackend::new(PipelineId(0, 0), IdNamespace(0)),
+                    reason,
+                }
+            }
+        }
+    }
+
+    pub fn path(&self) -> RenderPath {
+        match self {
+            Backend::Webrender(_) => RenderPath::Webrender,
+            Backend::Custom(_) => RenderPath::Custom,
+            Backend::Headless { reason, .. } => RenderPath::Headless {
+                reason: reason.clone(),
+            },
+        }
+    }
+
+    pub fn deinit(&mut self) {
+        if let Backend::Webrender(backend) = self {
+            backend.deinit();
+        }
+    }
+
+    pub fn resize(&mut self, client_size: Size2D<i32, DevicePixel>, dpi_scale: f32) {
+        match self {
+            Backend::Webrender(backend) => backend.resize(client_size, dpi_scale),
+            Backend::Custom(renderer) => renderer.resize(WindowMetrics {
+                scale_factor: dpi_scale,
+                size: client_size.to_f32()
+                    / Scale::<f32, LogicalPixel, DevicePixel>::new(dpi_scale),
+            }),
+            Backend::Headless { .. } => (),
+        }
+    }
+
+    /// The element painted at `position`.
+    pub fn element_at(&mut self, position: LogicalPoint, document: &Document) -> Option<u64> {
+        match self {
+            Backend::Webrender(backend) => backend.element_at(position),
+            Backend::Custom(_) | Backend::Headless { .. } => document.element_at(position),
+        }
+    }
+
+    /// Paints the layout into the next frame, returning the number of
+    /// items drawn along with their summaries, see `DirtyTracking`.
+    pub fn paint(
+        &mut self,
+        document: &Document,
+        layout: &LayoutTreeNode,
+        bounds: LogicalRect,
+        snap: Option<f32>,
+    ) -> (usize, Vec<ItemSummary>) {
+        match self {
+            Backend::Webrender(backend) => {
+                paint_tracked(backend.display_list(), document, layout, bounds, snap)
+            }
+            Backend::Custom(renderer) => {
+                paint_tracked(&mut **renderer, document, layout, bounds, snap)
+            }
+            Backend::Headless { display_list, .. } => {
+                paint_tracked(display_list, document, layout, bounds, snap)
+            }
+        }
+    }
+
+    /// Returns whether a frame was presented, which is never the case
+    /// when running headless.
+    pub fn present(&mut self, dirty: &DirtyRegion) -> bool {
+        match self {
+            Backend::Webrender(backend) => backend.present(dirty),
+            Backend::Custom(renderer) => renderer.present(dirty),
+            Backend::Headless { display_list, .. } => {
+                display_list.discard();
+                false
+            }
+        }
+    }
+}
+
+fn paint_tracked<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    document: &Document,
+    layout: &LayoutTreeNode,
+    bounds: LogicalRect,
+    snap: Option<f32>,
+) -> (usize, Vec<ItemSummary>) {
+    let mut tracking = DirtyTracking::new(renderer, bounds);
+    let items = renderer::paint(&mut tracking, document, layout, snap);
+    (items, tracking.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Backend, NumberedClips, RenderPath};
+    use crate::document::Document;
+    use crate::dom::WindowMetrics;
+    use crate::layout::{LayoutText, LogicalLength, LogicalPoint, LogicalRect};
+    use crate::mox_impl::{attr, elt};
+    use crate::render::dirty::{DamageTracker, DirtyRegion};
+    use crate::render::renderer::Renderer;
+    use crate::runtime::DecodedImage;
+    use crate::style::{sized_box, Border, Corners, Edges, Style};
+    use crate::Color;
+    use euclid::{point2, rect, size2};
+    use moxie::runtime::Runtime;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    /// Clips to rects, and records the clip of each rect pushed.
+    #[derive(Default)]
+    struct ClipRecorder {
+        rects: Vec<(LogicalRect, LogicalRect)>,
+        metrics: Option<WindowMetrics>,
+        frames: usize,
+    }
+
+    struct SharedRecorder(Rc<RefCell<ClipRecorder>>);
+
+    impl Renderer for SharedRecorder {
+        type Clip = LogicalRect;
+
+        fn root_clip(&self) -> LogicalRect {
+            rect(0.0, 0.0, 100.0, 100.0)
+        }
+
+        fn push_clip(
+            &mut self,
+            parent: LogicalRect,
+            rect: LogicalRect,
+            _radius: Corners<LogicalLength>,
+        ) -> LogicalRect {
+            parent.intersection(&rect).unwrap_or_else(LogicalRect::zero)
+        }
+
+        fn push_rect(
+            &mut self,
+            clip: LogicalRect,
+            rect: LogicalRect,
+            _color: Color,
+            _hit_test: Option<u64>,
+        ) {
+            self.0.borrow_mut().rects.push((clip, rect));
+        }
+
+        fn push_backdrop_blur(&mut self, _: LogicalRect, _: LogicalRect, _: LogicalLength) {}
+
+        fn push_border(
+            &mut self,
+            _: LogicalRect,
+            _: LogicalRect,
+            _: &Edges<Border>,
+            _: Corners<LogicalLength>,
+        ) {
+        }
+
+        fn push_image(&mut self, _: LogicalRect, _: LogicalRect, _: &Arc<DecodedImage>) {}
+
+        fn push_text(
+            &mut self,
+            _: LogicalRect,
+            _: LogicalRect,
+            _: LogicalPoint,
+            _: &LayoutText,
+            _: Color,
+        ) {
+        }
+
+        fn resize(&mut self, metrics: WindowMetrics) {
+            self.0.borrow_mut().metrics = Some(metrics);
+        }
+
+        fn present(&mut self, _dirty: &DirtyRegion) -> bool {
+            self.0.borrow_mut().frames += 1;
+            true
+        }
+    }
+
+    #[test]
+    fn custom_backend() {
+        let clip = Style::from_css_string(
+            "width: 40px; height: 40px; overflow: hidden; background_color: #fff",
+        );
+        let large = Style::from_css_string("width: 60px; height: 60px; background_color: #000");
+        let (window, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), large).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), clip)
+                .add_child(inner.clone())
+                .build();
+            (elt::window().add_child(outer).build(), inner)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+
+        let recorder = Rc::new(RefCell::new(ClipRecorder::default()));
+        let renderer = NumberedClips::new(SharedRecorder(recorder.clone()));
+        let mut backend = Backend::Custom(Box::new(renderer));
+        assert_eq!(backend.path(), RenderPath::Custom);
+
+        // The backend gets its own clips back, across frames.
+        let bounds = LogicalRect::new(point2(0.0, 0.0), document.content_size());
+        let layout = document.get_layout();
+        for frame in 1..=2 {
+            let (_, items) = backend.paint(&document, &layout, bounds, None);
+            let dirty = DamageTracker::default().update(items, bounds);
+            assert!(backend.present(&dirty));
+            let mut recorder = recorder.borrow_mut();
+            assert_eq!(recorder.frames, frame);
+            assert_eq!(
+                std::mem::take(&mut recorder.rects),
+                vec![
+                    (rect(0.0, 0.0, 100.0, 100.0), rect(0.0, 0.0, 40.0, 40.0)),
+                    (rect(0.0, 0.0, 40.0, 40.0), rect(0.0, 0.0, 60.0, 60.0)),
+                ]
+            );
+        }
+
+        backend.resize(size2(300, 200), 2.0);
+        assert_eq!(
+            recorder.borrow().metrics,
+            Some(WindowMetrics {
+                scale_factor: 2.0,
+                size: size2(150.0, 100.0),
+            })
+        );
+        assert_eq!(
+            backend.element_at(point2(10.0, 10.0), &document),
+            Some(inner.id())
+        );
+        assert_eq!(backend.element_at(point2(80.0, 80.0), &document), None);
+    }
+
+    #[test]
+    fn failed_init_runs_headless() {
+        let square = sized_box("square", 20.0, 20.0);
+        let (window, view) = Runtime::new().run_once(|| {
+            let view = elt::view().set_attr(attr::style(), square).build();
+            (elt::window().add_child(view.clone()).build(), view)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+
+        let mut backend = Backend::new(Err("simulated GPU failure".to_owned()));
+        assert_eq!(
+            backend.path(),
+            RenderPath::Headless {
+                reason: "simulated GPU failure".to_owned()
+            }
+        );
+
+        // Frames are painted and dropped, and hit testing still works.
+        let bounds = LogicalRect::new(point2(0.0, 0.0), document.content_size());
+        let layout = document.get_layout();
+        let (_, items) = backend.paint(&document, &layout, bounds, None);
+        let dirty = DamageTracker::default().update(items, bounds);
+        assert!(!backend.present(&dirty));
+        assert_eq!(
+            backend.element_at(point2(10.0, 10.0), &document),
+            Some(view.id())
+        );
+        assert_eq!(backend.element_at(point2(50.0, 50.0), &document), None);
+    }
+}
@@ -1,24 +1,17 @@
+use super::backend::{Backend, RenderPath, RendererFactory};
+use super::dirty::DamageTracker;
+use super::webrender_backend::WebrenderBackend;
+use super::{FrameStats, TextRendering};
 use crate::document::Document;
-use crate::dom::{Node, Window};
-use crate::layout::{LayoutText, LayoutTreeNode, LogicalPixel, RenderData};
-use crate::style::BorderStyle as DomBorderStyle;
-use crate::util::equal_rc::EqualRc;
+use crate::dom::{Node, Window, WindowMetrics};
+use crate::layout::{LogicalPixel, LogicalRect};
 use gleam::gl;
 use log::debug;
-use skribo::FontRef;
-use std::collections::HashMap;
 use std::rc::Rc;
-use std::sync::mpsc;
+use std::time::Instant;
 use webrender::{
-    api::{
-        units::DeviceIntRect, units::DevicePixel, units::LayoutSideOffsets, BorderDetails,
-        BorderRadius, BorderSide, BorderStyle, ClipId, ClipMode, ColorF, CommonItemProperties,
-        ComplexClipRegion, DisplayListBuilder, DocumentId, Epoch, FontInstanceKey, FontKey,
-        GlyphInstance, HitTestFlags, NormalBorder, PipelineId, PrimitiveFlags, RenderApi,
-        RenderNotifier, SpaceAndClipInfo, SpatialId, Transaction,
-    },
-    euclid::{point2, size2, Point2D, Rect, Scale, Size2D},
-    Renderer, RendererOptions,
+    api::units::DevicePixel,
+    euclid::{point2, size2, Point2D, Scale, Size2D},
 };
 use winit::{
     dpi::{LogicalPosition, PhysicalSize},
@@ -26,335 +19,190 @@ use winit::{
     window::Window as WinitWindow,
 };
 
-/// Used to wait for frames to be ready in Webrender.
-#[derive(Clone)]
-struct Notifier {
-    events_proxy: EventLoopProxy<()>,
-    tx: mpsc::Sender<()>,
-}
-
-impl RenderNotifier for Notifier {
-    fn clone(&self) -> Box<dyn RenderNotifier> {
-        Box::new(Clone::clone(self))
-    }
-
-    fn wake_up(&self) {
-        self.tx.send(()).unwrap();
-        let _ = self.events_proxy.send_event(());
-    }
-
-    fn new_frame_ready(&self, _: DocumentId, _: bool, _: bool, _: Option<u64>) {
-        self.wake_up();
-    }
-}
-
-/// Contains everything needed to display the DOM. It creates an
-/// Angle-based GL context, a Webrender rendering context, and manages
-/// the `LayoutEngine` and `RenderEngine` for creating the DOM's layout
-/// and paint trees. It handles bubbling input events through the DOM as
-/// well.
+/// Contains everything needed to display the DOM. It creates a
+/// Webrender rendering context, and manages the `Document` which
+/// produces the DOM's layout. Painting goes through the `Renderer`
+/// trait, with Webrender as the backend unless the app set its own, or
+/// nothing if the GPU couldn't be set up, see `RenderPath`. It handles bubbling input events
+/// through the DOM as well.
 pub struct Context {
-    api: RenderApi,
-    document_id: DocumentId,
-    rx: mpsc::Receiver<()>,
-    renderer: Option<Renderer>,
+    backend: Backend,
     pub document: Document,
     client_size: Size2D<i32, DevicePixel>,
     dpi_scale: f32,
-    fonts: HashMap<String, FontKey>,
-    font_instances: HashMap<(FontKey, usize), FontInstanceKey>,
+    /// When something first happened that needs a new frame.
+    requested: Option<Instant>,
+    last_frame_stats: Option<FrameStats>,
+    damage: DamageTracker,
+    /// Whether element boxes are snapped to the device pixel grid.
+    pixel_snap: bool,
+    /// Whether a frame has been presented, or painted when there's
+    /// nothing to present it to.
+    painted: bool,
 }
 
 impl Context {
+    /// Paints with the backend from `renderer` if it's set, and otherwise
+    /// with Webrender, given the window's GL functions or why they
+    /// couldn't be loaded.
     pub fn new(
-        gl: Rc<dyn gl::Gl>,
+        gl: Result<Rc<dyn gl::Gl>, String>,
+        renderer: Option<&RendererFactory>,
         parent_window: &WinitWindow,
         events_proxy: EventLoopProxy<()>,
         window: Node<Window>,
     ) -> Context {
-        let (tx, rx) = mpsc::channel();
-        let notifier = Box::new(Notifier { events_proxy, tx });
-
         let dpi_scale = parent_window.scale_factor() as f32;
         let inner_size = parent_window.inner_size();
         let client_size =
             Size2D::<i32, DevicePixel>::new(inner_size.width as i32, inner_size.height as i32);
 
-        let (renderer, sender) = Renderer::new(
-            gl,
-            notifier,
-            RendererOptions {
-                clear_color: Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
-                device_pixel_ratio: dpi_scale,
-                ..Default::default()
-            },
-            None,
-            client_size,
-        )
-        .unwrap();
-        let renderer = Some(renderer);
-        let api = sender.create_api();
-        let document_id = api.add_document(client_size, 0);
+        let size = client_size.to_f32() / Scale::<f32, LogicalPixel, DevicePixel>::new(dpi_scale);
 
-        let document = Document::new(
-            window,
-            client_size.to_f32() / Scale::<f32, LogicalPixel, DevicePixel>::new(dpi_scale),
-        );
+        let backend = match renderer {
+            Some(create) => Backend::Custom(create(WindowMetrics {
+                scale_factor: dpi_scale,
+                size,
+            })),
+            None => Backend::new(
+                gl.and_then(|gl| WebrenderBackend::new(gl, events_proxy, client_size, dpi_scale)),
+            ),
+        };
+
+        let document = Document::new(window, size);
 
         Context {
-            api,
-            document_id,
-            rx,
-            renderer,
+            backend,
             document,
             client_size,
             dpi_scale,
-            fonts: HashMap::new(),
-            font_instances: HashMap::new(),
+            requested: None,
+            last_frame_stats: None,
+            damage: DamageTracker::default(),
+            pixel_snap: false,
+            painted: false,
         }
     }
 
     pub fn deinit(&mut self) {
-        self.renderer.take().unwrap().deinit();
+        self.backend.deinit();
     }
 
-    pub fn set_dom_window(&mut self, new_node: Node<Window>) {
-        self.document.set_root(new_node);
+    pub fn metrics(&self) -> WindowMetrics {
+        WindowMetrics {
+            scale_factor: self.dpi_scale,
+            size: self.document.content_size(),
+        }
     }
 
-    pub fn resize(&mut self, size: PhysicalSize<u32>, dpi_scale: f32) {
-        self.client_size = size2(size.width as i32, size.height as i32);
-        self.dpi_scale = dpi_scale;
-        self.document.set_size(
-            self.client_size.to_f32() / Scale::<f32, LogicalPixel, DevicePixel>::new(dpi_scale),
-        );
+    pub fn render_path(&self) -> RenderPath {
+        self.backend.path()
     }
 
-    fn get_font(&mut self, font: &FontRef, txn: &mut Transaction) -> FontKey {
-        let full_name = font.font.full_name();
-        if let Some(&key) = self.fonts.get(&full_name) {
-            return key;
-        }
-        let key = self.api.generate_font_key();
-        let font_data = font.font.copy_font_data().unwrap().to_vec();
-        txn.add_raw_font(key, font_data, 0);
-        self.fonts.insert(full_name, key);
-
-        key
+    /// Whether the first frame is done, see `Runtime::on_first_paint()`.
+    pub fn painted(&self) -> bool {
+        self.painted
     }
 
-    fn get_font_instance(
-        &mut self,
-        key: FontKey,
-        size: usize,
-        txn: &mut Transaction,
-    ) -> FontInstanceKey {
-        if let Some(&instance) = self.font_instances.get(&(key, size)) {
-            return instance;
-        }
-        let instance = self.api.generate_font_instance_key();
-        txn.add_font_instance(instance, key, size as f32, None, None, vec![]);
-        self.font_instances.insert((key, size), instance);
-
-        instance
+    /// Notes that the next frame is needed from now, for measuring
+    /// latency.
+    pub fn request_frame(&mut self) {
+        self.requested.get_or_insert_with(Instant::now);
     }
 
-    fn render_child(
-        &mut self,
-        pipeline_id: PipelineId,
-        builder: &mut DisplayListBuilder,
-        transaction: &mut Transaction,
-        position: Point2D<f32, LogicalPixel>,
-        layout: &EqualRc<LayoutTreeNode>,
-    ) {
-        let rect = Rect::new(position, layout.size) * Scale::new(1.0);
-
-        let space_and_clip = SpaceAndClipInfo::root_scroll(pipeline_id);
-
-        match layout.render {
-            RenderData::Node(ref node) => {
-                let values = self.document.computed_values(node.id());
+    /// Returns the stats of the last frame, if one has been rendered
+    /// since the last call.
+    pub fn take_frame_stats(&mut self) -> Option<FrameStats> {
+        self.last_frame_stats.take()
+    }
 
-                let corner_radius = BorderRadius {
-                    top_left: size2(
-                        values.corner_radius.top_left.get(),
-                        values.corner_radius.top_left.get(),
-                    ),
-                    top_right: size2(
-                        values.corner_radius.top_right.get(),
-                        values.corner_radius.top_right.get(),
-                    ),
-                    bottom_left: size2(
-                        values.corner_radius.bottom_left.get(),
-                        values.corner_radius.bottom_left.get(),
-                    ),
-                    bottom_right: size2(
-                        values.corner_radius.bottom_right.get(),
-                        values.corner_radius.bottom_right.get(),
-                    ),
-                };
+    /// Sets how text is rasterized, see `TextRendering`. Glyphs don't
+    /// move when this changes, so the whole window is repainted.
+    pub fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        if text_rendering != self.document.text_rendering() {
+            self.document.set_text_rendering(text_rendering);
+            self.invalidate();
+        }
+    }
 
-                if values.background_color.alpha > 0 || node.interactive() {
-                    let clip_id = if !corner_radius.is_zero() {
-                        let region = ComplexClipRegion::new(rect, corner_radius, ClipMode::Clip);
-                        builder.define_clip(
-                            &SpaceAndClipInfo::root_scroll(pipeline_id),
-                            rect,
-                            vec![region],
-                        )
-                    } else {
-                        ClipId::root(pipeline_id)
-                    };
-                    let item_props = CommonItemProperties {
-                        clip_id,
-                        clip_rect: rect,
-                        spatial_id: SpatialId::root_scroll_node(pipeline_id),
-                        flags: PrimitiveFlags::empty(),
-                    };
-                    builder.push_rect(&item_props, rect, values.background_color.into());
-                    builder.push_hit_test(&item_props, (node.id(), 0));
-                }
+    /// Sets whether element boxes are snapped to the device pixel grid
+    /// when painting, see `renderer::paint()`.
+    pub fn set_pixel_snap(&mut self, pixel_snap: bool) {
+        if pixel_snap != self.pixel_snap {
+            self.pixel_snap = pixel_snap;
+            self.request_frame();
+        }
+    }
 
-                if values.border.visible() {
-                    let common = CommonItemProperties::new(rect, space_and_clip);
-                    let borders = values.border.map(|side| BorderSide {
-                        style: match side.style {
-                            DomBorderStyle::None => BorderStyle::None,
-                            DomBorderStyle::Solid => BorderStyle::Solid,
-                            DomBorderStyle::Double => BorderStyle::Double,
-                            DomBorderStyle::Dotted => BorderStyle::Dotted,
-                            DomBorderStyle::Dashed => BorderStyle::Dashed,
-                            DomBorderStyle::Hidden => BorderStyle::Hidden,
-                            DomBorderStyle::Groove => BorderStyle::Groove,
-                            DomBorderStyle::Ridge => BorderStyle::Ridge,
-                            DomBorderStyle::Inset => BorderStyle::Inset,
-                            DomBorderStyle::Outset => BorderStyle::Outset,
-                        },
-                        color: side.color.into(),
-                    });
-                    let widths = values.border.map(|side| side.width.get());
-                    builder.push_border(
-                        &common,
-                        rect,
-                        LayoutSideOffsets::new(
-                            widths.top,
-                            widths.right,
-                            widths.bottom,
-                            widths.left,
-                        ),
-                        BorderDetails::Normal(NormalBorder {
-                            left: borders.left,
-                            right: borders.right,
-                            top: borders.top,
-                            bottom: borders.bottom,
-                            radius: corner_radius,
-                            do_aa: true,
-                        }),
-                    )
-                }
+    pub fn set_dom_window(&mut self, new_node: Node<Window>) {
+        self.request_frame();
+        self.document.set_root(new_node);
+    }
 
-                for layout in &layout.children {
-                    self.render_child(
-                        pipeline_id,
-                        builder,
-                        transaction,
-                        position + layout.position.to_vector(),
-                        &layout.layout,
-                    );
-                }
-            }
-            RenderData::Text {
-                text:
-                    LayoutText {
-                        ref fragments,
-                        size,
-                    },
-                ref parent,
-            } => {
-                let values = self.document.computed_values(parent.id());
-                let color = values.text_color;
-                builder.push_simple_stacking_context(
-                    point2(0.0, 0.0),
-                    space_and_clip.spatial_id,
-                    PrimitiveFlags::IS_BACKFACE_VISIBLE,
-                );
-                for fragment in fragments {
-                    let glyphs = fragment
-                        .glyphs
-                        .iter()
-                        .map(|glyph| {
-                            let pos = position + glyph.offset.to_vector();
-                            GlyphInstance {
-                                index: glyph.index,
-                                point: pos * Scale::new(1.0),
-                            }
-                        })
-                        .collect::<Vec<_>>();
-                    let font_key = self.get_font(&fragment.font, transaction);
-                    let key = self.get_font_instance(font_key, size as usize, transaction);
-                    builder.push_text(
-                        &CommonItemProperties::new(rect, space_and_clip),
-                        rect,
-                        &glyphs[..],
-                        key,
-                        color.into(),
-                        None,
-                    );
-                }
-                builder.pop_stacking_context();
-            }
-        }
+    /// Makes the next frame repaint the whole window, for when the
+    /// contents of the window were lost, such as when the OS asks for
+    /// a redraw.
+    pub fn invalidate(&mut self) {
+        self.request_frame();
+        self.damage.invalidate();
     }
 
-    pub fn render(&mut self) {
-        let client_size = self.client_size;
-        let dpi_scale = Scale::new(self.dpi_scale);
-        let content_size = client_size.to_f32() / dpi_scale;
+    pub fn resize(&mut self, size: PhysicalSize<u32>, dpi_scale: f32) {
+        self.invalidate();
+        self.client_size = size2(size.width as i32, size.height as i32);
+        self.dpi_scale = dpi_scale;
+        self.backend.resize(self.client_size, dpi_scale);
+        self.document.set_size(
+            self.client_size.to_f32() / Scale::<f32, LogicalPixel, DevicePixel>::new(dpi_scale),
+        );
+    }
 
+    /// Renders a frame, returning whether anything was presented. When
+    /// nothing visible changed since the last frame, the frame is
+    /// skipped and the window should be left as it is.
+    pub fn render(&mut self) -> bool {
         debug!("render()");
-        let pipeline_id = PipelineId(0, 0);
-        let mut builder = DisplayListBuilder::new(pipeline_id);
-        let mut transaction = Transaction::new();
-
+        trace_span!(span = "render");
+        let start = Instant::now();
         let root_layout = self.document.get_layout();
-
-        for layout in &root_layout.children {
-            self.render_child(
-                pipeline_id,
-                &mut builder,
-                &mut transaction,
-                layout.position,
-                &layout.layout,
-            );
+        let layout_end = Instant::now();
+        let bounds = LogicalRect::new(Point2D::zero(), self.document.content_size());
+        let snap = if self.pixel_snap {
+            Some(self.dpi_scale)
+        } else {
+            None
+        };
+        let (items, summaries) = self
+            .backend
+            .paint(&self.document, &root_layout, bounds, snap);
+        let dirty = self.damage.update(summaries, bounds);
+        let paint_end = Instant::now();
+        let presented = {
+            trace_span!(span = "present");
+            self.backend.present(&dirty)
+        };
+        let present_end = Instant::now();
+        if let Backend::Headless { .. } = self.backend {
+            self.painted = true;
         }
-
-        transaction.set_display_list(Epoch(0), None, content_size, builder.finalize(), true);
-        transaction.set_root_pipeline(pipeline_id);
-        transaction.generate_frame();
-        self.api.set_document_view(
-            self.document_id,
-            DeviceIntRect::new(Point2D::zero(), client_size.to_i32()),
-            dpi_scale.get(),
-        );
-        self.api.send_transaction(self.document_id, transaction);
-        self.rx.recv().unwrap();
-        if let Some(ref mut renderer) = &mut self.renderer {
-            renderer.update();
-            let _ = renderer.render(client_size.to_i32());
-            let _ = renderer.flush_pipeline_info();
+        if !presented {
+            return false;
         }
+        self.painted = true;
+
+        self.last_frame_stats = Some(FrameStats {
+            layout_time: layout_end - start,
+            paint_time: paint_end - layout_end,
+            present_time: present_end - paint_end,
+            items,
+            latency: present_end - self.requested.take().unwrap_or(start),
+        });
+        true
     }
 
     pub fn element_at(&mut self, position: LogicalPosition<f32>) -> Option<u64> {
-        self.api
-            .hit_test(
-                self.document_id,
-                None,
-                point2(position.x as f32, position.y as f32),
-                HitTestFlags::empty(),
-            )
-            .items
-            .first()
-            .map(|item| item.tag.0)
+        self.backend
+            .element_at(point2(position.x as f32, position.y as f32), &self.document)
+            .map(|id| self.document.event_target(id))
     }
 }
@@ -0,0 +1,462 @@
+//! Tracking of which parts of the window changed between frames, so
+//! that frames where nothing visible changed can be skipped.
+//!
+//! Each item drawn is summarized as its visible bounds and a hash of
+//! how it's drawn. Comparing those summaries with the previous frame
+//! gives the dirty rects: the old and new bounds of anything that was
+//! added, removed, or drawn differently. Scrolling and other movement
+//! shows up as the moved items changing bounds, which marks both where
+//! they were and where they are now. When comparing can't be trusted,
+//! such as for the first frame, after a resize, or when the OS asks for
+//! a redraw, the whole window is marked dirty instead.
+
+use super::renderer::{CachedPaint, Renderer};
+use super::TextRendering;
+use crate::layout::{LayoutText, LogicalLength, LogicalPoint, LogicalRect};
+use crate::runtime::DecodedImage;
+use crate::style::{Border, Corners, Edges, TextShadow};
+use crate::Color;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// With more dirty rects than this, the whole window is repainted
+/// instead.
+const MAX_DIRTY_RECTS: usize = 8;
+
+/// The part of the window that changed since the last frame.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DirtyRegion {
+    /// Nothing visible changed, so the last frame can be kept.
+    Clean,
+    /// Only these rects changed.
+    Rects(Vec<LogicalRect>),
+    /// The whole window has to be repainted.
+    Full,
+}
+
+/// The visible bounds of a drawn item, and a hash of everything that
+/// affects how it's drawn.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ItemSummary {
+    pub bounds: LogicalRect,
+    pub hash: u64,
+}
+
+fn hash_rect(hasher: &mut impl Hasher, rect: &LogicalRect) {
+    for value in &[
+        rect.origin.x,
+        rect.origin.y,
+        rect.size.width,
+        rect.size.height,
+    ] {
+        value.to_bits().hash(hasher);
+    }
+}
+
+fn hash_color(hasher: &mut impl Hasher, color: Color) {
+    (color.red, color.green, color.blue, color.alpha).hash(hasher);
+}
+
+fn hash_text(hasher: &mut impl Hasher, text: &LayoutText) {
+    text.size.to_bits().hash(hasher);
+    for fragment in &text.fragments {
+        // Glyph indices are only meaningful within their font, so the
+        // same indices in a fallback font draw different glyphs.
+        fragment.font.font.full_name().hash(hasher);
+        for glyph in &fragment.glyphs {
+            glyph.index.hash(hasher);
+            glyph.offset.x.to_bits().hash(hasher);
+            glyph.offset.y.to_bits().hash(hasher);
+        }
+    }
+}
+
+fn hash_corners(hasher: &mut impl Hasher, radius: &Corners<LogicalLength>) {
+    for value in &[
+        radius.top_left,
+        radius.top_right,
+        radius.bottom_left,
+        radius.bottom_right,
+    ] {
+        value.get().to_bits().hash(hasher);
+    }
+}
+
+/// Wraps a renderer, forwarding everything to it while summarizing
+/// each item drawn. A subtree painted into the backend's cache is
+/// summarized as a single item, since that's what's composited.
+pub struct DirtyTracking<'a, R: Renderer + ?Sized> {
+    inner: &'a mut R,
+    bounds: LogicalRect,
+    items: Vec<ItemSummary>,
+    /// How many cached subtrees are being painted into, whose items
+    /// aren't summarized.
+    cached: usize,
+}
+
+impl<'a, R: Renderer + ?Sized> DirtyTracking<'a, R> {
+    /// `bounds` is the area of the window, which items are clipped to.
+    pub fn new(inner: &'a mut R, bounds: LogicalRect) -> Self {
+        DirtyTracking {
+            inner,
+            bounds,
+            items: vec![],
+            cached: 0,
+        }
+    }
+
+    /// Returns the summaries of the items drawn, in painting order.
+    pub fn finish(self) -> Vec<ItemSummary> {
+        self.items
+    }
+
+    /// Summarizes an item drawn in `clip`. The clip chain's hash is
+    /// mixed in, since rounded corners or a clip path changing changes
+    /// what's shown of the item, even where its bounds stay the same.
+    fn record(
+        &mut self,
+        clip: (R::Clip, LogicalRect, u64),
+        rect: LogicalRect,
+        mut hasher: DefaultHasher,
+    ) {
+        if self.cached > 0 {
+            return;
+        }
+        clip.2.hash(&mut hasher);
+        if let Some(bounds) = rect.intersection(&clip.1) {
+            self.items.push(ItemSummary {
+                bounds,
+                hash: hasher.finish(),
+            });
+        }
+    }
+}
+
+impl<'a, R: Renderer + ?Sized> Renderer for DirtyTracking<'a, R> {
+    /// The inner renderer's clip, the bounds it leaves visible, and a
+    /// hash of the shapes in the chain of clips.
+    type Clip = (R::Clip, LogicalRect, u64);
+
+    fn root_clip(&self) -> Self::Clip {
+        (self.inner.root_clip(), self.bounds, 0)
+    }
+
+    fn push_clip(
+        &mut self,
+        parent: Self::Clip,
+        rect: LogicalRect,
+        radius: Corners<LogicalLength>,
+    ) -> Self::Clip {
+        let clip = self.inner.push_clip(parent.0, rect, radius);
+        let bounds = parent
+            .1
+            .intersection(&rect)
+            .unwrap_or_else(LogicalRect::zero);
+        let mut hasher = DefaultHasher::new();
+        parent.2.hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash_corners(&mut hasher, &radius);
+        (clip, bounds, hasher.finish())
+    }
+
+    fn push_rect(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        color: Color,
+        hit_test: Option<u64>,
+    ) {
+        self.inner.push_rect(clip.0, rect, color, hit_test);
+        let mut hasher = DefaultHasher::new();
+        "rect".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash_color(&mut hasher, color);
+        // Hit testing uses the presented display list, so it's affected
+        // even though it isn't visible.
+        hit_test.hash(&mut hasher);
+        self.record(clip, rect, hasher);
+    }
+
+    fn push_backdrop_blur(&mut self, clip: Self::Clip, rect: LogicalRect, radius: LogicalLength) {
+        self.inner.push_backdrop_blur(clip.0, rect, radius);
+        let mut hasher = DefaultHasher::new();
+        "backdrop".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        radius.get().to_bits().hash(&mut hasher);
+        self.record(clip, rect, hasher);
+    }
+
+    fn push_border(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        border: &Edges<Border>,
+        radius: Corners<LogicalLength>,
+    ) {
+        self.inner.push_border(clip.0, rect, border, radius);
+        let mut hasher = DefaultHasher::new();
+        "border".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash_corners(&mut hasher, &radius);
+        for side in &[border.left, border.right, border.top, border.bottom] {
+            side.width.get().to_bits().hash(&mut hasher);
+            (side.style as u8).hash(&mut hasher);
+            hash_color(&mut hasher, side.color);
+        }
+        self.record(clip, rect, hasher);
+    }
+
+    fn push_image(&mut self, clip: Self::Clip, rect: LogicalRect, image: &Arc<DecodedImage>) {
+        self.inner.push_image(clip.0, rect, image);
+        let mut hasher = DefaultHasher::new();
+        "image".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        // Decoded images are shared, and never change once decoded.
+        (Arc::as_ptr(image) as usize).hash(&mut hasher);
+        self.record(clip, rect, hasher);
+    }
+
+    fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        self.inner.set_text_rendering(text_rendering);
+    }
+
+    fn push_text(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        color: Color,
+    ) {
+        self.inner.push_text(clip.0, rect, position, text, color);
+        let mut hasher = DefaultHasher::new();
+        "text".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash_color(&mut hasher, color);
+        hash_text(&mut hasher, text);
+        self.record(clip, rect, hasher);
+    }
+
+    fn push_text_shadow(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        shadow: &TextShadow,
+    ) {
+        self.inner
+            .push_text_shadow(clip.0, rect, position, text, shadow);
+        let mut hasher = DefaultHasher::new();
+        "text shadow".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash_color(&mut hasher, shadow.color);
+        for length in &[shadow.offset_x, shadow.offset_y, shadow.blur] {
+            length.get().to_bits().hash(&mut hasher);
+        }
+        hash_text(&mut hasher, text);
+        // The shadow spills out of the text's box by its offset and blur.
+        self.record(clip, shadow.bounds(rect), hasher);
+    }
+
+    fn begin_cached(
+        &mut self,
+        clip: Self::Clip,
+        id: u64,
+        rect: LogicalRect,
+        hash: u64,
+    ) -> CachedPaint<Self::Clip> {
+        let cached = match self.inner.begin_cached(clip.0, id, rect, hash) {
+            CachedPaint::Inline => return CachedPaint::Inline,
+            CachedPaint::Reused => CachedPaint::Reused,
+            CachedPaint::Repaint(inner) => {
+                CachedPaint::Repaint((inner, LogicalRect::new(LogicalPoint::zero(), rect.size), 0))
+            }
+        };
+        let mut hasher = DefaultHasher::new();
+        "cached".hash(&mut hasher);
+        hash_rect(&mut hasher, &rect);
+        hash.hash(&mut hasher);
+        self.record(clip, rect, hasher);
+        if let CachedPaint::Repaint(_) = cached {
+            self.cached += 1;
+        }
+        cached
+    }
+
+    fn end_cached(&mut self) {
+        self.inner.end_cached();
+        self.cached -= 1;
+    }
+
+    fn present(&mut self, dirty: &DirtyRegion) -> bool {
+        self.inner.present(dirty)
+    }
+}
+
+/// Remembers the items of the last frame, to compare the next one
+/// against.
+#[derive(Default)]
+pub struct DamageTracker {
+    previous: Option<Vec<ItemSummary>>,
+}
+
+impl DamageTracker {
+    /// Forgets the last frame, so the next one is fully repainted.
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    /// Compares a new frame with the last one, and remembers it for
+    /// next time.
+    pub fn update(&mut self, items: Vec<ItemSummary>, bounds: LogicalRect) -> DirtyRegion {
+        let region = match self.previous {
+            Some(ref previous) => diff(previous, &items, bounds),
+            None => DirtyRegion::Full,
+        };
+        self.previous = Some(items);
+        region
+    }
+}
+
+/// Everything between the common start and end of the two frames is
+/// considered changed. This handles an item changing, appearing or
+/// disappearing precisely, and anything more complicated conservatively.
+fn diff(previous: &[ItemSummary], current: &[ItemSummary], bounds: LogicalRect) -> DirtyRegion {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let changed = previous[prefix..previous.len() - suffix]
+        .iter()
+        .chain(&current[prefix..current.len() - suffix]);
+    let mut rects: Vec<LogicalRect> = vec![];
+    for item in changed {
+        if !rects.contains(&item.bounds) {
+            rects.push(item.bounds);
+        }
+    }
+
+    let area: f32 = rects.iter().map(|rect| rect.area()).sum();
+    if rects.is_empty() {
+        DirtyRegion::Clean
+    } else if rects.len() > MAX_DIRTY_RECTS || area >= bounds.area() / 2.0 {
+        DirtyRegion::Full
+    } else {
+        DirtyRegion::Rects(rects)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DamageTracker, DirtyRegion, DirtyTracking, ItemSummary};
+    use crate::document::Document;
+    use crate::layout::{LogicalLength, LogicalRect};
+    use crate::mox_impl::{attr, elt};
+    use crate::render::display_list::DisplayListBackend;
+    use crate::render::renderer;
+    use crate::style::{test_style, Corners, DisplayType, Style};
+    use crate::Color;
+    use euclid::{rect, size2};
+    use moxie::runtime::Runtime;
+    use webrender::api::{IdNamespace, PipelineId};
+
+    fn item(x: f32, hash: u64) -> ItemSummary {
+        ItemSummary {
+            bounds: rect(x, 0.0, 10.0, 10.0),
+            hash,
+        }
+    }
+
+    #[test]
+    fn localized_change() {
+        let window: LogicalRect = rect(0.0, 0.0, 200.0, 200.0);
+        let mut tracker = DamageTracker::default();
+        let frame = vec![item(0.0, 1), item(20.0, 2), item(40.0, 3)];
+        assert_eq!(tracker.update(frame.clone(), window), DirtyRegion::Full);
+        assert_eq!(tracker.update(frame, window), DirtyRegion::Clean);
+
+        // The middle item is hovered, and changes color.
+        let hovered = vec![item(0.0, 1), item(20.0, 4), item(40.0, 3)];
+        assert_eq!(
+            tracker.update(hovered, window),
+            DirtyRegion::Rects(vec![rect(20.0, 0.0, 10.0, 10.0)])
+        );
+
+        // An item moves, which dirties where it was and where it is.
+        let moved = vec![item(0.0, 1), item(25.0, 4), item(40.0, 3)];
+        assert_eq!(
+            tracker.update(moved, window),
+            DirtyRegion::Rects(vec![
+                rect(20.0, 0.0, 10.0, 10.0),
+                rect(25.0, 0.0, 10.0, 10.0)
+            ])
+        );
+
+        tracker.invalidate();
+        assert_eq!(tracker.update(vec![], window), DirtyRegion::Full);
+    }
+
+    #[test]
+    fn large_change_is_full() {
+        let window: LogicalRect = rect(0.0, 0.0, 15.0, 10.0);
+        let mut tracker = DamageTracker::default();
+        tracker.update(vec![item(0.0, 1)], window);
+        assert_eq!(
+            tracker.update(vec![item(0.0, 2)], window),
+            DirtyRegion::Full
+        );
+    }
+
+    #[test]
+    fn clip_shape_change_is_dirty() {
+        fn card(values: &mut crate::style::ComputedValues, radius: f32) {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(radius));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        }
+        let square = test_style("square", |values| card(values, 0.0));
+        let rounded = test_style("rounded", |values| card(values, 8.0));
+        let window = |style: Style| {
+            Runtime::new().run_once(|| {
+                elt::window()
+                    .add_child(elt::view().set_attr(attr::style(), style).build())
+                    .build()
+            })
+        };
+
+        let bounds: LogicalRect = rect(0.0, 0.0, 100.0, 100.0);
+        let mut document = Document::new(window(square), size2(100.0, 100.0));
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+        let mut damage = DamageTracker::default();
+        let mut frame = |document: &mut Document| {
+            let layout = document.get_layout();
+            let mut tracking = DirtyTracking::new(&mut backend, bounds);
+            renderer::paint(&mut tracking, document, &layout, None);
+            let dirty = damage.update(tracking.finish(), bounds);
+            backend.finish();
+            dirty
+        };
+        assert_eq!(frame(&mut document), DirtyRegion::Full);
+        assert_eq!(frame(&mut document), DirtyRegion::Clean);
+
+        // Only the clip the background is drawn in changes shape.
+        document.set_root(window(rounded));
+        assert_eq!(
+            frame(&mut document),
+            DirtyRegion::Rects(vec![rect(0.0, 0.0, 20.0, 20.0)])
+        );
+    }
+}
@@ -0,0 +1,850 @@
+use super::dirty::DirtyRegion;
+use super::renderer::{CachedPaint, Renderer};
+use super::{TextAntialiasing, TextRendering};
+use crate::layout::{LayoutText, LogicalLength, LogicalPoint, LogicalRect, LogicalSize};
+use crate::runtime::DecodedImage;
+use crate::style::{Border, BorderStyle as DomBorderStyle, Corners, Edges, TextShadow};
+use crate::Color;
+use skribo::FontRef;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use webrender::{
+    api::{
+        units::LayoutPoint, units::LayoutSideOffsets, units::LayoutVector2D, AlphaType,
+        BorderDetails, BorderRadius, BorderSide, BorderStyle, BuiltDisplayList, ClipMode, ColorF,
+        CommonItemProperties, ComplexClipRegion, DisplayListBuilder, Epoch, FilterOp,
+        FontInstanceKey, FontInstanceOptions, FontInstancePlatformOptions, FontKey, FontRenderMode,
+        GlyphInstance, IdNamespace, ImageData, ImageDescriptor, ImageDescriptorFlags, ImageFormat,
+        ImageKey, ImageRendering, NormalBorder, PipelineId, PrimitiveFlags, Shadow,
+        SpaceAndClipInfo, Transaction,
+    },
+    euclid::{size2, Scale},
+};
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use {super::TextHinting, webrender::api::FontHinting};
+
+fn border_radius(radius: Corners<LogicalLength>) -> BorderRadius {
+    BorderRadius {
+        top_left: size2(radius.top_left.get(), radius.top_left.get()),
+        top_right: size2(radius.top_right.get(), radius.top_right.get()),
+        bottom_left: size2(radius.bottom_left.get(), radius.bottom_left.get()),
+        bottom_right: size2(radius.bottom_right.get(), radius.bottom_right.get()),
+    }
+}
+
+/// The options Webrender rasterizes glyphs with for a mode. Hinting is
+/// only set through FreeType's options, as the other platforms' font
+/// backends hint on their own.
+fn font_instance_options(
+    rendering: TextRendering,
+) -> (FontInstanceOptions, Option<FontInstancePlatformOptions>) {
+    let options = FontInstanceOptions {
+        render_mode: match rendering.antialiasing {
+            TextAntialiasing::Subpixel => FontRenderMode::Subpixel,
+            TextAntialiasing::Grayscale => FontRenderMode::Alpha,
+            TextAntialiasing::None => FontRenderMode::Mono,
+        },
+        ..Default::default()
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let platform_options = Some(FontInstancePlatformOptions {
+        hinting: match rendering.hinting {
+            TextHinting::None => FontHinting::None,
+            TextHinting::Light => FontHinting::Light,
+            TextHinting::Full if rendering.antialiasing == TextAntialiasing::Subpixel => {
+                FontHinting::LCD
+            }
+            TextHinting::Full => FontHinting::Normal,
+        },
+        ..Default::default()
+    });
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    let platform_options = None;
+    (options, platform_options)
+}
+
+/// How many font instances are kept, beyond those used by the frame
+/// being built. Webrender rasterizes the glyphs of each instance, a font
+/// at a size, into its texture cache the first time they're drawn, and
+/// keeps them there across frames, so text which stays on screen is
+/// only rasterized once. Deleting an instance lets Webrender drop its
+/// glyphs, so text which animates its size doesn't keep a set of glyphs
+/// for every size it passed through.
+const MAX_FONT_INSTANCES: usize = 64;
+
+/// A font instance, and the last frame it was used in.
+struct FontInstance {
+    key: FontInstanceKey,
+    last_used: u64,
+}
+
+/// An uploaded image, and the last frame it was painted in.
+struct UploadedImage {
+    /// Kept alive so that the address it's found by isn't reused while
+    /// it's uploaded.
+    _image: Arc<DecodedImage>,
+    key: ImageKey,
+    last_used: u64,
+}
+
+/// How many subtrees are cached at once, see `PaintDetails::cache`.
+/// Elements which ask for it past this are painted in place.
+const MAX_CACHED_SUBTREES: usize = 32;
+
+/// The largest area, in logical pixels, of a cached subtree. Larger
+/// elements are painted in place, rather than taking up a texture the
+/// size of several windows.
+const MAX_CACHED_AREA: f32 = 2048.0 * 2048.0;
+
+/// A subtree painted into a pipeline of its own, which the display list
+/// embeds as an iframe. Webrender keeps what it rasterized for the
+/// pipeline until its display list is replaced, so it's only replaced
+/// when the subtree changes or is resized. It's removed after a frame
+/// which doesn't use it.
+struct CachedSubtree {
+    pipeline_id: PipelineId,
+    epoch: Epoch,
+    size: LogicalSize,
+    hash: u64,
+    last_used: u64,
+    /// The images its display list shows, by address, which are kept
+    /// while it's reused.
+    images: HashSet<usize>,
+}
+
+/// Collects paint commands into a Webrender display list, along with
+/// the font and image resources it uses, without presenting anything.
+/// Resources are only added to the transaction the first time they're
+/// used, and their keys are made from the given namespace, so the
+/// transactions have to be sent to a `RenderApi` in that namespace, in
+/// order. Font instances which haven't been used for a while are
+/// deleted once there are more than `MAX_FONT_INSTANCES`, the least
+/// recently used first. The display lists of cached subtrees are set in
+/// the transaction too, for pipelines with the same namespace as
+/// `pipeline_id`, numbered after it.
+pub(crate) struct DisplayListBackend {
+    pipeline_id: PipelineId,
+    namespace: IdNamespace,
+    last_key: u32,
+    builder: DisplayListBuilder,
+    transaction: Transaction,
+    fonts: HashMap<String, FontKey>,
+    font_instances: HashMap<(FontKey, usize, TextRendering), FontInstance>,
+    /// Counts the frames finished or discarded, for `FontInstance`.
+    frame: u64,
+    text_rendering: TextRendering,
+    /// Uploaded images by the address of their decoded pixels. Images
+    /// which a frame doesn't show, directly or in a cached subtree, are
+    /// deleted at the end of it.
+    images: HashMap<usize, UploadedImage>,
+    /// Cached subtrees by the id of their element.
+    cached: HashMap<u64, CachedSubtree>,
+    last_pipeline: u32,
+    /// The builders which cached subtrees being painted interrupted,
+    /// innermost last, with the elements of the subtrees.
+    outer_builders: Vec<(u64, DisplayListBuilder)>,
+}
+
+impl DisplayListBackend {
+    pub fn new(pipeline_id: PipelineId, namespace: IdNamespace) -> DisplayListBackend {
+        DisplayListBackend {
+            pipeline_id,
+            namespace,
+            last_key: 0,
+            builder: DisplayListBuilder::new(pipeline_id),
+            transaction: Transaction::new(),
+            fonts: HashMap::new(),
+            font_instances: HashMap::new(),
+            frame: 0,
+            text_rendering: TextRendering::deterministic(),
+            images: HashMap::new(),
+            cached: HashMap::new(),
+            last_pipeline: pipeline_id.1,
+            outer_builders: vec![],
+        }
+    }
+
+    pub fn pipeline_id(&self) -> PipelineId {
+        self.pipeline_id
+    }
+
+    /// Takes the display list painted since the last call, along with
+    /// the transaction adding the resources it uses for the first time.
+    pub fn finish(&mut self) -> (BuiltDisplayList, Transaction) {
+        self.end_frame();
+        let builder =
+            std::mem::replace(&mut self.builder, DisplayListBuilder::new(self.pipeline_id));
+        let (_, display_list) = builder.finalize();
+        let transaction = std::mem::replace(&mut self.transaction, Transaction::new());
+        (display_list, transaction)
+    }
+
+    /// Drops the display list painted since the last call, keeping the
+    /// resource updates for the next one.
+    pub fn discard(&mut self) {
+        self.end_frame();
+        self.builder = DisplayListBuilder::new(self.pipeline_id);
+    }
+
+    /// Deletes the least recently used font instances past the limit,
+    /// leaving those the frame uses, removes the cached subtrees the
+    /// frame didn't use, and deletes the images neither shows.
+    fn end_frame(&mut self) {
+        let frame = self.frame;
+        let transaction = &mut self.transaction;
+        self.cached.retain(|_, subtree| {
+            if subtree.last_used < frame {
+                transaction.remove_pipeline(subtree.pipeline_id);
+            }
+            subtree.last_used == frame
+        });
+        let cached = &self.cached;
+        self.images.retain(|address, uploaded| {
+            let shown = uploaded.last_used == frame
+                || cached
+                    .values()
+                    .any(|subtree| subtree.images.contains(address));
+            if !shown {
+                transaction.delete_image(uploaded.key);
+            }
+            shown
+        });
+
+        let excess = self.font_instances.len().saturating_sub(MAX_FONT_INSTANCES);
+        if excess > 0 {
+            let frame = self.frame;
+            let mut unused = self
+                .font_instances
+                .iter()
+                .filter(|(_, instance)| instance.last_used < frame)
+                .map(|(id, instance)| (instance.last_used, *id))
+                .collect::<Vec<_>>();
+            unused.sort_by_key(|(last_used, _)| *last_used);
+            for (_, id) in unused.into_iter().take(excess) {
+                if let Some(instance) = self.font_instances.remove(&id) {
+                    self.transaction.delete_font_instance(instance.key);
+                }
+            }
+        }
+        self.frame += 1;
+    }
+
+    fn next_pipeline(&mut self) -> PipelineId {
+        self.last_pipeline += 1;
+        PipelineId(self.pipeline_id.0, self.last_pipeline)
+    }
+
+    fn next_key(&mut self) -> u32 {
+        self.last_key += 1;
+        self.last_key
+    }
+
+    fn get_font(&mut self, font: &FontRef) -> FontKey {
+        let full_name = font.font.full_name();
+        if let Some(&key) = self.fonts.get(&full_name) {
+            return key;
+        }
+        let key = FontKey::new(self.namespace, self.next_key());
+        let font_data = font.font.copy_font_data().unwrap().to_vec();
+        self.transaction.add_raw_font(key, font_data, 0);
+        self.fonts.insert(full_name, key);
+
+        key
+    }
+
+    fn get_font_instance(&mut self, key: FontKey, size: usize) -> FontInstanceKey {
+        let rendering = self.text_rendering;
+        if let Some(instance) = self.font_instances.get_mut(&(key, size, rendering)) {
+            instance.last_used = self.frame;
+            return instance.key;
+        }
+        let instance = FontInstanceKey::new(self.namespace, self.next_key());
+        let (options, platform_options) = font_instance_options(rendering);
+        self.transaction.add_font_instance(
+            instance,
+            key,
+            size as f32,
+            Some(options),
+            platform_options,
+            vec![],
+        );
+        self.font_instances.insert(
+            (key, size, rendering),
+            FontInstance {
+                key: instance,
+                last_used: self.frame,
+            },
+        );
+
+        instance
+    }
+
+    fn get_image(&mut self, image: &Arc<DecodedImage>) -> ImageKey {
+        let address = Arc::as_ptr(image) as usize;
+        if let Some((id, _)) = self.outer_builders.last() {
+            if let Some(subtree) = self.cached.get_mut(id) {
+                subtree.images.insert(address);
+            }
+        }
+        if let Some(uploaded) = self.images.get_mut(&address) {
+            uploaded.last_used = self.frame;
+            return uploaded.key;
+        }
+        let key = ImageKey::new(self.namespace, self.next_key());
+        let descriptor = ImageDescriptor::new(
+            image.width as i32,
+            image.height as i32,
+            ImageFormat::RGBA8,
+            ImageDescriptorFlags::empty(),
+        );
+        self.transaction
+            .add_image(key, descriptor, ImageData::new(image.rgba.clone()), None);
+        self.images.insert(
+            address,
+            UploadedImage {
+                _image: image.clone(),
+                key,
+                last_used: self.frame,
+            },
+        );
+
+        key
+    }
+
+    /// Pushes the glyphs of a line of text, within a stacking context
+    /// which the caller pushes.
+    fn push_glyphs(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        color: Color,
+    ) {
+        let rect = rect * Scale::new(1.0);
+        for fragment in &text.fragments {
+            let glyphs = fragment
+                .glyphs
+                .iter()
+                .map(|glyph| {
+                    let pos = position + glyph.offset.to_vector();
+                    GlyphInstance {
+                        index: glyph.index,
+                        point: pos * Scale::new(1.0),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let font_key = self.get_font(&fragment.font);
+            let key = self.get_font_instance(font_key, text.size as usize);
+            self.builder.push_text(
+                &CommonItemProperties::new(rect, clip),
+                rect,
+                &glyphs[..],
+                key,
+                color.into(),
+                None,
+            );
+        }
+    }
+}
+
+impl Renderer for DisplayListBackend {
+    type Clip = SpaceAndClipInfo;
+
+    fn root_clip(&self) -> SpaceAndClipInfo {
+        SpaceAndClipInfo::root_scroll(self.pipeline_id)
+    }
+
+    fn push_clip(
+        &mut self,
+        parent: SpaceAndClipInfo,
+        rect: LogicalRect,
+        radius: Corners<LogicalLength>,
+    ) -> SpaceAndClipInfo {
+        let rect = rect * Scale::new(1.0);
+        let region = ComplexClipRegion::new(rect, border_radius(radius), ClipMode::Clip);
+        SpaceAndClipInfo {
+            clip_id: self.builder.define_clip(&parent, rect, vec![region]),
+            ..parent
+        }
+    }
+
+    fn push_rect(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        color: Color,
+        hit_test: Option<u64>,
+    ) {
+        let rect = rect * Scale::new(1.0);
+        let item_props = CommonItemProperties {
+            clip_id: clip.clip_id,
+            clip_rect: rect,
+            spatial_id: clip.spatial_id,
+            flags: PrimitiveFlags::empty(),
+        };
+        self.builder.push_rect(&item_props, rect, color.into());
+        if let Some(id) = hit_test {
+            self.builder.push_hit_test(&item_props, (id, 0));
+        }
+    }
+
+    fn push_backdrop_blur(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        radius: LogicalLength,
+    ) {
+        let rect = rect * Scale::new(1.0);
+        let common = CommonItemProperties::new(rect, clip);
+        self.builder.push_backdrop_filter(
+            &common,
+            &[FilterOp::Blur(radius.get(), radius.get())],
+            &[],
+            &[],
+        );
+    }
+
+    fn push_border(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        border: &Edges<Border>,
+        radius: Corners<LogicalLength>,
+    ) {
+        let rect = rect * Scale::new(1.0);
+        let common = CommonItemProperties::new(rect, clip);
+        let sides = border.map(|side| BorderSide {
+            style: match side.style {
+                DomBorderStyle::None => BorderStyle::None,
+                DomBorderStyle::Solid => BorderStyle::Solid,
+                DomBorderStyle::Double => BorderStyle::Double,
+                DomBorderStyle::Dotted => BorderStyle::Dotted,
+                DomBorderStyle::Dashed => BorderStyle::Dashed,
+                DomBorderStyle::Hidden => BorderStyle::Hidden,
+                DomBorderStyle::Groove => BorderStyle::Groove,
+                DomBorderStyle::Ridge => BorderStyle::Ridge,
+                DomBorderStyle::Inset => BorderStyle::Inset,
+                DomBorderStyle::Outset => BorderStyle::Outset,
+            },
+            color: side.color.into(),
+        });
+        let widths = border.map(|side| side.width.get());
+        self.builder.push_border(
+            &common,
+            rect,
+            LayoutSideOffsets::new(widths.top, widths.right, widths.bottom, widths.left),
+            BorderDetails::Normal(NormalBorder {
+                left: sides.left,
+                right: sides.right,
+                top: sides.top,
+                bottom: sides.bottom,
+                radius: border_radius(radius),
+                do_aa: true,
+            }),
+        )
+    }
+
+    fn push_image(&mut self, clip: SpaceAndClipInfo, rect: LogicalRect, image: &Arc<DecodedImage>) {
+        let key = self.get_image(image);
+        let rect = rect * Scale::new(1.0);
+        self.builder.push_image(
+            &CommonItemProperties::new(rect, clip),
+            rect,
+            ImageRendering::Auto,
+            AlphaType::PremultipliedAlpha,
+            key,
+            ColorF::WHITE,
+        );
+    }
+
+    fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+        self.text_rendering = text_rendering;
+    }
+
+    fn push_text(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        color: Color,
+    ) {
+        self.builder.push_simple_stacking_context(
+            LayoutPoint::zero(),
+            clip.spatial_id,
+            PrimitiveFlags::IS_BACKFACE_VISIBLE,
+        );
+        self.push_glyphs(clip, rect, position, text, color);
+        self.builder.pop_stacking_context();
+    }
+
+    fn push_text_shadow(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        shadow: &TextShadow,
+    ) {
+        self.builder.push_simple_stacking_context(
+            LayoutPoint::zero(),
+            clip.spatial_id,
+            PrimitiveFlags::IS_BACKFACE_VISIBLE,
+        );
+        // Webrender draws items pushed while a shadow is active as well as
+        // their shadows, so the glyphs themselves are clear here, and
+        // drawn by the `push_text()` which follows.
+        self.builder.push_shadow(
+            &clip,
+            Shadow {
+                offset: LayoutVector2D::new(shadow.offset_x.get(), shadow.offset_y.get()),
+                color: shadow.color.into(),
+                blur_radius: shadow.blur.get(),
+            },
+            true,
+        );
+        self.push_glyphs(clip, rect, position, text, Color::clear());
+        self.builder.pop_all_shadows();
+        self.builder.pop_stacking_context();
+    }
+
+    fn begin_cached(
+        &mut self,
+        clip: SpaceAndClipInfo,
+        id: u64,
+        rect: LogicalRect,
+        hash: u64,
+    ) -> CachedPaint<SpaceAndClipInfo> {
+        // A cached subtree inside of another is part of the outer one's
+        // display list, which is reused without painting it.
+        if rect.is_empty() || rect.area() > MAX_CACHED_AREA || !self.outer_builders.is_empty() {
+            return CachedPaint::Inline;
+        }
+        if !self.cached.contains_key(&id) {
+            if self.cached.len() >= MAX_CACHED_SUBTREES {
+                return CachedPaint::Inline;
+            }
+            let pipeline_id = self.next_pipeline();
+            self.cached.insert(
+                id,
+                CachedSubtree {
+                    pipeline_id,
+                    epoch: Epoch(0),
+                    size: LogicalSize::zero(),
+                    hash: 0,
+                    last_used: 0,
+                    images: HashSet::new(),
+                },
+            );
+        }
+        let subtree = self.cached.get_mut(&id).unwrap();
+        subtree.last_used = self.frame;
+        let pipeline_id = subtree.pipeline_id;
+        let bounds = rect * Scale::new(1.0);
+        self.builder
+            .push_iframe(bounds, bounds, &clip, pipeline_id, true);
+        if subtree.size == rect.size && subtree.hash == hash {
+            return CachedPaint::Reused;
+        }
+        subtree.size = rect.size;
+        subtree.hash = hash;
+        subtree.epoch.0 += 1;
+        subtree.images.clear();
+        let outer = std::mem::replace(&mut self.builder, DisplayListBuilder::new(pipeline_id));
+        self.outer_builders.push((id, outer));
+        CachedPaint::Repaint(SpaceAndClipInfo::root_scroll(pipeline_id))
+    }
+
+    fn end_cached(&mut self) {
+        let (id, outer) = self
+            .outer_builders
+            .pop()
+            .expect("end_cached() without begin_cached()");
+        let builder = std::mem::replace(&mut self.builder, outer);
+        let (_, display_list) = builder.finalize();
+        let subtree = &self.cached[&id];
+        self.transaction.set_display_list(
+            subtree.epoch,
+            None,
+            subtree.size * Scale::new(1.0),
+            (subtree.pipeline_id, display_list),
+            true,
+        );
+    }
+
+    /// Presenting is up to whatever owns the backend, which takes the
+    /// display list with `finish()`.
+    fn present(&mut self, dirty: &DirtyRegion) -> bool {
+        *dirty != DirtyRegion::Clean
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{font_instance_options, DisplayListBackend, MAX_FONT_INSTANCES};
+    use crate::document::Document;
+    use crate::layout::{LogicalLength, LogicalRect};
+    use crate::mox_impl::{attr, elt};
+    use crate::render::dirty::{DamageTracker, DirtyRegion, DirtyTracking};
+    use crate::render::renderer;
+    use crate::render::renderer::{CachedPaint, Renderer};
+    use crate::render::{TextAntialiasing, TextHinting, TextRendering};
+    use crate::runtime::DecodedImage;
+    use crate::style::{test_style, BackdropFilter, ComputedValues, Corners, DisplayType, Style};
+    use crate::Color;
+    use euclid::{rect, size2};
+    use moxie::runtime::Runtime;
+    use std::sync::Arc;
+    use webrender::api::{
+        DisplayItem, Epoch, FontKey, FontRenderMode, IdNamespace, PipelineId, SpaceAndClipInfo,
+    };
+
+    #[test]
+    fn fonts_are_kept_across_frames() {
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(
+                    elt::view()
+                        .add_child(elt::span().add_child("Hello".to_owned()).build())
+                        .build(),
+                )
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+
+        // Only the first frame adds the font, so Webrender keeps the
+        // glyphs it rasterized for it.
+        renderer::paint(&mut backend, &document, &layout, None);
+        let (_, first) = backend.finish();
+        assert!(!first.is_empty());
+        for _ in 0..3 {
+            renderer::paint(&mut backend, &document, &layout, None);
+            let (_, repeated) = backend.finish();
+            assert!(repeated.is_empty());
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_fonts() {
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+        let font = FontKey::new(IdNamespace(1), 1000);
+        // Text growing a pixel every frame, while a label stays at 12px.
+        for size in 20..120 {
+            backend.get_font_instance(font, 12);
+            backend.get_font_instance(font, size);
+            backend.finish();
+        }
+        assert_eq!(backend.font_instances.len(), MAX_FONT_INSTANCES);
+        let has_size = |backend: &DisplayListBackend, size| {
+            backend
+                .font_instances
+                .keys()
+                .any(|&(_, instance_size, _)| instance_size == size)
+        };
+        assert!(has_size(&backend, 12));
+        assert!(has_size(&backend, 119));
+        assert!(!has_size(&backend, 20));
+
+        // Those in use are kept even past the limit.
+        for size in 200..300 {
+            backend.get_font_instance(font, size);
+        }
+        let (_, transaction) = backend.finish();
+        assert!(!transaction.is_empty());
+        assert_eq!(backend.font_instances.len(), 100);
+        assert!((200..300).all(|size| has_size(&backend, size)));
+    }
+
+    #[test]
+    fn deletes_images_no_longer_shown() {
+        let image = |width| {
+            Arc::new(DecodedImage {
+                rgba: vec![0; 4 * width as usize],
+                width,
+                height: 1,
+            })
+        };
+        let (kept, dropped, cached) = (image(1), image(2), image(3));
+        let root = SpaceAndClipInfo::root_scroll(PipelineId(0, 0));
+        let bounds = rect(0.0, 0.0, 10.0, 10.0);
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+
+        backend.push_image(root, bounds, &kept);
+        backend.push_image(root, bounds, &dropped);
+        if let CachedPaint::Repaint(inner) = backend.begin_cached(root, 1, bounds, 0) {
+            backend.push_image(inner, bounds, &cached);
+            backend.end_cached();
+        }
+        backend.finish();
+        assert_eq!(backend.images.len(), 3);
+
+        // The cached subtree is reused without painting its image, which
+        // it still shows.
+        backend.push_image(root, bounds, &kept);
+        assert!(matches!(
+            backend.begin_cached(root, 1, bounds, 0),
+            CachedPaint::Reused
+        ));
+        let (_, transaction) = backend.finish();
+        assert!(!transaction.is_empty());
+        let address = |image: &Arc<DecodedImage>| Arc::as_ptr(image) as usize;
+        assert!(backend.images.contains_key(&address(&kept)));
+        assert!(backend.images.contains_key(&address(&cached)));
+        assert!(!backend.images.contains_key(&address(&dropped)));
+
+        // Once the subtree is gone, so is its image.
+        backend.push_image(root, bounds, &kept);
+        backend.finish();
+        assert_eq!(backend.images.len(), 1);
+    }
+
+    #[test]
+    fn cached_subtree_is_painted_once() {
+        let chart = test_style("chart", |values| {
+            values.cache = true;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(40.0).into());
+                block.height = Some(LogicalLength::new(30.0).into());
+            }
+        });
+        fn paint_bar(values: &mut ComputedValues, radius: f32) {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(radius));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(10.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        }
+        let bar = test_style("bar", |values| paint_bar(values, 0.0));
+        let rounded_bar = test_style("rounded_bar", |values| paint_bar(values, 4.0));
+        let short = test_style("short", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.height = Some(LogicalLength::new(0.0).into());
+            }
+        });
+        let tall = test_style("tall", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+        });
+        let mut runtime = Runtime::new();
+        let mut build = |moved: bool, bars: usize, rounded: bool| {
+            runtime.run_once(|| {
+                let spacer = if moved { tall } else { short };
+                let bar = if rounded { rounded_bar } else { bar };
+                let mut view = elt::view().set_attr(attr::style(), chart);
+                for _ in 0..bars {
+                    view = view.add_child(elt::view().set_attr(attr::style(), bar).build());
+                }
+                elt::window()
+                    .add_child(elt::view().set_attr(attr::style(), spacer).build())
+                    .add_child(view.build())
+                    .build()
+            })
+        };
+        let mut document = Document::new(build(false, 2, false), size2(100.0, 100.0));
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+        let mut frame = |document: &mut Document, backend: &mut DisplayListBackend| {
+            let layout = document.get_layout();
+            renderer::paint(backend, document, &layout, None);
+            let (_, transaction) = backend.finish();
+            let epochs = backend
+                .cached
+                .values()
+                .map(|subtree| (subtree.pipeline_id, subtree.epoch))
+                .collect::<Vec<_>>();
+            (transaction.is_empty(), epochs)
+        };
+
+        let cached = (PipelineId(0, 1), Epoch(1));
+        assert_eq!(frame(&mut document, &mut backend), (false, vec![cached]));
+        // A static subtree isn't painted again, so Webrender keeps what
+        // it rasterized, even when the chart moves.
+        assert_eq!(frame(&mut document, &mut backend), (true, vec![cached]));
+        document.set_root(build(true, 2, false));
+        assert_eq!(frame(&mut document, &mut backend), (true, vec![cached]));
+
+        // Anything changing inside of it repaints it.
+        document.set_root(build(true, 3, false));
+        let repainted = (PipelineId(0, 1), Epoch(2));
+        assert_eq!(frame(&mut document, &mut backend), (false, vec![repainted]));
+        // Including only the shape its children are clipped to.
+        document.set_root(build(true, 3, true));
+        let rounded = (PipelineId(0, 1), Epoch(3));
+        assert_eq!(frame(&mut document, &mut backend), (false, vec![rounded]));
+
+        // Once it's gone, so is its pipeline.
+        document.set_root(Runtime::new().run_once(|| elt::window().build()));
+        assert_eq!(frame(&mut document, &mut backend), (false, vec![]));
+    }
+
+    #[test]
+    fn backdrop_blur_through_dirty_tracking() {
+        fn frosted(values: &mut ComputedValues, radius: f32) {
+            values.backdrop_filter = BackdropFilter::Blur(LogicalLength::new(radius));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        }
+        let light = test_style("light", |values| frosted(values, 2.0));
+        let heavy = test_style("heavy", |values| frosted(values, 8.0));
+        let window = |style: Style| {
+            Runtime::new().run_once(|| {
+                elt::window()
+                    .add_child(elt::view().set_attr(attr::style(), style).build())
+                    .build()
+            })
+        };
+        let bounds: LogicalRect = rect(0.0, 0.0, 100.0, 100.0);
+        let mut document = Document::new(window(light), size2(100.0, 100.0));
+        let mut backend = DisplayListBackend::new(PipelineId(0, 0), IdNamespace(1));
+        let mut damage = DamageTracker::default();
+        // Paints the way windows do, through dirty tracking.
+        let mut frame = |document: &mut Document| {
+            let layout = document.get_layout();
+            let mut tracking = DirtyTracking::new(&mut backend, bounds);
+            renderer::paint(&mut tracking, document, &layout, None);
+            let dirty = damage.update(tracking.finish(), bounds);
+            let (display_list, _) = backend.finish();
+            let mut items = display_list.iter();
+            let mut blurs = 0;
+            while let Some(item) = items.next() {
+                if let DisplayItem::BackdropFilter(_) = item.item() {
+                    blurs += 1;
+                }
+            }
+            (blurs, dirty)
+        };
+        assert_eq!(frame(&mut document), (1, DirtyRegion::Full));
+        assert_eq!(frame(&mut document), (1, DirtyRegion::Clean));
+        document.set_root(window(heavy));
+        assert_eq!(
+            frame(&mut document),
+            (1, DirtyRegion::Rects(vec![rect(0.0, 0.0, 20.0, 20.0)]))
+        );
+    }
+
+    #[test]
+    fn text_rendering_options() {
+        let render_mode = |antialiasing| {
+            let rendering = TextRendering {
+                antialiasing,
+                hinting: TextHinting::None,
+            };
+            font_instance_options(rendering).0.render_mode
+        };
+        assert_eq!(
+            render_mode(TextAntialiasing::Subpixel),
+            FontRenderMode::Subpixel
+        );
+        assert_eq!(
+            render_mode(TextAntialiasing::Grayscale),
+            FontRenderMode::Alpha
+        );
+        assert_eq!(render_mode(TextAntialiasing::None), FontRenderMode::Mono);
+    }
+}
@@ -0,0 +1,109 @@
+//! Building the display lists of windows for hosts which composite
+//! them with their own Webrender pipeline, such as a game engine which
+//! already renders with Webrender. Only built with the
+//! `webrender-interop` feature.
+//!
+//! This exposes Webrender's types directly, so it's tied to the
+//! revision of Webrender this crate depends on, and the host has to use
+//! the same one. It isn't covered by the crate's usual compatibility
+//! guarantees: the display lists may change in any release, as the
+//! way elements are painted changes.
+
+use super::display_list::DisplayListBackend;
+use super::renderer;
+use crate::document::Document;
+use webrender::api::{units::LayoutSize, BuiltDisplayList, IdNamespace, PipelineId, Transaction};
+use webrender::euclid::Scale;
+
+/// Where the display lists of a window are built, with
+/// `HostedRuntime::build_display_list()`. The target keeps track of the
+/// fonts and images it has already added, so each frame's `resources`
+/// only add the new ones. Keep using the same target for a window, and
+/// send every frame's resources in order.
+pub struct DisplayListTarget {
+    backend: DisplayListBackend,
+}
+
+/// A window's display list, which the host sends in its own
+/// transaction with `Transaction::set_display_list()`.
+pub struct DisplayListFrame {
+    pub pipeline_id: PipelineId,
+    /// The size of the window's content, in layout pixels.
+    pub content_size: LayoutSize,
+    pub display_list: BuiltDisplayList,
+    /// Adds the fonts and images the display list uses for the first
+    /// time. It has to be sent before the display list.
+    pub resources: Transaction,
+}
+
+impl DisplayListTarget {
+    /// Builds display lists for `pipeline_id`, with resource keys in
+    /// `namespace`, which should be the host's
+    /// `RenderApi::get_namespace_id()`. Keys are counted from 1, so
+    /// the host can't generate keys of its own in the same namespace
+    /// with the same `RenderApi`. Elements with cached paint, see
+    /// `PaintDetails::cache`, are painted into pipelines of their own,
+    /// numbered after `pipeline_id`, whose display lists are set in
+    /// `resources`.
+    pub fn new(pipeline_id: PipelineId, namespace: IdNamespace) -> DisplayListTarget {
+        DisplayListTarget {
+            backend: DisplayListBackend::new(pipeline_id, namespace),
+        }
+    }
+
+    /// Paints the whole document, without pixel snapping.
+    pub(crate) fn build(&mut self, document: &mut Document) -> DisplayListFrame {
+        let layout = document.get_layout();
+        renderer::paint(&mut self.backend, document, &layout, None);
+        let (display_list, resources) = self.backend.finish();
+        DisplayListFrame {
+            pipeline_id: self.backend.pipeline_id(),
+            content_size: document.content_size() * Scale::new(1.0),
+            display_list,
+            resources,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DisplayListTarget;
+    use crate::document::Document;
+    use crate::layout::LogicalLength;
+    use crate::mox_impl::{attr, elt};
+    use crate::style::{test_style, DisplayType};
+    use crate::Color;
+    use euclid::size2;
+    use moxie::runtime::Runtime;
+    use webrender::api::{IdNamespace, PipelineId};
+
+    #[test]
+    fn builds_display_list() {
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+        let mut runtime = Runtime::new();
+        let mut build = |with_square: bool| {
+            let window = runtime.run_once(|| {
+                let mut window = elt::window();
+                if with_square {
+                    window = window.add_child(elt::view().set_attr(attr::style(), square).build());
+                }
+                window.build()
+            });
+            let mut document = Document::new(window, size2(100.0, 100.0));
+            let mut target = DisplayListTarget::new(PipelineId(0, 0), IdNamespace(1));
+            target.build(&mut document)
+        };
+
+        let empty = build(false);
+        let frame = build(true);
+        assert_eq!(frame.pipeline_id, PipelineId(0, 0));
+        assert_eq!(frame.content_size, size2(100.0, 100.0));
+        assert!(frame.display_list.data().len() > empty.display_list.data().len());
+    }
+}
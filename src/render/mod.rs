@@ -1,6 +1,29 @@
 //! This module handles creating the paint tree, as well as rendering it
 //! and processing user input queries against it.
 
+mod backend;
 pub mod context;
+pub mod dirty;
+mod display_list;
+#[cfg(feature = "webrender-interop")]
+mod interop;
+pub mod painter;
+mod present_mode;
+pub mod renderer;
+mod stats;
+mod text_rendering;
+mod webrender_backend;
 
+pub use backend::RenderPath;
+pub(crate) use backend::{NumberedClips, RendererFactory};
 pub use context::Context;
+pub use dirty::DirtyRegion;
+#[cfg(feature = "webrender-interop")]
+pub use interop::{DisplayListFrame, DisplayListTarget};
+pub use present_mode::PresentMode;
+pub(crate) use present_mode::SwapConfig;
+#[cfg(test)]
+pub(crate) use renderer::FrameCounter;
+pub use renderer::{CachedPaint, Renderer};
+pub use stats::FrameStats;
+pub use text_rendering::{TextAntialiasing, TextHinting, TextRendering};
@@ -0,0 +1,184 @@
+//! Drawing done by elements themselves, for content which isn't just
+//! a box, and for decorations painted over an element, such as
+//! selection highlights and focus rings.
+
+use crate::dom::ImageSource;
+use crate::layout::{LogicalLength, LogicalRect, LogicalSize};
+use crate::runtime::{DecodedImage, Images};
+use crate::style::{Border, Corners, Edges, ThemeColors};
+use crate::Color;
+use euclid::vec2;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum PaintCommand {
+    Rect(LogicalRect, Color),
+    RoundedRect(LogicalRect, Color, Corners<LogicalLength>),
+    Border(LogicalRect, Edges<Border>, Corners<LogicalLength>),
+    Image(LogicalRect, Arc<DecodedImage>),
+}
+
+/// Collects the drawing done by `Element::draw()` or a `PaintOverlay`.
+/// Coordinates are relative to the top left of the element, and
+/// everything is clipped to the element's border box.
+pub struct Painter {
+    size: LogicalSize,
+    content: LogicalRect,
+    colors: ThemeColors,
+    images: Option<Images>,
+    on_screen: bool,
+    pub(crate) commands: Vec<PaintCommand>,
+}
+
+impl Painter {
+    pub(crate) fn new(size: LogicalSize, content: LogicalRect, colors: ThemeColors) -> Painter {
+        Painter {
+            size,
+            content,
+            colors,
+            images: None,
+            on_screen: true,
+            commands: vec![],
+        }
+    }
+
+    /// Lets `image()` draw the images decoded in `images`. Without
+    /// them, images are never ready.
+    pub(crate) fn with_images(mut self, images: &Images) -> Painter {
+        self.images = Some(images.clone());
+        self
+    }
+
+    /// Whether any of the element is inside the window. Animated images
+    /// drawn while it isn't don't move on to their next frames.
+    pub(crate) fn on_screen(mut self, on_screen: bool) -> Painter {
+        self.on_screen = on_screen;
+        self
+    }
+
+    /// The size of the element's border box.
+    pub fn size(&self) -> LogicalSize {
+        self.size
+    }
+
+    /// The element's content box, which is inside its border and
+    /// padding.
+    pub fn content_rect(&self) -> LogicalRect {
+        self.content
+    }
+
+    /// The theme's colors for the element, for drawing parts of the
+    /// interface like selection highlights to match it.
+    pub fn theme_colors(&self) -> &ThemeColors {
+        &self.colors
+    }
+
+    /// Highlights selected content in the theme's selection color.
+    pub fn fill_selection(&mut self, rect: LogicalRect) {
+        let color = self.colors.selection_background();
+        self.fill_rect(rect, color);
+    }
+
+    pub fn fill_rect(&mut self, rect: LogicalRect, color: Color) {
+        self.commands.push(PaintCommand::Rect(rect, color));
+    }
+
+    /// Fills `rect` with `radius` on each corner. A square with a
+    /// radius of half its width is a circle.
+    pub fn fill_rounded_rect(&mut self, rect: LogicalRect, color: Color, radius: f32) {
+        self.commands.push(PaintCommand::RoundedRect(
+            rect,
+            color,
+            Corners::new_all_same(LogicalLength::new(radius)),
+        ));
+    }
+
+    /// Draws the same border along each edge of `rect`, with `radius`
+    /// on each corner.
+    pub fn border(&mut self, rect: LogicalRect, border: Border, radius: f32) {
+        self.commands.push(PaintCommand::Border(
+            rect,
+            Edges::new_all_same(border),
+            Corners::new_all_same(LogicalLength::new(radius)),
+        ));
+    }
+
+    /// Draws the image scaled to fit `rect` while keeping its aspect
+    /// ratio, centered within it. Returns `false` without drawing
+    /// anything if the image isn't decoded yet, in which case decoding
+    /// starts, and the window is repainted once it's done. Animated
+    /// images draw their current frame, and the window is repainted
+    /// each time it changes.
+    pub fn image(&mut self, rect: LogicalRect, source: &ImageSource) -> bool {
+        let on_screen = self.on_screen;
+        let image = self.images.as_ref().and_then(|images| {
+            if on_screen {
+                images.get(source)
+            } else {
+                images.get_hidden(source)
+            }
+        });
+        let image = match image {
+            Some(image) => image,
+            None => return false,
+        };
+        if image.width == 0 || image.height == 0 {
+            return true;
+        }
+        let size = LogicalSize::new(image.width as f32, image.height as f32);
+        let scale = (rect.size.width / size.width).min(rect.size.height / size.height);
+        let scaled = size * scale;
+        let origin = rect.origin
+            + vec2(
+                (rect.size.width - scaled.width) / 2.0,
+                (rect.size.height - scaled.height) / 2.0,
+            );
+        self.commands
+            .push(PaintCommand::Image(LogicalRect::new(origin, scaled), image));
+        true
+    }
+
+    /// Draws an image which was rasterized by the element, stretched to
+    /// `rect`. The same `Arc` should be passed each frame while the
+    /// image doesn't change, so that it's only uploaded once.
+    pub(crate) fn bitmap(&mut self, rect: LogicalRect, image: Arc<DecodedImage>) {
+        self.commands.push(PaintCommand::Image(rect, image));
+    }
+}
+
+/// A callback which paints over an element, set with the
+/// `paint_overlay` attribute. It runs each frame after the element and
+/// all of its children are painted, so the overlay appears above them.
+///
+/// ```rs
+/// let ring = PaintOverlay::new(|painter| {
+///     let rect = LogicalRect::new(LogicalPoint::zero(), painter.size());
+///     painter.border(rect, Border { width: LogicalLength::new(2.0), style: BorderStyle::Solid, color: FOCUS }, 4.0);
+/// });
+/// mox! { <button paint_overlay={ring}>...</button> }
+/// ```
+#[derive(Clone)]
+pub struct PaintOverlay(Arc<dyn Fn(&mut Painter) + Send + Sync>);
+
+impl PaintOverlay {
+    pub fn new(func: impl Fn(&mut Painter) + Send + Sync + 'static) -> PaintOverlay {
+        PaintOverlay(Arc::new(func))
+    }
+
+    pub(crate) fn paint(&self, painter: &mut Painter) {
+        (self.0)(painter)
+    }
+}
+
+impl Debug for PaintOverlay {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "PaintOverlay {{ ... }}")
+    }
+}
+
+impl PartialEq for PaintOverlay {
+    fn eq(&self, other: &PaintOverlay) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,83 @@
+//! How finished frames are handed to the screen.
+
+use log::warn;
+
+/// How frames are presented, set with `Runtime::present_mode()`. This
+/// trades latency for power use and tearing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Waits for the screen's vertical blank before showing each frame,
+    /// so frames never tear and aren't drawn faster than the screen
+    /// refreshes. This is the default, and the kindest to batteries.
+    Fifo,
+    /// Shows each frame as soon as it's done, for the lowest latency, at
+    /// the cost of tearing and of drawing frames that are never seen.
+    Immediate,
+    /// Replaces the waiting frame with each newer one, and shows the
+    /// latest at the vertical blank, for low latency without tearing.
+    /// OpenGL can't ask for this, so it falls back to `Fifo`.
+    Mailbox,
+}
+
+impl Default for PresentMode {
+    fn default() -> Self {
+        PresentMode::Fifo
+    }
+}
+
+/// What the window's swap chain is created with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SwapConfig {
+    /// The mode which is actually used, after falling back from ones
+    /// which aren't supported.
+    pub mode: PresentMode,
+    pub vsync: bool,
+}
+
+impl SwapConfig {
+    /// The config for an OpenGL swap chain, which can only turn vsync on
+    /// or off. Modes it can't do are logged and fall back to `Fifo`.
+    pub fn gl(mode: PresentMode) -> SwapConfig {
+        let mode = match mode {
+            PresentMode::Mailbox => {
+                warn!("Mailbox presentation isn't supported with OpenGL, using Fifo");
+                PresentMode::Fifo
+            }
+            mode => mode,
+        };
+        SwapConfig {
+            mode,
+            vsync: mode == PresentMode::Fifo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PresentMode, SwapConfig};
+
+    #[test]
+    fn mode_reaches_swap_config() {
+        assert_eq!(
+            SwapConfig::gl(PresentMode::default()),
+            SwapConfig {
+                mode: PresentMode::Fifo,
+                vsync: true,
+            }
+        );
+        assert_eq!(
+            SwapConfig::gl(PresentMode::Immediate),
+            SwapConfig {
+                mode: PresentMode::Immediate,
+                vsync: false,
+            }
+        );
+        assert_eq!(
+            SwapConfig::gl(PresentMode::Mailbox),
+            SwapConfig {
+                mode: PresentMode::Fifo,
+                vsync: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,1653 @@
+use super::dirty::DirtyRegion;
+use super::painter::{PaintCommand, Painter};
+use super::TextRendering;
+use crate::document::Document;
+use crate::dom::WindowMetrics;
+use crate::layout::{
+    LayoutText, LayoutTreeNode, LogicalLength, LogicalPoint, LogicalRect, LogicalSideOffsets,
+    LogicalSize, RenderData,
+};
+use crate::runtime::DecodedImage;
+use crate::style::{
+    BackdropFilter, BackgroundClip, Border, ComputedValues, Corners, DisplayType, Edges, Overflow,
+    PaintDetails, TextShadow, Visibility,
+};
+use crate::Color;
+use euclid::{point2, size2, vec2};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// A backend that draws the paint commands produced from the layout
+/// tree. Commands are pushed in painting order, and nothing is shown
+/// until `present()` is called, which lets backends build their display
+/// list up front and submit it all at once.
+pub trait Renderer {
+    /// Identifies a clip region created by `push_clip()`.
+    type Clip: Copy;
+
+    /// The clip covering the whole window, which everything else is
+    /// nested within.
+    fn root_clip(&self) -> Self::Clip;
+
+    /// Creates a clip to `rect` with rounded corners, nested within
+    /// `parent`.
+    fn push_clip(
+        &mut self,
+        parent: Self::Clip,
+        rect: LogicalRect,
+        radius: Corners<LogicalLength>,
+    ) -> Self::Clip;
+
+    /// Fills `rect` with a solid color. If `hit_test` is set, the rect
+    /// also reports the node with that id when hit testing.
+    fn push_rect(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        color: Color,
+        hit_test: Option<u64>,
+    );
+
+    /// Blurs what was painted behind `rect` so far. Backends which can't
+    /// do this leave the backdrop as it is.
+    fn push_backdrop_blur(&mut self, clip: Self::Clip, rect: LogicalRect, radius: LogicalLength);
+
+    /// Draws the borders along the edges of `rect`.
+    fn push_border(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        border: &Edges<Border>,
+        radius: Corners<LogicalLength>,
+    );
+
+    /// Draws a decoded image stretched over `rect`.
+    fn push_image(&mut self, clip: Self::Clip, rect: LogicalRect, image: &Arc<DecodedImage>);
+
+    /// Sets how the glyphs of the text pushed after it are rasterized.
+    /// Backends which can't change this keep their own.
+    fn set_text_rendering(&mut self, _text_rendering: TextRendering) {}
+
+    /// Draws a line of text, with its glyphs offset from `position`.
+    fn push_text(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        color: Color,
+    );
+
+    /// Draws the shadow of a line of text, for the `push_text()` of the
+    /// same line which follows it. Backends which can't blur draw a sharp
+    /// copy of the glyphs, offset by the shadow's offset.
+    fn push_text_shadow(
+        &mut self,
+        clip: Self::Clip,
+        rect: LogicalRect,
+        position: LogicalPoint,
+        text: &LayoutText,
+        shadow: &TextShadow,
+    ) {
+        let offset = vec2(shadow.offset_x.get(), shadow.offset_y.get());
+        self.push_text(
+            clip,
+            rect.translate(offset),
+            position + offset,
+            text,
+            shadow.color,
+        );
+    }
+
+    /// Starts painting an element whose paint is cached, see
+    /// `PaintDetails::cache`, at `rect`. `hash` identifies how its
+    /// subtree is drawn relative to its top left corner, and changes
+    /// when anything in it may have. Backends which don't cache have it
+    /// painted in place.
+    fn begin_cached(
+        &mut self,
+        _clip: Self::Clip,
+        _id: u64,
+        _rect: LogicalRect,
+        _hash: u64,
+    ) -> CachedPaint<Self::Clip> {
+        CachedPaint::Inline
+    }
+
+    /// Finishes painting a subtree into the cache, after
+    /// `begin_cached()` returned `CachedPaint::Repaint`.
+    fn end_cached(&mut self) {}
+
+    /// Called when the window is resized, or moves onto a screen with
+    /// another scale factor. Backends which draw at a fixed size can
+    /// ignore it.
+    fn resize(&mut self, _metrics: WindowMetrics) {}
+
+    /// Submits everything pushed since the last call to be displayed.
+    /// `dirty` is the part of the window that changed since the last
+    /// frame, which backends can use to skip work. Returns whether a
+    /// new frame was actually presented.
+    fn present(&mut self, dirty: &DirtyRegion) -> bool;
+}
+
+/// How an element whose paint is cached is painted, decided by the
+/// backend in `Renderer::begin_cached()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CachedPaint<Clip> {
+    /// Painted in place, as if it weren't cached.
+    Inline,
+    /// The backend composited the subtree as it was painted before, so
+    /// it isn't painted.
+    Reused,
+    /// The subtree is painted into the cache, relative to its top left
+    /// corner, within this clip, followed by `Renderer::end_cached()`.
+    Repaint(Clip),
+}
+
+/// A backend which draws nothing, and counts the frames presented, for
+/// tests which open windows.
+#[cfg(test)]
+pub(crate) struct FrameCounter(pub Arc<std::sync::atomic::AtomicUsize>);
+
+#[cfg(test)]
+impl Renderer for FrameCounter {
+    type Clip = ();
+
+    fn root_clip(&self) {}
+
+    fn push_clip(&mut self, _: (), _: LogicalRect, _: Corners<LogicalLength>) {}
+
+    fn push_rect(&mut self, _: (), _: LogicalRect, _: Color, _: Option<u64>) {}
+
+    fn push_backdrop_blur(&mut self, _: (), _: LogicalRect, _: LogicalLength) {}
+
+    fn push_border(&mut self, _: (), _: LogicalRect, _: &Edges<Border>, _: Corners<LogicalLength>) {
+    }
+
+    fn push_image(&mut self, _: (), _: LogicalRect, _: &Arc<DecodedImage>) {}
+
+    fn push_text(&mut self, _: (), _: LogicalRect, _: LogicalPoint, _: &LayoutText, _: Color) {}
+
+    fn present(&mut self, _dirty: &DirtyRegion) -> bool {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        true
+    }
+}
+
+/// Finds the rectangle of the caret of the given node, relative to
+/// `position`, by looking for the line of text that contains it.
+fn find_caret(
+    id: u64,
+    caret: usize,
+    position: LogicalPoint,
+    layout: &LayoutTreeNode,
+) -> Option<LogicalRect> {
+    if let RenderData::Text {
+        ref text,
+        ref parent,
+    } = layout.render
+    {
+        if parent.id() == id && text.range.start <= caret && caret <= text.range.end {
+            if let Some(x) = text.caret_position(caret) {
+                return Some(LogicalRect::new(
+                    position + vec2(x, 0.0),
+                    size2(1.0, layout.size.height),
+                ));
+            }
+        }
+    }
+    // Prefer the later line when the caret sits on a line boundary.
+    layout.children.iter().rev().find_map(|child| {
+        find_caret(
+            id,
+            caret,
+            position + child.position.to_vector(),
+            &child.layout,
+        )
+    })
+}
+
+/// Finds the caret of an editable element relative to its box, and how
+/// far its text is scrolled up to keep the caret in view.
+pub(crate) fn caret_scroll(
+    id: u64,
+    caret: usize,
+    values: &ComputedValues,
+    layout: &LayoutTreeNode,
+) -> (LogicalRect, f32) {
+    let caret_rect = find_caret(id, caret, point2(0.0, 0.0), layout)
+        .unwrap_or_else(|| LogicalRect::new(point2(0.0, 0.0), size2(1.0, values.text_size.get())));
+    let bottom_inset = if let DisplayType::Block(ref block) = values.display {
+        block.padding.bottom
+    } else {
+        0.0
+    } + values.border.bottom.width.get();
+    let scroll = (caret_rect.max_y() - (layout.size.height - bottom_inset)).max(0.0);
+    (caret_rect, scroll)
+}
+
+fn is_square(radius: &Corners<LogicalLength>) -> bool {
+    radius.top_left.get() == 0.0
+        && radius.top_right.get() == 0.0
+        && radius.bottom_left.get() == 0.0
+        && radius.bottom_right.get() == 0.0
+}
+
+/// The area inside the border of a box, and its corner radii. Each
+/// radius is reduced by the wider of the borders next to it, so the
+/// inner curve follows the outside of the border.
+fn inside_border(
+    rect: LogicalRect,
+    border: &Edges<Border>,
+    radius: &Corners<LogicalLength>,
+) -> (LogicalRect, Corners<LogicalLength>) {
+    let widths = border.map(|side| side.width.get());
+    let widths = LogicalSideOffsets::new(widths.top, widths.right, widths.bottom, widths.left);
+    inset_rounded(rect, widths, radius)
+}
+
+/// Insets a rounded box by `widths`, reducing each radius by the wider
+/// of the insets next to it.
+fn inset_rounded(
+    rect: LogicalRect,
+    widths: LogicalSideOffsets,
+    radius: &Corners<LogicalLength>,
+) -> (LogicalRect, Corners<LogicalLength>) {
+    let reduce = |radius: LogicalLength, first: f32, second: f32| {
+        LogicalLength::new((radius.get() - first.max(second)).max(0.0))
+    };
+    let radius = Corners {
+        top_left: reduce(radius.top_left, widths.top, widths.left),
+        top_right: reduce(radius.top_right, widths.top, widths.right),
+        bottom_left: reduce(radius.bottom_left, widths.bottom, widths.left),
+        bottom_right: reduce(radius.bottom_right, widths.bottom, widths.right),
+    };
+    (rect.inner_rect(widths), radius)
+}
+
+/// The area filled by the background of a box, and its corner radii,
+/// following `background_clip`.
+fn background_box(
+    rect: LogicalRect,
+    values: &ComputedValues,
+    paint: &PaintDetails,
+) -> (LogicalRect, Corners<LogicalLength>) {
+    let padding = match (paint.background_clip, &values.display) {
+        (BackgroundClip::BorderBox, _) => return (rect, paint.corner_radius),
+        (BackgroundClip::ContentBox, DisplayType::Block(block)) => block.padding,
+        _ => LogicalSideOffsets::zero(),
+    };
+    let border = paint.border.map(|side| side.width.get());
+    let widths = LogicalSideOffsets::new(
+        border.top + padding.top,
+        border.right + padding.right,
+        border.bottom + padding.bottom,
+        border.left + padding.left,
+    );
+    inset_rounded(rect, widths, &paint.corner_radius)
+}
+
+/// How far past an element a clip extends along an axis whose overflow
+/// is visible, which is far enough to never cut anything off.
+const UNCLIPPED: f32 = 1.0e6;
+
+/// The clip for the children of an element, if either axis of its
+/// overflow is hidden. A clip on one axis is unbounded on the other,
+/// and isn't rounded, since its corners aren't the element's.
+fn overflow_clip(
+    rect: LogicalRect,
+    values: &ComputedValues,
+    radius: &Corners<LogicalLength>,
+) -> Option<(LogicalRect, Corners<LogicalLength>)> {
+    let hidden_x = values.overflow_x == Overflow::Hidden;
+    let hidden_y = values.overflow_y == Overflow::Hidden;
+    let (inner, radius) = inside_border(rect, &values.border, radius);
+    match (hidden_x, hidden_y) {
+        (true, true) => Some((inner, radius)),
+        (false, false) => None,
+        _ => {
+            let outset = if hidden_x {
+                LogicalSideOffsets::new(UNCLIPPED, 0.0, UNCLIPPED, 0.0)
+            } else {
+                LogicalSideOffsets::new(0.0, UNCLIPPED, 0.0, UNCLIPPED)
+            };
+            let square = Corners::new_all_same(LogicalLength::new(0.0));
+            Some((inner.outer_rect(outset), square))
+        }
+    }
+}
+
+/// Rounds each edge of `rect` to the nearest device pixel, with
+/// halfway cases rounded away from zero. `scale` is the number of
+/// device pixels per logical pixel.
+pub fn snap_to_pixels(rect: LogicalRect, scale: f32) -> LogicalRect {
+    let min = (rect.min() * scale).round() / scale;
+    let max = (rect.max() * scale).round() / scale;
+    LogicalRect::new(min, (max - min).to_size())
+}
+
+/// Issues the paint commands for a layout tree, without presenting
+/// them. Returns the number of items drawn, not counting clips.
+///
+/// When `snap` is set to the device pixel ratio, the boxes of elements
+/// are snapped to the device pixel grid so that their edges stay crisp.
+/// Each box is snapped from its exact position rather than its
+/// parent's snapped one, so rounding doesn't accumulate through
+/// nesting. Text is left at its exact position.
+pub fn paint(
+    renderer: &mut impl Renderer,
+    document: &Document,
+    layout: &LayoutTreeNode,
+    snap: Option<f32>,
+) -> usize {
+    trace_span!(span = "paint", items = tracing::field::Empty);
+    let clip = renderer.root_clip();
+    renderer.set_text_rendering(document.text_rendering());
+    let mut portals = vec![];
+    let mut items = layout
+        .children
+        .iter()
+        .map(|child| {
+            paint_node(
+                renderer,
+                document,
+                snap,
+                clip,
+                child.position,
+                &child.layout,
+                &mut portals,
+            )
+        })
+        .sum();
+    // Portals are painted on top of everything else, in the order they
+    // were reached, with portals inside of them after them.
+    let mut index = 0;
+    while let Some(&(position, portal)) = portals.get(index) {
+        items += paint_node(
+            renderer,
+            document,
+            snap,
+            clip,
+            position,
+            portal,
+            &mut portals,
+        );
+        index += 1;
+    }
+    trace_record!(span, "items", items);
+    items
+}
+
+/// Paints an element and its children, apart from children which are
+/// portals, which are added to `portals` with their positions instead.
+fn paint_node<'a, R: Renderer>(
+    renderer: &mut R,
+    document: &Document,
+    snap: Option<f32>,
+    clip: R::Clip,
+    position: LogicalPoint,
+    layout: &'a LayoutTreeNode,
+    portals: &mut Vec<(LogicalPoint, &'a LayoutTreeNode)>,
+) -> usize {
+    if let RenderData::Node(ref node) = layout.render {
+        if node.paint(document.computed_values(node.id())).cache {
+            let id = node.id();
+            return paint_cached(
+                renderer, document, snap, clip, position, layout, id, portals,
+            );
+        }
+    }
+    paint_contents(renderer, document, snap, clip, position, layout, portals)
+}
+
+/// Paints an element whose paint is cached. The backend decides from a
+/// key whether the subtree has to be painted again, without painting it
+/// to find out. The key is the generation of the subtree's layout, which
+/// covers its nodes and computed values, along with what painting reads
+/// from the document rather than from the tree: decoded images, the
+/// caret while focus is within it, and how text is rasterized.
+#[allow(clippy::too_many_arguments)]
+fn paint_cached<'a, R: Renderer>(
+    renderer: &mut R,
+    document: &Document,
+    snap: Option<f32>,
+    clip: R::Clip,
+    position: LogicalPoint,
+    layout: &'a LayoutTreeNode,
+    id: u64,
+    portals: &mut Vec<(LogicalPoint, &'a LayoutTreeNode)>,
+) -> usize {
+    let rect = LogicalRect::new(position, layout.size);
+    let rect = match snap {
+        Some(scale) => snap_to_pixels(rect, scale),
+        None => rect,
+    };
+    let mut hasher = DefaultHasher::new();
+    layout.generation.hash(&mut hasher);
+    document.images().generation().hash(&mut hasher);
+    snap.map(f32::to_bits).hash(&mut hasher);
+    document.text_rendering().hash(&mut hasher);
+    if document.focus_within(id) {
+        (document.focused(), document.caret_visible()).hash(&mut hasher);
+    }
+    let hash = hasher.finish();
+    // Portals inside of it are painted on their own, whether or not the
+    // rest of it is painted this frame.
+    find_portals(layout, rect.origin, portals);
+
+    match renderer.begin_cached(clip, id, rect, hash) {
+        CachedPaint::Inline => paint_contents(
+            renderer,
+            document,
+            snap,
+            clip,
+            position,
+            layout,
+            &mut vec![],
+        ),
+        CachedPaint::Reused => 1,
+        CachedPaint::Repaint(clip) => {
+            let origin = point2(0.0, 0.0);
+            let items = paint_contents(renderer, document, snap, clip, origin, layout, &mut vec![]);
+            renderer.end_cached();
+            items
+        }
+    }
+}
+
+/// Collects the portals in the subtree at `position`, where painting it
+/// would defer them to.
+fn find_portals<'a>(
+    layout: &'a LayoutTreeNode,
+    position: LogicalPoint,
+    portals: &mut Vec<(LogicalPoint, &'a LayoutTreeNode)>,
+) {
+    for child in &layout.children {
+        let position = position + child.position.to_vector();
+        if is_portal(&child.layout) {
+            portals.push((position, &child.layout));
+        } else {
+            find_portals(&child.layout, position, portals);
+        }
+    }
+}
+
+fn paint_contents<'a, R: Renderer>(
+    renderer: &mut R,
+    document: &Document,
+    snap: Option<f32>,
+    clip: R::Clip,
+    position: LogicalPoint,
+    layout: &'a LayoutTreeNode,
+    portals: &mut Vec<(LogicalPoint, &'a LayoutTreeNode)>,
+) -> usize {
+    let rect = LogicalRect::new(position, layout.size);
+    let mut items = 0;
+
+    match layout.render {
+        RenderData::Node(ref node) => {
+            let rect = match snap {
+                Some(scale) => snap_to_pixels(rect, scale),
+                None => rect,
+            };
+            let values = document.computed_values(node.id());
+            let paint = node.paint(values);
+            // Hidden elements still clip their children, which can be
+            // visible.
+            let shown = values.visibility == Visibility::Visible;
+
+            let shape_clip = paint
+                .clip_path
+                .shape(rect)
+                .map(|(bounds, radius)| renderer.push_clip(clip, bounds, radius));
+            let outer_clip = clip;
+            let clip = shape_clip.unwrap_or(clip);
+
+            let blur = match paint.backdrop_filter {
+                BackdropFilter::Blur(radius) => Some(radius),
+                BackdropFilter::None => None,
+            };
+            let visible = paint.background_color.alpha > 0
+                || node.interactive()
+                || node.drag_region()
+                || node.title().is_some();
+            if shown && (visible || blur.is_some()) {
+                let mut hit_test = Some(node.id());
+                if visible && shape_clip.is_some() {
+                    // Hit testing ignores the clip path, so the element
+                    // is hit through a rect outside of it.
+                    renderer.push_rect(outer_clip, rect, Color::clear(), hit_test.take());
+                }
+                let clip = if is_square(&paint.corner_radius) {
+                    clip
+                } else {
+                    renderer.push_clip(clip, rect, paint.corner_radius)
+                };
+                if let Some(radius) = blur {
+                    renderer.push_backdrop_blur(clip, rect, radius);
+                    items += 1;
+                }
+                let (background, radius) = background_box(rect, values, &paint);
+                if visible && background != rect && paint.background_color.alpha > 0 {
+                    // The border and padding left unfilled are still
+                    // part of the element, and hit it.
+                    renderer.push_rect(clip, rect, Color::clear(), hit_test.take());
+                    let clip = if is_square(&radius) {
+                        clip
+                    } else {
+                        renderer.push_clip(clip, background, radius)
+                    };
+                    renderer.push_rect(clip, background, paint.background_color, None);
+                    items += 1;
+                } else if visible {
+                    renderer.push_rect(clip, rect, paint.background_color, hit_test);
+                    items += 1;
+                }
+            }
+
+            if shown && paint.border.visible() {
+                renderer.push_border(clip, rect, &paint.border, paint.corner_radius);
+                items += 1;
+            }
+
+            if shown {
+                let mut painter = Painter::new(
+                    rect.size,
+                    content_box(rect.size, values),
+                    values.theme_colors,
+                )
+                .with_images(document.images())
+                .on_screen(on_screen(document, rect));
+                node.draw(&mut painter);
+                items += paint_commands(renderer, clip, rect, &painter);
+            }
+
+            let mut child_clip = clip;
+            if paint.clip {
+                let square = Corners::new_all_same(LogicalLength::new(0.0));
+                child_clip = renderer.push_clip(child_clip, rect, square);
+            }
+            if let Some((inner, radius)) = overflow_clip(rect, values, &paint.corner_radius) {
+                child_clip = renderer.push_clip(clip, inner, radius);
+            }
+
+            let mut child_position = position;
+            if let Some(caret) = node.caret() {
+                // Editable text is clipped to the element, and scrolled
+                // so that the caret stays in view.
+                child_clip = renderer.push_clip(
+                    child_clip,
+                    rect,
+                    Corners::new_all_same(LogicalLength::new(0.0)),
+                );
+                let (caret_rect, scroll) = caret_scroll(node.id(), caret, values, layout);
+                child_position -= vec2(0.0, scroll);
+
+                if shown && document.focused() == Some(node.id()) && document.caret_visible() {
+                    let caret_rect = caret_rect.translate(child_position.to_vector());
+                    renderer.push_rect(child_clip, caret_rect, paint.text_color, None);
+                    items += 1;
+                }
+            }
+
+            for child in &layout.children {
+                let position = child_position + child.position.to_vector();
+                if is_portal(&child.layout) {
+                    portals.push((position, &child.layout));
+                    continue;
+                }
+                items += paint_node(
+                    renderer,
+                    document,
+                    snap,
+                    child_clip,
+                    position,
+                    &child.layout,
+                    portals,
+                );
+            }
+
+            if shown && paint.outline.visible() {
+                let (bounds, radius) = paint.outline.shape(rect, &paint.corner_radius);
+                let line = Edges::new_all_same(paint.outline.line());
+                renderer.push_border(outer_clip, bounds, &line, radius);
+                items += 1;
+            }
+
+            if let Some(overlay) = node.paint_overlay().filter(|_| shown) {
+                let mut painter = Painter::new(
+                    rect.size,
+                    content_box(rect.size, values),
+                    values.theme_colors,
+                )
+                .with_images(document.images())
+                .on_screen(on_screen(document, rect));
+                overlay.paint(&mut painter);
+                items += paint_commands(renderer, clip, rect, &painter);
+            }
+        }
+        RenderData::Text {
+            ref text,
+            ref parent,
+        } => {
+            let values = document.computed_values(parent.id());
+            if values.visibility == Visibility::Visible {
+                let paint = parent.paint(values);
+                if let Some(shadow) = paint.text_shadow.filter(|shadow| shadow.color.alpha > 0) {
+                    renderer.push_text_shadow(clip, rect, position, text, &shadow);
+                    items += 1;
+                }
+                renderer.push_text(clip, rect, position, text, paint.text_color);
+                items += 1;
+            }
+        }
+    }
+    items
+}
+
+pub(crate) fn is_portal(layout: &LayoutTreeNode) -> bool {
+    match layout.render {
+        RenderData::Node(ref node) => node.portal().is_some(),
+        RenderData::Text { .. } => false,
+    }
+}
+
+/// Whether any of `rect` is inside the window.
+fn on_screen(document: &Document, rect: LogicalRect) -> bool {
+    rect.intersects(&LogicalRect::new(
+        LogicalPoint::zero(),
+        document.content_size(),
+    ))
+}
+
+/// The content box of an element, relative to its border box.
+fn content_box(size: LogicalSize, values: &ComputedValues) -> LogicalRect {
+    let padding = if let DisplayType::Block(ref block) = values.display {
+        block.padding
+    } else {
+        LogicalSideOffsets::zero()
+    };
+    let border = values.border.map(|side| side.width.get());
+    LogicalRect::new(point2(0.0, 0.0), size).inner_rect(LogicalSideOffsets::new(
+        padding.top + border.top,
+        padding.right + border.right,
+        padding.bottom + border.bottom,
+        padding.left + border.left,
+    ))
+}
+
+/// Replays the drawing collected by a painter for the element at
+/// `rect`, clipped to it.
+fn paint_commands<R: Renderer>(
+    renderer: &mut R,
+    clip: R::Clip,
+    rect: LogicalRect,
+    painter: &Painter,
+) -> usize {
+    if painter.commands.is_empty() {
+        return 0;
+    }
+
+    let clip = renderer.push_clip(clip, rect, Corners::new_all_same(LogicalLength::new(0.0)));
+    let offset = rect.origin.to_vector();
+    for command in &painter.commands {
+        match command {
+            PaintCommand::Rect(bounds, color) => {
+                renderer.push_rect(clip, bounds.translate(offset), *color, None)
+            }
+            PaintCommand::RoundedRect(bounds, color, radius) => {
+                let bounds = bounds.translate(offset);
+                let clip = renderer.push_clip(clip, bounds, *radius);
+                renderer.push_rect(clip, bounds, *color, None)
+            }
+            PaintCommand::Border(bounds, border, radius) => {
+                renderer.push_border(clip, bounds.translate(offset), border, *radius)
+            }
+            PaintCommand::Image(bounds, image) => {
+                renderer.push_image(clip, bounds.translate(offset), image)
+            }
+        }
+    }
+    painter.commands.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{paint, snap_to_pixels, Renderer, UNCLIPPED};
+    use crate::document::Document;
+    use crate::dom::{ImageSource, Node, Portal, View};
+    use crate::layout::{LayoutText, LogicalLength, LogicalPoint, LogicalRect, LogicalSideOffsets};
+    use crate::mox_impl::{attr, elt};
+    use crate::render::dirty::DirtyRegion;
+    use crate::render::painter::PaintOverlay;
+    use crate::render::{TextAntialiasing, TextHinting, TextRendering};
+    use crate::runtime::{DecodedImage, Images};
+    use crate::style::{
+        sized_box, test_style, BackdropFilter, BackgroundClip, Border, BorderStyle, ClipPath,
+        ClipRadius, Corners, DisplayType, Edges, Outline, Overflow, Style, TextShadow, ThemeColors,
+    };
+    use crate::Color;
+    use euclid::{point2, rect, size2};
+    use moxie::runtime::Runtime;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, PartialEq)]
+    enum Command {
+        Clip(usize, LogicalRect, Corners<LogicalLength>),
+        Rect(usize, LogicalRect, Color, Option<u64>),
+        Backdrop(usize, LogicalRect, LogicalLength),
+        Border(usize, LogicalRect),
+        Image(usize, LogicalRect),
+        Text(usize, LogicalRect),
+        TextShadow(usize, LogicalRect, Color),
+        Present,
+    }
+
+    #[derive(Default)]
+    struct RecordingRenderer {
+        clips: usize,
+        commands: Vec<Command>,
+        text_rendering: Option<TextRendering>,
+    }
+
+    impl Renderer for RecordingRenderer {
+        type Clip = usize;
+
+        fn root_clip(&self) -> usize {
+            0
+        }
+
+        fn push_clip(
+            &mut self,
+            parent: usize,
+            rect: LogicalRect,
+            radius: Corners<LogicalLength>,
+        ) -> usize {
+            self.clips += 1;
+            self.commands.push(Command::Clip(parent, rect, radius));
+            self.clips
+        }
+
+        fn push_rect(
+            &mut self,
+            clip: usize,
+            rect: LogicalRect,
+            color: Color,
+            hit_test: Option<u64>,
+        ) {
+            self.commands
+                .push(Command::Rect(clip, rect, color, hit_test));
+        }
+
+        fn push_backdrop_blur(&mut self, clip: usize, rect: LogicalRect, radius: LogicalLength) {
+            self.commands.push(Command::Backdrop(clip, rect, radius));
+        }
+
+        fn push_border(
+            &mut self,
+            clip: usize,
+            rect: LogicalRect,
+            _border: &Edges<Border>,
+            _radius: Corners<LogicalLength>,
+        ) {
+            self.commands.push(Command::Border(clip, rect));
+        }
+
+        fn push_image(&mut self, clip: usize, rect: LogicalRect, _image: &Arc<DecodedImage>) {
+            self.commands.push(Command::Image(clip, rect));
+        }
+
+        fn set_text_rendering(&mut self, text_rendering: TextRendering) {
+            self.text_rendering = Some(text_rendering);
+        }
+
+        fn push_text(
+            &mut self,
+            clip: usize,
+            rect: LogicalRect,
+            _position: LogicalPoint,
+            _text: &LayoutText,
+            _color: Color,
+        ) {
+            self.commands.push(Command::Text(clip, rect));
+        }
+
+        fn push_text_shadow(
+            &mut self,
+            clip: usize,
+            rect: LogicalRect,
+            _position: LogicalPoint,
+            _text: &LayoutText,
+            shadow: &TextShadow,
+        ) {
+            self.commands
+                .push(Command::TextShadow(clip, rect, shadow.color));
+        }
+
+        fn present(&mut self, _dirty: &DirtyRegion) -> bool {
+            self.commands.push(Command::Present);
+            true
+        }
+    }
+
+    #[test]
+    fn records_commands() {
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(4.0));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+        });
+
+        let (window, view) = Runtime::new().run_once(|| {
+            let view = elt::view().set_attr(attr::style(), square).build();
+            let window = elt::window().add_child(view.clone()).build();
+            (window, view)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        assert_eq!(paint(&mut renderer, &document, &layout, None), 1);
+        renderer.present(&DirtyRegion::Full);
+
+        let bounds: LogicalRect = rect(0.0, 0.0, 20.0, 10.0);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                Command::Clip(0, bounds, Corners::new_all_same(LogicalLength::new(4.0))),
+                Command::Rect(1, bounds, Color::new(255, 0, 0, 255), Some(view.id())),
+                Command::Present,
+            ]
+        );
+    }
+
+    #[test]
+    fn background_clip_leaves_padding() {
+        let padded = test_style("padded", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.background_clip = BackgroundClip::ContentBox;
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(10.0));
+            values.border = Edges::new_all_same(Border {
+                width: LogicalLength::new(2.0),
+                style: BorderStyle::Solid,
+                color: Color::black(),
+            });
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+                block.padding = LogicalSideOffsets::new_all_same(5.0);
+            }
+        });
+
+        let (window, view) = Runtime::new().run_once(|| {
+            let view = elt::view().set_attr(attr::style(), padded).build();
+            (elt::window().add_child(view.clone()).build(), view)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+
+        // The whole border box is hit, and only the content box is
+        // filled, with its corners reduced by the border and padding.
+        let bounds: LogicalRect = rect(0.0, 0.0, 34.0, 24.0);
+        let content: LogicalRect = rect(7.0, 7.0, 20.0, 10.0);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                Command::Clip(0, bounds, Corners::new_all_same(LogicalLength::new(10.0))),
+                Command::Rect(1, bounds, Color::clear(), Some(view.id())),
+                Command::Clip(1, content, Corners::new_all_same(LogicalLength::new(3.0))),
+                Command::Rect(2, content, Color::new(255, 0, 0, 255), None),
+                Command::Border(0, bounds),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlay_above_children() {
+        let red = test_style("red", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.padding = LogicalSideOffsets::new_all_same(5.0);
+            }
+        });
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(0, 255, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(10.0).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+        });
+        let highlight = PaintOverlay::new(|painter| {
+            let size = painter.size();
+            painter.fill_rect(rect(0.0, 0.0, size.width, 2.0), Color::new(0, 0, 255, 255));
+        });
+
+        let (window, outer, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), square).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), red)
+                .set_attr(attr::paint_overlay(), highlight)
+                .add_child(inner.clone())
+                .build();
+            let window = elt::window().add_child(outer.clone()).build();
+            (window, outer, inner)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        assert_eq!(paint(&mut renderer, &document, &layout, None), 3);
+
+        let bounds: LogicalRect = rect(0.0, 0.0, 20.0, 20.0);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                Command::Rect(0, bounds, Color::new(255, 0, 0, 255), Some(outer.id())),
+                Command::Rect(
+                    0,
+                    rect(5.0, 5.0, 10.0, 10.0),
+                    Color::new(0, 255, 0, 255),
+                    Some(inner.id())
+                ),
+                Command::Clip(0, bounds, Corners::new_all_same(LogicalLength::new(0.0))),
+                Command::Rect(
+                    1,
+                    rect(0.0, 0.0, 20.0, 2.0),
+                    Color::new(0, 0, 255, 255),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn themed_selection() {
+        let square = sized_box("square", 10.0, 10.0);
+        let selection = PaintOverlay::new(|painter| {
+            painter.fill_selection(rect(0.0, 0.0, 5.0, 10.0));
+        });
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(
+                    elt::view()
+                        .set_attr(attr::style(), square)
+                        .set_attr(attr::paint_overlay(), selection)
+                        .build(),
+                )
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let selection_color = |document: &mut Document| {
+            let layout = document.get_layout();
+            let mut renderer = RecordingRenderer::default();
+            paint(&mut renderer, document, &layout, None);
+            renderer
+                .commands
+                .iter()
+                .find_map(|command| match *command {
+                    Command::Rect(1, _, color, None) => Some(color),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            selection_color(&mut document),
+            ThemeColors::default().selection_background()
+        );
+
+        let dark = test_style("dark", |values| {
+            values.theme_colors.selection_background = Some(Color::new(80, 0, 120, 255));
+        });
+        document.set_theme(Some(dark));
+        assert_eq!(selection_color(&mut document), Color::new(80, 0, 120, 255));
+    }
+
+    #[test]
+    fn outline_outside_layout() {
+        let padded = test_style("padded", |values| {
+            values.overflow_x = Overflow::Hidden;
+            values.overflow_y = Overflow::Hidden;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.padding = LogicalSideOffsets::new_all_same(10.0);
+            }
+        });
+        let ringed = test_style("ringed", |values| {
+            values.outline = Outline {
+                width: LogicalLength::new(2.0),
+                style: BorderStyle::Solid,
+                color: Color::new(0, 0, 255, 255),
+                offset: LogicalLength::new(3.0),
+            };
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+
+        let (window, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), ringed).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), padded)
+                .add_child(inner.clone())
+                .build();
+            (elt::window().add_child(outer).build(), inner)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        assert_eq!(
+            document.rect(inner.id()),
+            Some(rect(10.0, 10.0, 20.0, 20.0))
+        );
+
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+        // The outline is only clipped by the parent's overflow, to
+        // inside of its box.
+        assert_eq!(
+            renderer.commands.last(),
+            Some(&Command::Border(1, rect(5.0, 5.0, 30.0, 30.0)))
+        );
+        assert_eq!(
+            renderer.commands[1],
+            Command::Clip(
+                0,
+                rect(0.0, 0.0, 40.0, 40.0),
+                Corners::new_all_same(LogicalLength::new(0.0))
+            )
+        );
+    }
+
+    #[test]
+    fn portal_escapes_clip() {
+        let anchor = test_style("anchor", |values| {
+            values.background_color = Color::new(128, 128, 128, 255);
+            values.overflow_x = Overflow::Hidden;
+            values.overflow_y = Overflow::Hidden;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(30.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+        let menu = test_style("menu", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(60.0).into());
+                block.height = Some(LogicalLength::new(40.0).into());
+            }
+        });
+
+        let (window, menu) = Runtime::new().run_once(|| {
+            let menu = elt::view()
+                .set_attr(attr::style(), menu)
+                .set_attr(attr::portal(), Some(Portal::Below))
+                .build();
+            let anchor = elt::view()
+                .set_attr(attr::style(), anchor)
+                .add_child(menu.clone())
+                .build();
+            (elt::window().add_child(anchor).build(), menu)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        assert_eq!(document.rect(menu.id()), Some(rect(0.0, 20.0, 60.0, 40.0)));
+
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+        // The anchor clips its children, but the portal is painted
+        // afterwards with the window's clip.
+        assert_eq!(
+            renderer.commands[1],
+            Command::Clip(
+                0,
+                rect(0.0, 0.0, 30.0, 20.0),
+                Corners::new_all_same(LogicalLength::new(0.0))
+            )
+        );
+        assert_eq!(
+            renderer.commands.last(),
+            Some(&Command::Rect(
+                0,
+                rect(0.0, 20.0, 60.0, 40.0),
+                Color::new(255, 0, 0, 255),
+                Some(menu.id())
+            ))
+        );
+        assert_eq!(document.element_at(point2(50.0, 50.0)), Some(menu.id()));
+    }
+
+    #[test]
+    fn circle_clip_path() {
+        let avatar = test_style("avatar", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.clip_path = ClipPath::Circle(ClipRadius::Fraction(0.5));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(40.0).into());
+                block.height = Some(LogicalLength::new(40.0).into());
+            }
+        });
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(0, 0, 255, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(40.0).into());
+                block.height = Some(LogicalLength::new(40.0).into());
+            }
+        });
+
+        let (window, outer, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), square).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), avatar)
+                .add_child(inner.clone())
+                .build();
+            let window = elt::window().add_child(outer.clone()).build();
+            (window, outer, inner)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        assert_eq!(paint(&mut renderer, &document, &layout, None), 2);
+
+        // The square child is clipped to the circle inscribed in it,
+        // while the element is still hit as a rectangle.
+        let bounds: LogicalRect = rect(0.0, 0.0, 40.0, 40.0);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                Command::Clip(0, bounds, Corners::new_all_same(LogicalLength::new(20.0))),
+                Command::Rect(0, bounds, Color::clear(), Some(outer.id())),
+                Command::Rect(1, bounds, Color::new(255, 0, 0, 255), None),
+                Command::Rect(1, bounds, Color::new(0, 0, 255, 255), Some(inner.id())),
+            ]
+        );
+        assert_eq!(document.element_at(point2(1.0, 1.0)), Some(inner.id()));
+    }
+
+    #[test]
+    fn backdrop_blur() {
+        let frosted = test_style("frosted", |values| {
+            values.background_color = Color::new(255, 255, 255, 128);
+            values.backdrop_filter = BackdropFilter::Blur(LogicalLength::new(8.0));
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+        });
+
+        let (window, panel) = Runtime::new().run_once(|| {
+            let panel = elt::view().set_attr(attr::style(), frosted).build();
+            let window = elt::window().add_child(panel.clone()).build();
+            (window, panel)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        assert_eq!(paint(&mut renderer, &document, &layout, None), 2);
+
+        // The backdrop is blurred before the panel is painted over it.
+        let bounds: LogicalRect = rect(0.0, 0.0, 20.0, 10.0);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                Command::Backdrop(0, bounds, LogicalLength::new(8.0)),
+                Command::Rect(0, bounds, Color::new(255, 255, 255, 128), Some(panel.id())),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_clip_paths() {
+        assert!(ClipPath::circle(ClipRadius::Length(LogicalLength::new(-1.0))).is_err());
+        assert!(ClipPath::circle(ClipRadius::Fraction(std::f32::NAN)).is_err());
+        let offsets = LogicalSideOffsets::new(1.0, 2.0, -3.0, 4.0);
+        assert!(ClipPath::inset(offsets, LogicalLength::new(0.0)).is_err());
+
+        // Insets larger than the box clip everything.
+        let inset = ClipPath::inset(
+            LogicalSideOffsets::new_all_same(15.0),
+            LogicalLength::new(8.0),
+        )
+        .unwrap();
+        assert_eq!(
+            inset.shape(rect(0.0, 0.0, 40.0, 20.0)),
+            Some((
+                rect(15.0, 15.0, 10.0, 0.0),
+                Corners::new_all_same(LogicalLength::new(0.0))
+            ))
+        );
+    }
+
+    #[test]
+    fn snaps_to_device_pixels() {
+        let scale = 1.5;
+        let snapped = snap_to_pixels(rect(10.3, 0.5, 20.2, 1.0), scale);
+        assert_eq!(snapped.min_x(), 10.0);
+        for value in &[
+            snapped.min_x(),
+            snapped.min_y(),
+            snapped.max_x(),
+            snapped.max_y(),
+        ] {
+            let device = value * scale;
+            assert!((device - device.round()).abs() < 1e-4);
+        }
+        // Halfway cases round away from zero.
+        assert_eq!(
+            snap_to_pixels(rect(0.5, -0.5, 1.0, 1.0), 1.0),
+            rect(1.0, -1.0, 1.0, 2.0)
+        );
+
+        let padded = test_style("padded", |values| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.padding = LogicalSideOffsets::new_all_same(0.3);
+            }
+            values.background_color = Color::clear();
+        });
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(0, 255, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(10.0).into());
+                block.height = Some(LogicalLength::new(10.0).into());
+            }
+        });
+
+        // Each level of nesting adds 0.3px, which would round down every
+        // time if snapping were relative to the parent.
+        let (window, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), square).build();
+            let mut outer = inner.clone();
+            for _ in 0..3 {
+                outer = elt::view()
+                    .set_attr(attr::style(), padded)
+                    .add_child(outer)
+                    .build();
+            }
+            (elt::window().add_child(outer).build(), inner)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, Some(1.0));
+        assert_eq!(
+            renderer.commands,
+            vec![Command::Rect(
+                0,
+                rect(1.0, 1.0, 10.0, 10.0),
+                Color::new(0, 255, 0, 255),
+                Some(inner.id())
+            )]
+        );
+    }
+
+    #[test]
+    fn clips_to_rounded_corners() {
+        let rounded = test_style("rounded", |values| {
+            values.background_color = Color::new(255, 0, 0, 255);
+            values.corner_radius = Corners::new_all_same(LogicalLength::new(10.0));
+            values.overflow_x = Overflow::Hidden;
+            values.overflow_y = Overflow::Hidden;
+            values.border = Edges::new_all_same(Border {
+                width: LogicalLength::new(2.0),
+                style: BorderStyle::Solid,
+                color: Color::black(),
+            });
+        });
+        let square = test_style("square", |values| {
+            values.background_color = Color::new(0, 255, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(20.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+
+        let (window, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), square).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), rounded)
+                .add_child(inner.clone())
+                .build();
+            (elt::window().add_child(outer).build(), inner)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+
+        let clip = Command::Clip(
+            0,
+            rect(2.0, 2.0, 20.0, 20.0),
+            Corners::new_all_same(LogicalLength::new(8.0)),
+        );
+        let position = renderer
+            .commands
+            .iter()
+            .position(|command| *command == clip);
+        assert!(position.is_some());
+        assert_eq!(
+            renderer.commands[position.unwrap() + 1],
+            Command::Rect(
+                2,
+                rect(2.0, 2.0, 20.0, 20.0),
+                Color::new(0, 255, 0, 255),
+                Some(inner.id())
+            )
+        );
+    }
+
+    #[test]
+    fn clips_to_bounds() {
+        let clipped = test_style("clipped", |values| {
+            values.clip = true;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(30.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+        let both = test_style("both", |values| {
+            values.clip = true;
+            values.overflow_x = Overflow::Hidden;
+            values.overflow_y = Overflow::Hidden;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(30.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+        let wide = test_style("wide", |values| {
+            values.background_color = Color::new(0, 255, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(50.0).into());
+                block.height = Some(LogicalLength::new(50.0).into());
+            }
+        });
+        let square = Corners::new_all_same(LogicalLength::new(0.0));
+        let render = |style: Style| {
+            let (window, inner) = Runtime::new().run_once(|| {
+                let inner = elt::view().set_attr(attr::style(), wide).build();
+                let outer = elt::view()
+                    .set_attr(attr::style(), style)
+                    .add_child(inner.clone())
+                    .build();
+                (elt::window().add_child(outer).build(), inner)
+            });
+            let mut document = Document::new(window, size2(100.0, 100.0));
+            let layout = document.get_layout();
+            let mut renderer = RecordingRenderer::default();
+            paint(&mut renderer, &document, &layout, None);
+            (renderer.commands, inner)
+        };
+        let child = |clip, inner: Node<View>| {
+            Command::Rect(
+                clip,
+                rect(0.0, 0.0, 50.0, 50.0),
+                Color::new(0, 255, 0, 255),
+                Some(inner.id()),
+            )
+        };
+
+        // The child hangs out of the box, and is cut off at its edges.
+        let (commands, inner) = render(clipped);
+        assert_eq!(
+            commands[..2],
+            [
+                Command::Clip(0, rect(0.0, 0.0, 30.0, 20.0), square),
+                child(1, inner)
+            ]
+        );
+
+        // Overflow clips inside of it.
+        let (commands, inner) = render(both);
+        assert_eq!(
+            commands[..3],
+            [
+                Command::Clip(0, rect(0.0, 0.0, 30.0, 20.0), square),
+                Command::Clip(1, rect(0.0, 0.0, 30.0, 20.0), square),
+                child(2, inner)
+            ]
+        );
+    }
+
+    #[test]
+    fn clips_one_axis() {
+        let clipped = test_style("clipped", |values| {
+            values.overflow_x = Overflow::Hidden;
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(30.0).into());
+                block.height = Some(LogicalLength::new(20.0).into());
+            }
+        });
+        let wide = test_style("wide", |values| {
+            values.background_color = Color::new(0, 255, 0, 255);
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = Some(LogicalLength::new(50.0).into());
+                block.height = Some(LogicalLength::new(50.0).into());
+            }
+        });
+
+        let (window, inner) = Runtime::new().run_once(|| {
+            let inner = elt::view().set_attr(attr::style(), wide).build();
+            let outer = elt::view()
+                .set_attr(attr::style(), clipped)
+                .add_child(inner.clone())
+                .build();
+            (elt::window().add_child(outer).build(), inner)
+        });
+
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+
+        // Cut off at the right edge, while it still hangs out below.
+        let clip = Command::Clip(
+            0,
+            rect(0.0, -UNCLIPPED, 30.0, 20.0 + 2.0 * UNCLIPPED),
+            Corners::new_all_same(LogicalLength::new(0.0)),
+        );
+        let position = renderer
+            .commands
+            .iter()
+            .position(|command| *command == clip);
+        assert!(position.is_some());
+        assert_eq!(
+            renderer.commands[position.unwrap() + 1],
+            Command::Rect(
+                1,
+                rect(0.0, 0.0, 50.0, 50.0),
+                Color::new(0, 255, 0, 255),
+                Some(inner.id())
+            )
+        );
+    }
+
+    #[test]
+    fn image_after_placeholder() {
+        let square = sized_box("square", 20.0, 10.0);
+        let (gate, wait) = channel::<()>();
+        let wait = Mutex::new(wait);
+        let images = Images::new(move |_source| {
+            wait.lock().unwrap().recv().unwrap();
+            Ok(DecodedImage {
+                rgba: vec![255; 8],
+                width: 2,
+                height: 1,
+            }
+            .into())
+        });
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(
+                    elt::image()
+                        .set_attr(attr::style(), square)
+                        .set_attr(attr::src(), ImageSource::from(vec![1, 2, 3]))
+                        .build(),
+                )
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.set_images(images.clone());
+        let layout = document.get_layout();
+        let bounds: LogicalRect = rect(0.0, 0.0, 20.0, 10.0);
+        let clip = || Command::Clip(0, bounds, Corners::new_all_same(LogicalLength::new(0.0)));
+
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+        assert_eq!(
+            renderer.commands,
+            vec![
+                clip(),
+                Command::Rect(1, bounds, Color::new(128, 128, 128, 64), None),
+            ]
+        );
+
+        gate.send(()).unwrap();
+        while !images.take_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+        assert_eq!(renderer.commands, vec![clip(), Command::Image(1, bounds)]);
+    }
+
+    #[test]
+    fn text_rendering_reaches_renderer() {
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(elt::span().add_child("Text").build())
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let layout = document.get_layout();
+        let mut renderer = RecordingRenderer::default();
+        paint(&mut renderer, &document, &layout, None);
+        assert_eq!(
+            renderer.text_rendering,
+            Some(TextRendering::deterministic())
+        );
+
+        let sharp = TextRendering {
+            antialiasing: TextAntialiasing::Subpixel,
+            hinting: TextHinting::Full,
+        };
+        document.set_text_rendering(sharp);
+        paint(&mut renderer, &document, &layout, None);
+        assert_eq!(renderer.text_rendering, Some(sharp));
+    }
+
+    #[test]
+    fn text_shadow_behind_text() {
+        let heading = |style| {
+            let window = Runtime::new().run_once(|| {
+                elt::window()
+                    .add_child(
+                        elt::span()
+                            .set_attr(attr::style(), style)
+                            .add_child("Heading")
+                            .build(),
+                    )
+                    .build()
+            });
+            Document::new(window, size2(200.0, 100.0))
+        };
+        let plain = test_style("plain", |_| {});
+        let shadowed = test_style("shadowed", |values| {
+            values.text_shadow = Some(TextShadow {
+                offset_x: LogicalLength::new(1.0),
+                offset_y: LogicalLength::new(1.0),
+                blur: LogicalLength::new(2.0),
+                color: Color::black(),
+            });
+        });
+
+        let paint_commands = |mut document: Document| {
+            let layout = document.get_layout();
+            let mut renderer = RecordingRenderer::default();
+            paint(&mut renderer, &document, &layout, None);
+            renderer.commands
+        };
+        let plain = paint_commands(heading(plain));
+        let commands = paint_commands(heading(shadowed));
+        assert!(!plain
+            .iter()
+            .any(|command| matches!(command, Command::TextShadow(..))));
+
+        // The shadow is drawn right before the text, which is laid out
+        // the same as without it.
+        let text = commands
+            .iter()
+            .position(|command| matches!(command, Command::Text(..)))
+            .unwrap();
+        assert!(plain.contains(&commands[text]));
+        match (&commands[text - 1], &commands[text]) {
+            (Command::TextShadow(clip, bounds, color), Command::Text(text_clip, text_bounds)) => {
+                assert_eq!((clip, bounds), (text_clip, text_bounds));
+                assert_eq!(*color, Color::black());
+            }
+            (command, _) => panic!("expected the shadow before the text, got {:?}", command),
+        }
+    }
+
+    /// Collects the names of the spans which are entered.
+    #[cfg(feature = "tracing")]
+    #[derive(Default)]
+    struct SpanNames {
+        names: Arc<Mutex<Vec<&'static str>>>,
+        next_id: std::sync::atomic::AtomicU64,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for SpanNames {
+        fn enabled(&self, _: &tracing::Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes) -> tracing::span::Id {
+            self.names.lock().unwrap().push(span.metadata().name());
+            let id = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tracing::span::Id::from_u64(id + 1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event) {}
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_layout_and_paint() {
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(elt::view().add_child(elt::view().build()).build())
+                .build()
+        });
+
+        let subscriber = SpanNames::default();
+        let names = subscriber.names.clone();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut document = Document::new(window, size2(100.0, 100.0));
+            let layout = document.get_layout();
+            paint(&mut RecordingRenderer::default(), &document, &layout, None);
+        });
+
+        // One span for each stage, rather than for each element.
+        assert_eq!(*names.lock().unwrap(), vec!["layout", "paint"]);
+    }
+}
@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+/// Timings and counts for a single rendered frame. Collecting these
+/// only takes a few clock reads per frame, so it's always enabled.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Time spent computing styles and layout.
+    pub layout_time: Duration,
+    /// Time spent walking the layout tree to build the display list.
+    pub paint_time: Duration,
+    /// Time spent submitting the display list and waiting for the
+    /// backend to draw it.
+    pub present_time: Duration,
+    /// The number of items in the display list, not counting clips.
+    pub items: usize,
+    /// Time from when the frame was first needed, such as by an input
+    /// event or a DOM update, until it was presented.
+    pub latency: Duration,
+}
@@ -0,0 +1,77 @@
+//! How glyphs are rasterized, trading sharpness for smoothness.
+
+/// How the edges of glyphs are smoothed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextAntialiasing {
+    /// Smooths each color channel of a pixel separately, which is the
+    /// sharpest on LCD screens, but shows color fringes on others and
+    /// when the screen is rotated.
+    Subpixel,
+    /// Smooths whole pixels.
+    Grayscale,
+    /// No smoothing, so glyphs have jagged edges.
+    None,
+}
+
+/// How strongly the outlines of glyphs are fitted to the pixel grid.
+/// Hinting makes small text crisper on low-DPI screens, at the cost of
+/// distorting the shapes of glyphs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextHinting {
+    None,
+    /// Only fits glyphs vertically, which keeps their shapes.
+    Light,
+    Full,
+}
+
+/// How text is rasterized in every window, set with
+/// `Runtime::text_rendering()`.
+///
+/// The default follows each platform's usual look: subpixel
+/// antialiasing with full hinting on Windows, grayscale antialiasing
+/// without hinting on macOS, and subpixel antialiasing with light
+/// hinting elsewhere. Windows and macOS hint with their own font
+/// rasterizers, so `hinting` only has an effect on other platforms.
+///
+/// Rendering a document without a window, such as in tests, always uses
+/// `TextRendering::deterministic()`, so that its output doesn't depend
+/// on the platform.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextRendering {
+    pub antialiasing: TextAntialiasing,
+    pub hinting: TextHinting,
+}
+
+impl TextRendering {
+    /// Grayscale antialiasing without hinting, which rasterizes the same
+    /// way on every platform and screen.
+    pub fn deterministic() -> TextRendering {
+        TextRendering {
+            antialiasing: TextAntialiasing::Grayscale,
+            hinting: TextHinting::None,
+        }
+    }
+}
+
+impl Default for TextRendering {
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        TextRendering {
+            antialiasing: TextAntialiasing::Subpixel,
+            hinting: TextHinting::Full,
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn default() -> Self {
+        TextRendering::deterministic()
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn default() -> Self {
+        TextRendering {
+            antialiasing: TextAntialiasing::Subpixel,
+            hinting: TextHinting::Light,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::dirty::DirtyRegion;
+use super::display_list::DisplayListBackend;
+use crate::layout::LogicalPoint;
+use gleam::gl;
+use std::rc::Rc;
+use std::sync::mpsc;
+use webrender::{
+    api::{
+        units::DeviceIntRect, units::DevicePixel, ColorF, DocumentId, Epoch, HitTestFlags,
+        PipelineId, RenderApi, RenderNotifier,
+    },
+    euclid::{Point2D, Scale, Size2D},
+    Renderer as WrRenderer, RendererOptions,
+};
+use winit::event_loop::EventLoopProxy;
+
+/// Used to wait for frames to be ready in Webrender.
+#[derive(Clone)]
+struct Notifier {
+    events_proxy: EventLoopProxy<()>,
+    tx: mpsc::Sender<()>,
+}
+
+impl RenderNotifier for Notifier {
+    fn clone(&self) -> Box<dyn RenderNotifier> {
+        Box::new(Clone::clone(self))
+    }
+
+    fn wake_up(&self) {
+        self.tx.send(()).unwrap();
+        let _ = self.events_proxy.send_event(());
+    }
+
+    fn new_frame_ready(&self, _: DocumentId, _: bool, _: bool, _: Option<u64>) {
+        self.wake_up();
+    }
+}
+
+/// Renders through Webrender into the current GL context. Paint
+/// commands are collected into a display list, which is sent along
+/// with any new font resources when the frame is presented.
+pub struct WebrenderBackend {
+    api: RenderApi,
+    document_id: DocumentId,
+    rx: mpsc::Receiver<()>,
+    renderer: Option<WrRenderer>,
+    display_list: DisplayListBackend,
+    client_size: Size2D<i32, DevicePixel>,
+    dpi_scale: f32,
+}
+
+impl WebrenderBackend {
+    pub fn new(
+        gl: Rc<dyn gl::Gl>,
+        events_proxy: EventLoopProxy<()>,
+        client_size: Size2D<i32, DevicePixel>,
+        dpi_scale: f32,
+    ) -> Result<WebrenderBackend, String> {
+        let (tx, rx) = mpsc::channel();
+        let notifier = Box::new(Notifier { events_proxy, tx });
+
+        let (renderer, sender) = WrRenderer::new(
+            gl,
+            notifier,
+            RendererOptions {
+                clear_color: Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
+                device_pixel_ratio: dpi_scale,
+                ..Default::default()
+            },
+            None,
+            client_size,
+        )
+        .map_err(|err| format!("can't start Webrender: {:?}", err))?;
+        let api = sender.create_api();
+        let document_id = api.add_document(client_size, 0);
+        let display_list = DisplayListBackend::new(PipelineId(0, 0), api.get_namespace_id());
+
+        Ok(WebrenderBackend {
+            api,
+            document_id,
+            rx,
+            renderer: Some(renderer),
+            display_list,
+            client_size,
+            dpi_scale,
+        })
+    }
+
+    pub fn deinit(&mut self) {
+        self.renderer.take().unwrap().deinit();
+    }
+
+    pub fn resize(&mut self, client_size: Size2D<i32, DevicePixel>, dpi_scale: f32) {
+        self.client_size = client_size;
+        self.dpi_scale = dpi_scale;
+    }
+
+    pub fn element_at(&mut self, position: LogicalPoint) -> Option<u64> {
+        self.api
+            .hit_test(
+                self.document_id,
+                None,
+                position * Scale::new(1.0),
+                HitTestFlags::empty(),
+            )
+            .items
+            .first()
+            .map(|item| item.tag.0)
+    }
+
+    /// The display list of the next frame, which is painted into.
+    pub(crate) fn display_list(&mut self) -> &mut DisplayListBackend {
+        &mut self.display_list
+    }
+
+    /// Webrender can't present part of a frame here, so any change
+    /// repaints the whole window. Clean frames are skipped entirely,
+    /// keeping any resource updates for the next frame.
+    pub fn present(&mut self, dirty: &DirtyRegion) -> bool {
+        if *dirty == DirtyRegion::Clean {
+            self.display_list.discard();
+            return false;
+        }
+
+        let client_size = self.client_size;
+        let dpi_scale = Scale::new(self.dpi_scale);
+        let content_size = client_size.to_f32() / dpi_scale;
+
+        let pipeline_id = self.display_list.pipeline_id();
+        let (display_list, mut transaction) = self.display_list.finish();
+        transaction.set_display_list(
+            Epoch(0),
+            None,
+            content_size,
+            (pipeline_id, display_list),
+            true,
+        );
+        transaction.set_root_pipeline(pipeline_id);
+        transaction.generate_frame();
+        self.api.set_document_view(
+            self.document_id,
+            DeviceIntRect::new(Point2D::zero(), client_size),
+            dpi_scale.get(),
+        );
+        self.api.send_transaction(self.document_id, transaction);
+        self.rx.recv().unwrap();
+        if let Some(ref mut renderer) = &mut self.renderer {
+            renderer.update();
+            let _ = renderer.render(client_size);
+            let _ = renderer.flush_pipeline_info();
+        }
+        true
+    }
+}
@@ -0,0 +1,204 @@
+//! Blinking the caret of the focused text area.
+
+use crate::layout::LogicalRect;
+use std::time::{Duration, Instant};
+
+/// How the caret of a focused text area blinks, set with
+/// `Runtime::caret_blink()`. While blinking, the caret is solid right
+/// after each keystroke, and when it moves, and it stops blinking, and
+/// stays solid, while its window isn't focused.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CaretBlink {
+    /// The caret is shown for `interval`, then hidden for `interval`.
+    Blink { interval: Duration },
+    /// The caret never blinks.
+    Steady,
+}
+
+impl CaretBlink {
+    /// The platform's setting, where it's detected, and otherwise
+    /// blinking every 530 milliseconds, the default on Windows.
+    pub fn system() -> CaretBlink {
+        system_caret_blink().unwrap_or(CaretBlink::Blink {
+            interval: Duration::from_millis(530),
+        })
+    }
+}
+
+/// Reads the GTK settings which desktops on Linux and the BSDs use for
+/// the caret. Other platforms don't have a way to read them without
+/// linking their UI frameworks.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_caret_blink() -> Option<CaretBlink> {
+    gtk_caret_blink(&super::motion::gtk_settings()?)
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn system_caret_blink() -> Option<CaretBlink> {
+    None
+}
+
+/// The blinking set by `gtk-cursor-blink` and `gtk-cursor-blink-time`
+/// in GTK's `settings.ini`, where the time is for a whole cycle of
+/// being shown and hidden. Returns `None` when neither is set.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn gtk_caret_blink(settings: &str) -> Option<CaretBlink> {
+    let mut blink = None;
+    let mut time = None;
+    for line in settings.lines() {
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        match key {
+            "gtk-cursor-blink" => blink = Some(value != "false" && value != "0"),
+            "gtk-cursor-blink-time" => time = value.parse::<u64>().ok(),
+            _ => {}
+        }
+    }
+    match (blink, time) {
+        (Some(false), _) => Some(CaretBlink::Steady),
+        (_, Some(time)) => Some(CaretBlink::Blink {
+            interval: Duration::from_millis(time / 2),
+        }),
+        _ => None,
+    }
+}
+
+/// Follows the caret of the focused element in one window, and whether
+/// it's shown at a given time.
+pub(crate) struct CaretBlinker {
+    blink: CaretBlink,
+    /// The caret as of the latest layout, if an editable element is
+    /// focused.
+    caret: Option<LogicalRect>,
+    window_focused: bool,
+    /// When the caret was last made solid, which blinking counts from.
+    since: Instant,
+}
+
+impl CaretBlinker {
+    pub fn new(blink: CaretBlink, now: Instant) -> CaretBlinker {
+        CaretBlinker {
+            blink,
+            caret: None,
+            window_focused: true,
+            since: now,
+        }
+    }
+
+    /// Takes the caret as of the latest layout. When it moves, appears
+    /// or disappears, it's solid again.
+    pub fn caret(&mut self, caret: Option<LogicalRect>, now: Instant) {
+        if caret != self.caret {
+            self.caret = caret;
+            self.since = now;
+        }
+    }
+
+    /// A key was pressed or a character typed, which makes the caret
+    /// solid even if it didn't move.
+    pub fn typed(&mut self, now: Instant) {
+        self.since = now;
+    }
+
+    pub fn window_focused(&mut self, focused: bool, now: Instant) {
+        self.window_focused = focused;
+        self.since = now;
+    }
+
+    /// How long each phase lasts, if the caret is blinking.
+    fn interval(&self) -> Option<Duration> {
+        match self.blink {
+            CaretBlink::Blink { interval }
+                if interval > Duration::from_secs(0)
+                    && self.caret.is_some()
+                    && self.window_focused =>
+            {
+                Some(interval)
+            }
+            _ => None,
+        }
+    }
+
+    /// How many phases have passed since the caret was made solid.
+    fn phases(&self, interval: Duration, now: Instant) -> u32 {
+        let elapsed = now.saturating_duration_since(self.since);
+        (elapsed.as_nanos() / interval.as_nanos()) as u32
+    }
+
+    pub fn visible(&self, now: Instant) -> bool {
+        match self.interval() {
+            Some(interval) => self.phases(interval, now) % 2 == 0,
+            None => true,
+        }
+    }
+
+    /// When the caret is next shown or hidden, or `None` when it isn't
+    /// blinking.
+    pub fn deadline(&self, now: Instant) -> Option<Instant> {
+        let interval = self.interval()?;
+        Some(self.since + interval * (self.phases(interval, now) + 1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{gtk_caret_blink, CaretBlink, CaretBlinker};
+    use crate::layout::LogicalRect;
+    use euclid::{point2, size2};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn caret_blinks_at_interval() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let caret = LogicalRect::new(point2(10.0, 0.0), size2(1.0, 16.0));
+        let mut blinker = CaretBlinker::new(CaretBlink::Blink { interval: ms(500) }, start);
+
+        // Nothing is focused.
+        assert!(blinker.visible(start + ms(600)));
+        assert_eq!(blinker.deadline(start), None);
+
+        blinker.caret(Some(caret), start);
+        assert!(blinker.visible(start + ms(499)));
+        assert!(!blinker.visible(start + ms(500)));
+        assert!(!blinker.visible(start + ms(999)));
+        assert!(blinker.visible(start + ms(1000)));
+        assert_eq!(blinker.deadline(start + ms(700)), Some(start + ms(1000)));
+
+        // A keystroke while it's hidden shows it at once, and blinking
+        // starts over.
+        blinker.typed(start + ms(1600));
+        assert!(blinker.visible(start + ms(1600)));
+        assert!(!blinker.visible(start + ms(2100)));
+
+        // Losing focus stops the blinking, with the caret shown.
+        blinker.caret(None, start + ms(2200));
+        assert!(blinker.visible(start + ms(2700)));
+        assert_eq!(blinker.deadline(start + ms(2700)), None);
+        blinker.caret(Some(caret), start + ms(3000));
+        blinker.window_focused(false, start + ms(3000));
+        assert!(blinker.visible(start + ms(3500)));
+        assert_eq!(blinker.deadline(start + ms(3500)), None);
+
+        let mut steady = CaretBlinker::new(CaretBlink::Steady, start);
+        steady.caret(Some(caret), start);
+        assert!(steady.visible(start + ms(500)));
+        assert_eq!(steady.deadline(start), None);
+    }
+
+    #[test]
+    fn reads_gtk_settings() {
+        assert_eq!(
+            gtk_caret_blink("[Settings]\ngtk-cursor-blink-time = 1000\n"),
+            Some(CaretBlink::Blink {
+                interval: Duration::from_millis(500)
+            })
+        );
+        assert_eq!(
+            gtk_caret_blink("[Settings]\ngtk-cursor-blink=false\ngtk-cursor-blink-time=1000"),
+            Some(CaretBlink::Steady)
+        );
+        assert_eq!(gtk_caret_blink("[Settings]\ngtk-theme-name=Adwaita"), None);
+    }
+}
@@ -0,0 +1,99 @@
+//! Following the OS preference for a light or dark interface.
+
+use crate::dom::ColorScheme;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct SchemeState {
+    /// The scheme components see, and which was last delivered.
+    current: ColorScheme,
+    /// The latest scheme reported by a window, if it hasn't been
+    /// delivered yet.
+    pending: Option<ColorScheme>,
+}
+
+/// The system color scheme, shared between the windows and components.
+///
+/// Each window reports the scheme, so a single toggle usually arrives
+/// once per window, and some platforms send it several times. Reports
+/// are only delivered once per frame, after the frame's events, and
+/// only if the scheme differs from the last one delivered, so apps see
+/// one event per actual change.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ColorSchemeState(Arc<Mutex<SchemeState>>);
+
+impl ColorSchemeState {
+    pub fn current(&self) -> ColorScheme {
+        self.0.lock().unwrap().current
+    }
+
+    pub fn report(&self, scheme: ColorScheme) {
+        self.0.lock().unwrap().pending = Some(scheme);
+    }
+
+    /// Returns the new scheme, if it changed since it was last taken.
+    pub fn take_change(&self) -> Option<ColorScheme> {
+        let mut state = self.0.lock().unwrap();
+        let scheme = state.pending.take()?;
+        if scheme == state.current {
+            return None;
+        }
+        state.current = scheme;
+        Some(scheme)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColorSchemeState;
+    use crate::document::Document;
+    use crate::dom::ColorScheme;
+    use crate::mox_impl::{elt, event};
+    use euclid::size2;
+    use moxie::runtime::Runtime;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn scheme_change_fires_event() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let log = events.clone();
+        let window = Runtime::new().run_once(|| {
+            let log = log.clone();
+            elt::window()
+                .on_event(event::on_color_scheme_change(), move |event| {
+                    log.lock().unwrap().push(event.scheme);
+                })
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        let state = ColorSchemeState::default();
+        let mut deliver = |document: &mut Document| {
+            if let Some(scheme) = state.take_change() {
+                document.color_scheme(scheme);
+            }
+        };
+        assert_eq!(state.current(), ColorScheme::Light);
+
+        // Several reports of the same change only fire once.
+        state.report(ColorScheme::Dark);
+        state.report(ColorScheme::Dark);
+        deliver(&mut document);
+        state.report(ColorScheme::Dark);
+        deliver(&mut document);
+        assert_eq!(state.current(), ColorScheme::Dark);
+        assert_eq!(*events.lock().unwrap(), vec![ColorScheme::Dark]);
+
+        // Toggling back and forth within a frame doesn't fire at all.
+        state.report(ColorScheme::Light);
+        state.report(ColorScheme::Dark);
+        deliver(&mut document);
+        assert_eq!(*events.lock().unwrap(), vec![ColorScheme::Dark]);
+
+        state.report(ColorScheme::Light);
+        deliver(&mut document);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![ColorScheme::Dark, ColorScheme::Light]
+        );
+    }
+}
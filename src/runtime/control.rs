@@ -0,0 +1,82 @@
+//! Commands sent to windows from components and event handlers. They're
+//! queued, and carried out on the main thread between frames.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use winit::event_loop::EventLoopProxy;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum WindowCommand {
+    Minimize,
+    Maximize,
+    Restore,
+    Close,
+}
+
+#[derive(Default)]
+struct CommandQueue {
+    commands: Vec<(usize, WindowCommand)>,
+    proxy: Option<EventLoopProxy<()>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct WindowCommands(Arc<Mutex<CommandQueue>>);
+
+impl fmt::Debug for WindowCommands {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "WindowCommands {{ ... }}")
+    }
+}
+
+impl WindowCommands {
+    pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        self.0.lock().unwrap().proxy = Some(proxy);
+    }
+
+    fn push(&self, window: usize, command: WindowCommand) {
+        let mut queue = self.0.lock().unwrap();
+        queue.commands.push((window, command));
+        if let Some(proxy) = &queue.proxy {
+            let _ = proxy.send_event(());
+        }
+    }
+
+    pub(crate) fn take(&self) -> Vec<(usize, WindowCommand)> {
+        std::mem::take(&mut self.0.lock().unwrap().commands)
+    }
+}
+
+/// Controls one of the application's windows, for things like custom
+/// title bars. Get it with `Runtime::window()`.
+///
+/// Windows are still declared by the tree: a window opens when a
+/// `<window>` is added to the app, and closes when it's removed.
+/// Requests to windows which don't exist are ignored.
+#[derive(Clone, Debug)]
+pub struct WindowControl {
+    pub(crate) window: usize,
+    pub(crate) commands: WindowCommands,
+}
+
+impl WindowControl {
+    pub fn minimize(&self) {
+        self.commands.push(self.window, WindowCommand::Minimize);
+    }
+
+    pub fn maximize(&self) {
+        self.commands.push(self.window, WindowCommand::Maximize);
+    }
+
+    /// Un-minimizes and un-maximizes the window.
+    pub fn restore(&self) {
+        self.commands.push(self.window, WindowCommand::Restore);
+    }
+
+    /// Asks the window to close, the same as the user pressing its
+    /// close button. This calls its `on_close` handler, or shuts down
+    /// the application if there isn't one. The window only closes once
+    /// it's removed from the tree.
+    pub fn close(&self) {
+        self.commands.push(self.window, WindowCommand::Close);
+    }
+}
@@ -0,0 +1,159 @@
+//! Moving windows by dragging the parts of them marked with the
+//! `drag_region` attribute, for custom title bars.
+
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalPosition;
+
+/// Two presses on a drag region within this time toggle maximizing the
+/// window, like double clicking a title bar.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+
+/// What dragging asks the OS to do with the window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum DragRequest {
+    /// Moves the outer top left corner of the window.
+    MoveTo(PhysicalPosition<i32>),
+    ToggleMaximize,
+}
+
+/// Follows the left mouse button on the drag regions of a window.
+/// Positions are physical, and cursor positions are relative to the
+/// window, like winit reports them.
+#[derive(Default)]
+pub(crate) struct DragTracker {
+    /// Where in the window the cursor grabbed it, while dragging.
+    grab: Option<PhysicalPosition<f64>>,
+    last_press: Option<Instant>,
+}
+
+impl DragTracker {
+    /// The button was pressed, where `in_region` is whether the cursor
+    /// is over a drag region. Returns whether the drag tracker took the
+    /// press, in which case it shouldn't go to the DOM.
+    pub fn press(
+        &mut self,
+        in_region: bool,
+        cursor: PhysicalPosition<f64>,
+        now: Instant,
+    ) -> (bool, Option<DragRequest>) {
+        if !in_region {
+            self.last_press = None;
+            return (false, None);
+        }
+        let double = self
+            .last_press
+            .map_or(false, |last| now.duration_since(last) <= DOUBLE_CLICK_TIME);
+        if double {
+            self.last_press = None;
+            return (true, Some(DragRequest::ToggleMaximize));
+        }
+        self.last_press = Some(now);
+        self.grab = Some(cursor);
+        (true, None)
+    }
+
+    /// The cursor moved, while the window's outer position is `window`.
+    /// While dragging, the window is moved so that the point it was
+    /// grabbed at follows the cursor.
+    pub fn moved(
+        &mut self,
+        cursor: PhysicalPosition<f64>,
+        window: PhysicalPosition<i32>,
+    ) -> Option<DragRequest> {
+        let grab = self.grab?;
+        let x = window.x + (cursor.x - grab.x).round() as i32;
+        let y = window.y + (cursor.y - grab.y).round() as i32;
+        if (x, y) == (window.x, window.y) {
+            return None;
+        }
+        Some(DragRequest::MoveTo(PhysicalPosition::new(x, y)))
+    }
+
+    /// Ends the drag when the button is released, returning whether
+    /// the release belongs to it.
+    pub fn release(&mut self) -> bool {
+        self.grab.take().is_some()
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.grab.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DragRequest, DragTracker};
+    use crate::document::Document;
+    use crate::mox_impl::{attr, elt};
+    use crate::style::sized_box;
+    use euclid::{point2, size2};
+    use moxie::runtime::Runtime;
+    use std::time::{Duration, Instant};
+    use winit::dpi::PhysicalPosition;
+
+    #[test]
+    fn drag_moves_window() {
+        let bar = sized_box("bar", 100.0, 20.0);
+        let close = sized_box("close", 20.0, 20.0);
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(
+                    elt::view()
+                        .set_attr(attr::style(), bar)
+                        .set_attr(attr::drag_region(), true)
+                        .add_child(elt::button().set_attr(attr::style(), close).build())
+                        .build(),
+                )
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let in_region = |x, y| {
+            document
+                .element_at(point2(x, y))
+                .map_or(false, |id| document.in_drag_region(id))
+        };
+
+        // The button in the title bar is clicked as usual.
+        let start = Instant::now();
+        let mut drag = DragTracker::default();
+        let window = PhysicalPosition::new(100, 100);
+        assert_eq!(
+            drag.press(
+                in_region(10.0, 10.0),
+                PhysicalPosition::new(10.0, 10.0),
+                start
+            ),
+            (false, None)
+        );
+        assert_eq!(drag.moved(PhysicalPosition::new(30.0, 15.0), window), None);
+        assert!(!drag.release());
+
+        assert_eq!(
+            drag.press(
+                in_region(50.0, 10.0),
+                PhysicalPosition::new(50.0, 10.0),
+                start
+            ),
+            (true, None)
+        );
+        assert_eq!(
+            drag.moved(PhysicalPosition::new(70.0, 15.0), window),
+            Some(DragRequest::MoveTo(PhysicalPosition::new(120, 105)))
+        );
+        assert!(drag.release());
+        assert_eq!(drag.moved(PhysicalPosition::new(90.0, 50.0), window), None);
+
+        // Double clicking the title bar.
+        let later = start + Duration::from_millis(200);
+        assert_eq!(
+            drag.press(
+                in_region(50.0, 10.0),
+                PhysicalPosition::new(50.0, 10.0),
+                later
+            ),
+            (true, Some(DragRequest::ToggleMaximize))
+        );
+        assert!(!in_region(50.0, 50.0));
+    }
+}
@@ -0,0 +1,477 @@
+//! Recognizing long presses and swipes from the left mouse button, or
+//! touches and trackpad clicks which are reported as it, and pinches
+//! and rotations from two touches.
+
+use crate::dom::input::{PointerId, TouchPhase};
+use crate::dom::{GesturePhase, PinchEvent, RotateEvent, SwipeDirection};
+use crate::layout::LogicalPoint;
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+/// Thresholds for recognizing gestures, set with `Runtime::gestures()`.
+/// Distances are in logical pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GestureConfig {
+    /// How long the pointer has to be held for a long press.
+    pub long_press_time: Duration,
+    /// How far the pointer can move while being held before it's no
+    /// longer a long press.
+    pub long_press_slop: f32,
+    /// How far the pointer has to travel between being pressed and
+    /// released for a swipe.
+    pub swipe_distance: f32,
+    /// The slowest speed, in logical pixels per second, the pointer can
+    /// travel at for a swipe. Slower drags are ignored.
+    pub swipe_speed: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            long_press_time: Duration::from_millis(500),
+            long_press_slop: 8.0,
+            swipe_distance: 50.0,
+            swipe_speed: 300.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Gesture {
+    LongPress,
+    Swipe(SwipeDirection),
+    Pinch(PinchEvent),
+    Rotate(RotateEvent),
+}
+
+struct Press {
+    /// The element under the pointer when it was pressed, which gets
+    /// the gestures.
+    target: u64,
+    start: LogicalPoint,
+    time: Instant,
+    /// Whether it can still become a long press, which it can't once
+    /// the pointer moves away.
+    long_press: bool,
+}
+
+/// Follows the pointer while it's pressed. Only one gesture is
+/// recognized per press: a long press once it's held long enough, or a
+/// swipe when it's released after a fast drag. Moving the pointer
+/// beyond the slop cancels a pending long press, so a swipe never
+/// becomes one.
+#[derive(Default)]
+pub(crate) struct GestureRecognizer {
+    config: GestureConfig,
+    press: Option<Press>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer {
+            config,
+            press: None,
+        }
+    }
+
+    /// The pointer was pressed at `position`, over `target` if it's
+    /// over an element.
+    pub fn press(&mut self, target: Option<u64>, position: LogicalPoint, now: Instant) {
+        self.press = target.map(|target| Press {
+            target,
+            start: position,
+            time: now,
+            long_press: true,
+        });
+    }
+
+    /// Forgets the press, such as when the window loses focus while
+    /// it's held.
+    pub fn cancel(&mut self) {
+        self.press = None;
+    }
+
+    pub fn moved(&mut self, position: LogicalPoint) {
+        if let Some(press) = &mut self.press {
+            if (position - press.start).length() > self.config.long_press_slop {
+                press.long_press = false;
+            }
+        }
+    }
+
+    /// When a long press is due, if one is pending, for waking up the
+    /// event loop.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.press
+            .as_ref()
+            .filter(|press| press.long_press)
+            .map(|press| press.time + self.config.long_press_time)
+    }
+
+    /// Reports a long press once the pointer has been held long enough,
+    /// which ends the gesture.
+    pub fn poll(&mut self, now: Instant) -> Option<(u64, Gesture)> {
+        if now < self.deadline()? {
+            return None;
+        }
+        let press = self.press.take()?;
+        Some((press.target, Gesture::LongPress))
+    }
+
+    /// The pointer was released at `position`, which ends the gesture.
+    pub fn release(&mut self, position: LogicalPoint, now: Instant) -> Option<(u64, Gesture)> {
+        if let Some(long_press) = self.poll(now) {
+            return Some(long_press);
+        }
+        let press = self.press.take()?;
+        let travel = position - press.start;
+        let seconds = now.duration_since(press.time).as_secs_f32();
+        let fast = seconds == 0.0 || travel.length() / seconds >= self.config.swipe_speed;
+        if travel.length() < self.config.swipe_distance || !fast {
+            return None;
+        }
+        let direction = if travel.x.abs() >= travel.y.abs() {
+            if travel.x > 0.0 {
+                SwipeDirection::Right
+            } else {
+                SwipeDirection::Left
+            }
+        } else if travel.y > 0.0 {
+            SwipeDirection::Down
+        } else {
+            SwipeDirection::Up
+        };
+        Some((press.target, Gesture::Swipe(direction)))
+    }
+}
+
+struct TwoFingers {
+    /// The distance between the fingers when the gesture began.
+    distance: f32,
+    scale: f32,
+    /// The direction from the first finger to the second at the last
+    /// event, for following it across a half turn.
+    direction: f32,
+    angle: f32,
+    centroid: LogicalPoint,
+}
+
+/// Follows touches to recognize pinches and rotations from the first
+/// two fingers down. Further fingers are ignored. The gesture ends when
+/// either of the two lifts, and the finger left down can start another
+/// gesture with the next one.
+#[derive(Default)]
+pub(crate) struct TransformRecognizer {
+    /// The tracked touches, at most two, with where they last were.
+    touches: Vec<(PointerId, LogicalPoint)>,
+    gesture: Option<TwoFingers>,
+}
+
+impl TransformRecognizer {
+    /// Takes a touch, returning the pinch and rotation it makes, if any.
+    pub fn touch(
+        &mut self,
+        pointer: PointerId,
+        phase: TouchPhase,
+        position: LogicalPoint,
+    ) -> Option<(PinchEvent, RotateEvent)> {
+        let index = self.touches.iter().position(|(id, _)| *id == pointer);
+        match (phase, index) {
+            (TouchPhase::Started, None) if self.touches.len() < 2 => {
+                self.touches.push((pointer, position));
+                if self.touches.len() < 2 {
+                    return None;
+                }
+                let (distance, direction, centroid) = self.measure();
+                self.gesture = Some(TwoFingers {
+                    distance,
+                    scale: 1.0,
+                    direction,
+                    angle: 0.0,
+                    centroid,
+                });
+                Some(self.event(GesturePhase::Began, 1.0, 0.0))
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                self.touches[index].1 = position;
+                if self.gesture.is_none() {
+                    return None;
+                }
+                let (distance, direction, centroid) = self.measure();
+                let gesture = self.gesture.as_mut().unwrap();
+                let scale = if gesture.distance > 0.0 {
+                    distance / gesture.distance
+                } else {
+                    1.0
+                };
+                let mut turn = direction - gesture.direction;
+                if turn > PI {
+                    turn -= 2.0 * PI;
+                } else if turn < -PI {
+                    turn += 2.0 * PI;
+                }
+                let scale_delta = scale / gesture.scale;
+                gesture.scale = scale;
+                gesture.direction = direction;
+                gesture.angle += turn;
+                gesture.centroid = centroid;
+                Some(self.event(GesturePhase::Changed, scale_delta, turn))
+            }
+            (TouchPhase::Ended, Some(index)) | (TouchPhase::Cancelled, Some(index)) => {
+                self.touches.remove(index);
+                if self.gesture.is_none() {
+                    return None;
+                }
+                let event = self.event(GesturePhase::Ended, 1.0, 0.0);
+                self.gesture = None;
+                Some(event)
+            }
+            _ => None,
+        }
+    }
+
+    /// The distance and direction between the two fingers, and the
+    /// point midway between them.
+    fn measure(&self) -> (f32, f32, LogicalPoint) {
+        let (first, second) = (self.touches[0].1, self.touches[1].1);
+        let offset = second - first;
+        (
+            offset.length(),
+            offset.y.atan2(offset.x),
+            first.lerp(second, 0.5),
+        )
+    }
+
+    fn event(
+        &self,
+        phase: GesturePhase,
+        scale_delta: f32,
+        angle_delta: f32,
+    ) -> (PinchEvent, RotateEvent) {
+        let gesture = self.gesture.as_ref().unwrap();
+        (
+            PinchEvent {
+                phase,
+                centroid: gesture.centroid,
+                scale: gesture.scale,
+                scale_delta,
+            },
+            RotateEvent {
+                phase,
+                centroid: gesture.centroid,
+                angle: gesture.angle,
+                angle_delta,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Gesture, GestureConfig, GestureRecognizer, TransformRecognizer};
+    use crate::document::Document;
+    use crate::dom::input::{PointerId, TouchPhase};
+    use crate::dom::{GesturePhase, SwipeDirection};
+    use crate::mox_impl::{attr, elt, event};
+    use crate::style::sized_box;
+    use euclid::{point2, size2};
+    use moxie::runtime::Runtime;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn long_press() {
+        let start = Instant::now();
+        let mut gestures = GestureRecognizer::default();
+        gestures.press(Some(1), point2(10.0, 10.0), start);
+        assert_eq!(gestures.deadline(), Some(start + ms(500)));
+
+        // Wobbling within the slop is still holding still.
+        gestures.moved(point2(14.0, 13.0));
+        assert_eq!(gestures.poll(start + ms(400)), None);
+        assert_eq!(
+            gestures.poll(start + ms(500)),
+            Some((1, Gesture::LongPress))
+        );
+        assert_eq!(gestures.poll(start + ms(600)), None);
+        assert_eq!(gestures.deadline(), None);
+        assert_eq!(gestures.release(point2(14.0, 13.0), start + ms(700)), None);
+
+        // Released too soon.
+        gestures.press(Some(1), point2(10.0, 10.0), start);
+        assert_eq!(gestures.release(point2(10.0, 10.0), start + ms(300)), None);
+        assert_eq!(gestures.poll(start + ms(600)), None);
+
+        // Nothing is recognized away from any element.
+        gestures.press(None, point2(10.0, 10.0), start);
+        assert_eq!(gestures.poll(start + ms(600)), None);
+    }
+
+    #[test]
+    fn swipe_cancels_long_press() {
+        let config = GestureConfig {
+            long_press_time: ms(300),
+            swipe_distance: 40.0,
+            ..GestureConfig::default()
+        };
+        let start = Instant::now();
+        let mut gestures = GestureRecognizer::new(config);
+        gestures.press(Some(2), point2(100.0, 10.0), start);
+        gestures.moved(point2(80.0, 12.0));
+        assert_eq!(gestures.deadline(), None);
+        assert_eq!(gestures.poll(start + ms(300)), None);
+        assert_eq!(
+            gestures.release(point2(50.0, 15.0), start + ms(100)),
+            Some((2, Gesture::Swipe(SwipeDirection::Left)))
+        );
+
+        gestures.press(Some(2), point2(10.0, 10.0), start);
+        assert_eq!(
+            gestures.release(point2(12.0, 90.0), start + ms(150)),
+            Some((2, Gesture::Swipe(SwipeDirection::Down)))
+        );
+
+        // Too short, and too slow.
+        gestures.press(Some(2), point2(10.0, 10.0), start);
+        assert_eq!(gestures.release(point2(30.0, 10.0), start + ms(50)), None);
+        gestures.press(Some(2), point2(10.0, 10.0), start);
+        gestures.moved(point2(60.0, 10.0));
+        assert_eq!(gestures.release(point2(110.0, 10.0), start + ms(400)), None);
+    }
+
+    #[test]
+    fn gestures_bubble_to_handlers() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let (long_presses, swipes) = (events.clone(), events.clone());
+        let square = sized_box("square", 20.0, 20.0);
+        let (window, button) = Runtime::new().run_once(|| {
+            let button = elt::button().set_attr(attr::style(), square).build();
+            let (long_presses, swipes) = (long_presses.clone(), swipes.clone());
+            let window = elt::window()
+                .add_child(
+                    elt::view()
+                        .on_event(event::on_long_press(), move |_| {
+                            long_presses.lock().unwrap().push(None);
+                        })
+                        .on_event(event::on_swipe(), move |event| {
+                            swipes.lock().unwrap().push(Some(event.direction));
+                        })
+                        .add_child(button.clone())
+                        .build(),
+                )
+                .build();
+            (window, button)
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let target = document.element_at(point2(5.0, 5.0));
+        assert_eq!(target, Some(button.id()));
+
+        assert!(document.gesture(button.id(), Gesture::LongPress));
+        assert!(document.gesture(button.id(), Gesture::Swipe(SwipeDirection::Up)));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![None, Some(SwipeDirection::Up)]
+        );
+    }
+
+    #[test]
+    fn two_finger_transform() {
+        let (first, second, third) = (
+            PointerId::touch(0),
+            PointerId::touch(1),
+            PointerId::touch(2),
+        );
+        let mut transforms = TransformRecognizer::default();
+        assert_eq!(
+            transforms.touch(first, TouchPhase::Started, point2(40.0, 50.0)),
+            None
+        );
+        let (pinch, rotate) = transforms
+            .touch(second, TouchPhase::Started, point2(60.0, 50.0))
+            .unwrap();
+        assert_eq!(pinch.phase, GesturePhase::Began);
+        assert_eq!(pinch.centroid, point2(50.0, 50.0));
+        assert_eq!((pinch.scale, rotate.angle), (1.0, 0.0));
+
+        // A third finger doesn't take part.
+        assert_eq!(
+            transforms.touch(third, TouchPhase::Started, point2(0.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            transforms.touch(third, TouchPhase::Moved, point2(10.0, 0.0)),
+            None
+        );
+
+        // Turned a quarter turn clockwise while spreading to twice the
+        // distance.
+        let (pinch, rotate) = transforms
+            .touch(second, TouchPhase::Moved, point2(40.0, 90.0))
+            .unwrap();
+        assert_eq!(pinch.phase, GesturePhase::Changed);
+        assert_eq!((pinch.scale, pinch.scale_delta), (2.0, 2.0));
+        assert_eq!(pinch.centroid, point2(40.0, 70.0));
+        assert!((rotate.angle - std::f32::consts::FRAC_PI_2).abs() < 1.0e-5);
+        let (pinch, _) = transforms
+            .touch(first, TouchPhase::Moved, point2(40.0, 70.0))
+            .unwrap();
+        assert_eq!((pinch.scale, pinch.scale_delta), (1.0, 0.5));
+
+        // Lifting either finger ends the gesture where it was.
+        let (pinch, rotate) = transforms
+            .touch(first, TouchPhase::Ended, point2(40.0, 70.0))
+            .unwrap();
+        assert_eq!(pinch.phase, GesturePhase::Ended);
+        assert_eq!(pinch.scale, 1.0);
+        assert!((rotate.angle - std::f32::consts::FRAC_PI_2).abs() < 1.0e-5);
+        assert_eq!(
+            transforms.touch(second, TouchPhase::Moved, point2(0.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn spread_pinches_view() {
+        let scales = Arc::new(Mutex::new(vec![]));
+        let square = sized_box("square", 100.0, 100.0);
+        let (window, button) = Runtime::new().run_once(|| {
+            let button = elt::button().set_attr(attr::style(), square).build();
+            let scales = scales.clone();
+            let window = elt::window()
+                .add_child(
+                    elt::view()
+                        .on_event(event::on_pinch(), move |event| {
+                            scales.lock().unwrap().push((event.phase, event.scale));
+                        })
+                        .add_child(button.clone())
+                        .build(),
+                )
+                .build();
+            (window, button)
+        });
+        let mut document = Document::new(window, size2(200.0, 200.0));
+        document.get_layout();
+        assert_eq!(document.element_at(point2(50.0, 50.0)), Some(button.id()));
+
+        let (first, second) = (PointerId::touch(0), PointerId::touch(1));
+        document.touch(first, TouchPhase::Started, point2(45.0, 50.0));
+        document.touch(second, TouchPhase::Started, point2(55.0, 50.0));
+        document.touch(first, TouchPhase::Moved, point2(30.0, 50.0));
+        document.touch(second, TouchPhase::Moved, point2(70.0, 50.0));
+        document.touch(second, TouchPhase::Ended, point2(70.0, 50.0));
+        assert_eq!(
+            *scales.lock().unwrap(),
+            vec![
+                (GesturePhase::Began, 1.0),
+                (GesturePhase::Changed, 2.5),
+                (GesturePhase::Changed, 4.0),
+                (GesturePhase::Ended, 4.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use super::{ElementPath, MainEvent, Runtime, RuntimeEvent, RuntimeState};
+use crate::dom::node::NodeRef;
+use crate::layout::LogicalPoint;
+use crate::render::RenderPath;
+#[cfg(feature = "webrender-interop")]
+use crate::render::{DisplayListFrame, DisplayListTarget};
+use log::debug;
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use winit::{
+    event::Event,
+    event_loop::{ControlFlow, EventLoop},
+    platform::desktop::EventLoopExtDesktop,
+};
+
+/// An application driven by a loop the host owns, such as a game loop
+/// or another GUI toolkit, created with `Runtime::into_hosted()`.
+///
+/// Each tick, the host should call `pump_events()` and then
+/// `render_once()`:
+///
+/// - `pump_events()` processes the OS events which are pending, without
+///   waiting for more. Input is sent to the DOM, and redraws requested
+///   by the OS are handled immediately, since they mean the window's
+///   contents were lost. Root component updates are deferred to the
+///   next `render_once()`.
+/// - `render_once()` runs the root component if any input changed
+///   something, and renders the windows whose contents changed.
+///
+/// Both must be called on the thread which created the runtime, which
+/// has to be the main thread on some platforms. Once
+/// `pump_events()` returns `false`, the application has shut down and
+/// the hosted runtime should be dropped.
+pub struct HostedRuntime {
+    runtime: Runtime,
+    event_loop: EventLoop<()>,
+    thread: Option<JoinHandle<()>>,
+    sender: Sender<MainEvent>,
+    needs_update: bool,
+}
+
+impl HostedRuntime {
+    pub(super) fn new(
+        runtime: Runtime,
+        event_loop: EventLoop<()>,
+        thread: JoinHandle<()>,
+        sender: Sender<MainEvent>,
+    ) -> HostedRuntime {
+        HostedRuntime {
+            runtime,
+            event_loop,
+            thread: Some(thread),
+            sender,
+            needs_update: true,
+        }
+    }
+
+    /// Processes the pending OS events, returning whether the
+    /// application is still running. Once a handler asks to shut down,
+    /// this returns `false` right away.
+    pub fn pump_events(&mut self) -> bool {
+        let HostedRuntime {
+            runtime,
+            event_loop,
+            sender,
+            needs_update,
+            ..
+        } = self;
+        runtime.enter_contexts(sender, |runtime| {
+            event_loop.run_return(|event, _target, control_flow| {
+                *control_flow = match event {
+                    Event::RedrawEventsCleared => ControlFlow::Exit,
+                    _ => ControlFlow::Poll,
+                };
+                *needs_update |= runtime.handle(event);
+            });
+        });
+        // Handlers may have asked to shut down, which shouldn't wait for
+        // the next `render_once()`.
+        if self.runtime.poll_shutdown() {
+            self.runtime.timers.clear();
+            self.runtime.tasks.clear();
+            return false;
+        }
+        true
+    }
+
+    /// Updates the DOM if anything changed since the last call, and
+    /// renders the windows that need it.
+    pub fn render_once(&mut self) {
+        if std::mem::replace(&mut self.needs_update, false) {
+            let HostedRuntime {
+                runtime,
+                event_loop,
+                sender,
+                ..
+            } = self;
+            runtime.enter_contexts(sender, |runtime| runtime.update_runtime(event_loop));
+        }
+        self.runtime.report_frames();
+    }
+
+    /// Brings every window up to date before returning, for tests which
+    /// change state and then check the result without waiting:
+    ///
+    /// 1. The pending OS events are processed, like in `pump_events()`.
+    /// 2. Replayed input, cursor moves, gestures, timers and futures
+    ///    which are due run, followed by window commands, color scheme
+    ///    changes, window metrics and resizes from the last layout.
+    /// 3. The root component runs, even if nothing asked for an update,
+    ///    so that state changed from outside of the runtime shows up,
+    ///    and the DOM is reconciled.
+    /// 4. Every window is laid out, painted and presented, and frame,
+    ///    first paint and layout callbacks are called.
+    ///
+    /// Presenting waits for Webrender to finish the frame, so once this
+    /// returns, `find_by_id()` and the windows' contents reflect the
+    /// new tree. Returns whether the application is still running.
+    pub fn render_and_sync(&mut self) -> bool {
+        if !self.pump_events() {
+            return false;
+        }
+        self.needs_update = false;
+        let HostedRuntime {
+            runtime,
+            event_loop,
+            sender,
+            ..
+        } = self;
+        runtime.enter_contexts(sender, |runtime| {
+            if let Some(app) = runtime.sync_app() {
+                runtime.reconcile(app, event_loop);
+            }
+            runtime.render_all();
+        });
+        self.runtime.report_frames();
+        self.is_running()
+    }
+
+    /// See `Runtime::find_by_id()`.
+    pub fn find_by_id(&self, id: &str) -> Option<ElementPath> {
+        self.runtime.find_by_id(id)
+    }
+
+    /// See `Runtime::query_all()`.
+    pub fn query_all(&self, predicate: impl Fn(NodeRef) -> bool) -> Vec<ElementPath> {
+        self.runtime.query_all(predicate)
+    }
+
+    /// See `Runtime::render_path()`.
+    pub fn render_path(&self, window: usize) -> Option<RenderPath> {
+        self.runtime.render_path(window)
+    }
+
+    /// See `Runtime::to_local()`.
+    pub fn to_local(&self, element: &ElementPath, point: LogicalPoint) -> Option<LogicalPoint> {
+        self.runtime.to_local(element, point)
+    }
+
+    /// Builds the current display list of the window at `window`, in
+    /// the order the windows were created, for a host which composites
+    /// it itself. Returns `None` if there's no such window. This lays
+    /// out the window if needed, but doesn't update the DOM or present
+    /// anything, so `render_once()` should still be called each tick.
+    /// See `DisplayListTarget` for the caveats.
+    #[cfg(feature = "webrender-interop")]
+    pub fn build_display_list(
+        &mut self,
+        window: usize,
+        target: &mut DisplayListTarget,
+    ) -> Option<DisplayListFrame> {
+        let window_id = self.runtime.window_ids.get(window)?;
+        let window = self.runtime.windows.get_mut(window_id)?;
+        Some(window.build_display_list(target))
+    }
+
+    pub fn is_running(&self) -> bool {
+        !matches!(self.runtime.state, RuntimeState::Shutdown)
+    }
+}
+
+impl Drop for HostedRuntime {
+    fn drop(&mut self) {
+        self.runtime.timers.clear();
+        self.runtime.tasks.clear();
+        if let RuntimeState::Running { sender, .. } = &self.runtime.state {
+            let _ = sender.send(RuntimeEvent::Shutdown);
+        }
+        if let Some(thread) = self.thread.take() {
+            debug!("Waiting for runtime thread to exit");
+            thread.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mox_impl::{attr, elt};
+    use crate::render::{FrameCounter, RenderPath};
+    use crate::runtime::Runtime;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    // Tests which open windows need a display, so they only run with
+    // `cargo test -- --ignored --test-threads=1`.
+    #[test]
+    #[ignore]
+    fn pumps_and_renders() {
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+        let mut hosted = Runtime::new(|| {
+            let view = elt::view().set_attr(attr::id(), "content").build();
+            elt::app()
+                .add_child(elt::window().add_child(view).build())
+                .build()
+        })
+        .renderer(move |_| FrameCounter(counter.clone()))
+        .into_hosted();
+
+        // The window is created by the first `render_once()`, and painted
+        // once the OS asks for it in a later `pump_events()`.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while frames.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+            assert!(hosted.pump_events());
+            hosted.render_once();
+        }
+        assert!(frames.load(Ordering::SeqCst) >= 1);
+        assert_eq!(hosted.render_path(0), Some(RenderPath::Custom));
+        assert_eq!(hosted.render_path(1), None);
+        assert!(hosted.find_by_id("content").is_some());
+        assert!(hosted.is_running());
+    }
+}
@@ -0,0 +1,671 @@
+//! Decoding the images shown by `<image>` elements on worker threads,
+//! so that large images don't hold up the frame they first appear in,
+//! and playing the frames of animated ones.
+
+use crate::dom::ImageSource;
+use image::{AnimationDecoder, Frame, ImageFormat, RgbaImage};
+use log::warn;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use winit::event_loop::EventLoopProxy;
+
+/// The most threads decoding at once.
+const WORKERS: usize = 4;
+
+/// How many images are kept decoded once nothing shows them, so that
+/// showing one again soon after doesn't decode it again. Past this, the
+/// least recently used are dropped, along with the bytes of their
+/// sources, and decoded again if they're shown later.
+const MAX_UNUSED_IMAGES: usize = 32;
+
+/// Decoded pixels, ready to be uploaded. The color channels are
+/// premultiplied by alpha.
+#[derive(PartialEq)]
+pub struct DecodedImage {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Debug for DecodedImage {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "DecodedImage({}x{})", self.width, self.height)
+    }
+}
+
+/// The frames of an animated image, each with how long it's shown.
+#[derive(Debug)]
+pub(crate) struct Animation {
+    pub frames: Vec<(Arc<DecodedImage>, Duration)>,
+    /// How many times the frames play through, or `None` to repeat
+    /// them forever.
+    pub plays: Option<u32>,
+}
+
+/// What decoding an image gives.
+#[derive(Debug)]
+pub(crate) enum Decoded {
+    Still(DecodedImage),
+    Animated(Animation),
+}
+
+impl From<DecodedImage> for Decoded {
+    fn from(image: DecodedImage) -> Self {
+        Decoded::Still(image)
+    }
+}
+
+type Decoder = dyn Fn(&ImageSource) -> Result<Decoded, String> + Send + Sync;
+
+enum Entry {
+    Pending,
+    Ready(Arc<DecodedImage>),
+    Playing(Playback),
+    Failed,
+}
+
+/// An entry, and the last call to `retain()` it was used by.
+struct Cached {
+    entry: Entry,
+    last_used: u64,
+}
+
+/// Where an animated image is in its frames.
+struct Playback {
+    animation: Animation,
+    frame: usize,
+    /// How many times the frames have played through.
+    played: u32,
+    /// When the current frame is replaced by the next one, or `None`
+    /// while the animation is paused or has finished.
+    due: Option<Instant>,
+    /// Whether the current frame was painted on screen, which it has to
+    /// be for the animation to move on.
+    shown: bool,
+}
+
+impl Playback {
+    fn new(animation: Animation) -> Playback {
+        Playback {
+            animation,
+            frame: 0,
+            played: 0,
+            due: None,
+            shown: false,
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        self.animation.frames[self.frame].1
+    }
+
+    fn finished(&self) -> bool {
+        self.animation
+            .plays
+            .map_or(false, |plays| self.played >= plays)
+    }
+
+    /// When `advance()` next has something to do. An animation which was
+    /// paused, or hasn't started, is started once it's shown.
+    fn deadline(&self) -> Option<Instant> {
+        if self.finished() || self.animation.frames.len() < 2 {
+            None
+        } else if self.due.is_none() && self.shown {
+            Some(Instant::now())
+        } else {
+            self.due
+        }
+    }
+
+    /// Moves on to the frames which are due at `now`, returning whether
+    /// the current frame changed. An animation whose frame wasn't shown
+    /// since it last moved on is paused, on whichever frame it was, and
+    /// starts again from that frame once it's shown.
+    fn advance(&mut self, now: Instant) -> bool {
+        if self.finished() || self.animation.frames.len() < 2 {
+            return false;
+        }
+        let mut due = match self.due {
+            Some(due) if now < due => return false,
+            Some(due) if self.shown => due,
+            _ => {
+                self.due = if self.shown {
+                    Some(now + self.delay())
+                } else {
+                    None
+                };
+                return false;
+            }
+        };
+        let start = self.frame;
+        while now >= due && !self.finished() {
+            if self.frame + 1 < self.animation.frames.len() {
+                self.frame += 1;
+            } else {
+                self.played += 1;
+                if !self.finished() {
+                    self.frame = 0;
+                }
+            }
+            // After a long stall, the frame after it gets its whole delay
+            // rather than skipping ahead.
+            due = if now >= due + self.delay() {
+                now + self.delay()
+            } else {
+                due + self.delay()
+            };
+        }
+        self.due = Some(due).filter(|_| !self.finished());
+        self.shown = false;
+        self.frame != start
+    }
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<ImageSource, Cached>,
+    queue: VecDeque<ImageSource>,
+    /// Counts the calls to `retain()`, for `Cached`.
+    tick: u64,
+    workers: usize,
+    /// Set when an image finished decoding, until `take_finished()`.
+    finished: bool,
+    /// Counts the changes to what's painted for any source, see
+    /// `generation()`.
+    generation: u64,
+    proxy: Option<EventLoopProxy<()>>,
+}
+
+/// The decoded images of every window, by source. Images are decoded
+/// the first time they're painted, and until they're ready, `get()`
+/// returns `None` so that a placeholder is painted instead. Worker
+/// threads are started as images are requested, and exit once there's
+/// nothing left to decode.
+///
+/// Decoded images stay cached while they're shown, and a few of those
+/// which aren't shown any more are kept, so showing the same source
+/// again doesn't decode it again. An image which isn't shown anywhere
+/// by the time it's decoded is dropped, see `retain()`.
+///
+/// Animated images play their frames as `advance()` is called, and each
+/// new frame repaints the windows. An animation only moves on while its
+/// frame is painted on screen, so that ones which are scrolled out of
+/// view, hidden or removed pause where they are until they're painted
+/// again.
+#[derive(Clone)]
+pub(crate) struct Images {
+    state: Arc<Mutex<State>>,
+    decoder: Arc<Decoder>,
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        Images::new(decode)
+    }
+}
+
+impl Images {
+    pub fn new(
+        decoder: impl Fn(&ImageSource) -> Result<Decoded, String> + Send + Sync + 'static,
+    ) -> Images {
+        Images {
+            state: Arc::default(),
+            decoder: Arc::new(decoder),
+        }
+    }
+
+    /// Wakes up the event loop when an image is decoded.
+    pub fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        self.state.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// The decoded image, or `None` if it isn't ready, in which case
+    /// it's queued for decoding if it wasn't already. Images which
+    /// failed to decode are never ready. For animated images, this is
+    /// the current frame, which counts as shown.
+    pub fn get(&self, source: &ImageSource) -> Option<Arc<DecodedImage>> {
+        self.frame(source, true)
+    }
+
+    /// Like `get()`, but for painting an image which is off screen, so
+    /// an animated one doesn't count as shown.
+    pub fn get_hidden(&self, source: &ImageSource) -> Option<Arc<DecodedImage>> {
+        self.frame(source, false)
+    }
+
+    fn frame(&self, source: &ImageSource, shown: bool) -> Option<Arc<DecodedImage>> {
+        let mut state = self.state.lock().unwrap();
+        let tick = state.tick;
+        if let Some(cached) = state.entries.get_mut(source) {
+            cached.last_used = tick;
+            return match &mut cached.entry {
+                Entry::Ready(image) => Some(image.clone()),
+                Entry::Playing(playback) => {
+                    playback.shown |= shown;
+                    Some(playback.animation.frames[playback.frame].0.clone())
+                }
+                _ => None,
+            };
+        }
+        state.entries.insert(
+            source.clone(),
+            Cached {
+                entry: Entry::Pending,
+                last_used: tick,
+            },
+        );
+        state.queue.push_back(source.clone());
+        if state.workers < WORKERS {
+            state.workers += 1;
+            let images = self.clone();
+            std::thread::spawn(move || images.work());
+        }
+        None
+    }
+
+    /// Forgets pending images whose source isn't `live`, since nothing
+    /// shows them any more. If a decode has already started, its result
+    /// is dropped when it finishes. Of the other images which aren't
+    /// live, the least recently used past `MAX_UNUSED_IMAGES` are
+    /// dropped.
+    pub fn retain(&self, live: impl Fn(&ImageSource) -> bool) {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        state.entries.retain(|source, cached| {
+            if live(source) {
+                cached.last_used = tick;
+                true
+            } else {
+                !matches!(cached.entry, Entry::Pending)
+            }
+        });
+        state.queue.retain(|source| live(source));
+
+        let mut unused = state
+            .entries
+            .iter()
+            .filter(|(_, cached)| cached.last_used < tick)
+            .map(|(source, cached)| (cached.last_used, source.clone()))
+            .collect::<Vec<_>>();
+        let excess = unused.len().saturating_sub(MAX_UNUSED_IMAGES);
+        if excess > 0 {
+            unused.sort_by_key(|(last_used, _)| *last_used);
+            for (_, source) in unused.into_iter().take(excess) {
+                state.entries.remove(&source);
+            }
+        }
+    }
+
+    /// Whether any images were decoded since the last call, so that the
+    /// windows showing their placeholders have to be repainted.
+    pub fn take_finished(&self) -> bool {
+        std::mem::replace(&mut self.state.lock().unwrap().finished, false)
+    }
+
+    /// Moves animated images on to the frames which are due at `now`,
+    /// returning whether any of them changed, so that the windows have
+    /// to be repainted.
+    pub fn advance(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let changed =
+            state
+                .entries
+                .values_mut()
+                .fold(false, |changed, cached| match &mut cached.entry {
+                    Entry::Playing(playback) => playback.advance(now) | changed,
+                    _ => changed,
+                });
+        if changed {
+            state.generation += 1;
+        }
+        changed
+    }
+
+    /// Changes whenever an image finishes decoding, or an animated image
+    /// moves on to another frame, so that cached painting can tell when
+    /// the images in it may look different.
+    pub fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// When the next frame of an animated image is due, or `None` if
+    /// none are playing.
+    pub fn next_frame(&self) -> Option<Instant> {
+        let state = self.state.lock().unwrap();
+        state
+            .entries
+            .values()
+            .filter_map(|cached| match &cached.entry {
+                Entry::Playing(playback) => playback.deadline(),
+                _ => None,
+            })
+            .min()
+    }
+
+    fn work(&self) {
+        loop {
+            let source = {
+                let mut state = self.state.lock().unwrap();
+                match state.queue.pop_front() {
+                    Some(source) => source,
+                    None => {
+                        state.workers -= 1;
+                        return;
+                    }
+                }
+            };
+            let result = (self.decoder)(&source);
+            let mut state = self.state.lock().unwrap();
+            if let Some(cached) = state.entries.get_mut(&source) {
+                cached.entry = match result {
+                    Ok(Decoded::Still(image)) => Entry::Ready(Arc::new(image)),
+                    Ok(Decoded::Animated(animation)) if animation.frames.is_empty() => {
+                        warn!("Failed to decode image: it has no frames");
+                        Entry::Failed
+                    }
+                    Ok(Decoded::Animated(animation)) => Entry::Playing(Playback::new(animation)),
+                    Err(err) => {
+                        warn!("Failed to decode image: {}", err);
+                        Entry::Failed
+                    }
+                };
+                state.finished = true;
+                state.generation += 1;
+                if let Some(proxy) = &state.proxy {
+                    let _ = proxy.send_event(());
+                }
+            }
+        }
+    }
+}
+
+fn decode(source: &ImageSource) -> Result<Decoded, String> {
+    let bytes = match source {
+        ImageSource::Bytes(bytes) => bytes.to_vec(),
+        ImageSource::Path(path) => std::fs::read(path).map_err(|err| err.to_string())?,
+    };
+    let animation = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Gif) => {
+            let decoder = image::codecs::gif::GifDecoder::new(Cursor::new(&bytes[..]))
+                .map_err(|err| err.to_string())?;
+            Some((decoder.into_frames(), gif_plays(&bytes)))
+        }
+        Ok(ImageFormat::Png) => {
+            let decoder = image::codecs::png::PngDecoder::new(Cursor::new(&bytes[..]))
+                .map_err(|err| err.to_string())?;
+            if decoder.is_apng() {
+                Some((decoder.apng().into_frames(), apng_plays(&bytes)))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    if let Some((frames, plays)) = animation {
+        let frames = frames.collect_frames().map_err(|err| err.to_string())?;
+        if frames.len() > 1 {
+            let frames = frames
+                .into_iter()
+                .map(|frame| {
+                    let delay = frame_delay(&frame);
+                    (Arc::new(premultiplied(frame.into_buffer())), delay)
+                })
+                .collect();
+            return Ok(Decoded::Animated(Animation { frames, plays }));
+        }
+    }
+    let image = image::load_from_memory(&bytes).map_err(|err| err.to_string())?;
+    Ok(Decoded::Still(premultiplied(image.into_rgba())))
+}
+
+fn premultiplied(image: RgbaImage) -> DecodedImage {
+    let (width, height) = image.dimensions();
+    let mut rgba = image.into_raw();
+    for pixel in rgba.chunks_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = (*channel as u32 * alpha / 255) as u8;
+        }
+    }
+    DecodedImage {
+        rgba,
+        width,
+        height,
+    }
+}
+
+/// How long a frame is shown. Like browsers, frames which ask for 10
+/// milliseconds or less are shown for 100, since many GIFs were made
+/// for viewers which couldn't go faster, and rely on it.
+fn frame_delay(frame: &Frame) -> Duration {
+    let (numer, denom) = frame.delay().numer_denom_ms();
+    let ms = if denom == 0 { 0 } else { numer / denom };
+    Duration::from_millis(if ms <= 10 { 100 } else { ms as u64 })
+}
+
+/// How many times a GIF plays, from the count in its `NETSCAPE2.0`
+/// extension of how many times it repeats. Without the extension, it
+/// plays once, and a count of 0 repeats it forever.
+fn gif_plays(bytes: &[u8]) -> Option<u32> {
+    let marker = b"NETSCAPE2.0";
+    let start = match bytes
+        .windows(marker.len())
+        .position(|window| window == marker)
+    {
+        Some(start) => start + marker.len(),
+        None => return Some(1),
+    };
+    match bytes.get(start..start + 4) {
+        Some([3, 1, low, high]) => match u16::from_le_bytes([*low, *high]) {
+            0 => None,
+            repeats => Some(repeats as u32 + 1),
+        },
+        _ => Some(1),
+    }
+}
+
+/// How many times an APNG plays, from `num_plays` in its `acTL` chunk,
+/// where 0 repeats it forever.
+fn apng_plays(bytes: &[u8]) -> Option<u32> {
+    let start = bytes.windows(4).position(|window| window == b"acTL")? + 4;
+    let plays = bytes.get(start + 4..start + 8)?;
+    match u32::from_be_bytes([plays[0], plays[1], plays[2], plays[3]]) {
+        0 => None,
+        plays => Some(plays),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        apng_plays, gif_plays, Animation, Decoded, DecodedImage, Images, MAX_UNUSED_IMAGES,
+    };
+    use crate::dom::ImageSource;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    fn wait_for_workers(images: &Images) {
+        while images.state.lock().unwrap().workers > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn sources_compare_by_contents() {
+        let hash = |source: &ImageSource| {
+            let mut hasher = DefaultHasher::new();
+            source.hash(&mut hasher);
+            hasher.finish()
+        };
+        let bytes = ImageSource::from(vec![1, 2, 3]);
+        assert_eq!(bytes, bytes.clone());
+        assert_eq!(bytes, ImageSource::from(vec![1, 2, 3]));
+        assert_eq!(hash(&bytes), hash(&ImageSource::from(vec![1, 2, 3])));
+        assert_ne!(bytes, ImageSource::from(vec![1, 2, 4]));
+    }
+
+    #[test]
+    fn decodes_once_and_drops_unmounted() {
+        let decoded = Arc::new(AtomicUsize::new(0));
+        let (gate, wait) = channel::<()>();
+        let wait = Mutex::new(wait);
+        let count = decoded.clone();
+        let images = Images::new(move |_source| {
+            wait.lock().unwrap().recv().unwrap();
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(DecodedImage {
+                rgba: vec![0; 4],
+                width: 1,
+                height: 1,
+            }
+            .into())
+        });
+        let shown = ImageSource::from(vec![1]);
+        let unmounted = ImageSource::from(vec![2]);
+
+        assert_eq!(images.get(&shown), None);
+        assert_eq!(images.get(&shown), None);
+        gate.send(()).unwrap();
+        wait_for_workers(&images);
+        assert!(images.take_finished());
+        let image = images.get(&shown).unwrap();
+        assert!(Arc::ptr_eq(&image, &images.get(&shown).unwrap()));
+        assert_eq!(decoded.load(Ordering::SeqCst), 1);
+
+        // Removed from the tree while it's being decoded.
+        assert_eq!(images.get(&unmounted), None);
+        images.retain(|source| *source == shown);
+        gate.send(()).unwrap();
+        wait_for_workers(&images);
+        assert!(!images.take_finished());
+        assert!(images.get(&shown).is_some());
+        assert_eq!(images.state.lock().unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_images() {
+        let decoded = Arc::new(AtomicUsize::new(0));
+        let count = decoded.clone();
+        let images = Images::new(move |_source| {
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(DecodedImage {
+                rgba: vec![0; 4],
+                width: 1,
+                height: 1,
+            }
+            .into())
+        });
+        let source = |index: u8| ImageSource::from(vec![index]);
+
+        // Each image is shown on its own, replacing the one before it.
+        let shown = MAX_UNUSED_IMAGES as u8 + 8;
+        for index in 0..shown {
+            assert_eq!(images.get(&source(index)), None);
+            wait_for_workers(&images);
+            assert!(images.get(&source(index)).is_some());
+            images.retain(|live| *live == source(index));
+        }
+        assert_eq!(
+            images.state.lock().unwrap().entries.len(),
+            MAX_UNUSED_IMAGES + 1
+        );
+
+        // Once none are shown, the most recently used are kept.
+        images.retain(|_| false);
+        assert_eq!(
+            images.state.lock().unwrap().entries.len(),
+            MAX_UNUSED_IMAGES
+        );
+        assert!(images.get(&source(shown - 1)).is_some());
+        assert_eq!(decoded.load(Ordering::SeqCst), shown as usize);
+
+        // An evicted image is decoded again.
+        assert_eq!(images.get(&source(0)), None);
+        wait_for_workers(&images);
+        assert!(images.get(&source(0)).is_some());
+        assert_eq!(decoded.load(Ordering::SeqCst), shown as usize + 1);
+    }
+
+    #[test]
+    fn animation_advances_after_delays() {
+        let ms = Duration::from_millis;
+        let frame = |shade| {
+            Arc::new(DecodedImage {
+                rgba: vec![shade; 4],
+                width: 1,
+                height: 1,
+            })
+        };
+        let frames = vec![(frame(0), ms(100)), (frame(1), ms(50)), (frame(2), ms(200))];
+        let images = Images::new(move |_source| {
+            Ok(Decoded::Animated(Animation {
+                frames: frames.clone(),
+                plays: Some(2),
+            }))
+        });
+        let source = ImageSource::from(vec![1]);
+        let shade = || images.get(&source).unwrap().rgba[0];
+
+        assert_eq!(images.get(&source), None);
+        wait_for_workers(&images);
+        assert!(images.take_finished());
+        let start = Instant::now();
+        assert_eq!(shade(), 0);
+        assert!(!images.advance(start));
+        assert_eq!(images.next_frame(), Some(start + ms(100)));
+
+        assert!(!images.advance(start + ms(99)));
+        assert_eq!(shade(), 0);
+        assert!(images.advance(start + ms(100)));
+        assert_eq!(shade(), 1);
+        assert!(!images.advance(start + ms(149)));
+        assert!(images.advance(start + ms(150)));
+        assert_eq!(shade(), 2);
+        assert!(images.advance(start + ms(350)));
+        assert_eq!(shade(), 0);
+
+        // Not painted after moving on to the second frame, so it pauses
+        // there, and starts again once it's painted.
+        assert!(images.advance(start + ms(450)));
+        assert!(!images.advance(start + ms(500)));
+        assert_eq!(images.next_frame(), None);
+        assert_eq!(shade(), 1);
+        assert!(!images.advance(start + ms(1000)));
+        assert!(!images.advance(start + ms(1049)));
+        assert!(images.advance(start + ms(1050)));
+        assert_eq!(shade(), 2);
+
+        // Stops on the last frame after playing twice.
+        assert!(!images.advance(start + ms(1250)));
+        assert_eq!(shade(), 2);
+        assert_eq!(images.next_frame(), None);
+        assert!(!images.advance(start + ms(5000)));
+        assert_eq!(shade(), 2);
+
+        assert_eq!(gif_plays(b"GIF89a"), Some(1));
+        assert_eq!(
+            gif_plays(b"GIF89a!\xffNETSCAPE2.0\x03\x01\x00\x00\x00"),
+            None
+        );
+        assert_eq!(
+            gif_plays(b"GIF89a!\xffNETSCAPE2.0\x03\x01\x02\x00\x00"),
+            Some(3)
+        );
+        assert_eq!(
+            apng_plays(b"\x00\x00\x00\x08acTL\x00\x00\x00\x03\x00\x00\x00\x00"),
+            None
+        );
+        assert_eq!(
+            apng_plays(b"\x00\x00\x00\x08acTL\x00\x00\x00\x03\x00\x00\x00\x04"),
+            Some(4)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Telling the OS where the caret is, so that the candidate windows of
+//! input methods open next to the text being typed.
+
+use crate::layout::{LogicalPoint, LogicalRect};
+
+/// Follows the caret of the focused element in one window. Only the
+/// window the OS has focused reports its caret, as that's the one the
+/// input method is typing into.
+#[derive(Default)]
+pub(crate) struct ImeTracker {
+    focused: bool,
+    reported: Option<LogicalRect>,
+}
+
+impl ImeTracker {
+    /// The window gained or lost focus. The caret is reported again when
+    /// it comes back, since another window may have moved the input
+    /// method in the meantime.
+    pub fn focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.reported = None;
+    }
+
+    /// Takes the caret as of the latest layout, returning where to place
+    /// the input method if it should be moved. It goes below the caret,
+    /// where the candidate window opens.
+    pub fn update(&mut self, caret: Option<LogicalRect>) -> Option<LogicalPoint> {
+        let caret = caret.filter(|_| self.focused)?;
+        if self.reported == Some(caret) {
+            return None;
+        }
+        self.reported = Some(caret);
+        Some(caret.bottom_left())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ImeTracker;
+    use crate::document::Document;
+    use crate::dom::elements::text_area::TextValue;
+    use crate::mox_impl::{attr, elt};
+    use crate::runtime::RecordedInput;
+    use euclid::{point2, size2};
+    use moxie::runtime::Runtime;
+
+    #[test]
+    fn caret_moves_update_ime() {
+        let text_area = |caret| {
+            Runtime::new().run_once(|| {
+                elt::window()
+                    .add_child(
+                        elt::text_area()
+                            .set_attr(
+                                attr::value(),
+                                TextValue {
+                                    text: "some text".to_owned(),
+                                    caret,
+                                },
+                            )
+                            .build(),
+                    )
+                    .build()
+            })
+        };
+        let click = |document: &mut Document| {
+            document.input(&RecordedInput::CursorMoved(point2(2.0, 2.0)));
+            document.input(&RecordedInput::MouseButton1(true));
+            document.input(&RecordedInput::MouseButton1(false));
+        };
+        let window = text_area(0);
+        let mut document = Document::new(window, size2(200.0, 100.0));
+        document.get_layout();
+
+        let mut ime = ImeTracker::default();
+        assert_eq!(ime.update(document.caret_rect()), None);
+
+        // Nothing is reported while the window isn't focused.
+        click(&mut document);
+        assert_eq!(ime.update(document.caret_rect()), None);
+
+        ime.focused(true);
+        let first = ime.update(document.caret_rect()).unwrap();
+        assert_eq!(ime.update(document.caret_rect()), None);
+
+        document.set_root(text_area(9));
+        document.get_layout();
+        click(&mut document);
+        let second = ime.update(document.caret_rect()).unwrap();
+        assert!(second.x > first.x);
+        assert_eq!(second.y, first.y);
+
+        // Coming back to the window reports the caret again.
+        ime.focused(false);
+        assert_eq!(ime.update(document.caret_rect()), None);
+        ime.focused(true);
+        assert_eq!(ime.update(document.caret_rect()), Some(second));
+    }
+}
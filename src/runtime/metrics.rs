@@ -0,0 +1,91 @@
+//! Sharing the scale factor and size of each window with components.
+
+use crate::dom::WindowMetrics;
+use std::sync::{Arc, Mutex};
+
+/// The metrics of each window, in the order the windows were created,
+/// as of the last delivery. Shared between the runtime and components,
+/// which read it through `Runtime::window_metrics()`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MetricsState(Arc<Mutex<Vec<WindowMetrics>>>);
+
+impl MetricsState {
+    pub fn get(&self, window: usize) -> Option<WindowMetrics> {
+        self.0.lock().unwrap().get(window).copied()
+    }
+
+    /// Takes the current metrics of every window, returning the indices
+    /// of the windows whose metrics differ from the last update, which
+    /// includes windows that weren't there yet.
+    pub fn update(&self, metrics: Vec<WindowMetrics>) -> Vec<usize> {
+        let mut current = self.0.lock().unwrap();
+        let changed = metrics
+            .iter()
+            .enumerate()
+            .filter(|(index, metrics)| current.get(*index) != Some(metrics))
+            .map(|(index, _)| index)
+            .collect();
+        *current = metrics;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MetricsState;
+    use crate::document::Document;
+    use crate::dom::WindowMetrics;
+    use crate::mox_impl::{elt, event};
+    use crate::Runtime;
+    use euclid::size2;
+    use moxie::runtime::Runtime as MoxieRuntime;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn descendant_reads_new_scale() {
+        let metrics = |scale_factor| WindowMetrics {
+            scale_factor,
+            size: size2(100.0, 100.0),
+        };
+        let state = MetricsState::default();
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut runtime = MoxieRuntime::new();
+        let mut render = |state: &MetricsState| {
+            let log = events.clone();
+            illicit::Layer::new().offer(state.clone()).enter(|| {
+                runtime.run_once(|| {
+                    let log = log.clone();
+                    // Read from deep inside of the window, without the
+                    // window passing it down.
+                    let label = || {
+                        let scale = Runtime::window_metrics(0).map(|metrics| metrics.scale_factor);
+                        elt::span().add_child(format!("{:?}", scale)).build()
+                    };
+                    let window = elt::window()
+                        .on_event(event::on_metrics_change(), move |event| {
+                            log.lock().unwrap().push(event.metrics.scale_factor);
+                        })
+                        .add_child(elt::view().add_child(label()).build())
+                        .build();
+                    (window, Runtime::window_metrics(0))
+                })
+            })
+        };
+
+        let (window, read) = render(&state);
+        assert_eq!(read, None);
+        let mut document = Document::new(window, size2(100.0, 100.0));
+
+        assert_eq!(state.update(vec![metrics(1.0)]), vec![0]);
+        document.metrics(metrics(1.0));
+        assert_eq!(state.update(vec![metrics(1.0)]), Vec::<usize>::new());
+
+        // Moving onto a monitor with twice the scale.
+        assert_eq!(state.update(vec![metrics(2.0)]), vec![0]);
+        document.metrics(metrics(2.0));
+        let (window, read) = render(&state);
+        document.set_root(window);
+        assert_eq!(read, Some(metrics(2.0)));
+        assert_eq!(*events.lock().unwrap(), vec![1.0, 2.0]);
+    }
+}
@@ -1,24 +1,93 @@
 use crate::dom::devtools::DevToolsRegistry;
-use crate::dom::{App, Node};
+use crate::dom::input::KeyboardState;
+use crate::dom::node::NodeRef;
+use crate::dom::{App, ColorScheme, Node, WindowMetrics};
+use crate::layout::{LogicalPoint, LogicalRect, RectChange};
+use crate::render::{
+    FrameStats, NumberedClips, PresentMode, RenderPath, Renderer, RendererFactory, TextRendering,
+};
+use crate::style::{Style, Stylesheet};
 use crate::util::outer_join::{outer_join, Joined};
+use color_scheme::ColorSchemeState;
+use control::WindowCommands;
 use log::{debug, info};
+use metrics::MetricsState;
 use moxie::runtime::Runtime as MoxieRuntime;
-use std::collections::HashMap;
+use pacing::FramePacer;
+use recording::{Recorder, Replay};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use winit::platform::desktop::EventLoopExtDesktop;
 use winit::{
-    event::Event,
+    event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
     window::WindowId,
 };
 
+mod caret;
+mod color_scheme;
+mod control;
+mod drag;
+mod gesture;
+mod hosted;
+mod images;
+mod ime;
+mod metrics;
+mod motion;
+mod pacing;
+mod recording;
+mod tasks;
+mod timers;
+mod tooltip;
 mod window;
 
+pub use caret::CaretBlink;
+pub use control::WindowControl;
+pub use gesture::GestureConfig;
+pub(crate) use gesture::{Gesture, TransformRecognizer};
+pub use hosted::HostedRuntime;
+pub use images::DecodedImage;
+pub(crate) use images::Images;
+pub(crate) use motion::MotionState;
+pub use recording::{RecordedInput, Recording, ReplayTiming};
+pub(crate) use tasks::Tasks;
+pub use timers::TimerHandle;
+pub(crate) use timers::Timers;
+
 /// Contains the event loop and the root component of the application.
 pub struct Runtime {
     state: RuntimeState,
     windows: HashMap<WindowId, window::Window>,
     window_ids: Vec<WindowId>,
     proxy: Option<EventLoopProxy<()>>,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    frame_callback: Option<Box<dyn FnMut(&FrameStats)>>,
+    first_paint_callback: Option<Box<dyn FnOnce()>>,
+    layout_callback: Option<Box<dyn FnMut(&[RectChange])>>,
+    sheet: Stylesheet,
+    pixel_snap: bool,
+    text_rendering: TextRendering,
+    present_mode: PresentMode,
+    renderer: Option<RendererFactory>,
+    frame_pacer: FramePacer,
+    timers: Timers,
+    tasks: Tasks,
+    keyboard: KeyboardState,
+    commands: WindowCommands,
+    color_scheme: ColorSchemeState,
+    motion: MotionState,
+    metrics: MetricsState,
+    gestures: GestureConfig,
+    caret_blink: CaretBlink,
+    images: Images,
 }
 
 enum RuntimeState {
@@ -44,16 +113,88 @@ enum MainEvent {
     Shutdown,
 }
 
+/// Where an element was found by `Runtime::find_by_id()` or
+/// `Runtime::query_all()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ElementPath {
+    /// The index of the element's window among the app's windows.
+    pub window: usize,
+    /// The id of the element's node.
+    pub node: u64,
+    /// The border box of the element relative to its window, as of the
+    /// last layout.
+    pub rect: Option<LogicalRect>,
+}
+
+/// The root component panicked while the app was running, see
+/// `Runtime::run()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RunError;
+
+impl Display for RunError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "the root component panicked")
+    }
+}
+
+impl std::error::Error for RunError {}
+
 #[derive(Debug)]
 struct RuntimeMessageSender(Sender<MainEvent>);
 
+/// The state which the main thread shares with components, offered to
+/// both of them as illicit contexts.
+#[derive(Clone)]
+struct SharedContexts {
+    timers: Timers,
+    tasks: Tasks,
+    keyboard: KeyboardState,
+    commands: WindowCommands,
+    color_scheme: ColorSchemeState,
+    motion: MotionState,
+    metrics: MetricsState,
+}
+
+impl SharedContexts {
+    fn layer(&self) -> illicit::Layer {
+        illicit::Layer::new()
+            .offer(self.timers.clone())
+            .offer(self.tasks.clone())
+            .offer(self.keyboard.clone())
+            .offer(self.commands.clone())
+            .offer(self.color_scheme.clone())
+            .offer(self.motion.clone())
+            .offer(self.metrics.clone())
+    }
+}
+
 impl Runtime {
     /// Create a new runtime based on the application's root component.
     pub fn new(mut root: impl FnMut() -> Node<App> + 'static + Sync + Send) -> Runtime {
+        let contexts = SharedContexts {
+            timers: Timers::default(),
+            tasks: Tasks::default(),
+            keyboard: KeyboardState::default(),
+            commands: WindowCommands::default(),
+            color_scheme: ColorSchemeState::default(),
+            motion: MotionState::detect(),
+            metrics: MetricsState::default(),
+        };
+        let component_contexts = contexts.clone();
+        let SharedContexts {
+            timers,
+            tasks,
+            keyboard,
+            commands,
+            color_scheme,
+            motion,
+            metrics,
+        } = contexts;
         Runtime {
             state: RuntimeState::Stopped {
                 root_func: Box::new(move || {
-                    illicit::Layer::new()
+                    component_contexts
+                        .layer()
                         .offer(DevToolsRegistry::new())
                         .enter(|| {
                             topo::call(|| {
@@ -68,6 +209,455 @@ impl Runtime {
             windows: HashMap::new(),
             window_ids: vec![],
             proxy: None,
+            recorder: None,
+            replay: None,
+            frame_callback: None,
+            first_paint_callback: None,
+            layout_callback: None,
+            sheet: Stylesheet::default(),
+            pixel_snap: false,
+            text_rendering: TextRendering::default(),
+            present_mode: PresentMode::default(),
+            renderer: None,
+            frame_pacer: FramePacer::default(),
+            timers,
+            tasks,
+            keyboard,
+            commands,
+            color_scheme,
+            motion,
+            metrics,
+            gestures: GestureConfig::default(),
+            caret_blink: CaretBlink::system(),
+            images: Images::default(),
+        }
+    }
+
+    /// Sets a stylesheet applied to every window, which overrides the
+    /// default look of the built-in elements. Use element selectors to
+    /// restyle all elements of a type, for example:
+    ///
+    /// ```rs
+    /// define_style! {
+    ///     static THEME = {
+    ///         if element: Button {
+    ///             background_color: rgb(221, 221, 221),
+    ///         }
+    ///     };
+    /// }
+    ///
+    /// Runtime::new(|| app!()).theme(THEME).start();
+    /// ```
+    pub fn theme(mut self, theme: Style) -> Runtime {
+        self.sheet.theme = Some(theme);
+        self
+    }
+
+    /// Registers a style shared by every element which lists `class` in
+    /// its `class` attribute, in all windows. An element's own style
+    /// takes precedence over its classes, and when it has several, the
+    /// ones listed later take precedence.
+    ///
+    /// ```rs
+    /// Runtime::new(|| app!()).class("card", CARD_STYLE).start();
+    ///
+    /// mox! { <view class="card selected" /> }
+    /// ```
+    pub fn class(mut self, class: &str, style: Style) -> Runtime {
+        self.sheet.classes.insert(class.to_owned(), style);
+        self
+    }
+
+    /// Snaps the boxes of elements to the device pixel grid when
+    /// painting, so that thin borders and edges stay crisp instead of
+    /// blurring across pixels. Text keeps its exact position. This is
+    /// off by default.
+    pub fn pixel_snap(mut self, pixel_snap: bool) -> Runtime {
+        self.pixel_snap = pixel_snap;
+        self
+    }
+
+    /// Sets how text is rasterized in every window, in place of the
+    /// platform's default, see `TextRendering`.
+    pub fn text_rendering(mut self, text_rendering: TextRendering) -> Runtime {
+        self.text_rendering = text_rendering;
+        self
+    }
+
+    /// Sets how every window presents its frames, which is
+    /// `PresentMode::Fifo`, waiting for vsync, by default. A mode the
+    /// platform can't do is logged, and falls back to `Fifo`.
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Runtime {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Paints every window with a backend of the app's own in place of
+    /// Webrender, such as a software rasterizer, or one drawing into a
+    /// surface the app presents itself. `create` is called as each
+    /// window opens, with its metrics, and the backend is told when
+    /// they change with `Renderer::resize()`. No GL context is created
+    /// for the windows, and hit testing is done against the layout.
+    pub fn renderer<R: Renderer + 'static>(
+        mut self,
+        create: impl Fn(WindowMetrics) -> R + 'static,
+    ) -> Runtime {
+        self.renderer = Some(Rc::new(move |metrics| {
+            Box::new(NumberedClips::new(create(metrics))) as Box<dyn Renderer<Clip = usize>>
+        }));
+        self
+    }
+
+    /// Caps how many frames a second are rendered while styles are
+    /// transitioning, to save power. Without a cap, animations render as
+    /// often as they can, which with `PresentMode::Fifo` is once each
+    /// time the screen refreshes, since presenting waits for vsync. A
+    /// cap above the refresh rate then does nothing, and one below it
+    /// looks smoothest when it divides it, like 30 on a 60Hz screen, so
+    /// every frame is shown for as many refreshes. With the other modes
+    /// the cap is the only limit.
+    ///
+    /// Only frames rendered to move animations along are held back.
+    /// Input, timers and other changes render at once, as usual, moving
+    /// the animations along with them.
+    pub fn max_frame_rate(mut self, fps: f32) -> Runtime {
+        self.frame_pacer = FramePacer::new(fps);
+        self
+    }
+
+    /// Sets the thresholds for recognizing long presses and swipes in
+    /// every window, in place of `GestureConfig::default()`.
+    pub fn gestures(mut self, config: GestureConfig) -> Runtime {
+        self.gestures = config;
+        self
+    }
+
+    /// Sets how the caret of focused text areas blinks, in place of the
+    /// platform's setting, `CaretBlink::system()`.
+    pub fn caret_blink(mut self, blink: CaretBlink) -> Runtime {
+        self.caret_blink = blink;
+        self
+    }
+
+    /// Overrides the OS preference for reduced motion, for apps which
+    /// let users choose it themselves. While motion is reduced, the
+    /// items of `animated_list()` enter and exit at once, and styles
+    /// with a `transition` change at once, skipping their animations.
+    /// The OS preference is read from GTK's settings on Linux and the
+    /// BSDs, and motion isn't reduced by default elsewhere.
+    ///
+    /// Nothing is exempt from this in the built-in elements, since
+    /// their feedback, such as hover and press styles, already changes
+    /// at once. Animations which an app drives itself with timers
+    /// aren't affected, so it should check `Runtime::reduced_motion()`
+    /// and skip those which are decorative. Ones which show that
+    /// something is happening, such as a spinner, can keep running.
+    pub fn reduce_motion(self, reduce: bool) -> Runtime {
+        self.motion.set(reduce);
+        self
+    }
+
+    /// Calls `func` with the stats of each frame after it's presented,
+    /// for profiling.
+    pub fn on_frame(mut self, func: impl FnMut(&FrameStats) + 'static) -> Runtime {
+        self.frame_callback = Some(Box::new(func));
+        self
+    }
+
+    /// Calls `func` once, after the first frame of any window has been
+    /// presented, for measuring startup or hiding a splash screen. When
+    /// the GPU couldn't be set up, see `RenderPath::Headless`, frames
+    /// aren't presented, so it's called once the first one is painted.
+    /// Later frames, such as ones for resizes, never call it again.
+    pub fn on_first_paint(mut self, func: impl FnOnce() + 'static) -> Runtime {
+        self.first_paint_callback = Some(Box::new(func));
+        self
+    }
+
+    /// Calls `func` with the elements whose rects changed after layout,
+    /// for integrations such as input methods and accessibility bridges
+    /// that track element geometry. This is called at most once per
+    /// window each time events are processed, and only when something
+    /// moved, appeared or disappeared.
+    pub fn on_layout_change(mut self, func: impl FnMut(&[RectChange]) + 'static) -> Runtime {
+        self.layout_callback = Some(Box::new(func));
+        self
+    }
+
+    fn report_frames(&mut self) {
+        let painted = self.windows.values().any(window::Window::painted);
+        self.report_first_paint(painted);
+        if let Some(callback) = &mut self.frame_callback {
+            for window in self.windows.values_mut() {
+                if let Some(stats) = window.take_frame_stats() {
+                    callback(&stats);
+                }
+            }
+        }
+        if let Some(callback) = &mut self.layout_callback {
+            for window in self.windows.values_mut() {
+                let changes = window.take_layout_changes();
+                if !changes.is_empty() {
+                    callback(&changes);
+                }
+            }
+        }
+    }
+
+    fn report_first_paint(&mut self, painted: bool) {
+        if painted {
+            if let Some(callback) = self.first_paint_callback.take() {
+                callback();
+            }
+        }
+    }
+
+    /// Records the input sent to each window into a file at `path`, so
+    /// that it can be replayed later with `replay_events()`.
+    pub fn record_events(mut self, path: impl AsRef<Path>) -> io::Result<Runtime> {
+        self.recorder = Some(Recorder::create(path)?);
+        Ok(self)
+    }
+
+    /// Replays the input from a file written by `record_events()` once
+    /// the application starts. Cursor positions are hit tested against
+    /// the current layout, so a tree which differs slightly from when
+    /// the events were recorded can still be driven. Events for windows
+    /// that don't exist are skipped.
+    pub fn replay_events(
+        mut self,
+        path: impl AsRef<Path>,
+        timing: ReplayTiming,
+    ) -> io::Result<Runtime> {
+        self.replay = Some(Replay::new(Recording::load(path)?, timing));
+        Ok(self)
+    }
+
+    /// Finds the first element with the given `id` attribute, looking
+    /// through the windows in order, and each window in tree order. This
+    /// reflects the tree from the last render.
+    pub fn find_by_id(&self, id: &str) -> Option<ElementPath> {
+        self.window_ids
+            .iter()
+            .enumerate()
+            .find_map(|(index, window_id)| {
+                let document = self.windows.get(window_id)?.document();
+                let node = document.find_by_id(id)?;
+                Some(ElementPath {
+                    window: index,
+                    node,
+                    rect: document.rect(node),
+                })
+            })
+    }
+
+    /// Finds every element for which `predicate` returns true, such as
+    /// all buttons with `|node| node.is::<Button>()`, or all elements of
+    /// a class. They're in the order of their windows, and in tree order
+    /// within each window. Like `find_by_id()`, this reflects the tree
+    /// from the last render.
+    pub fn query_all(&self, predicate: impl Fn(NodeRef) -> bool) -> Vec<ElementPath> {
+        let mut found = vec![];
+        for (index, window_id) in self.window_ids.iter().enumerate() {
+            let document = match self.windows.get(window_id) {
+                Some(window) => window.document(),
+                None => continue,
+            };
+            found.extend(
+                document
+                    .query_all(&predicate)
+                    .into_iter()
+                    .map(|node| ElementPath {
+                        window: index,
+                        node,
+                        rect: document.rect(node),
+                    }),
+            );
+        }
+        found
+    }
+
+    /// How the window at `window` is rendered, in the order the windows
+    /// were created. Windows run headless when the GPU can't be set up,
+    /// which is logged, so apps can check this to tell the user why the
+    /// window is blank. Returns `None` if there's no such window.
+    pub fn render_path(&self, window: usize) -> Option<RenderPath> {
+        let window_id = self.window_ids.get(window)?;
+        Some(self.windows.get(window_id)?.render_path())
+    }
+
+    /// Maps a point relative to an element's window into the element's
+    /// own space, where the top left corner of its border box is the
+    /// origin, using the last layout. This is for widgets which hit
+    /// test their own drawing. Returns `None` if the element isn't laid
+    /// out, or if the point can't be mapped.
+    pub fn to_local(&self, element: &ElementPath, point: LogicalPoint) -> Option<LogicalPoint> {
+        let window_id = self.window_ids.get(element.window)?;
+        self.windows
+            .get(window_id)?
+            .document()
+            .to_local(element.node, point)
+    }
+
+    /// Sends recorded events which are due to their windows. This stops
+    /// after any event which changes something, so that the DOM is
+    /// updated before the next one is sent.
+    fn replay_due(&mut self) -> bool {
+        if let Some(replay) = &mut self.replay {
+            while let Some(event) = replay.next_due() {
+                let window = self
+                    .window_ids
+                    .get(event.window)
+                    .and_then(|id| self.windows.get_mut(id));
+                if let Some(window) = window {
+                    if window.replay(&event.input) {
+                        return true;
+                    }
+                } else {
+                    debug!("Skipping event for missing window {}", event.window);
+                }
+            }
+        }
+        false
+    }
+
+    /// Delivers each window's latest cursor move, once all of this
+    /// frame's events have been handled.
+    fn flush_cursors(&mut self) -> bool {
+        self.windows
+            .values_mut()
+            .fold(false, |changed, window| window.flush_cursor() | changed)
+    }
+
+    fn run_window_commands(&mut self) -> bool {
+        let mut changed = false;
+        for (index, command) in self.commands.take() {
+            let window = self
+                .window_ids
+                .get(index)
+                .and_then(|id| self.windows.get_mut(id));
+            match window {
+                Some(window) => changed |= window.command(command),
+                None => debug!("Skipping command for missing window {}", index),
+            }
+        }
+        changed
+    }
+
+    /// Sends the color scheme to every window if it changed since it was
+    /// last sent.
+    fn deliver_color_scheme(&mut self) -> bool {
+        match self.color_scheme.take_change() {
+            Some(scheme) => self.windows.values_mut().fold(false, |changed, window| {
+                window.color_scheme(scheme) | changed
+            }),
+            None => false,
+        }
+    }
+
+    /// Repaints the windows once images they're waiting for are
+    /// decoded, or animated images they show move on to another frame.
+    fn deliver_images(&mut self) {
+        let finished = self.images.take_finished();
+        if self.images.advance(Instant::now()) | finished {
+            for window in self.windows.values_mut() {
+                window.render();
+            }
+        }
+    }
+
+    /// Renders the windows with style transitions in progress, so they
+    /// move on each frame, as often as the frame rate allows.
+    fn advance_transitions(&mut self) {
+        let now = Instant::now();
+        if !self.frame_pacer.due(now) {
+            return;
+        }
+        let mut rendered = false;
+        for window in self.windows.values_mut() {
+            if window.animating() {
+                window.render();
+                rendered = true;
+            }
+        }
+        if rendered {
+            self.frame_pacer.frame(now);
+        }
+    }
+
+    /// Shows or hides the carets which are due to blink.
+    fn blink_carets(&mut self) {
+        for window in self.windows.values_mut() {
+            window.blink_caret();
+        }
+    }
+
+    /// Shares the scale factor and size of every window with components,
+    /// and tells the windows whose metrics changed since the last call.
+    /// Returns whether any changed, so that components reading them are
+    /// rendered again.
+    fn deliver_metrics(&mut self) -> bool {
+        let metrics = self
+            .window_ids
+            .iter()
+            .map(|id| self.windows[id].metrics())
+            .collect::<Vec<_>>();
+        let changed = self.metrics.update(metrics.clone());
+        for &index in &changed {
+            if let Some(window) = self.windows.get_mut(&self.window_ids[index]) {
+                window.metrics_changed(metrics[index]);
+            }
+        }
+        !changed.is_empty()
+    }
+
+    /// Sends resizes to the elements of every window laid out since the
+    /// last call. Elements which change size in response are reported
+    /// after the next layout, in the next frame.
+    fn deliver_resizes(&mut self) -> bool {
+        self.windows
+            .values_mut()
+            .fold(false, |changed, window| window.deliver_resizes() | changed)
+    }
+
+    /// Delivers long presses which are due in any window.
+    fn poll_gestures(&mut self) -> bool {
+        self.windows
+            .values_mut()
+            .fold(false, |changed, window| window.poll_gestures() | changed)
+    }
+
+    fn control_flow(&self) -> ControlFlow {
+        // Resizes are delivered once the events after the layout are,
+        // and transitions move on every frame, which may be capped.
+        if self.windows.values().any(window::Window::resizes_due) {
+            return ControlFlow::Poll;
+        }
+        let animating = self.windows.values().any(window::Window::animating);
+        let frame = self.frame_pacer.deadline().filter(|_| animating);
+        if animating && frame.is_none() {
+            return ControlFlow::Poll;
+        }
+        let replay = self.replay.as_ref().and_then(Replay::next_time);
+        let gestures = self
+            .windows
+            .values()
+            .filter_map(window::Window::gesture_deadline);
+        let carets = self
+            .windows
+            .values()
+            .filter_map(window::Window::caret_deadline);
+        let next = replay
+            .into_iter()
+            .chain(self.timers.next_due())
+            .chain(gestures)
+            .chain(carets)
+            .chain(self.images.next_frame())
+            .chain(frame)
+            .min();
+        match next {
+            Some(time) => ControlFlow::WaitUntil(time),
+            None => ControlFlow::Wait,
         }
     }
 
@@ -78,146 +668,411 @@ impl Runtime {
         target: &EventLoopWindowTarget<()>,
         control_flow: &mut ControlFlow,
     ) {
-        let mut did_process = false;
+        if self.handle(event) {
+            self.update_runtime(target);
+        }
+        self.report_frames();
+        // Handlers and components may have started timers.
+        *control_flow = self.control_flow();
+        if self.poll_shutdown() {
+            self.timers.clear();
+            self.tasks.clear();
+            *control_flow = ControlFlow::Exit;
+        }
+    }
+
+    /// Sends an event to the window it's for, returning whether the
+    /// DOM needs to be updated.
+    fn handle(&mut self, event: Event<()>) -> bool {
         match event {
             Event::RedrawRequested(window_id) => {
                 let window = self.windows.get_mut(&window_id).unwrap();
-                window.render();
+                window.redraw();
+                false
             }
             Event::WindowEvent { event, window_id } => {
+                if let WindowEvent::ThemeChanged(theme) = event {
+                    self.color_scheme.report(window::to_color_scheme(theme));
+                }
                 let window = self.windows.get_mut(&window_id).unwrap();
-                let res = window.process(event);
-                did_process = res;
+                if let Some(recorder) = &mut self.recorder {
+                    let index = self.window_ids.iter().position(|id| *id == window_id);
+                    if let (Some(index), Some(input)) = (index, window.recorded_input(&event)) {
+                        recorder.record(index, input);
+                    }
+                }
+                window.process(event)
             }
-            _ => *control_flow = ControlFlow::Wait,
-        }
-        if did_process {
-            self.update_runtime(target);
+            Event::MainEventsCleared => {
+                self.deliver_images();
+                self.advance_transitions();
+                self.blink_carets();
+                self.run_pending()
+            }
+            _ => false,
         }
-        if let RuntimeState::Shutdown = &self.state {
-            *control_flow = ControlFlow::Exit;
+    }
+
+    /// Runs everything which is due once the OS events are handled, in
+    /// order: replayed input, the latest cursor moves, gestures, timers,
+    /// futures which were woken, window commands, color scheme changes,
+    /// window metrics and resizes from the last layout. Returns whether
+    /// the DOM needs to be updated.
+    fn run_pending(&mut self) -> bool {
+        self.replay_due()
+            | self.flush_cursors()
+            | self.poll_gestures()
+            | self.timers.run_due(Instant::now())
+            | self.tasks.run_ready()
+            | self.run_window_commands()
+            | self.deliver_color_scheme()
+            | self.deliver_metrics()
+            | self.deliver_resizes()
+    }
+
+    /// Runs whatever is due, and then the root component, whether or
+    /// not anything asked for an update, so that the tree reflects every
+    /// state change made so far. See `HostedRuntime::render_and_sync()`.
+    fn sync_app(&mut self) -> Option<Node<App>> {
+        self.run_pending();
+        self.next_app()
+    }
+
+    /// Renders every window, including ones created since the last
+    /// frame.
+    fn render_all(&mut self) {
+        for window in self.windows.values_mut() {
+            window.render();
         }
     }
 
     /// Updates the moxie runtime and reconciles the DOM changes,
     /// re-rendering if things have changed.
     fn update_runtime(&mut self, event_loop: &EventLoopWindowTarget<()>) {
-        if let RuntimeState::Running {
-            sender, receiver, ..
-        } = &self.state
-        {
-            sender.send(RuntimeEvent::UpdateRuntime).unwrap();
+        if let Some(app) = self.next_app() {
+            self.reconcile(app, event_loop);
+        }
+    }
 
-            if let Ok(event) = receiver.recv() {
-                match event {
-                    MainEvent::UpdateRuntime(app) => {
-                        let window_ids = self.window_ids.drain(..).collect::<Vec<_>>();
-                        for joined in outer_join(app.children(), window_ids) {
-                            match joined {
-                                Joined::Both(dom_window, window_id) => {
-                                    let window = self.windows.get_mut(&window_id).unwrap();
-                                    window.set_dom_window(dom_window.clone());
-                                    window.render();
-                                    self.window_ids.push(window_id);
-                                }
-                                Joined::Left(dom_window) => {
-                                    let window = window::Window::new(
-                                        dom_window.clone(),
-                                        event_loop,
-                                        self.proxy.as_ref().unwrap().clone(),
-                                    );
-                                    let id = window.window_id();
-                                    self.windows.insert(id, window);
-                                    self.window_ids.push(id);
-                                }
-                                Joined::Right(window_id) => {
-                                    self.windows.remove(&window_id);
-                                }
-                            }
-                        }
-                    }
-                    MainEvent::Shutdown => {
+    /// Runs the root component on the runtime thread, returning the new
+    /// tree, or `None` once the application has shut down.
+    fn next_app(&mut self) -> Option<Node<App>> {
+        match &self.state {
+            RuntimeState::Running { sender, receiver } => {
+                sender.send(RuntimeEvent::UpdateRuntime).unwrap();
+                match receiver.recv() {
+                    Ok(MainEvent::UpdateRuntime(app)) => Some(app),
+                    Ok(MainEvent::Shutdown) => {
                         debug!("Main thread received shutdown request");
                         sender.send(RuntimeEvent::Shutdown).unwrap();
                         self.state = RuntimeState::Shutdown;
+                        None
                     }
+                    Err(_) => None,
                 }
             }
-        } else if let RuntimeState::Shutdown = &self.state {
-        } else {
-            panic!("Invalid state");
-        }
-    }
-
-    /// Start up the application.
-    pub fn start(mut self) {
-        let Runtime {
-            state,
-            windows,
-            window_ids,
-            ..
-        } = self;
-
-        if let RuntimeState::Stopped { mut root_func } = state {
-            let (sender_a, receiver_b) = channel();
-            let (sender_b, receiver_a) = channel();
-            let sender_c = sender_b.clone();
-
-            let thread = std::thread::spawn(move || {
-                let mut moxie_runtime = MoxieRuntime::new();
-
-                while let Ok(event) = receiver_b.recv() {
-                    match event {
-                        RuntimeEvent::UpdateRuntime => {
-                            let app = moxie_runtime.run_once(&mut root_func);
-
-                            match sender_b.send(MainEvent::UpdateRuntime(app)) {
-                                Ok(_) => (),
-                                Err(err) => {
-                                    debug!("Runtime thread send error: {}", err);
-                                    break;
-                                }
+            RuntimeState::Shutdown => None,
+            RuntimeState::Stopped { .. } => panic!("Invalid state"),
+        }
+    }
+
+    /// Handles a shutdown request which is already queued, such as one
+    /// from an event handler, without running the root component first.
+    /// Returns whether the application has shut down. Replies from the
+    /// runtime thread are all received by `next_app()`, so only
+    /// shutdown requests are queued in between.
+    fn poll_shutdown(&mut self) -> bool {
+        if let RuntimeState::Running { sender, receiver } = &self.state {
+            if let Ok(MainEvent::Shutdown) = receiver.try_recv() {
+                debug!("Main thread received shutdown request");
+                let _ = sender.send(RuntimeEvent::Shutdown);
+                self.state = RuntimeState::Shutdown;
+            }
+        }
+        matches!(self.state, RuntimeState::Shutdown)
+    }
+
+    fn reconcile(&mut self, app: Node<App>, event_loop: &EventLoopWindowTarget<()>) {
+        trace_span!(span = "update");
+        let window_ids = self.window_ids.drain(..).collect::<Vec<_>>();
+        for joined in outer_join(app.children(), window_ids) {
+            match joined {
+                Joined::Both(dom_window, window_id) => {
+                    let window = self.windows.get_mut(&window_id).unwrap();
+                    window.set_dom_window(dom_window.clone());
+                    window.render();
+                    self.window_ids.push(window_id);
+                }
+                Joined::Left(dom_window) => {
+                    let window = window::Window::new(
+                        dom_window.clone(),
+                        event_loop,
+                        self.proxy.as_ref().unwrap().clone(),
+                        self.sheet.clone(),
+                        self.keyboard.clone(),
+                        self.images.clone(),
+                        self.pixel_snap,
+                        self.text_rendering,
+                        self.present_mode,
+                        self.gestures,
+                        self.caret_blink,
+                        self.motion.clone(),
+                        self.renderer.clone(),
+                    );
+                    if let Some(scheme) = window.system_color_scheme() {
+                        self.color_scheme.report(scheme);
+                    }
+                    let id = window.window_id();
+                    self.windows.insert(id, window);
+                    self.window_ids.push(id);
+                }
+                Joined::Right(window_id) => {
+                    self.windows.remove(&window_id);
+                }
+            }
+        }
+
+        // Images which were unmounted before they were decoded are no
+        // longer needed.
+        let live = self
+            .windows
+            .values()
+            .flat_map(|window| window.document().image_sources())
+            .collect::<HashSet<_>>();
+        self.images.retain(|source| live.contains(source));
+    }
+
+    /// Starts the thread which runs the root component, returning it
+    /// along with the sender used for shutdown requests.
+    fn launch(&mut self) -> (JoinHandle<()>, Sender<MainEvent>) {
+        let mut root_func = match std::mem::replace(&mut self.state, RuntimeState::Shutdown) {
+            RuntimeState::Stopped { root_func } => root_func,
+            _ => panic!("Already running"),
+        };
+
+        let (sender_a, receiver_b) = channel();
+        let (sender_b, receiver_a) = channel();
+        let sender_c = sender_b.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut moxie_runtime = MoxieRuntime::new();
+
+            while let Ok(event) = receiver_b.recv() {
+                match event {
+                    RuntimeEvent::UpdateRuntime => {
+                        let app = moxie_runtime.run_once(&mut root_func);
+
+                        match sender_b.send(MainEvent::UpdateRuntime(app)) {
+                            Ok(_) => (),
+                            Err(err) => {
+                                debug!("Runtime thread send error: {}", err);
+                                break;
                             }
                         }
-                        RuntimeEvent::Shutdown => {
-                            debug!("Runtime thread received shutdown request");
-                            break;
-                        }
+                    }
+                    RuntimeEvent::Shutdown => {
+                        debug!("Runtime thread received shutdown request");
+                        break;
                     }
                 }
-                debug!("Runtime thread exit");
-            });
+            }
+            debug!("Runtime thread exit");
+        });
 
-            let event_loop = EventLoop::new();
+        self.state = RuntimeState::Running {
+            sender: sender_a,
+            receiver: receiver_a,
+        };
+        (thread, sender_c)
+    }
 
-            self = Runtime {
-                state: RuntimeState::Running {
-                    sender: sender_a,
-                    receiver: receiver_a,
-                },
-                windows,
-                window_ids,
-                proxy: Some(event_loop.create_proxy()),
-            };
+    /// Creates the event loop, which wakes up for timers, tasks, window
+    /// commands and decoded images.
+    fn create_event_loop(&mut self) -> EventLoop<()> {
+        let event_loop = new_event_loop();
+        self.proxy = Some(event_loop.create_proxy());
+        self.timers.set_proxy(event_loop.create_proxy());
+        self.tasks.set_proxy(event_loop.create_proxy());
+        self.commands.set_proxy(event_loop.create_proxy());
+        self.images.set_proxy(event_loop.create_proxy());
+        event_loop
+    }
+
+    /// Start up the application, running it on this thread until it
+    /// shuts down, even while it has no windows. Panics if the root
+    /// component panicked. See `run()` for returning once the last
+    /// window closes.
+    pub fn start(self) {
+        if let Err(err) = self.run_until(false) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Starts the application without taking over the thread, for
+    /// embedding in a host which runs its own loop. See `HostedRuntime`
+    /// for how it's driven.
+    pub fn into_hosted(mut self) -> HostedRuntime {
+        let (thread, sender) = self.launch();
+        let event_loop = self.create_event_loop();
+        HostedRuntime::new(self, event_loop, thread, sender)
+    }
+
+    /// Runs the application on this thread, like `start()`, but returns
+    /// once it shuts down or its last window is removed from the tree,
+    /// such as by an `on_close` handler. An app with no windows to begin
+    /// with returns at once. Returns an error if the root component
+    /// panicked.
+    pub fn run(self) -> Result<(), RunError> {
+        self.run_until(true)
+    }
+
+    /// Runs the event loop until the app shuts down, or when
+    /// `last_window` is set, until it has no windows left. The root
+    /// component runs once before the event loop is created, so that an
+    /// app which starts without windows doesn't need one.
+    fn run_until(mut self, last_window: bool) -> Result<(), RunError> {
+        let (thread, sender) = self.launch();
+        let app = self.enter_contexts(&sender, Runtime::next_app);
+        // Without an app, it already shut down or the root component
+        // panicked.
+        let app = app.filter(|app| !last_window || !app.children().is_empty());
 
-            illicit::Layer::new()
-                .offer(RuntimeMessageSender(sender_c))
-                .enter(|| {
-                    self.update_runtime(&event_loop);
-                    event_loop.run(move |event, target, control_flow| {
-                        self.process(event, target, control_flow)
-                    });
+        if let Some(app) = app {
+            let mut event_loop = self.create_event_loop();
+            self.enter_contexts(&sender, |runtime| {
+                runtime.reconcile(app, &event_loop);
+                event_loop.run_return(|event, target, control_flow| {
+                    runtime.process(event, target, control_flow);
+                    if last_window && runtime.windows.is_empty() {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 });
+            });
+            // Close the windows before the event loop they belong to.
+            self.windows.clear();
+        }
+
+        self.timers.clear();
+        self.tasks.clear();
+        if let RuntimeState::Running { sender, .. } = &self.state {
+            let _ = sender.send(RuntimeEvent::Shutdown);
+        }
+        debug!("Waiting for runtime thread to exit");
+        thread.join().map_err(|_| RunError)
+    }
+
+    /// Runs `f` with the contexts which event handlers and window
+    /// updates on the main thread expect, such as the timers and the
+    /// sender for `shutdown()`.
+    fn enter_contexts<R>(
+        &mut self,
+        sender: &Sender<MainEvent>,
+        f: impl FnOnce(&mut Runtime) -> R,
+    ) -> R {
+        let contexts = SharedContexts {
+            timers: self.timers.clone(),
+            tasks: self.tasks.clone(),
+            keyboard: self.keyboard.clone(),
+            commands: self.commands.clone(),
+            color_scheme: self.color_scheme.clone(),
+            motion: self.motion.clone(),
+            metrics: self.metrics.clone(),
+        };
+        contexts
+            .layer()
+            .offer(RuntimeMessageSender(sender.clone()))
+            .enter(|| f(self))
+    }
+
+    /// Calls `callback` once, after `delay`. Like `shutdown()`, this
+    /// can be called from components and event handlers. The callback
+    /// runs on the main thread between frames, and the DOM is updated
+    /// afterwards, so it can change state like an event handler. Timers
+    /// which haven't fired when the application shuts down are dropped
+    /// without running.
+    pub fn set_timeout(delay: Duration, callback: impl FnOnce() + Send + 'static) -> TimerHandle {
+        let mut callback = Some(callback);
+        illicit::expect::<Timers>().schedule(
+            Instant::now(),
+            delay,
+            None,
+            Box::new(move || {
+                if let Some(callback) = callback.take() {
+                    callback();
+                }
+            }),
+        )
+    }
+
+    /// Calls `callback` every `period` until the returned handle is
+    /// cancelled, in the same way as `set_timeout()`. When frames take
+    /// longer than the period, the missed calls are skipped rather than
+    /// made in a burst.
+    pub fn set_interval(period: Duration, callback: impl FnMut() + Send + 'static) -> TimerHandle {
+        illicit::expect::<Timers>().schedule(
+            Instant::now(),
+            period,
+            Some(period),
+            Box::new(callback),
+        )
+    }
 
-            debug!("Waiting for runtime thread to exit");
-            // After the event loop exits (due to app shutdown), wait
-            // for  the render thread to realize it's time to exit.
-            thread.join().unwrap();
-        } else {
-            panic!("Already running");
+    /// Runs `future` to completion on the main thread, for work such as
+    /// loading data in the background. Like `set_timeout()`, this can
+    /// be called from components and event handlers.
+    ///
+    /// The future is polled between frames whenever it's woken, and the
+    /// DOM is updated after it's polled, so it can change state like an
+    /// event handler. It has to be `Send` because components run on a
+    /// different thread from the main thread. Blocking work should be
+    /// done on another thread, with the future waiting for its result,
+    /// for example over a channel which wakes it. `use_future()` wraps
+    /// this for components. Futures which haven't finished when the
+    /// application shuts down are dropped.
+    pub fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+        illicit::expect::<Tasks>().spawn(Box::pin(future));
+    }
+
+    /// Controls the window at `index` among the app's windows, like in
+    /// `ElementPath`. This can be called from components and event
+    /// handlers.
+    pub fn window(index: usize) -> WindowControl {
+        WindowControl {
+            window: index,
+            commands: (*illicit::expect::<WindowCommands>()).clone(),
         }
     }
 
+    /// The keys which are held down in any of the application's windows.
+    /// This can be called from components and event handlers.
+    pub fn keyboard() -> KeyboardState {
+        (*illicit::expect::<KeyboardState>()).clone()
+    }
+
+    /// Whether the OS is set to a light or dark interface, as of the
+    /// last `ColorSchemeChangeEvent`. This is `Light` on platforms which
+    /// don't report it. This can be called from components and event
+    /// handlers.
+    pub fn color_scheme() -> ColorScheme {
+        illicit::expect::<ColorSchemeState>().current()
+    }
+
+    /// The scale factor and size of the window at `window`, in the order
+    /// the windows were created, as of the last `MetricsChangeEvent`.
+    /// Returns `None` before the window has opened. This can be called
+    /// from components and event handlers, and components are rendered
+    /// again when any window's metrics change.
+    pub fn window_metrics(window: usize) -> Option<WindowMetrics> {
+        illicit::expect::<MetricsState>().get(window)
+    }
+
+    /// Whether animations should be reduced, from the OS preference or
+    /// `reduce_motion()`. This can be called from components and event
+    /// handlers.
+    pub fn reduced_motion() -> bool {
+        illicit::expect::<MotionState>().reduced()
+    }
+
     pub fn shutdown() {
         info!("Shutdown requested");
         let sender = illicit::expect::<RuntimeMessageSender>();
@@ -227,3 +1082,163 @@ impl Runtime {
             .expect("Sending shutdown request");
     }
 }
+
+#[cfg(not(test))]
+fn new_event_loop() -> EventLoop<()> {
+    EventLoop::new()
+}
+
+/// Tests run off the main thread, where winit only creates event loops
+/// when asked to.
+#[cfg(all(
+    test,
+    any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+))]
+fn new_event_loop() -> EventLoop<()> {
+    winit::platform::unix::EventLoopExtUnix::new_any_thread()
+}
+
+#[cfg(all(test, target_os = "windows"))]
+fn new_event_loop() -> EventLoop<()> {
+    winit::platform::windows::EventLoopExtWindows::new_any_thread()
+}
+
+#[cfg(all(test, target_os = "macos"))]
+fn new_event_loop() -> EventLoop<()> {
+    EventLoop::new()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Runtime, RuntimeMessageSender, RuntimeState};
+    use crate::dom::App;
+    use crate::mox_impl::elt;
+    use crate::render::FrameCounter;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn drives_updates_manually() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let mut runtime = Runtime::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            elt::app().build()
+        });
+
+        let (thread, sender) = runtime.launch();
+        for _ in 0..3 {
+            assert!(runtime.next_app().is_some());
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        illicit::Layer::new()
+            .offer(RuntimeMessageSender(sender))
+            .enter(Runtime::shutdown);
+        assert!(runtime.next_app().is_none());
+        assert!(matches!(runtime.state, RuntimeState::Shutdown));
+        assert!(runtime.next_app().is_none());
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn polls_queued_shutdown() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let mut runtime = Runtime::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            elt::app().build()
+        });
+
+        let (thread, sender) = runtime.launch();
+        assert!(!runtime.poll_shutdown());
+        assert!(runtime.next_app().is_some());
+        assert!(!runtime.poll_shutdown());
+
+        // The request is seen without running the root component again.
+        illicit::Layer::new()
+            .offer(RuntimeMessageSender(sender))
+            .enter(Runtime::shutdown);
+        assert!(runtime.poll_shutdown());
+        assert!(matches!(runtime.state, RuntimeState::Shutdown));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn run_returns_without_windows() {
+        assert_eq!(Runtime::new(|| elt::app().build()).run(), Ok(()));
+        assert_eq!(App::run(|| elt::app().build()), Ok(()));
+    }
+
+    // Tests which open windows need a display, so they only run with
+    // `cargo test -- --ignored --test-threads=1`.
+    #[test]
+    #[ignore]
+    fn run_presents_window() {
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+        let result = Runtime::new(|| elt::app().add_child(elt::window().build()).build())
+            .renderer(move |_| FrameCounter(counter.clone()))
+            .on_first_paint(Runtime::shutdown)
+            .run();
+        assert_eq!(result, Ok(()));
+        assert!(frames.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[test]
+    fn first_paint_fires_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut runtime = Runtime::new(|| elt::app().build()).on_first_paint(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        // Frames before the first one is done, such as ones which were
+        // skipped, don't count.
+        runtime.report_frames();
+        runtime.report_first_paint(false);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        runtime.report_first_paint(true);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Frames after it, such as for resizes, don't call it again.
+        runtime.report_first_paint(true);
+        runtime.report_first_paint(true);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn sync_runs_due_timers_first() {
+        let state = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::new(AtomicUsize::new(0));
+        let (read, write) = (state.clone(), seen.clone());
+        let mut runtime = Runtime::new(move || {
+            write.store(read.load(Ordering::SeqCst), Ordering::SeqCst);
+            elt::app().build()
+        });
+
+        let (thread, _sender) = runtime.launch();
+        assert!(runtime.next_app().is_some());
+        runtime.timers.schedule(
+            Instant::now(),
+            Duration::from_secs(0),
+            None,
+            Box::new(move || state.store(1, Ordering::SeqCst)),
+        );
+        assert!(runtime.sync_app().is_some());
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+
+        // Dropping the sender stops the runtime thread.
+        runtime.state = RuntimeState::Shutdown;
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! Following the OS preference for reduced motion.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Whether animations should be reduced, shared between the runtime and
+/// components. This starts out as the OS preference, on platforms where
+/// it's detected, and can be overridden with `Runtime::reduce_motion()`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MotionState(Arc<AtomicBool>);
+
+impl MotionState {
+    pub fn detect() -> MotionState {
+        MotionState(Arc::new(AtomicBool::new(system_reduced_motion())))
+    }
+
+    pub fn reduced(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, reduced: bool) {
+        self.0.store(reduced, Ordering::Relaxed);
+    }
+}
+
+/// Reads GTK's `settings.ini`, which desktops on Linux and the BSDs use
+/// for preferences like these.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(super) fn gtk_settings() -> Option<String> {
+    use std::path::PathBuf;
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    std::fs::read_to_string(config.join("gtk-3.0/settings.ini")).ok()
+}
+
+/// Reads the GTK setting which desktops on Linux and the BSDs use to
+/// turn off animations. Other platforms don't have a way to read the
+/// preference without linking their UI frameworks, so motion isn't
+/// reduced there unless the app asks for it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_reduced_motion() -> bool {
+    gtk_settings().map_or(false, |settings| animations_disabled(&settings))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn system_reduced_motion() -> bool {
+    false
+}
+
+/// Whether GTK's `settings.ini` sets `gtk-enable-animations` to false.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn animations_disabled(settings: &str) -> bool {
+    settings.lines().any(|line| {
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        key == "gtk-enable-animations" && (value == "false" || value == "0")
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::animations_disabled;
+
+    #[test]
+    fn reads_gtk_setting() {
+        assert!(animations_disabled(
+            "[Settings]\ngtk-theme-name=Adwaita\ngtk-enable-animations = false\n"
+        ));
+        assert!(animations_disabled("[Settings]\ngtk-enable-animations=0"));
+        assert!(!animations_disabled(
+            "[Settings]\ngtk-enable-animations=true"
+        ));
+        assert!(!animations_disabled("[Settings]\ngtk-theme-name=Adwaita"));
+    }
+}
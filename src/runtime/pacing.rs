@@ -0,0 +1,94 @@
+//! Capping how often frames are rendered for animations.
+
+use std::time::{Duration, Instant};
+
+/// Spaces out the frames rendered for animations, with at least the
+/// interval of the maximum frame rate between them, set with
+/// `Runtime::max_frame_rate()`. Frames are counted from when the last
+/// one was due rather than when it was rendered, so that the event loop
+/// waking up late doesn't slow the frame rate down, unless it's so late
+/// that a whole frame was missed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FramePacer {
+    interval: Option<Duration>,
+    last: Option<Instant>,
+}
+
+impl FramePacer {
+    /// Caps frames to `max_fps` a second, or doesn't cap them if it
+    /// isn't a positive number.
+    pub fn new(max_fps: f32) -> FramePacer {
+        FramePacer {
+            interval: if max_fps > 0.0 && max_fps.is_finite() {
+                Some(Duration::from_secs_f32(1.0 / max_fps))
+            } else {
+                None
+            },
+            last: None,
+        }
+    }
+
+    /// Whether a frame can be rendered at `now`.
+    pub fn due(&self, now: Instant) -> bool {
+        match (self.last, self.interval) {
+            (Some(last), Some(interval)) => now >= last + interval,
+            _ => true,
+        }
+    }
+
+    /// Takes a frame rendered at `now`.
+    pub fn frame(&mut self, now: Instant) {
+        self.last = match (self.last, self.interval) {
+            (Some(last), Some(interval)) if now < last + interval * 2 => Some(last + interval),
+            _ => Some(now),
+        };
+    }
+
+    /// When the next frame can be rendered, or `None` when frames aren't
+    /// capped, and animations render as often as the event loop runs.
+    pub fn deadline(&self) -> Option<Instant> {
+        let interval = self.interval?;
+        Some(self.last.map_or_else(Instant::now, |last| last + interval))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FramePacer;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn caps_animation_frames() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut pacer = FramePacer::new(30.0);
+        // An animation polling the pacer every millisecond for a second.
+        let mut frames = vec![];
+        for tick in 0..1000 {
+            let now = start + ms(tick);
+            if pacer.due(now) {
+                pacer.frame(now);
+                frames.push(now);
+            }
+        }
+        assert_eq!(frames.len(), 30);
+        for pair in frames.windows(2) {
+            let spacing = pair[1] - pair[0];
+            assert!(ms(33) <= spacing && spacing <= ms(34), "{:?}", spacing);
+        }
+        let next = pacer.deadline().unwrap() - *frames.last().unwrap();
+        assert!(ms(33) <= next && next <= ms(34), "{:?}", next);
+
+        // After a pause, the next frame is rendered at once, and counted
+        // from.
+        let resumed = start + ms(5000);
+        assert!(pacer.due(resumed));
+        pacer.frame(resumed);
+        assert!(!pacer.due(resumed + ms(30)));
+        assert!(pacer.due(resumed + ms(34)));
+
+        let uncapped = FramePacer::new(0.0);
+        assert!(uncapped.due(start));
+        assert_eq!(uncapped.deadline(), None);
+    }
+}
@@ -0,0 +1,394 @@
+//! Recording of input events to a file, and replaying them later, for
+//! reproducing bugs and integration testing.
+//!
+//! Recordings are stored as text, with one event per line in the form
+//! `<milliseconds> <window> <input>`, for example `1520 0 move 40 12.5`.
+
+use crate::dom::input::{Key, PointerId, TouchPhase};
+use crate::layout::LogicalPoint;
+use euclid::point2;
+use log::warn;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Input to a window, in the form it's recorded in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RecordedInput {
+    /// The cursor moved to the given position, in logical pixels.
+    CursorMoved(LogicalPoint),
+    CursorLeft,
+    /// The left mouse button was pressed (`true`) or released.
+    MouseButton1(bool),
+    /// A touch started, moved or ended at the given position.
+    Touch(PointerId, TouchPhase, LogicalPoint),
+    Key(Key),
+    KeyReleased(Key),
+    /// The window lost focus.
+    Blur,
+    Character(char),
+    CloseRequested,
+}
+
+/// A single recorded input, along with when it happened relative to
+/// the start of the recording, and the index of the window it was sent
+/// to among the app's windows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RecordedEvent {
+    pub time: Duration,
+    pub window: usize,
+    pub input: RecordedInput,
+}
+
+/// Returned when a recording can't be parsed.
+#[derive(Debug, PartialEq)]
+pub struct ParseRecordingError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseRecordingError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid recorded event on line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseRecordingError {}
+
+fn key_name(key: Key) -> String {
+    let name = match key {
+        Key::Enter => "Enter",
+        Key::Backspace => "Backspace",
+        Key::Delete => "Delete",
+        Key::Left => "Left",
+        Key::Right => "Right",
+        Key::Up => "Up",
+        Key::Down => "Down",
+        Key::Home => "Home",
+        Key::End => "End",
+        Key::Tab => "Tab",
+        Key::Escape => "Escape",
+        Key::Space => "Space",
+        Key::Char(character) => return character.to_string(),
+    };
+    name.to_owned()
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "Enter" => Key::Enter,
+        "Backspace" => Key::Backspace,
+        "Delete" => Key::Delete,
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "Tab" => Key::Tab,
+        "Escape" => Key::Escape,
+        "Space" => Key::Space,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(character), None) if character.is_ascii_alphanumeric() => {
+                    Key::Char(character.to_ascii_lowercase())
+                }
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+impl fmt::Display for RecordedEvent {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} {} ", self.time.as_millis(), self.window)?;
+        match self.input {
+            RecordedInput::CursorMoved(position) => {
+                write!(fmt, "move {} {}", position.x, position.y)
+            }
+            RecordedInput::CursorLeft => write!(fmt, "leave"),
+            RecordedInput::MouseButton1(true) => write!(fmt, "button1 down"),
+            RecordedInput::MouseButton1(false) => write!(fmt, "button1 up"),
+            RecordedInput::Touch(pointer, phase, position) => {
+                let phase = match phase {
+                    TouchPhase::Started => "start",
+                    TouchPhase::Moved => "move",
+                    TouchPhase::Ended => "end",
+                    TouchPhase::Cancelled => "cancel",
+                };
+                write!(
+                    fmt,
+                    "touch {} {} {} {}",
+                    pointer.0, phase, position.x, position.y
+                )
+            }
+            RecordedInput::Key(key) => write!(fmt, "key {}", key_name(key)),
+            RecordedInput::KeyReleased(key) => write!(fmt, "key_up {}", key_name(key)),
+            RecordedInput::Blur => write!(fmt, "blur"),
+            // Stored as a code point so whitespace survives.
+            RecordedInput::Character(character) => write!(fmt, "char {}", character as u32),
+            RecordedInput::CloseRequested => write!(fmt, "close"),
+        }
+    }
+}
+
+impl RecordedEvent {
+    fn parse(line: &str) -> Option<RecordedEvent> {
+        let mut words = line.split_whitespace();
+        let time = Duration::from_millis(words.next()?.parse().ok()?);
+        let window = words.next()?.parse().ok()?;
+        let input = match words.next()? {
+            "move" => {
+                let x = words.next()?.parse().ok()?;
+                let y = words.next()?.parse().ok()?;
+                RecordedInput::CursorMoved(point2(x, y))
+            }
+            "leave" => RecordedInput::CursorLeft,
+            "button1" => match words.next()? {
+                "down" => RecordedInput::MouseButton1(true),
+                "up" => RecordedInput::MouseButton1(false),
+                _ => return None,
+            },
+            "touch" => {
+                let pointer = PointerId(words.next()?.parse().ok()?);
+                let phase = match words.next()? {
+                    "start" => TouchPhase::Started,
+                    "move" => TouchPhase::Moved,
+                    "end" => TouchPhase::Ended,
+                    "cancel" => TouchPhase::Cancelled,
+                    _ => return None,
+                };
+                let x = words.next()?.parse().ok()?;
+                let y = words.next()?.parse().ok()?;
+                RecordedInput::Touch(pointer, phase, point2(x, y))
+            }
+            "key" => RecordedInput::Key(parse_key(words.next()?)?),
+            "key_up" => RecordedInput::KeyReleased(parse_key(words.next()?)?),
+            "blur" => RecordedInput::Blur,
+            "char" => RecordedInput::Character(std::char::from_u32(words.next()?.parse().ok()?)?),
+            "close" => RecordedInput::CloseRequested,
+            _ => return None,
+        };
+        if words.next().is_some() {
+            return None;
+        }
+        Some(RecordedEvent {
+            time,
+            window,
+            input,
+        })
+    }
+}
+
+/// A sequence of recorded events, in the order they happened.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl FromStr for Recording {
+    type Err = ParseRecordingError;
+
+    fn from_str(string: &str) -> Result<Recording, ParseRecordingError> {
+        let events = string
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                RecordedEvent::parse(line).ok_or(ParseRecordingError { line: index + 1 })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Recording { events })
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(fmt, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// How quickly recorded events are replayed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplayTiming {
+    /// Events are sent with the same delays between them as when they
+    /// were recorded.
+    Original,
+    /// Events are sent as quickly as the runtime can process them.
+    AsFastAsPossible,
+}
+
+/// Writes events to a file as they happen, so the recording survives
+/// the application crashing.
+pub(crate) struct Recorder {
+    start: Instant,
+    output: LineWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Recorder> {
+        Ok(Recorder {
+            start: Instant::now(),
+            output: LineWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(&mut self, window: usize, input: RecordedInput) {
+        let event = RecordedEvent {
+            time: self.start.elapsed(),
+            window,
+            input,
+        };
+        if let Err(err) = writeln!(self.output, "{}", event) {
+            warn!("Failed to record event: {}", err);
+        }
+    }
+}
+
+/// Keeps track of which recorded events are due to be replayed.
+pub(crate) struct Replay {
+    events: std::vec::IntoIter<RecordedEvent>,
+    next: Option<RecordedEvent>,
+    timing: ReplayTiming,
+    start: Option<Instant>,
+}
+
+impl Replay {
+    pub fn new(recording: Recording, timing: ReplayTiming) -> Replay {
+        let mut events = recording.events.into_iter();
+        let next = events.next();
+        Replay {
+            events,
+            next,
+            timing,
+            start: None,
+        }
+    }
+
+    /// Returns the next event if it's due. Timing starts from the first
+    /// call.
+    pub fn next_due(&mut self) -> Option<RecordedEvent> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let next = self.next?;
+        if self.timing == ReplayTiming::Original && start.elapsed() < next.time {
+            return None;
+        }
+        self.next = self.events.next();
+        Some(next)
+    }
+
+    /// When the next event is due, or `None` if the replay is done.
+    pub fn next_time(&self) -> Option<Instant> {
+        let next = self.next?;
+        let start = self.start.unwrap_or_else(Instant::now);
+        Some(match self.timing {
+            ReplayTiming::Original => start + next.time,
+            ReplayTiming::AsFastAsPossible => start,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RecordedEvent, RecordedInput, Recording};
+    use crate::document::Document;
+    use crate::dom::input::{Key, PointerId, TouchPhase};
+    use crate::mox_impl::{attr, elt, event};
+    use crate::style::sized_box;
+    use euclid::{point2, size2};
+    use moxie::runtime::Runtime;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn event(millis: u64, input: RecordedInput) -> RecordedEvent {
+        RecordedEvent {
+            time: Duration::from_millis(millis),
+            window: 0,
+            input,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = Recording {
+            events: vec![
+                event(0, RecordedInput::CursorMoved(point2(4.5, 10.0))),
+                event(10, RecordedInput::MouseButton1(true)),
+                event(
+                    15,
+                    RecordedInput::Touch(PointerId(2), TouchPhase::Cancelled, point2(1.0, 2.5)),
+                ),
+                event(20, RecordedInput::Key(Key::Backspace)),
+                event(25, RecordedInput::Key(Key::Char('w'))),
+                event(26, RecordedInput::KeyReleased(Key::Char('w'))),
+                event(27, RecordedInput::Blur),
+                event(30, RecordedInput::Character(' ')),
+                event(40, RecordedInput::CursorLeft),
+                event(50, RecordedInput::CloseRequested),
+            ],
+        };
+        assert_eq!(recording.to_string().parse(), Ok(recording));
+        assert!("0 0 jump".parse::<Recording>().is_err());
+    }
+
+    fn button_document() -> (Document, Arc<AtomicUsize>) {
+        let clicks = Arc::new(AtomicUsize::new(0));
+        let counter = clicks.clone();
+        let square = sized_box("square", 20.0, 20.0);
+        let window = Runtime::new().run_once(|| {
+            let button = elt::button()
+                .set_attr(attr::style(), square)
+                .on_event(event::on_click(), move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .build();
+            elt::window()
+                .add_child(elt::view().add_child(button).build())
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        (document, clicks)
+    }
+
+    #[test]
+    fn replay_clicks() {
+        let recorded = vec![
+            event(0, RecordedInput::CursorMoved(point2(5.0, 5.0))),
+            event(100, RecordedInput::MouseButton1(true)),
+            event(150, RecordedInput::MouseButton1(false)),
+            event(200, RecordedInput::CursorMoved(point2(50.0, 50.0))),
+            event(300, RecordedInput::MouseButton1(true)),
+            event(350, RecordedInput::MouseButton1(false)),
+        ];
+
+        let (mut document, clicks) = button_document();
+        for event in &recorded {
+            document.input(&event.input);
+        }
+        assert_eq!(clicks.load(Ordering::SeqCst), 1);
+
+        let recording: Recording = Recording { events: recorded }.to_string().parse().unwrap();
+        let (mut document, clicks) = button_document();
+        for event in &recording.events {
+            document.input(&event.input);
+        }
+        assert_eq!(clicks.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Futures started with `Runtime::spawn()`. They're polled on the main
+//! thread between frames, like timers, whenever they've been woken. A
+//! future can be woken from any thread, which also wakes the event
+//! loop, so work done elsewhere can hand its result back by completing
+//! a future.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use winit::event_loop::EventLoopProxy;
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[derive(Default)]
+struct TaskQueue {
+    next_id: u64,
+    /// The pending tasks, except for any which are being polled.
+    tasks: HashMap<u64, BoxFuture>,
+    /// The tasks which were woken since they were last polled.
+    ready: Vec<u64>,
+    proxy: Option<EventLoopProxy<()>>,
+}
+
+impl TaskQueue {
+    fn wake(&mut self, id: u64) {
+        if !self.ready.contains(&id) {
+            self.ready.push(id);
+            if let Some(proxy) = &self.proxy {
+                let _ = proxy.send_event(());
+            }
+        }
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    queue: Weak<Mutex<TaskQueue>>,
+}
+
+impl TaskWaker {
+    fn wake(&self) {
+        if let Some(queue) = self.queue.upgrade() {
+            queue.lock().unwrap().wake(self.id);
+        }
+    }
+
+    fn into_waker(self: Arc<Self>) -> Waker {
+        unsafe { Waker::from_raw(raw_waker(self)) }
+    }
+}
+
+// The waker's data is an `Arc<TaskWaker>` turned into a pointer.
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(clone_waker, wake, wake_by_ref, drop_waker);
+
+fn raw_waker(waker: Arc<TaskWaker>) -> RawWaker {
+    RawWaker::new(Arc::into_raw(waker) as *const (), &WAKER_VTABLE)
+}
+
+unsafe fn clone_waker(data: *const ()) -> RawWaker {
+    let waker = ManuallyDrop::new(Arc::from_raw(data as *const TaskWaker));
+    raw_waker(Arc::clone(&waker))
+}
+
+unsafe fn wake(data: *const ()) {
+    Arc::from_raw(data as *const TaskWaker).wake();
+}
+
+unsafe fn wake_by_ref(data: *const ()) {
+    ManuallyDrop::new(Arc::from_raw(data as *const TaskWaker)).wake();
+}
+
+unsafe fn drop_waker(data: *const ()) {
+    drop(Arc::from_raw(data as *const TaskWaker));
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Tasks(Arc<Mutex<TaskQueue>>);
+
+impl fmt::Debug for Tasks {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Tasks {{ ... }}")
+    }
+}
+
+impl Tasks {
+    pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        self.0.lock().unwrap().proxy = Some(proxy);
+    }
+
+    pub(crate) fn spawn(&self, future: BoxFuture) {
+        let mut queue = self.0.lock().unwrap();
+        let id = queue.next_id;
+        queue.next_id += 1;
+        queue.tasks.insert(id, future);
+        queue.wake(id);
+    }
+
+    /// Polls the tasks which were woken, returning whether there were
+    /// any, in which case they may have changed state.
+    pub(crate) fn run_ready(&self) -> bool {
+        let ready = std::mem::take(&mut self.0.lock().unwrap().ready);
+        let mut polled = false;
+        for id in ready {
+            let task = self.0.lock().unwrap().tasks.remove(&id);
+            let mut future = match task {
+                Some(future) => future,
+                None => continue,
+            };
+            let waker = Arc::new(TaskWaker {
+                id,
+                queue: Arc::downgrade(&self.0),
+            })
+            .into_waker();
+            // The lock isn't held while polling, so the future can
+            // spawn more tasks and wake itself.
+            if let Poll::Pending = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                self.0.lock().unwrap().tasks.insert(id, future);
+            }
+            polled = true;
+        }
+        polled
+    }
+
+    /// Drops every pending task without finishing it, for shutdown.
+    pub(crate) fn clear(&self) {
+        let tasks = {
+            let mut queue = self.0.lock().unwrap();
+            queue.ready.clear();
+            std::mem::take(&mut queue.tasks)
+        };
+        // Dropping a future can wake other tasks, which needs the lock.
+        drop(tasks);
+    }
+}
@@ -0,0 +1,205 @@
+//! Timers started with `Runtime::set_timeout()` and
+//! `Runtime::set_interval()`. The queue is shared between the threads,
+//! since timers can be started from components as well as event
+//! handlers, but callbacks always run on the main thread, between
+//! frames, and the DOM is updated after them like after input.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use winit::event_loop::EventLoopProxy;
+
+struct Timer {
+    id: u64,
+    due: Instant,
+    interval: Option<Duration>,
+    callback: Box<dyn FnMut() + Send>,
+}
+
+#[derive(Default)]
+struct TimerQueue {
+    next_id: u64,
+    timers: Vec<Timer>,
+    /// The timers whose callbacks are running, which aren't in
+    /// `timers`.
+    running: Vec<u64>,
+    /// Running timers which were cancelled from a callback.
+    cancelled: Vec<u64>,
+    /// Wakes the event loop when a timer is added, in case it's waiting
+    /// for events rather than polling.
+    proxy: Option<EventLoopProxy<()>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Timers(Arc<Mutex<TimerQueue>>);
+
+impl fmt::Debug for Timers {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Timers {{ ... }}")
+    }
+}
+
+impl Timers {
+    pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<()>) {
+        self.0.lock().unwrap().proxy = Some(proxy);
+    }
+
+    pub(crate) fn schedule(
+        &self,
+        now: Instant,
+        delay: Duration,
+        interval: Option<Duration>,
+        callback: Box<dyn FnMut() + Send>,
+    ) -> TimerHandle {
+        let mut queue = self.0.lock().unwrap();
+        let id = queue.next_id;
+        queue.next_id += 1;
+        queue.timers.push(Timer {
+            id,
+            due: now + delay,
+            interval,
+            callback,
+        });
+        if let Some(proxy) = &queue.proxy {
+            let _ = proxy.send_event(());
+        }
+        TimerHandle {
+            id,
+            timers: self.clone(),
+        }
+    }
+
+    fn cancel(&self, id: u64) {
+        let mut queue = self.0.lock().unwrap();
+        queue.timers.retain(|timer| timer.id != id);
+        if queue.running.contains(&id) {
+            queue.cancelled.push(id);
+        }
+    }
+
+    /// When the next timer is due, for the event loop to wait until.
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        let queue = self.0.lock().unwrap();
+        queue.timers.iter().map(|timer| timer.due).min()
+    }
+
+    /// Runs the callbacks of the timers which are due at `now`,
+    /// returning whether there were any. Each timer runs at most once
+    /// per call, so an interval which fell behind skips the missed
+    /// runs rather than catching up.
+    pub(crate) fn run_due(&self, now: Instant) -> bool {
+        let mut due = {
+            let mut queue = self.0.lock().unwrap();
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                queue.timers.drain(..).partition(|timer| timer.due <= now);
+            queue.timers = waiting;
+            queue.running = due.iter().map(|timer| timer.id).collect();
+            due
+        };
+        if due.is_empty() {
+            return false;
+        }
+        due.sort_by_key(|timer| timer.due);
+
+        // The lock isn't held while callbacks run, so they can start
+        // and cancel timers.
+        for timer in &mut due {
+            (timer.callback)();
+        }
+
+        let mut queue = self.0.lock().unwrap();
+        for mut timer in due {
+            let interval = match timer.interval {
+                Some(interval) if !queue.cancelled.contains(&timer.id) => interval,
+                _ => continue,
+            };
+            timer.due += interval;
+            if timer.due <= now {
+                timer.due = now + interval;
+            }
+            queue.timers.push(timer);
+        }
+        queue.running.clear();
+        queue.cancelled.clear();
+        true
+    }
+
+    /// Drops every pending timer without running it, for shutdown.
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().timers.clear();
+    }
+}
+
+/// Identifies a timer started with `Runtime::set_timeout()` or
+/// `Runtime::set_interval()`, so that it can be cancelled. Dropping the
+/// handle doesn't cancel the timer.
+#[derive(Clone, Debug)]
+pub struct TimerHandle {
+    id: u64,
+    timers: Timers,
+}
+
+impl TimerHandle {
+    /// Stops the timer, so its callback won't run again. This does
+    /// nothing if it's a timeout which already ran. An interval can
+    /// cancel itself from its own callback.
+    pub fn cancel(&self) {
+        self.timers.cancel(self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Timers;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn counter() -> (Arc<AtomicUsize>, Box<dyn FnMut() + Send>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let callback = Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        (count, callback)
+    }
+
+    #[test]
+    fn timeout_fires_once() {
+        let start = Instant::now();
+        let millis = |millis| start + Duration::from_millis(millis);
+        let timers = Timers::default();
+        let (count, callback) = counter();
+        timers.schedule(start, Duration::from_millis(100), None, callback);
+        assert_eq!(timers.next_due(), Some(millis(100)));
+
+        assert!(!timers.run_due(millis(50)));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert!(timers.run_due(millis(100)));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(!timers.run_due(millis(500)));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn interval_repeats_until_cancelled() {
+        let start = Instant::now();
+        let millis = |millis| start + Duration::from_millis(millis);
+        let timers = Timers::default();
+        let (count, callback) = counter();
+        let interval = Duration::from_millis(10);
+        let handle = timers.schedule(start, interval, Some(interval), callback);
+
+        assert!(timers.run_due(millis(10)));
+        assert!(timers.run_due(millis(20)));
+        // Missed runs are skipped.
+        assert!(timers.run_due(millis(55)));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert_eq!(timers.next_due(), Some(millis(65)));
+
+        handle.cancel();
+        assert!(!timers.run_due(millis(100)));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,79 @@
+//! Showing the `title` of the hovered element in the OS's tooltip.
+
+/// Follows the tooltip text of one window. It changes as soon as the
+/// cursor moves onto an element with a different `title`, or the title
+/// of the hovered element changes, and the OS then waits its usual
+/// hover delay before showing it again. Moving onto an element without
+/// a title, or out of the window, hides it.
+#[derive(Default)]
+pub(crate) struct TooltipTracker {
+    shown: Option<String>,
+}
+
+impl TooltipTracker {
+    /// Takes the tooltip of the element hovered now, returning the text
+    /// to show, or `None` to hide it, if it changed.
+    pub fn update(&mut self, tooltip: Option<&str>) -> Option<Option<String>> {
+        if self.shown.as_deref() == tooltip {
+            return None;
+        }
+        self.shown = tooltip.map(str::to_owned);
+        Some(self.shown.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TooltipTracker;
+    use crate::document::Document;
+    use crate::mox_impl::{attr, elt};
+    use crate::runtime::RecordedInput;
+    use crate::style::sized_box;
+    use euclid::{point2, size2};
+    use moxie::runtime::Runtime;
+
+    #[test]
+    fn hover_requests_tooltip() {
+        let row = sized_box("row", 50.0, 10.0);
+        let window = Runtime::new().run_once(|| {
+            elt::window()
+                .add_child(
+                    elt::view()
+                        .set_attr(attr::title(), "Save the file".to_owned())
+                        .add_child(elt::button().set_attr(attr::style(), row).build())
+                        .build(),
+                )
+                .add_child(elt::button().set_attr(attr::style(), row).build())
+                .add_child(
+                    elt::span()
+                        .set_attr(attr::title(), "Plain text".to_owned())
+                        .add_child("Some text".to_owned())
+                        .build(),
+                )
+                .build()
+        });
+        let mut document = Document::new(window, size2(100.0, 100.0));
+        document.get_layout();
+        let mut tooltip = TooltipTracker::default();
+        let mut hover = |document: &mut Document, y| {
+            document.input(&RecordedInput::CursorMoved(point2(5.0, y)));
+            document.flush_cursor();
+            tooltip.update(document.tooltip())
+        };
+
+        // The button inherits the title of its parent.
+        assert_eq!(
+            hover(&mut document, 5.0),
+            Some(Some("Save the file".to_owned()))
+        );
+        assert_eq!(hover(&mut document, 6.0), None);
+        assert_eq!(hover(&mut document, 15.0), Some(None));
+        // Elements with a title are hovered without a background.
+        assert_eq!(
+            hover(&mut document, 25.0),
+            Some(Some("Plain text".to_owned()))
+        );
+        assert_eq!(hover(&mut document, 90.0), Some(None));
+        assert_eq!(hover(&mut document, 95.0), None);
+    }
+}
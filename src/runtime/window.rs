@@ -1,105 +1,713 @@
-use crate::dom::{Node, Window as DomWindow};
-use crate::render::Context;
+use crate::document::Document;
+use crate::dom::elements::window::IconImage;
+use crate::dom::input::{Key, KeyboardState, PointerId, TouchPhase};
+use crate::dom::{
+    ColorScheme, Decorations, Node, Window as DomWindow, WindowIcon, WindowLevel, WindowMetrics,
+    WindowState,
+};
+use crate::layout::{LogicalPoint, RectChange};
+use crate::render::{
+    Context, FrameStats, PresentMode, RenderPath, RendererFactory, SwapConfig, TextRendering,
+};
+#[cfg(feature = "webrender-interop")]
+use crate::render::{DisplayListFrame, DisplayListTarget};
+use crate::runtime::caret::{CaretBlink, CaretBlinker};
+use crate::runtime::control::WindowCommand;
+use crate::runtime::drag::{DragRequest, DragTracker};
+use crate::runtime::gesture::{GestureConfig, GestureRecognizer};
+use crate::runtime::ime::ImeTracker;
+use crate::runtime::tooltip::TooltipTracker;
+use crate::runtime::{Images, MotionState, RecordedInput};
+use crate::style::Stylesheet;
+use euclid::point2;
 use gleam::gl;
 use glutin::{ContextBuilder, ContextWrapper, PossiblyCurrent};
-use log::debug;
+use log::{debug, warn};
+use std::rc::Rc;
+use std::time::Instant;
 use winit::{
-    event::{ElementState, MouseButton, WindowEvent},
+    dpi::{LogicalPosition, PhysicalPosition},
+    event::{
+        ElementState, KeyboardInput, MouseButton, Touch, TouchPhase as WinitTouchPhase,
+        VirtualKeyCode, WindowEvent,
+    },
     event_loop::{EventLoopProxy, EventLoopWindowTarget},
-    window::{Window as WinitWindow, WindowBuilder, WindowId},
+    window::{Icon, Theme, Window as WinitWindow, WindowBuilder, WindowId},
 };
 
+/// Remembers the icon last set on a window, so that it's only decoded
+/// when the `icon` attribute changes.
+#[derive(Default)]
+struct IconState {
+    source: Option<WindowIcon>,
+}
+
+impl IconState {
+    /// Returns the icon to set on the window, or `None` if it should be
+    /// left as it is. An icon which fails to decode is logged and then
+    /// ignored until the attribute changes again.
+    fn update(&mut self, icon: Option<&WindowIcon>) -> Option<Option<IconImage>> {
+        if icon == self.source.as_ref() {
+            return None;
+        }
+        self.source = icon.cloned();
+        match icon.map(WindowIcon::decode) {
+            None => Some(None),
+            Some(Ok(image)) => Some(Some(image)),
+            Some(Err(err)) => {
+                warn!(
+                    "Keeping the previous window icon, failed to decode: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Remembers an attribute last applied to the window, so that it's only
+/// set again when the attribute changes.
+struct AppliedState<T> {
+    value: T,
+}
+
+impl<T: Copy + PartialEq> AppliedState<T> {
+    fn new(value: T) -> AppliedState<T> {
+        AppliedState { value }
+    }
+
+    /// Returns the value to set on the window, if it changed.
+    fn update(&mut self, value: T) -> Option<T> {
+        if value == self.value {
+            return None;
+        }
+        self.value = value;
+        Some(value)
+    }
+}
+
+/// Tracks whether the window is minimized or maximized, since winit
+/// doesn't report it. This follows the commands sent to the window, and
+/// a window being resized to nothing is taken as it being minimized by
+/// the user, which is how Windows reports it. Maximizing through the
+/// title bar isn't detected.
+struct StateTracker {
+    state: WindowState,
+    /// What the window goes back to when it's no longer minimized.
+    unminimized: WindowState,
+}
+
+impl StateTracker {
+    fn new() -> StateTracker {
+        StateTracker {
+            state: WindowState::Normal,
+            unminimized: WindowState::Normal,
+        }
+    }
+
+    /// Returns the new state, if it changed.
+    fn set(&mut self, state: WindowState) -> Option<WindowState> {
+        if state == self.state {
+            return None;
+        }
+        if self.state != WindowState::Minimized {
+            self.unminimized = self.state;
+        }
+        self.state = state;
+        Some(state)
+    }
+
+    fn command(&mut self, command: WindowCommand) -> Option<WindowState> {
+        match command {
+            WindowCommand::Minimize => self.set(WindowState::Minimized),
+            WindowCommand::Maximize => self.set(WindowState::Maximized),
+            WindowCommand::Restore => self.set(WindowState::Normal),
+            WindowCommand::Close => None,
+        }
+    }
+
+    fn resized(&mut self, width: u32, height: u32) -> Option<WindowState> {
+        if width == 0 && height == 0 {
+            self.set(WindowState::Minimized)
+        } else if self.state == WindowState::Minimized {
+            self.set(self.unminimized)
+        } else {
+            None
+        }
+    }
+}
+
+fn to_winit_icon(image: Option<IconImage>) -> Option<Icon> {
+    let image = image?;
+    match Icon::from_rgba(image.rgba, image.width, image.height) {
+        Ok(icon) => Some(icon),
+        Err(err) => {
+            warn!("Invalid window icon: {}", err);
+            None
+        }
+    }
+}
+
+/// What a window is drawn into.
+enum Surface {
+    Gl(ContextWrapper<PossiblyCurrent, WinitWindow>),
+    /// A window without an OpenGL context, for when one couldn't be
+    /// created, and for backends set with `Runtime::renderer()`, which
+    /// draw on their own. Otherwise nothing is drawn into it, see
+    /// `RenderPath::Headless`.
+    Plain(WinitWindow),
+}
+
+impl Surface {
+    fn window(&self) -> &WinitWindow {
+        match self {
+            Surface::Gl(gl_context) => gl_context.window(),
+            Surface::Plain(window) => window,
+        }
+    }
+
+    fn swap_buffers(&self) {
+        if let Surface::Gl(gl_context) = self {
+            if let Err(err) = gl_context.swap_buffers() {
+                warn!("Can't present the frame: {}", err);
+            }
+        }
+    }
+}
+
+/// Creates the window with an OpenGL context, preferring one with
+/// multisampling, and loads its functions. Returns why not if there's
+/// no context which works, after which the window has been closed.
+fn create_gl(
+    window_builder: WindowBuilder,
+    event_loop: &EventLoopWindowTarget<()>,
+    swap: SwapConfig,
+) -> Result<(ContextWrapper<PossiblyCurrent, WinitWindow>, Rc<dyn gl::Gl>), String> {
+    debug!("Presenting frames with {:?}", swap.mode);
+    let gl_context = ContextBuilder::new()
+        .with_vsync(swap.vsync)
+        .with_multisampling(4)
+        .with_depth_buffer(2)
+        .build_windowed(window_builder.clone(), event_loop)
+        .or_else(|err| {
+            debug!(
+                "Preferred OpenGL context failed, trying the default: {}",
+                err
+            );
+            ContextBuilder::new()
+                .with_vsync(swap.vsync)
+                .build_windowed(window_builder, event_loop)
+        })
+        .map_err(|err| format!("can't create an OpenGL context: {}", err))?;
+    let gl_context = unsafe { gl_context.make_current() }
+        .map_err(|(_, err)| format!("can't make the OpenGL context current: {}", err))?;
+    let gl = match gl_context.get_api() {
+        glutin::Api::OpenGl => unsafe {
+            gl::GlFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
+        },
+        glutin::Api::OpenGlEs => unsafe {
+            gl::GlesFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
+        },
+        glutin::Api::WebGl => return Err("WebGL isn't supported".to_owned()),
+    };
+    Ok((gl_context, gl))
+}
+
 /// Wrapper around a `winit::Window` and a `Context` for rendering the
 /// DOM.
 pub struct Window {
-    gl_context: ContextWrapper<PossiblyCurrent, WinitWindow>,
+    surface: Surface,
     context: Context,
+    icon: IconState,
+    state: StateTracker,
+    decorations: AppliedState<Decorations>,
+    level: AppliedState<WindowLevel>,
+    drag: DragTracker,
+    ime: ImeTracker,
+    gestures: GestureRecognizer,
+    tooltip: TooltipTracker,
+    caret: CaretBlinker,
+    /// The latest cursor position, before moves are coalesced.
+    cursor: Option<PhysicalPosition<f64>>,
 }
 
 impl Window {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         dom_window: Node<DomWindow>,
         event_loop: &EventLoopWindowTarget<()>,
         proxy: EventLoopProxy<()>,
+        sheet: Stylesheet,
+        keyboard: KeyboardState,
+        images: Images,
+        pixel_snap: bool,
+        text_rendering: TextRendering,
+        present_mode: PresentMode,
+        gestures: GestureConfig,
+        caret_blink: CaretBlink,
+        motion: MotionState,
+        renderer: Option<RendererFactory>,
     ) -> Window {
+        let mut icon = IconState::default();
+        let window_icon = icon
+            .update(dom_window.element().icon.as_ref())
+            .and_then(to_winit_icon);
+        let decorations = dom_window.element().decorations;
+        let level = dom_window.element().level;
         let window_builder = WindowBuilder::new()
             .with_title(&dom_window.element().title[..])
-            .with_decorations(true)
+            .with_window_icon(window_icon)
+            .with_decorations(decorations == Decorations::Full)
+            .with_always_on_top(level == WindowLevel::AlwaysOnTop)
             .with_transparent(true);
 
-        let gl_context = match ContextBuilder::new().with_multisampling(4).with_depth_buffer(2).build_windowed(window_builder.clone(), &event_loop)
-        {
-            Ok(d) => d,
-            Err(err1) => match glutin::ContextBuilder::new().build_windowed(window_builder, &event_loop)
-            {
-                Ok(d) => {
-                    println!("Preferred glutin context failed, falling back on default");
-                    d
-                }
-                Err(err2) => panic!("Can't create an OpenGL window. Please file an issue at https://github.com/dabreegster/abstreet/issues/ and include output.txt. First attempt {}, second attempt {}", err1, err2),
-            }
+        // A backend set by the app draws without a GL context of ours.
+        let gl = match renderer {
+            Some(_) => Err("the app set its own renderer".to_owned()),
+            None => create_gl(
+                window_builder.clone(),
+                event_loop,
+                SwapConfig::gl(present_mode),
+            ),
         };
-
-        // let gl_context = ContextBuilder::new()
-        // .with_gl(glutin::GlRequest::GlThenGles {
-        // opengl_version: (3, 2),
-        // opengles_version: (3, 0),
-        // })
-        // .with_multisampling(4)
-        // .with_depth_buffer(24)
-        // .build_windowed(window_builder, &event_loop)
-        // .unwrap();
-
-        let gl_context = unsafe { gl_context.make_current().unwrap() };
-
-        let gl = match gl_context.get_api() {
-            glutin::Api::OpenGl => unsafe {
-                gl::GlFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
-            },
-            glutin::Api::OpenGlEs => unsafe {
-                gl::GlesFns::load_with(|symbol| gl_context.get_proc_address(symbol) as *const _)
-            },
-            glutin::Api::WebGl => unimplemented!(),
+        let (surface, gl) = match gl {
+            Ok((gl_context, gl)) => (Surface::Gl(gl_context), Ok(gl)),
+            Err(reason) => {
+                let window = window_builder
+                    .build(event_loop)
+                    .expect("Can't create a window");
+                (Surface::Plain(window), Err(reason))
+            }
         };
 
-        let mut context = Context::new(gl, gl_context.window(), proxy, dom_window);
-        context.render();
-        gl_context.swap_buffers().unwrap();
+        let mut context = Context::new(gl, renderer.as_ref(), surface.window(), proxy, dom_window);
+        context.document.set_stylesheet(sheet);
+        context.document.set_keyboard(keyboard);
+        context.document.set_images(images);
+        context.document.set_motion(motion);
+        context.set_pixel_snap(pixel_snap);
+        context.set_text_rendering(text_rendering);
+        if context.render() {
+            surface.swap_buffers();
+        }
 
         Window {
-            gl_context,
+            surface,
             context,
+            icon,
+            state: StateTracker::new(),
+            decorations: AppliedState::new(decorations),
+            level: AppliedState::new(level),
+            drag: DragTracker::default(),
+            ime: ImeTracker::default(),
+            gestures: GestureRecognizer::new(gestures),
+            tooltip: TooltipTracker::default(),
+            caret: CaretBlinker::new(caret_blink, Instant::now()),
+            cursor: None,
         }
     }
 
+    pub fn render_path(&self) -> RenderPath {
+        self.context.render_path()
+    }
+
     pub fn window_id(&self) -> WindowId {
-        self.gl_context.window().id()
+        self.surface.window().id()
     }
 
     pub fn set_dom_window(&mut self, new_node: Node<DomWindow>) {
-        self.gl_context
-            .window()
-            .set_title(&new_node.element().title[..]);
+        let window = self.surface.window();
+        window.set_title(&new_node.element().title[..]);
+        if let Some(image) = self.icon.update(new_node.element().icon.as_ref()) {
+            window.set_window_icon(to_winit_icon(image));
+        }
+        if let Some(decorations) = self.decorations.update(new_node.element().decorations) {
+            window.set_decorations(decorations == Decorations::Full);
+        }
+        if let Some(level) = self.level.update(new_node.element().level) {
+            window.set_always_on_top(level == WindowLevel::AlwaysOnTop);
+        }
         self.context.set_dom_window(new_node);
     }
 
     pub fn render(&mut self) {
-        self.context.render();
-        self.gl_context.swap_buffers().unwrap();
+        if self.context.render() {
+            self.surface.swap_buffers();
+        }
+        self.report_caret();
+        self.report_tooltip();
+    }
+
+    /// Updates the native tooltip if the hovered element's `title`
+    /// changed.
+    fn report_tooltip(&mut self) {
+        if let Some(text) = self.tooltip.update(self.context.document.tooltip()) {
+            set_native_tooltip(self.surface.window(), text.as_deref());
+        }
+    }
+
+    /// Moves the input method to the caret, if it moved since it was
+    /// last reported, and restarts its blinking.
+    fn report_caret(&mut self) {
+        let caret = self.context.document.caret_rect();
+        self.caret.caret(caret, Instant::now());
+        // The caret was laid out in the frame just painted, so showing it
+        // again takes another one.
+        if self.sync_caret() {
+            self.surface.window().request_redraw();
+        }
+        if let Some(position) = self.ime.update(caret) {
+            let position = LogicalPosition::new(position.x as f64, position.y as f64);
+            self.surface.window().set_ime_position(position);
+        }
+    }
+
+    /// Shows or hides the caret for where it is in its blinking,
+    /// returning whether that changed.
+    fn sync_caret(&mut self) -> bool {
+        let visible = self.caret.visible(Instant::now());
+        self.context.document.set_caret_visible(visible)
+    }
+
+    /// When the caret is next shown or hidden.
+    pub fn caret_deadline(&self) -> Option<Instant> {
+        self.caret.deadline(Instant::now())
+    }
+
+    /// Repaints the caret if it blinked.
+    pub fn blink_caret(&mut self) {
+        if self.sync_caret() {
+            self.render();
+        }
+    }
+
+    /// Repaints the whole window, for when the OS asks for it.
+    pub fn redraw(&mut self) {
+        self.context.invalidate();
+        self.render();
+    }
+
+    /// The color scheme the OS reports for the window, on platforms
+    /// which report it.
+    #[cfg(target_os = "windows")]
+    pub fn system_color_scheme(&self) -> Option<ColorScheme> {
+        use winit::platform::windows::WindowExtWindows;
+        Some(to_color_scheme(self.surface.window().theme()))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn system_color_scheme(&self) -> Option<ColorScheme> {
+        None
+    }
+
+    /// Tells the DOM the color scheme changed, returning whether it
+    /// needs to be updated.
+    pub fn metrics(&self) -> WindowMetrics {
+        self.context.metrics()
+    }
+
+    /// Tells the window element that its metrics changed.
+    pub fn metrics_changed(&mut self, metrics: WindowMetrics) -> bool {
+        self.context.document.metrics(metrics)
+    }
+
+    pub fn color_scheme(&mut self, scheme: ColorScheme) -> bool {
+        let changed = self.context.document.color_scheme(scheme);
+        if changed {
+            self.context.request_frame();
+        }
+        changed
+    }
+
+    /// Tells the DOM about elements which were resized by the last
+    /// layout, returning whether it needs to be updated.
+    pub fn deliver_resizes(&mut self) -> bool {
+        let changed = self.context.document.deliver_resizes();
+        if changed {
+            self.context.request_frame();
+        }
+        changed
+    }
+
+    pub fn resizes_due(&self) -> bool {
+        self.context.document.resizes_due()
+    }
+
+    pub fn animating(&self) -> bool {
+        self.context.document.animating()
+    }
+
+    /// Converts an event into the input it's recorded as, if it's one
+    /// that gets recorded.
+    pub fn recorded_input(&self, event: &WindowEvent) -> Option<RecordedInput> {
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let scale = self.surface.window().scale_factor();
+                let position = position.to_logical::<f32>(scale);
+                Some(RecordedInput::CursorMoved(point2(position.x, position.y)))
+            }
+            WindowEvent::CursorLeft { .. } => Some(RecordedInput::CursorLeft),
+            WindowEvent::Touch(touch) => Some(self.touch_input(touch)),
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => Some(RecordedInput::MouseButton1(state == ElementState::Pressed)),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => map_key(keycode).map(match state {
+                ElementState::Pressed => RecordedInput::Key,
+                ElementState::Released => RecordedInput::KeyReleased,
+            }),
+            WindowEvent::Focused(false) => Some(RecordedInput::Blur),
+            WindowEvent::ReceivedCharacter(character) => Some(RecordedInput::Character(character)),
+            WindowEvent::CloseRequested => Some(RecordedInput::CloseRequested),
+            _ => None,
+        }
+    }
+
+    fn touch_input(&self, touch: Touch) -> RecordedInput {
+        let scale = self.surface.window().scale_factor();
+        let position = touch.location.to_logical::<f32>(scale);
+        let phase = match touch.phase {
+            WinitTouchPhase::Started => TouchPhase::Started,
+            WinitTouchPhase::Moved => TouchPhase::Moved,
+            WinitTouchPhase::Ended => TouchPhase::Ended,
+            WinitTouchPhase::Cancelled => TouchPhase::Cancelled,
+        };
+        RecordedInput::Touch(
+            PointerId::touch(touch.id),
+            phase,
+            point2(position.x, position.y),
+        )
+    }
+
+    pub fn replay(&mut self, input: &RecordedInput) -> bool {
+        let changed = self.context.document.input(input);
+        if changed {
+            self.context.request_frame();
+        }
+        changed
+    }
+
+    pub fn take_frame_stats(&mut self) -> Option<FrameStats> {
+        self.context.take_frame_stats()
+    }
+
+    pub fn painted(&self) -> bool {
+        self.context.painted()
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.context.document
+    }
+
+    #[cfg(feature = "webrender-interop")]
+    pub fn build_display_list(&mut self, target: &mut DisplayListTarget) -> DisplayListFrame {
+        target.build(&mut self.context.document)
+    }
+
+    pub fn take_layout_changes(&mut self) -> Vec<RectChange> {
+        self.context.document.take_layout_changes()
     }
 
     pub fn process(&mut self, event: WindowEvent) -> bool {
+        match event {
+            WindowEvent::Focused(focused) => {
+                self.ime.focused(focused);
+                self.caret.window_focused(focused, Instant::now());
+                self.report_caret();
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::ReceivedCharacter(_) => {
+                self.caret.typed(Instant::now());
+                if self.sync_caret() {
+                    self.surface.window().request_redraw();
+                }
+            }
+            _ => {}
+        }
+        let changed = self.dispatch(event);
+        if changed {
+            self.context.request_frame();
+        }
+        self.report_tooltip();
+        changed
+    }
+
+    /// Carries out a command from a `WindowControl`, returning whether
+    /// the DOM needs to be updated.
+    pub fn command(&mut self, command: WindowCommand) -> bool {
+        let window = self.surface.window();
+        match command {
+            WindowCommand::Minimize => window.set_minimized(true),
+            WindowCommand::Maximize => {
+                window.set_minimized(false);
+                window.set_maximized(true);
+            }
+            WindowCommand::Restore => {
+                window.set_minimized(false);
+                window.set_maximized(false);
+            }
+            WindowCommand::Close => {}
+        }
+        let changed = match command {
+            WindowCommand::Close => self.context.document.close_requested(),
+            _ => match self.state.command(command) {
+                Some(state) => self.context.document.window_state(state),
+                None => false,
+            },
+        };
+        if changed {
+            self.context.request_frame();
+        }
+        changed
+    }
+
+    /// Carries out a request from dragging a drag region.
+    fn drag(&mut self, request: DragRequest) -> bool {
+        match request {
+            DragRequest::MoveTo(position) => {
+                self.surface.window().set_outer_position(position);
+                false
+            }
+            DragRequest::ToggleMaximize => {
+                let command = match self.state.state {
+                    WindowState::Maximized => WindowCommand::Restore,
+                    _ => WindowCommand::Maximize,
+                };
+                self.command(command)
+            }
+        }
+    }
+
+    /// Passes a left button press to the drag tracker, returning
+    /// whether the press was on a drag region, and isn't for the DOM.
+    fn drag_press(&mut self) -> (bool, bool) {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => return (false, false),
+        };
+        let scale = self.surface.window().scale_factor();
+        let in_region = self
+            .context
+            .element_at(cursor.to_logical::<f32>(scale))
+            .map_or(false, |id| self.context.document.in_drag_region(id));
+        match self.drag.press(in_region, cursor, Instant::now()) {
+            (taken, Some(request)) => (taken, self.drag(request)),
+            (taken, None) => (taken, false),
+        }
+    }
+
+    /// The latest cursor position in logical pixels.
+    fn logical_cursor(&self) -> Option<LogicalPoint> {
+        let scale = self.surface.window().scale_factor();
+        let position = self.cursor?.to_logical::<f32>(scale);
+        Some(point2(position.x, position.y))
+    }
+
+    /// When a pending long press is due.
+    pub fn gesture_deadline(&self) -> Option<Instant> {
+        self.gestures.deadline()
+    }
+
+    /// Delivers a long press once the pointer has been held long enough,
+    /// returning whether the DOM needs to be updated.
+    pub fn poll_gestures(&mut self) -> bool {
+        let changed = match self.gestures.poll(Instant::now()) {
+            Some((target, gesture)) => self.context.document.gesture(target, gesture),
+            None => false,
+        };
+        if changed {
+            self.context.request_frame();
+        }
+        changed
+    }
+
+    /// Hit tests the latest cursor move, if there was one since the
+    /// last call. Moves are coalesced, so this is called once per frame.
+    pub fn flush_cursor(&mut self) -> bool {
+        let changed = self.dispatch_cursor();
+        if changed {
+            self.context.request_frame();
+        }
+        self.report_tooltip();
+        changed
+    }
+
+    fn dispatch_cursor(&mut self) -> bool {
+        match self.context.document.take_cursor_move() {
+            Some(position) => {
+                let position = LogicalPosition::new(position.x, position.y);
+                let element = self.context.element_at(position);
+                self.context.document.mouse_move(element)
+            }
+            None => false,
+        }
+    }
+
+    fn dispatch(&mut self, event: WindowEvent) -> bool {
+        // Other input has to see where the cursor is now.
+        let moved = match event {
+            WindowEvent::CursorMoved { .. } | WindowEvent::CursorLeft { .. } => false,
+            _ => self.dispatch_cursor(),
+        };
+        moved | self.dispatch_input(event)
+    }
+
+    fn dispatch_input(&mut self, event: WindowEvent) -> bool {
         match event {
             WindowEvent::Resized(size) => {
                 debug!("resize {}x{}", size.width, size.height);
-                let factor = self.gl_context.window().scale_factor();
+                let factor = self.surface.window().scale_factor();
                 self.context.resize(size, factor as f32);
                 self.render();
+                if let Some(state) = self.state.resized(size.width, size.height) {
+                    return self.context.document.window_state(state);
+                }
+            }
+            // Sent when the window moves onto a monitor with a different
+            // scale, or the monitor's scale changes.
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                debug!("scale factor {}", scale_factor);
+                self.context.resize(*new_inner_size, scale_factor as f32);
+                self.render();
             }
             WindowEvent::CursorMoved { position, .. } => {
-                let scale = self.gl_context.window().scale_factor();
-                let element = self.context.element_at(position.to_logical(scale));
-                return self.context.document.mouse_move(element);
+                self.cursor = Some(position);
+                if self.drag.dragging() {
+                    // Windows can't be placed on Wayland, so this does
+                    // nothing there.
+                    let window = self.surface.window().outer_position();
+                    if let Some(request) = window
+                        .ok()
+                        .and_then(|window| self.drag.moved(position, window))
+                    {
+                        return self.drag(request);
+                    }
+                    return false;
+                }
+                let scale = self.surface.window().scale_factor();
+                let position = position.to_logical::<f32>(scale);
+                let position = point2(position.x, position.y);
+                self.gestures.moved(position);
+                self.context.document.cursor_moved(position);
             }
             WindowEvent::MouseInput {
                 state,
@@ -107,22 +715,242 @@ impl Window {
                 ..
             } => {
                 let pressed = state == ElementState::Pressed;
-                return self.context.document.mouse_button1(pressed);
+                let now = Instant::now();
+                if pressed {
+                    let (taken, changed) = self.drag_press();
+                    if taken {
+                        self.gestures.cancel();
+                        return changed;
+                    }
+                    if let Some(position) = self.logical_cursor() {
+                        let target = self.context.document.element_at(position);
+                        self.gestures.press(target, position, now);
+                    }
+                } else if self.drag.release() {
+                    return false;
+                }
+                let changed = self.context.document.mouse_button1(pressed);
+                if pressed {
+                    return changed;
+                }
+                let gesture = match self.logical_cursor() {
+                    Some(position) => self.gestures.release(position, now),
+                    None => {
+                        self.gestures.cancel();
+                        None
+                    }
+                };
+                return match gesture {
+                    Some((target, gesture)) => {
+                        self.context.document.gesture(target, gesture) | changed
+                    }
+                    None => changed,
+                };
             }
             WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.context.document.take_cursor_move();
                 return self.context.document.mouse_move(None);
             }
+            WindowEvent::Touch(touch) => {
+                let input = self.touch_input(touch);
+                return self.context.document.input(&input);
+            }
             WindowEvent::CloseRequested => {
                 return self.context.document.close_requested();
             }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(key) = map_key(keycode) {
+                    return self.context.document.key_pressed(key);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Released,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(key) = map_key(keycode) {
+                    self.context.document.key_released(key);
+                }
+            }
+            WindowEvent::Focused(false) => {
+                self.gestures.cancel();
+                self.context.document.blur();
+            }
+            WindowEvent::ReceivedCharacter(character) => {
+                return self.context.document.character(character);
+            }
             _ => (),
         }
         false
     }
 }
 
+/// Shows `text` in the OS's tooltip for the window, after the OS's hover
+/// delay, or hides the tooltip. winit doesn't expose native tooltips on
+/// any platform yet, so nothing is shown for now.
+fn set_native_tooltip(_window: &WinitWindow, text: Option<&str>) {
+    debug!("Tooltip: {:?}", text);
+}
+
+pub(crate) fn to_color_scheme(theme: Theme) -> ColorScheme {
+    match theme {
+        Theme::Light => ColorScheme::Light,
+        Theme::Dark => ColorScheme::Dark,
+    }
+}
+
+fn map_key(keycode: VirtualKeyCode) -> Option<Key> {
+    match keycode {
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(Key::Enter),
+        VirtualKeyCode::Back => Some(Key::Backspace),
+        VirtualKeyCode::Delete => Some(Key::Delete),
+        VirtualKeyCode::Left => Some(Key::Left),
+        VirtualKeyCode::Right => Some(Key::Right),
+        VirtualKeyCode::Up => Some(Key::Up),
+        VirtualKeyCode::Down => Some(Key::Down),
+        VirtualKeyCode::Home => Some(Key::Home),
+        VirtualKeyCode::End => Some(Key::End),
+        VirtualKeyCode::Tab => Some(Key::Tab),
+        VirtualKeyCode::Escape => Some(Key::Escape),
+        VirtualKeyCode::Space => Some(Key::Space),
+        VirtualKeyCode::A => Some(Key::Char('a')),
+        VirtualKeyCode::B => Some(Key::Char('b')),
+        VirtualKeyCode::C => Some(Key::Char('c')),
+        VirtualKeyCode::D => Some(Key::Char('d')),
+        VirtualKeyCode::E => Some(Key::Char('e')),
+        VirtualKeyCode::F => Some(Key::Char('f')),
+        VirtualKeyCode::G => Some(Key::Char('g')),
+        VirtualKeyCode::H => Some(Key::Char('h')),
+        VirtualKeyCode::I => Some(Key::Char('i')),
+        VirtualKeyCode::J => Some(Key::Char('j')),
+        VirtualKeyCode::K => Some(Key::Char('k')),
+        VirtualKeyCode::L => Some(Key::Char('l')),
+        VirtualKeyCode::M => Some(Key::Char('m')),
+        VirtualKeyCode::N => Some(Key::Char('n')),
+        VirtualKeyCode::O => Some(Key::Char('o')),
+        VirtualKeyCode::P => Some(Key::Char('p')),
+        VirtualKeyCode::Q => Some(Key::Char('q')),
+        VirtualKeyCode::R => Some(Key::Char('r')),
+        VirtualKeyCode::S => Some(Key::Char('s')),
+        VirtualKeyCode::T => Some(Key::Char('t')),
+        VirtualKeyCode::U => Some(Key::Char('u')),
+        VirtualKeyCode::V => Some(Key::Char('v')),
+        VirtualKeyCode::W => Some(Key::Char('w')),
+        VirtualKeyCode::X => Some(Key::Char('x')),
+        VirtualKeyCode::Y => Some(Key::Char('y')),
+        VirtualKeyCode::Z => Some(Key::Char('z')),
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(Key::Char('0')),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(Key::Char('1')),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(Key::Char('2')),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(Key::Char('3')),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(Key::Char('4')),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(Key::Char('5')),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(Key::Char('6')),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(Key::Char('7')),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(Key::Char('8')),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(Key::Char('9')),
+        _ => None,
+    }
+}
+
 impl Drop for Window {
     fn drop(&mut self) {
         self.context.deinit();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{AppliedState, IconState, StateTracker};
+    use crate::dom::elements::window::IconImage;
+    use crate::dom::{Node, Window, WindowIcon, WindowLevel, WindowState};
+    use crate::mox_impl::{attr, elt};
+    use crate::runtime::control::WindowCommand;
+    use crate::runtime::drag::{DragRequest, DragTracker};
+    use moxie::runtime::Runtime;
+
+    #[test]
+    fn tracks_window_state() {
+        let mut state = StateTracker::new();
+        assert_eq!(
+            state.command(WindowCommand::Minimize),
+            Some(WindowState::Minimized)
+        );
+        assert_eq!(state.command(WindowCommand::Minimize), None);
+        assert_eq!(
+            state.command(WindowCommand::Maximize),
+            Some(WindowState::Maximized)
+        );
+
+        // The user minimizes the window, and brings it back maximized.
+        assert_eq!(state.resized(0, 0), Some(WindowState::Minimized));
+        assert_eq!(state.resized(800, 600), Some(WindowState::Maximized));
+        assert_eq!(state.resized(800, 500), None);
+        assert_eq!(
+            state.command(WindowCommand::Restore),
+            Some(WindowState::Normal)
+        );
+    }
+
+    #[test]
+    fn level_reaches_window() {
+        let mut runtime = Runtime::new();
+        let mut render =
+            |level| runtime.run_once(|| elt::window().set_attr(attr::level(), level).build());
+        let level = |window: Node<Window>| window.element().level;
+
+        // The window is created with the initial level, and only changes
+        // to it are set on the window after.
+        let initial = level(render(WindowLevel::Normal));
+        let mut state = AppliedState::new(initial);
+        assert_eq!(
+            state.update(level(render(WindowLevel::AlwaysOnTop))),
+            Some(WindowLevel::AlwaysOnTop)
+        );
+        assert_eq!(state.update(level(render(WindowLevel::AlwaysOnTop))), None);
+        assert_eq!(
+            state.update(level(render(WindowLevel::Normal))),
+            Some(WindowLevel::Normal)
+        );
+    }
+
+    #[test]
+    fn icon_reaches_window() {
+        let pixels = vec![255, 0, 0, 255, 0, 0, 255, 255];
+        let icon = WindowIcon::Rgba {
+            pixels: pixels.clone().into(),
+            width: 2,
+            height: 1,
+        };
+        let mut state = IconState::default();
+        assert_eq!(state.update(None), None);
+        assert_eq!(
+            state.update(Some(&icon)),
+            Some(Some(IconImage {
+                rgba: pixels,
+                width: 2,
+                height: 1,
+            }))
+        );
+        // Unchanged between renders.
+        assert_eq!(state.update(Some(&icon)), None);
+
+        // Bad data keeps the previous icon.
+        let garbage = WindowIcon::from(vec![1, 2, 3]);
+        assert_eq!(state.update(Some(&garbage)), None);
+        assert_eq!(state.update(None), Some(None));
+    }
+}
@@ -0,0 +1,342 @@
+//! Animating numeric style properties when their values change.
+
+use super::{ComputedValues, Corners, Dimension, DisplayType, Edges};
+use crate::layout::{LogicalLength, LogicalSideOffsets};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A numeric property which can be animated with the `transition`
+/// style attribute, for example `transition: tween(width, 200)`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AnimatedProperty {
+    Width,
+    Height,
+    TextSize,
+    /// All four corners.
+    CornerRadius,
+    /// All four sides.
+    Padding,
+    /// All four sides.
+    Margin,
+    /// The widths of all four sides.
+    BorderWidth,
+    FlexGrow,
+}
+
+const COUNT: usize = 8;
+
+const PROPERTIES: [AnimatedProperty; COUNT] = [
+    AnimatedProperty::Width,
+    AnimatedProperty::Height,
+    AnimatedProperty::TextSize,
+    AnimatedProperty::CornerRadius,
+    AnimatedProperty::Padding,
+    AnimatedProperty::Margin,
+    AnimatedProperty::BorderWidth,
+    AnimatedProperty::FlexGrow,
+];
+
+fn dimension(value: Option<Dimension>) -> Option<Vec<f32>> {
+    value.map(|value| {
+        let (pixels, fraction) = value.components();
+        vec![pixels, fraction]
+    })
+}
+
+fn sides(offsets: LogicalSideOffsets) -> Vec<f32> {
+    vec![offsets.top, offsets.right, offsets.bottom, offsets.left]
+}
+
+fn to_sides(numbers: &[f32]) -> LogicalSideOffsets {
+    LogicalSideOffsets::new(numbers[0], numbers[1], numbers[2], numbers[3])
+}
+
+impl AnimatedProperty {
+    /// The numbers the property's value is made of, which are
+    /// interpolated separately. Returns `None` when the value isn't a
+    /// number, such as an automatic width, so it can't be animated to
+    /// or from.
+    fn read(self, values: &ComputedValues) -> Option<Vec<f32>> {
+        let block = match values.display {
+            DisplayType::Block(ref block) => Some(block),
+            DisplayType::Inline(_) => None,
+        };
+        match self {
+            AnimatedProperty::Width => dimension(block?.width),
+            AnimatedProperty::Height => dimension(block?.height),
+            AnimatedProperty::TextSize => Some(vec![values.text_size.get()]),
+            AnimatedProperty::CornerRadius => {
+                let Corners {
+                    top_left,
+                    top_right,
+                    bottom_left,
+                    bottom_right,
+                } = values.corner_radius;
+                Some(
+                    [top_left, top_right, bottom_left, bottom_right]
+                        .iter()
+                        .map(|radius| radius.get())
+                        .collect(),
+                )
+            }
+            AnimatedProperty::Padding => Some(sides(block?.padding)),
+            AnimatedProperty::Margin => Some(sides(block?.margin)),
+            AnimatedProperty::BorderWidth => {
+                let Edges {
+                    left,
+                    right,
+                    top,
+                    bottom,
+                } = values.border;
+                Some(vec![
+                    top.width.get(),
+                    right.width.get(),
+                    bottom.width.get(),
+                    left.width.get(),
+                ])
+            }
+            AnimatedProperty::FlexGrow => Some(vec![block?.flex_grow]),
+        }
+    }
+
+    /// Sets the property from numbers in the form `read()` returns.
+    fn write(self, values: &mut ComputedValues, numbers: &[f32]) {
+        let length = LogicalLength::new;
+        let block = match values.display {
+            DisplayType::Block(ref mut block) => Some(block),
+            DisplayType::Inline(_) => None,
+        };
+        match (self, block) {
+            (AnimatedProperty::Width, Some(block)) => {
+                block.width = Some(Dimension::from_components(numbers[0], numbers[1]))
+            }
+            (AnimatedProperty::Height, Some(block)) => {
+                block.height = Some(Dimension::from_components(numbers[0], numbers[1]))
+            }
+            (AnimatedProperty::TextSize, _) => values.text_size = length(numbers[0]),
+            (AnimatedProperty::CornerRadius, _) => {
+                values.corner_radius = Corners {
+                    top_left: length(numbers[0]),
+                    top_right: length(numbers[1]),
+                    bottom_left: length(numbers[2]),
+                    bottom_right: length(numbers[3]),
+                }
+            }
+            (AnimatedProperty::Padding, Some(block)) => block.padding = to_sides(numbers),
+            (AnimatedProperty::Margin, Some(block)) => block.margin = to_sides(numbers),
+            (AnimatedProperty::BorderWidth, _) => {
+                values.border.top.width = length(numbers[0]);
+                values.border.right.width = length(numbers[1]);
+                values.border.bottom.width = length(numbers[2]);
+                values.border.left.width = length(numbers[3]);
+            }
+            (AnimatedProperty::FlexGrow, Some(block)) => block.flex_grow = numbers[0],
+            (_, None) => {}
+        }
+    }
+
+    fn index(self) -> usize {
+        PROPERTIES
+            .iter()
+            .position(|property| *property == self)
+            .unwrap()
+    }
+}
+
+/// Animates `property` over `duration` whenever its value changes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PropertyTransition {
+    pub property: AnimatedProperty,
+    pub duration: Duration,
+}
+
+/// How long each property takes to animate to a new value, set with the
+/// `transition` style attribute. Properties without a duration change at
+/// once. This isn't inherited.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Transitions {
+    durations: [Option<Duration>; COUNT],
+}
+
+impl Transitions {
+    pub fn duration(&self, property: AnimatedProperty) -> Option<Duration> {
+        self.durations[property.index()]
+    }
+
+    pub(crate) fn set(&mut self, transition: PropertyTransition) {
+        self.durations[transition.property.index()] = Some(transition.duration);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Transitions::default();
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (AnimatedProperty, Duration)> + '_ {
+        PROPERTIES
+            .iter()
+            .zip(&self.durations)
+            .filter_map(|(property, duration)| Some((*property, (*duration)?)))
+    }
+}
+
+/// A property animating from one value to another.
+struct Tween {
+    from: Vec<f32>,
+    /// The value from the styles, which is what's shown once the tween
+    /// finishes.
+    to: Vec<f32>,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Tween {
+    /// A tween which has already finished at `value`.
+    fn settled(value: Vec<f32>, now: Instant) -> Tween {
+        Tween {
+            from: value.clone(),
+            to: value,
+            start: now,
+            duration: Duration::from_secs(0),
+        }
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start).as_nanos() as f64;
+        (elapsed / self.duration.as_nanos() as f64).min(1.0) as f32
+    }
+
+    /// The value shown at `now`, moving linearly from `from` to `to`.
+    fn sample(&self, now: Instant) -> Vec<f32> {
+        let progress = self.progress(now);
+        self.from
+            .iter()
+            .zip(&self.to)
+            .map(|(from, to)| from + (to - from) * progress)
+            .collect()
+    }
+}
+
+/// Follows the properties of each node which have transitions, as their
+/// values change between style passes, and replaces the values from the
+/// styles with the ones in between while they animate.
+#[derive(Default)]
+pub(crate) struct Animator {
+    tweens: HashMap<(u64, AnimatedProperty), Tween>,
+}
+
+impl Animator {
+    /// Takes the values computed for node `id` from its styles, and
+    /// changes each property with a transition to where it is at `now`.
+    ///
+    /// A property starts animating once its value differs from the one
+    /// it was last heading to. It animates from the value which was
+    /// shown at that time, so when an animation is interrupted by
+    /// another change, the new one continues from wherever the old one
+    /// got to instead of jumping back to either end. When the value
+    /// appears for the first time, or only one of the two values is a
+    /// number, it's shown at once. Motion is reduced by showing every
+    /// change at once.
+    pub fn update(&mut self, id: u64, values: &mut ComputedValues, now: Instant, reduced: bool) {
+        let transitions = values.transitions;
+        for (property, duration) in transitions.iter() {
+            let target = match property.read(values) {
+                Some(target) => target,
+                None => {
+                    self.tweens.remove(&(id, property));
+                    continue;
+                }
+            };
+            let tween = match self.tweens.remove(&(id, property)) {
+                Some(tween) if tween.to == target => tween,
+                Some(tween) if tween.to.len() == target.len() && !reduced => Tween {
+                    from: tween.sample(now),
+                    to: target,
+                    start: now,
+                    duration,
+                },
+                _ => Tween::settled(target, now),
+            };
+            property.write(values, &tween.sample(now));
+            self.tweens.insert((id, property), tween);
+        }
+        for property in &PROPERTIES {
+            if transitions.duration(*property).is_none() {
+                self.tweens.remove(&(id, *property));
+            }
+        }
+    }
+
+    /// Whether any property is still animating at `now`, so the document
+    /// has to be styled and laid out again.
+    pub fn animating(&self, now: Instant) -> bool {
+        self.tweens.values().any(|tween| tween.progress(now) < 1.0)
+    }
+
+    /// Forgets the nodes which were removed from the tree.
+    pub fn retain(&mut self, live: impl Fn(u64) -> bool) {
+        self.tweens.retain(|&(id, _), _| live(id));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AnimatedProperty, Animator, PropertyTransition};
+    use crate::style::{ComputedValues, Dimension, DisplayType};
+    use std::time::{Duration, Instant};
+
+    fn with_width(width: f32) -> ComputedValues {
+        let mut values = ComputedValues::default();
+        if let DisplayType::Block(ref mut block) = values.display {
+            block.width = Some(Dimension::pixels(width));
+        }
+        values.transitions.set(PropertyTransition {
+            property: AnimatedProperty::Width,
+            duration: Duration::from_millis(100),
+        });
+        values
+    }
+
+    fn width(values: &ComputedValues) -> f32 {
+        match values.display {
+            DisplayType::Block(block) => block.width.unwrap().resolve(0.0).get(),
+            DisplayType::Inline(_) => panic!("not a block"),
+        }
+    }
+
+    fn shown(animator: &mut Animator, target: f32, now: Instant) -> f32 {
+        let mut values = with_width(target);
+        animator.update(1, &mut values, now, false);
+        width(&values)
+    }
+
+    #[test]
+    fn interpolates_width() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut animator = Animator::default();
+        assert_eq!(shown(&mut animator, 100.0, start), 100.0);
+        assert!(!animator.animating(start));
+
+        assert_eq!(shown(&mut animator, 200.0, start), 100.0);
+        assert!(animator.animating(start));
+        assert!((shown(&mut animator, 200.0, start + ms(50)) - 150.0).abs() < 0.01);
+        assert_eq!(shown(&mut animator, 200.0, start + ms(100)), 200.0);
+        assert!(!animator.animating(start + ms(100)));
+
+        // Interrupted halfway back, it heads to the new value from where
+        // it got to.
+        let back = start + ms(200);
+        shown(&mut animator, 100.0, back);
+        assert!((shown(&mut animator, 300.0, back + ms(50)) - 150.0).abs() < 0.01);
+        assert!((shown(&mut animator, 300.0, back + ms(100)) - 225.0).abs() < 0.01);
+
+        // Reduced motion shows changes at once.
+        let mut values = with_width(50.0);
+        animator.update(1, &mut values, back + ms(200), true);
+        assert_eq!(width(&values), 50.0);
+        assert!(!animator.animating(back + ms(200)));
+    }
+}
@@ -0,0 +1,363 @@
+//! Sizes relative to the parent's, written as `calc()` expressions.
+
+use crate::layout::LogicalLength;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A size along one axis, made of a length in pixels and a fraction of
+/// the size of the parent's content box along the same axis. Plain
+/// lengths have no fraction. `calc()` expressions are reduced to this
+/// when they're parsed, so they're resolved during layout without
+/// evaluating them again.
+///
+/// Parsed sizes can also have a multiple of the root's text size, from
+/// `rem`, which is turned into pixels when the style is applied to an
+/// element, see `resolve_rems()`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Dimension {
+    pixels: f32,
+    fraction: f32,
+    rems: f32,
+}
+
+impl From<LogicalLength> for Dimension {
+    fn from(length: LogicalLength) -> Self {
+        Dimension::pixels(length.get())
+    }
+}
+
+impl Dimension {
+    pub fn pixels(pixels: f32) -> Dimension {
+        Dimension {
+            pixels,
+            fraction: 0.0,
+            rems: 0.0,
+        }
+    }
+
+    /// A share of the parent's size, such as 0.5 for `50%`.
+    pub fn fraction(fraction: f32) -> Dimension {
+        Dimension {
+            pixels: 0.0,
+            fraction,
+            rems: 0.0,
+        }
+    }
+
+    fn rems(rems: f32) -> Dimension {
+        Dimension {
+            pixels: 0.0,
+            fraction: 0.0,
+            rems,
+        }
+    }
+
+    /// Turns the `rem` part into pixels, given the root's text size.
+    pub(crate) fn resolve_rems(self, root_text_size: f32) -> Dimension {
+        Dimension {
+            pixels: self.pixels + self.rems * root_text_size,
+            fraction: self.fraction,
+            rems: 0.0,
+        }
+    }
+
+    /// The length in pixels and the fraction of the parent's size.
+    pub(crate) fn components(self) -> (f32, f32) {
+        (self.pixels, self.fraction)
+    }
+
+    pub(crate) fn from_components(pixels: f32, fraction: f32) -> Dimension {
+        Dimension {
+            pixels,
+            fraction,
+            rems: 0.0,
+        }
+    }
+
+    /// The size in pixels, given the size of the parent's content box.
+    /// When the parent's size is unbounded, the fraction is left out.
+    pub fn resolve(self, parent: f32) -> LogicalLength {
+        if self.fraction == 0.0 || !parent.is_finite() {
+            return LogicalLength::new(self.pixels);
+        }
+        LogicalLength::new(self.pixels + self.fraction * parent)
+    }
+
+    /// Parses a length in pixels, percent or multiples of the root's
+    /// text size, such as `20px`, `50%` or `1.5rem`, or an expression
+    /// combining them, optionally wrapped in `calc()`, such as
+    /// `calc(100% - 20px)`.
+    ///
+    /// Expressions are made of `+`, `-`, `*` and `/`, where `*` and `/`
+    /// bind tighter, operators of the same precedence apply from left
+    /// to right, and parentheses group. Only lengths can be added to
+    /// lengths; at least one side of `*` has to be a plain number; and
+    /// `/` only divides by plain numbers other than zero. The result
+    /// has to be a length. Returns `None` for anything else.
+    pub fn parse(text: &str) -> Option<Dimension> {
+        let text = text.trim();
+        let expr = match text.strip_prefix("calc(") {
+            Some(rest) => rest.strip_suffix(')')?,
+            None => text,
+        };
+        let mut parser = Parser {
+            chars: expr.chars().peekable(),
+        };
+        let value = parser.sum()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return None;
+        }
+        match value {
+            Value::Length(dimension) => Some(dimension),
+            Value::Number(_) => None,
+        }
+    }
+}
+
+/// Writes the shortest percentage which parses back to `fraction`, if
+/// there's one within a few decimals.
+pub(super) fn write_percent(fraction: f32) -> String {
+    let percent = fraction * 100.0;
+    (0..9)
+        .map(|precision| format!("{:.*}", precision, percent))
+        .find(|text| text.parse::<f32>().ok().map(|value| value / 100.0) == Some(fraction))
+        .unwrap_or_else(|| percent.to_string())
+}
+
+/// Writes the dimension in the form `parse()` takes, as a plain length
+/// or percentage when it's only one of them.
+impl fmt::Display for Dimension {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let terms = [
+            (
+                self.fraction * 100.0,
+                write_percent(self.fraction.abs()),
+                "%",
+            ),
+            (self.rems, self.rems.abs().to_string(), "rem"),
+            (self.pixels, self.pixels.abs().to_string(), "px"),
+        ];
+        let mut terms = terms.iter().filter(|(value, _, _)| *value != 0.0);
+        let (value, first, unit) = match terms.next() {
+            Some(term) => term,
+            None => return write!(fmt, "0px"),
+        };
+        let sign = if *value < 0.0 { "-" } else { "" };
+        let rest = terms
+            .map(|(value, text, unit)| {
+                let operator = if *value < 0.0 { '-' } else { '+' };
+                format!(" {} {}{}", operator, text, unit)
+            })
+            .collect::<String>();
+        if rest.is_empty() {
+            write!(fmt, "{}{}{}", sign, first, unit)
+        } else {
+            write!(fmt, "calc({}{}{}{})", sign, first, unit, rest)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Value {
+    Number(f32),
+    Length(Dimension),
+}
+
+impl Value {
+    fn add(self, other: Value, sign: f32) -> Option<Value> {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => Some(Value::Number(a + sign * b)),
+            (Value::Length(a), Value::Length(b)) => Some(Value::Length(Dimension {
+                pixels: a.pixels + sign * b.pixels,
+                fraction: a.fraction + sign * b.fraction,
+                rems: a.rems + sign * b.rems,
+            })),
+            _ => None,
+        }
+    }
+
+    fn scale(self, factor: f32) -> Value {
+        match self {
+            Value::Number(value) => Value::Number(value * factor),
+            Value::Length(dimension) => Value::Length(Dimension {
+                pixels: dimension.pixels * factor,
+                fraction: dimension.fraction * factor,
+                rems: dimension.rems * factor,
+            }),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    /// Takes `c` if it's the next character after any whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&c) {
+            self.chars.next();
+            return true;
+        }
+        false
+    }
+
+    fn sum(&mut self) -> Option<Value> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value = value.add(self.product()?, 1.0)?;
+            } else if self.eat('-') {
+                value = value.add(self.product()?, -1.0)?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<Value> {
+        let mut value = self.factor()?;
+        loop {
+            if self.eat('*') {
+                value = match (value, self.factor()?) {
+                    (value, Value::Number(factor)) | (Value::Number(factor), value) => {
+                        value.scale(factor)
+                    }
+                    _ => return None,
+                };
+            } else if self.eat('/') {
+                match self.factor()? {
+                    Value::Number(divisor) if divisor != 0.0 => value = value.scale(1.0 / divisor),
+                    _ => return None,
+                }
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<Value> {
+        if self.eat('-') {
+            return Some(self.factor()?.scale(-1.0));
+        }
+        if self.eat('(') {
+            let value = self.sum()?;
+            return if self.eat(')') { Some(value) } else { None };
+        }
+        self.skip_whitespace();
+        let mut number = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_digit() && c != '.' {
+                break;
+            }
+            number.push(c);
+            self.chars.next();
+        }
+        let number = number.parse::<f32>().ok()?;
+        if self.chars.peek() == Some(&'%') {
+            self.chars.next();
+            return Some(Value::Length(Dimension::fraction(number / 100.0)));
+        }
+        if self.chars.peek() == Some(&'p') {
+            self.chars.next();
+            if self.chars.next() != Some('x') {
+                return None;
+            }
+            return Some(Value::Length(Dimension::pixels(number)));
+        }
+        if self.chars.peek() == Some(&'r') {
+            self.chars.next();
+            if self.chars.next() != Some('e') || self.chars.next() != Some('m') {
+                return None;
+            }
+            return Some(Value::Length(Dimension::rems(number)));
+        }
+        Some(Value::Number(number))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Dimension;
+    use crate::layout::LogicalLength;
+
+    fn resolve(text: &str, parent: f32) -> Option<f32> {
+        Dimension::parse(text).map(|dimension| dimension.resolve(parent).get())
+    }
+
+    #[test]
+    fn resolves_against_parent() {
+        assert_eq!(resolve("calc(100% - 20px)", 200.0), Some(180.0));
+        assert_eq!(resolve("20px", 200.0), Some(20.0));
+        assert_eq!(resolve("50%", 200.0), Some(100.0));
+        // `*` and `/` bind tighter than `+` and `-`.
+        assert_eq!(resolve("calc(10px + 50% / 2 * 3)", 200.0), Some(160.0));
+        assert_eq!(resolve("calc((10px + 50%) / 2)", 200.0), Some(55.0));
+        assert_eq!(resolve("calc(2 * -(25% - 5px))", 200.0), Some(-90.0));
+        assert_eq!(
+            Dimension::from(LogicalLength::new(3.0)),
+            Dimension::pixels(3.0)
+        );
+    }
+
+    #[test]
+    fn writes_parsable() {
+        for dimension in &[
+            Dimension::pixels(20.5),
+            Dimension::fraction(0.35),
+            Dimension::parse("calc(100% - 20px)").unwrap(),
+            Dimension::parse("calc(12.5% + 0.35px)").unwrap(),
+            Dimension::parse("-2rem").unwrap(),
+            Dimension::parse("calc(50% - 1.5rem + 4px)").unwrap(),
+        ] {
+            assert_eq!(Dimension::parse(&dimension.to_string()), Some(*dimension));
+        }
+        assert_eq!(
+            Dimension::parse("calc(100% - 20px)").unwrap().to_string(),
+            "calc(100% - 20px)"
+        );
+        assert_eq!(
+            Dimension::parse("calc(1rem - 50% + 2rem)")
+                .unwrap()
+                .to_string(),
+            "calc(-50% + 3rem)"
+        );
+    }
+
+    #[test]
+    fn resolves_rems() {
+        let dimension = Dimension::parse("calc(50% + 2rem - 4px)").unwrap();
+        assert_eq!(dimension.resolve_rems(16.0).resolve(100.0).get(), 78.0);
+        assert_eq!(dimension.resolve_rems(10.0).resolve(100.0).get(), 66.0);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for text in &[
+            "",
+            "calc()",
+            "calc(100% - 20px",
+            "calc(100% -)",
+            "calc(100% 20px)",
+            "calc(10px * 10px)",
+            "calc(10px / 0)",
+            "calc(10 / 10px)",
+            "calc(100% + 2)",
+            "calc(2 * 3)",
+            "20em",
+            "20re",
+            "20p",
+        ] {
+            assert_eq!(Dimension::parse(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,1369 @@
+//! Styles written as text, in the form of CSS declarations, so that
+//! themes can be saved, shared and edited outside of the application.
+
+use super::calc::write_percent;
+use super::{
+    root_text_size, Alignment, AnimatedProperty, Attributes, BackdropFilter, BackgroundClip,
+    BlockValues, Border, BorderStyle, Calc, ClipPath, ClipRadius, ComputedValues, Corners,
+    Dimension, Direction, DisplayType, Edges, Outline, Overflow, PropertyTransition, Style,
+    StyleData, TextShadow, ThemeColors, Transitions, Visibility,
+};
+use crate::layout::{LogicalLength, LogicalSideOffsets};
+use crate::style_impl::{apply, attribute, func, keyword};
+use crate::Color;
+use log::warn;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A property which can be written as text. `parse` sets it from the
+/// value of a declaration, `write` reads it back, or gives `None` when
+/// it's written through another property, and `copy` takes it from one
+/// set of values to another.
+struct Property {
+    name: &'static str,
+    parse: fn(&str, &mut ComputedValues) -> Option<()>,
+    write: fn(&ComputedValues) -> Option<String>,
+    copy: fn(&ComputedValues, &mut ComputedValues),
+}
+
+/// A property stored in a field of `ComputedValues`. It's set by a
+/// `parse` function which applies the value itself, or from the value
+/// `$parse` gives, which is applied through `$attribute` if one's
+/// given.
+macro_rules! property {
+    ($name:literal, |$values:ident| $field:expr, parse = $set:expr, $write:expr) => {
+        Property {
+            name: $name,
+            parse: $set,
+            write: |$values| Some($write(&$field)),
+            copy: |from, to| {
+                let value = {
+                    let $values = from;
+                    $field
+                };
+                let $values = to;
+                $field = value;
+            },
+        }
+    };
+    ($name:literal, |$values:ident| $field:expr, $attribute:path => $parse:expr, $write:expr) => {
+        property!(
+            $name,
+            |$values| $field,
+            parse = |text, values| {
+                apply(values, $attribute(), $parse(text)?);
+                Some(())
+            },
+            $write
+        )
+    };
+    ($name:literal, |$values:ident| $field:expr, $parse:expr, $write:expr) => {
+        property!(
+            $name,
+            |$values| $field,
+            parse = |text, $values| {
+                $field = $parse(text)?;
+                Some(())
+            },
+            $write
+        )
+    };
+}
+
+/// A property stored in the `BlockValues` of block elements, which
+/// inline elements don't have. It's set by a `parse` function, or from
+/// the value `$parse` gives.
+macro_rules! block_property {
+    ($name:literal, |$block:ident| $field:expr, parse = $set:expr, $write:expr) => {
+        Property {
+            name: $name,
+            parse: $set,
+            write: |values| match values.display {
+                DisplayType::Block(ref $block) => Some($write(&$field)),
+                DisplayType::Inline(_) => None,
+            },
+            copy: |from, to| {
+                if let DisplayType::Block($block) = from.display {
+                    let value = $field;
+                    if let DisplayType::Block(ref mut $block) = to.display {
+                        $field = value;
+                    }
+                }
+            },
+        }
+    };
+    ($name:literal, |$block:ident| $field:expr, $parse:expr, $write:expr) => {
+        block_property!(
+            $name,
+            |$block| $field,
+            parse = |text, values| {
+                let value = $parse(text)?;
+                if let DisplayType::Block(ref mut $block) = values.display {
+                    $field = value;
+                }
+                Some(())
+            },
+            $write
+        )
+    };
+}
+
+/// A size in the `BlockValues`, whose `rem` part is resolved when it's
+/// applied to an element, since the root's text size isn't known when
+/// it's parsed. Sizes other than `auto` are set through `$attribute`,
+/// as `calc()` expressions.
+macro_rules! size_property {
+    ($name:literal, |$block:ident| $field:expr, $attribute:path) => {
+        Property {
+            copy: |from, to| {
+                if let DisplayType::Block($block) = from.display {
+                    let value = match ($field, root_text_size()) {
+                        (Some(size), Some(root)) => Some(size.resolve_rems(root)),
+                        (value, _) => value,
+                    };
+                    if let DisplayType::Block(ref mut $block) = to.display {
+                        $field = value;
+                    }
+                }
+            },
+            ..block_property!(
+                $name,
+                |$block| $field,
+                parse = |text, values| {
+                    match parse_size(text)? {
+                        Some(size) => apply(values, $attribute(), Calc(Some(size))),
+                        None => {
+                            if let DisplayType::Block(ref mut $block) = values.display {
+                                $field = None;
+                            }
+                        }
+                    }
+                    Some(())
+                },
+                write_size
+            )
+        }
+    };
+}
+
+/// One edge of the border, which is only written when the edges differ.
+/// The attribute fills in the other edges from the parent's, which
+/// isn't known when parsing, so the edge is set directly.
+macro_rules! border_edge {
+    ($name:literal, $edge:ident) => {
+        Property {
+            name: $name,
+            parse: |text, values| {
+                values.border.$edge = parse_border(text)?;
+                Some(())
+            },
+            write: |values| {
+                if same_edges(&values.border) {
+                    return None;
+                }
+                Some(write_border(&values.border.$edge))
+            },
+            copy: |from, to| to.border.$edge = from.border.$edge,
+        }
+    };
+}
+
+/// A theme color, which is `auto` when it's left to the default.
+macro_rules! theme_color {
+    ($name:literal, $field:ident) => {
+        property!(
+            $name,
+            |values| values.theme_colors.$field,
+            parse = |text, values| {
+                match parse_theme_color(text)? {
+                    Some(color) => apply(values, attribute::$field(), color),
+                    None => values.theme_colors.$field = None,
+                }
+                Some(())
+            },
+            write_theme_color
+        )
+    };
+}
+
+/// Applies the keyword `$text` names through `$attribute`, or returns
+/// `None` for any other text.
+macro_rules! apply_keyword {
+    ($text:expr, $values:expr, $attribute:expr, { $($keyword:literal => $value:expr),+ $(,)? }) => {
+        match $text.trim() {
+            $($keyword => apply($values, $attribute, $value),)+
+            _ => return None,
+        }
+    };
+}
+
+/// The supported properties, in the order they're written. `display`
+/// comes first since it resets the other block properties, and
+/// shorthands come before the properties they cover.
+///
+/// Values are applied through the same attributes as in
+/// `define_style!`, so that they set the same fields either way.
+/// Lengths are the exception, since attributes take them in sixtieths
+/// of a pixel, and parsed lengths are kept exact so that they're
+/// written back the same.
+static PROPERTIES: &[Property] = &[
+    Property {
+        name: "display",
+        parse: |text, values| {
+            apply_keyword!(text, values, attribute::display(), {
+                "block" => keyword::block(),
+                "none" => keyword::none(),
+            });
+            Some(())
+        },
+        write: |values| Some(if values.display_none { "none" } else { "block" }.to_owned()),
+        copy: |from, to| {
+            if !from.display_none {
+                to.display = DisplayType::Block(BlockValues::default());
+            }
+            to.display_none = from.display_none;
+        },
+    },
+    property!(
+        "visibility",
+        |values| values.visibility,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::visibility(), {
+                "visible" => keyword::visible(),
+                "hidden" => keyword::hidden(),
+            });
+            Some(())
+        },
+        |visibility: &Visibility| match visibility {
+            Visibility::Visible => "visible".to_owned(),
+            Visibility::Hidden => "hidden".to_owned(),
+        }
+    ),
+    block_property!(
+        "direction",
+        |block| block.direction,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::direction(), {
+                "vertical" => keyword::vertical(),
+                "horizontal" => keyword::horizontal(),
+            });
+            Some(())
+        },
+        |direction: &Direction| match direction {
+            Direction::Vertical => "vertical".to_owned(),
+            Direction::Horizontal => "horizontal".to_owned(),
+        }
+    ),
+    size_property!("width", |block| block.width, attribute::width),
+    size_property!("height", |block| block.height, attribute::height),
+    size_property!("min_width", |block| block.min_width, attribute::min_width),
+    size_property!(
+        "min_height",
+        |block| block.min_height,
+        attribute::min_height
+    ),
+    size_property!("max_width", |block| block.max_width, attribute::max_width),
+    size_property!(
+        "max_height",
+        |block| block.max_height,
+        attribute::max_height
+    ),
+    block_property!(
+        "flex_grow",
+        |block| block.flex_grow,
+        parse = |text, values| {
+            apply(values, attribute::flex_grow(), parse_number(text)? as f64);
+            Some(())
+        },
+        |grow: &f32| grow.to_string()
+    ),
+    block_property!(
+        "align_items",
+        |block| block.align_items,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::align_items(), {
+                "start" => keyword::start(),
+                "baseline" => keyword::baseline(),
+            });
+            Some(())
+        },
+        write_alignment
+    ),
+    Property {
+        name: "align_self",
+        parse: |text, values| {
+            // There's no attribute for leaving it to the parent.
+            if text.trim() == "auto" {
+                values.align_self = None;
+                return Some(());
+            }
+            apply_keyword!(text, values, attribute::align_self(), {
+                "start" => keyword::start(),
+                "baseline" => keyword::baseline(),
+            });
+            Some(())
+        },
+        write: |values| {
+            Some(
+                values
+                    .align_self
+                    .as_ref()
+                    .map_or("auto".to_owned(), write_alignment),
+            )
+        },
+        copy: |from, to| to.align_self = from.align_self,
+    },
+    block_property!("margin", |block| block.margin, parse_sides, write_sides),
+    block_property!("padding", |block| block.padding, parse_sides, write_sides),
+    Property {
+        name: "text_size",
+        parse: |text, values| {
+            match text.trim().strip_suffix("rem") {
+                Some(rems) => values.text_size_rems = Some(parse_number(rems)?),
+                None => {
+                    values.text_size = parse_length(text)?;
+                    values.text_size_rems = None;
+                }
+            }
+            Some(())
+        },
+        write: |values| {
+            Some(match values.text_size_rems {
+                Some(rems) => format!("{}rem", rems),
+                None => write_length(&values.text_size),
+            })
+        },
+        copy: |from, to| match (from.text_size_rems, root_text_size()) {
+            (Some(rems), Some(root)) => {
+                to.text_size = LogicalLength::new(rems * root);
+                to.text_size_rems = None;
+            }
+            (rems, _) => {
+                to.text_size = from.text_size;
+                to.text_size_rems = rems;
+            }
+        },
+    },
+    property!(
+        "text_color",
+        |values| values.text_color,
+        attribute::text_color => parse_color,
+        write_color
+    ),
+    property!(
+        "text_shadow",
+        |values| values.text_shadow,
+        parse = |text, values| {
+            match parse_text_shadow(text)? {
+                Some(shadow) => apply(values, attribute::text_shadow(), shadow),
+                None => apply(values, attribute::text_shadow(), keyword::none()),
+            }
+            Some(())
+        },
+        write_text_shadow
+    ),
+    property!(
+        "background_color",
+        |values| values.background_color,
+        attribute::background_color => parse_color,
+        write_color
+    ),
+    property!(
+        "background_clip",
+        |values| values.background_clip,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::background_clip(), {
+                "border_box" => keyword::border_box(),
+                "padding_box" => keyword::padding_box(),
+                "content_box" => keyword::content_box(),
+            });
+            Some(())
+        },
+        |clip: &BackgroundClip| match clip {
+            BackgroundClip::BorderBox => "border_box".to_owned(),
+            BackgroundClip::PaddingBox => "padding_box".to_owned(),
+            BackgroundClip::ContentBox => "content_box".to_owned(),
+        }
+    ),
+    Property {
+        name: "border",
+        parse: |text, values| {
+            apply(values, attribute::border(), parse_border(text)?);
+            Some(())
+        },
+        write: |values| {
+            if !same_edges(&values.border) {
+                return None;
+            }
+            Some(write_border(&values.border.top))
+        },
+        copy: |from, to| to.border = from.border,
+    },
+    border_edge!("border_top", top),
+    border_edge!("border_right", right),
+    border_edge!("border_bottom", bottom),
+    border_edge!("border_left", left),
+    property!(
+        "corner_radius",
+        |values| values.corner_radius,
+        parse_corners,
+        write_corners
+    ),
+    Property {
+        name: "outline",
+        parse: |text, values| {
+            if text.trim() == "none" {
+                apply(values, attribute::outline(), keyword::none());
+                return Some(());
+            }
+            let line = parse_border(text)?;
+            let outline = Outline {
+                width: line.width,
+                style: line.style,
+                color: line.color,
+                offset: LogicalLength::new(0.0),
+            };
+            apply(values, attribute::outline(), outline);
+            Some(())
+        },
+        write: |values| {
+            let line = values.outline.line();
+            if line == Outline::none().line() {
+                return Some("none".to_owned());
+            }
+            Some(write_border(&line))
+        },
+        copy: |from, to| {
+            to.outline = Outline {
+                offset: to.outline.offset,
+                ..from.outline
+            }
+        },
+    },
+    property!(
+        "outline_offset",
+        |values| values.outline.offset,
+        parse_length,
+        write_length
+    ),
+    Property {
+        name: "overflow",
+        parse: |text, values| {
+            apply_keyword!(text, values, attribute::overflow(), {
+                "visible" => keyword::visible(),
+                "hidden" => keyword::hidden(),
+            });
+            Some(())
+        },
+        write: |_| None,
+        copy: |from, to| {
+            to.overflow_x = from.overflow_x;
+            to.overflow_y = from.overflow_y;
+        },
+    },
+    property!(
+        "overflow_x",
+        |values| values.overflow_x,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::overflow_x(), {
+                "visible" => keyword::visible(),
+                "hidden" => keyword::hidden(),
+            });
+            Some(())
+        },
+        write_overflow
+    ),
+    property!(
+        "overflow_y",
+        |values| values.overflow_y,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::overflow_y(), {
+                "visible" => keyword::visible(),
+                "hidden" => keyword::hidden(),
+            });
+            Some(())
+        },
+        write_overflow
+    ),
+    property!(
+        "clip",
+        |values| values.clip,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::clip(), {
+                "bounds" => keyword::bounds(),
+                "none" => keyword::none(),
+            });
+            Some(())
+        },
+        write_clip
+    ),
+    property!(
+        "clip_path",
+        |values| values.clip_path,
+        attribute::clip_path => parse_clip_path,
+        write_clip_path
+    ),
+    property!(
+        "cache",
+        |values| values.cache,
+        parse = |text, values| {
+            apply_keyword!(text, values, attribute::cache(), {
+                "contents" => keyword::contents(),
+                "none" => keyword::none(),
+            });
+            Some(())
+        },
+        write_cache
+    ),
+    property!(
+        "backdrop_filter",
+        |values| values.backdrop_filter,
+        attribute::backdrop_filter => parse_backdrop_filter,
+        write_backdrop_filter
+    ),
+    Property {
+        name: "transition",
+        parse: |text, values| {
+            let transitions = match text.trim() {
+                "none" => vec![],
+                text => text
+                    .split(',')
+                    .map(parse_transition)
+                    .collect::<Option<Vec<_>>>()?,
+            };
+            // Like in CSS, the declaration replaces any transitions
+            // which were already set.
+            apply(values, attribute::transition(), keyword::none());
+            for transition in transitions {
+                apply(values, attribute::transition(), transition);
+            }
+            Some(())
+        },
+        write: |values| Some(write_transitions(&values.transitions)),
+        copy: |from, to| to.transitions = from.transitions,
+    },
+    theme_color!("selection_background", selection_background),
+    theme_color!("selection_color", selection_color),
+];
+
+fn parse_number(text: &str) -> Option<f32> {
+    text.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// A length in pixels, such as `12px`. Zero can be written without the
+/// unit.
+fn parse_length(text: &str) -> Option<LogicalLength> {
+    let text = text.trim();
+    let number = if text == "0" {
+        text
+    } else {
+        text.strip_suffix("px")?
+    };
+    parse_number(number).map(LogicalLength::new)
+}
+
+fn write_length(length: &LogicalLength) -> String {
+    format!("{}px", length.get())
+}
+
+/// Splits off the first word, for values made of several parts.
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace)?;
+    Some((&text[..end], &text[end..]))
+}
+
+/// A color in the form `Display` writes it, `#rrggbb` or `#rrggbbaa`,
+/// or shortened to `#rgb` or `#rgba`, as `rgb(R, G, B)` or
+/// `rgba(R, G, B, A)`, or any form `Color::parse()` takes.
+fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim();
+    let components = text
+        .strip_prefix("rgba(")
+        .or_else(|| text.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'));
+    match (components, text.strip_prefix('#')) {
+        (Some(components), _) => Color::parse(&components.replace(' ', "")).ok(),
+        (None, Some(hex)) if hex.len() == 3 || hex.len() == 4 => {
+            let doubled = hex.chars().flat_map(|digit| vec![digit, digit]);
+            Color::from_hex(&doubled.collect::<String>()).ok()
+        }
+        (None, _) => Color::parse(text).ok(),
+    }
+}
+
+fn write_color(color: &Color) -> String {
+    color.to_string()
+}
+
+/// Theme colors are `auto` when they're left to the default.
+fn parse_theme_color(text: &str) -> Option<Option<Color>> {
+    match text.trim() {
+        "auto" => Some(None),
+        text => parse_color(text).map(Some),
+    }
+}
+
+fn write_theme_color(color: &Option<Color>) -> String {
+    color.map_or_else(|| "auto".to_owned(), |color| color.to_string())
+}
+
+/// Sizes are `auto` when they're left to the content.
+fn parse_size(text: &str) -> Option<Option<Dimension>> {
+    match text.trim() {
+        "auto" => Some(None),
+        text => Dimension::parse(text).map(Some),
+    }
+}
+
+fn write_size(size: &Option<Dimension>) -> String {
+    size.map_or_else(|| "auto".to_owned(), |size| size.to_string())
+}
+
+/// One to four lengths, for the top, right, bottom and left sides in
+/// that order, like CSS's `padding`. Missing sides repeat their
+/// opposite.
+fn parse_sides(text: &str) -> Option<LogicalSideOffsets> {
+    let lengths = text
+        .split_whitespace()
+        .map(parse_length)
+        .collect::<Option<Vec<_>>>()?;
+    let (top, right, bottom, left) = match lengths[..] {
+        [all] => (all, all, all, all),
+        [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+        [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+        [top, right, bottom, left] => (top, right, bottom, left),
+        _ => return None,
+    };
+    Some(LogicalSideOffsets::from_lengths(top, right, bottom, left))
+}
+
+fn write_sides(sides: &LogicalSideOffsets) -> String {
+    let lengths = [sides.top, sides.right, sides.bottom, sides.left];
+    if lengths.iter().all(|length| *length == sides.top) {
+        return write_length(&LogicalLength::new(sides.top));
+    }
+    lengths
+        .iter()
+        .map(|length| write_length(&LogicalLength::new(*length)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One length for every corner, or four, for the top left, top right,
+/// bottom right and bottom left corners in that order.
+fn parse_corners(text: &str) -> Option<Corners<LogicalLength>> {
+    let lengths = text
+        .split_whitespace()
+        .map(parse_length)
+        .collect::<Option<Vec<_>>>()?;
+    match lengths[..] {
+        [all] => Some(Corners::new_all_same(all)),
+        [top_left, top_right, bottom_right, bottom_left] => Some(Corners {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        }),
+        _ => None,
+    }
+}
+
+fn write_corners(corners: &Corners<LogicalLength>) -> String {
+    if *corners == Corners::new_all_same(corners.top_left) {
+        return write_length(&corners.top_left);
+    }
+    [
+        corners.top_left,
+        corners.top_right,
+        corners.bottom_right,
+        corners.bottom_left,
+    ]
+    .iter()
+    .map(write_length)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+const BORDER_STYLES: &[(BorderStyle, &str)] = &[
+    (BorderStyle::None, "none"),
+    (BorderStyle::Solid, "solid"),
+    (BorderStyle::Double, "double"),
+    (BorderStyle::Dotted, "dotted"),
+    (BorderStyle::Dashed, "dashed"),
+    (BorderStyle::Hidden, "hidden"),
+    (BorderStyle::Groove, "groove"),
+    (BorderStyle::Ridge, "ridge"),
+    (BorderStyle::Inset, "inset"),
+    (BorderStyle::Outset, "outset"),
+];
+
+/// A width, style and color, such as `1px solid rgb(160, 160, 160)`.
+fn parse_border(text: &str) -> Option<Border> {
+    let (width, rest) = split_word(text)?;
+    let (style, color) = split_word(rest)?;
+    let style = BORDER_STYLES
+        .iter()
+        .find(|(_, name)| *name == style)
+        .map(|(style, _)| *style)?;
+    Some(Border {
+        width: parse_length(width)?,
+        style,
+        color: parse_color(color)?,
+    })
+}
+
+fn write_border(border: &Border) -> String {
+    let style = BORDER_STYLES
+        .iter()
+        .find(|(style, _)| *style == border.style)
+        .map(|(_, name)| *name)
+        .unwrap();
+    format!(
+        "{} {} {}",
+        write_length(&border.width),
+        style,
+        write_color(&border.color)
+    )
+}
+
+fn same_edges(edges: &Edges<Border>) -> bool {
+    *edges == Edges::new_all_same(edges.top)
+}
+
+fn write_alignment(alignment: &Alignment) -> String {
+    match alignment {
+        Alignment::Start => "start".to_owned(),
+        Alignment::Baseline => "baseline".to_owned(),
+    }
+}
+
+fn write_overflow(overflow: &Overflow) -> String {
+    match overflow {
+        Overflow::Visible => "visible".to_owned(),
+        Overflow::Hidden => "hidden".to_owned(),
+    }
+}
+
+/// The text inside `name(...)`.
+fn function<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.trim()
+        .strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+/// `none`, `circle(R)` with a length or a percentage of the shorter
+/// side, or `inset(SIDES)` with the sides as for `padding`, optionally
+/// followed by `round R`.
+fn parse_clip_path(text: &str) -> Option<ClipPath> {
+    if text.trim() == "none" {
+        return Some(ClipPath::None);
+    }
+    if let Some(radius) = function(text, "circle") {
+        let radius = match radius.trim().strip_suffix('%') {
+            Some(percent) => ClipRadius::Fraction(parse_number(percent)? / 100.0),
+            None => ClipRadius::Length(parse_length(radius)?),
+        };
+        return ClipPath::circle(radius).ok();
+    }
+    let inset = function(text, "inset")?;
+    let (offsets, radius) = match inset.find("round") {
+        Some(index) => (&inset[..index], parse_length(&inset[index + 5..])?),
+        None => (inset, LogicalLength::new(0.0)),
+    };
+    ClipPath::inset(parse_sides(offsets)?, radius).ok()
+}
+
+fn write_clip_path(clip_path: &ClipPath) -> String {
+    match *clip_path {
+        ClipPath::None => "none".to_owned(),
+        ClipPath::Circle(ClipRadius::Length(radius)) => {
+            format!("circle({})", write_length(&radius))
+        }
+        ClipPath::Circle(ClipRadius::Fraction(fraction)) => {
+            format!("circle({}%)", write_percent(fraction))
+        }
+        ClipPath::Inset { offsets, radius } if radius.get() == 0.0 => {
+            format!("inset({})", write_sides(&offsets))
+        }
+        ClipPath::Inset { offsets, radius } => format!(
+            "inset({} round {})",
+            write_sides(&offsets),
+            write_length(&radius)
+        ),
+    }
+}
+
+/// `bounds` clips to the border box, and `none` doesn't.
+fn write_clip(clip: &bool) -> String {
+    if *clip { "bounds" } else { "none" }.to_owned()
+}
+
+/// `contents` caches the painted element and its children, and `none`
+/// doesn't.
+fn write_cache(cache: &bool) -> String {
+    if *cache { "contents" } else { "none" }.to_owned()
+}
+
+/// `none`, or the horizontal and vertical offsets, the blur radius and
+/// the color, such as `1px 1px 2px #000`. The blur can be left out for a
+/// sharp shadow, and the lengths can be written without `px`.
+fn parse_text_shadow(text: &str) -> Option<Option<TextShadow>> {
+    if text.trim() == "none" {
+        return Some(None);
+    }
+    let length =
+        |text: &str| parse_length(text).or_else(|| parse_number(text).map(LogicalLength::new));
+    let (offset_x, rest) = split_word(text)?;
+    let (offset_y, rest) = split_word(rest)?;
+    let (blur, color) = match split_word(rest) {
+        Some((blur, color)) if length(blur).is_some() => (length(blur)?, color),
+        _ => (LogicalLength::new(0.0), rest),
+    };
+    if blur.get() < 0.0 {
+        return None;
+    }
+    Some(Some(TextShadow {
+        offset_x: length(offset_x)?,
+        offset_y: length(offset_y)?,
+        blur,
+        color: parse_color(color)?,
+    }))
+}
+
+fn write_text_shadow(shadow: &Option<TextShadow>) -> String {
+    match shadow {
+        None => "none".to_owned(),
+        Some(shadow) => format!(
+            "{} {} {} {}",
+            write_length(&shadow.offset_x),
+            write_length(&shadow.offset_y),
+            write_length(&shadow.blur),
+            write_color(&shadow.color)
+        ),
+    }
+}
+
+const ANIMATED_PROPERTIES: &[(AnimatedProperty, &str)] = &[
+    (AnimatedProperty::Width, "width"),
+    (AnimatedProperty::Height, "height"),
+    (AnimatedProperty::TextSize, "text_size"),
+    (AnimatedProperty::CornerRadius, "corner_radius"),
+    (AnimatedProperty::Padding, "padding"),
+    (AnimatedProperty::Margin, "margin"),
+    (AnimatedProperty::BorderWidth, "border_width"),
+    (AnimatedProperty::FlexGrow, "flex_grow"),
+];
+
+/// A property and how long it animates for, such as `width 200ms` or
+/// `padding 0.5s`. Negative durations are invalid.
+fn parse_transition(text: &str) -> Option<PropertyTransition> {
+    let (name, duration) = split_word(text)?;
+    let property = ANIMATED_PROPERTIES
+        .iter()
+        .find(|(_, property)| *property == name.replace('-', "_"))
+        .map(|(property, _)| *property)?;
+    let duration = duration.trim();
+    let milliseconds = match duration.strip_suffix("ms") {
+        Some(milliseconds) => parse_number(milliseconds)?,
+        None => parse_number(duration.strip_suffix('s')?)? * 1000.0,
+    };
+    if milliseconds < 0.0 {
+        return None;
+    }
+    Some(func::tween(property, milliseconds as f64))
+}
+
+/// The transitions separated by commas, or `none`.
+fn write_transitions(transitions: &Transitions) -> String {
+    let transitions = transitions
+        .iter()
+        .map(|(property, duration)| {
+            let name = ANIMATED_PROPERTIES
+                .iter()
+                .find(|(animated, _)| *animated == property)
+                .map(|(_, name)| *name)
+                .unwrap();
+            format!("{} {}ms", name, duration.as_secs_f64() * 1000.0)
+        })
+        .collect::<Vec<_>>();
+    if transitions.is_empty() {
+        return "none".to_owned();
+    }
+    transitions.join(", ")
+}
+
+fn parse_backdrop_filter(text: &str) -> Option<BackdropFilter> {
+    if text.trim() == "none" {
+        return Some(BackdropFilter::None);
+    }
+    let radius = parse_length(function(text, "blur")?)?;
+    if radius.get() < 0.0 {
+        return None;
+    }
+    Some(BackdropFilter::Blur(radius))
+}
+
+fn write_backdrop_filter(filter: &BackdropFilter) -> String {
+    match filter {
+        BackdropFilter::None => "none".to_owned(),
+        BackdropFilter::Blur(radius) => format!("blur({})", write_length(radius)),
+    }
+}
+
+/// Values which differ from the defaults in every supported property,
+/// for telling which properties a style sets: applied on top of these
+/// and on top of the defaults, the properties it sets come out the same.
+fn sentinel_values() -> ComputedValues {
+    let length = LogicalLength::new(-1.0);
+    let color = Color::new(1, 2, 3, 4);
+    let mut transitions = Transitions::default();
+    for (property, _) in ANIMATED_PROPERTIES {
+        transitions.set(PropertyTransition {
+            property: *property,
+            duration: Duration::from_nanos(1),
+        });
+    }
+    let border = Border {
+        width: length,
+        style: BorderStyle::Groove,
+        color,
+    };
+    ComputedValues {
+        display: DisplayType::Block(BlockValues {
+            direction: Direction::Horizontal,
+            margin: LogicalSideOffsets::new_all_same(-1.0),
+            padding: LogicalSideOffsets::new_all_same(-1.0),
+            width: Some(length.into()),
+            height: Some(length.into()),
+            min_width: Some(length.into()),
+            min_height: Some(length.into()),
+            max_width: Some(length.into()),
+            max_height: Some(length.into()),
+            flex_grow: -1.0,
+            align_items: Alignment::Baseline,
+        }),
+        display_none: true,
+        visibility: Visibility::Hidden,
+        align_self: Some(Alignment::Baseline),
+        text_size: length,
+        text_size_rems: None,
+        text_color: color,
+        text_shadow: Some(TextShadow {
+            offset_x: length,
+            offset_y: length,
+            blur: length,
+            color,
+        }),
+        background_color: color,
+        background_clip: BackgroundClip::ContentBox,
+        border: Edges::new_all_same(border),
+        corner_radius: Corners::new_all_same(length),
+        overflow_x: Overflow::Hidden,
+        overflow_y: Overflow::Hidden,
+        clip: true,
+        clip_path: ClipPath::Circle(ClipRadius::Fraction(-1.0)),
+        cache: true,
+        backdrop_filter: BackdropFilter::Blur(length),
+        theme_colors: ThemeColors {
+            selection_background: Some(color),
+            selection_color: Some(color),
+        },
+        outline: Outline {
+            width: length,
+            style: BorderStyle::Groove,
+            color,
+            offset: length,
+        },
+        transitions,
+    }
+}
+
+/// The declarations of a style parsed by `Style::from_css_string()`.
+pub struct CssDeclarations {
+    /// The values after parsing every declaration, on top of the
+    /// defaults.
+    values: ComputedValues,
+    /// The declared properties, in order.
+    properties: Vec<&'static Property>,
+}
+
+impl fmt::Debug for CssDeclarations {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list()
+            .entries(self.properties.iter().map(|property| property.name))
+            .finish()
+    }
+}
+
+impl CssDeclarations {
+    pub(crate) fn apply(&self, values: &mut ComputedValues) {
+        for property in &self.properties {
+            (property.copy)(&self.values, values);
+        }
+    }
+}
+
+/// A declaration which couldn't be parsed by `Style::from_str()`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseCssError {
+    /// A declaration without a colon.
+    MissingValue(String),
+    /// A property which isn't supported, by name.
+    UnknownProperty(String),
+    /// A property and the value which isn't valid for it.
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ParseCssError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseCssError::MissingValue(declaration) => {
+                write!(fmt, "declaration without a value: {}", declaration)
+            }
+            ParseCssError::UnknownProperty(name) => write!(fmt, "unknown property: {}", name),
+            ParseCssError::InvalidValue(name, value) => {
+                write!(fmt, "invalid value for {}: {}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseCssError {}
+
+/// A parsed style, and the declarations which were skipped.
+#[derive(Clone)]
+struct Parsed {
+    style: Style,
+    errors: Vec<ParseCssError>,
+}
+
+thread_local! {
+    /// The styles parsed on this thread, by their text, so that parsing
+    /// the same text again gives the same style.
+    static PARSED: RefCell<HashMap<String, Parsed>> = RefCell::new(HashMap::new());
+}
+
+/// Parses the declarations, skipping those which can't be parsed, or
+/// gives the style which was parsed from the same text before.
+fn parse(text: &str) -> Parsed {
+    if let Some(parsed) = PARSED.with(|parsed| parsed.borrow().get(text).cloned()) {
+        return parsed;
+    }
+
+    let mut values = ComputedValues::default();
+    let mut properties = vec![];
+    let mut errors = vec![];
+    for declaration in text.split(';').map(str::trim) {
+        if declaration.is_empty() {
+            continue;
+        }
+        let colon = match declaration.find(':') {
+            Some(colon) => colon,
+            None => {
+                errors.push(ParseCssError::MissingValue(declaration.to_owned()));
+                continue;
+            }
+        };
+        let name = declaration[..colon].trim().replace('-', "_");
+        let value = &declaration[colon + 1..];
+        let property = match PROPERTIES.iter().find(|property| property.name == name) {
+            Some(property) => property,
+            None => {
+                errors.push(ParseCssError::UnknownProperty(name));
+                continue;
+            }
+        };
+        if (property.parse)(value, &mut values).is_none() {
+            errors.push(ParseCssError::InvalidValue(name, value.trim().to_owned()));
+            continue;
+        }
+        properties.push(property);
+    }
+
+    let css = CssDeclarations { values, properties };
+    let style = Style(Box::leak(Box::new(StyleData {
+        attributes: Attributes {
+            apply: |_| {},
+            get_attributes: Vec::new,
+        },
+        css: Some(Box::leak(Box::new(css))),
+        sub_styles: &[],
+        name: "css",
+        file: file!(),
+        line: line!(),
+    })));
+    let parsed = Parsed { style, errors };
+    PARSED.with(|cache| {
+        cache.borrow_mut().insert(text.to_owned(), parsed.clone());
+    });
+    parsed
+}
+
+/// Parses a style like `Style::from_css_string()`, but fails at the
+/// first declaration which isn't supported or valid instead of
+/// skipping it.
+impl FromStr for Style {
+    type Err = ParseCssError;
+
+    fn from_str(text: &str) -> Result<Style, ParseCssError> {
+        let parsed = parse(text);
+        match parsed.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(parsed.style),
+        }
+    }
+}
+
+impl Style {
+    /// Parses a style from declarations of the form `name: value`,
+    /// separated by semicolons, such as `background_color: rgb(50, 180,
+    /// 200); padding: 4px 8px`. The names are those of the attributes
+    /// in `define_style!`, with hyphens allowed in place of
+    /// underscores. Lengths are in pixels, sizes can also be
+    /// percentages or `calc()` expressions, and sizes and `text_size`
+    /// can be in `rem`, multiples of the root element's text size.
+    /// Transitions are written as in CSS, such as `transition: width
+    /// 200ms, padding 0.5s`, and replace any transitions already set.
+    ///
+    /// Like in CSS, unknown properties and invalid values are skipped,
+    /// logging a warning, and later declarations override earlier ones.
+    /// Use `str::parse()` to get an error for them instead.
+    ///
+    /// Styles live for the rest of the program, like those from
+    /// `define_style!`. Each thread keeps the styles it parsed by their
+    /// text, so parsing the same text again gives the same style
+    /// without using more memory, but themes with many variations
+    /// should still be parsed once rather than on every update.
+    pub fn from_css_string(text: &str) -> Style {
+        let first = PARSED.with(|parsed| !parsed.borrow().contains_key(text));
+        let parsed = parse(text);
+        if first {
+            for error in &parsed.errors {
+                warn!("Ignoring {}", error);
+            }
+        }
+        parsed.style
+    }
+
+    /// Writes the properties the style sets, in the form
+    /// `from_css_string()` takes, so that parsing the result gives a
+    /// style which sets the same values.
+    ///
+    /// Values which depend on the parent's, such as edges left out of
+    /// `padding`, are written as they resolve against the defaults.
+    /// Transitions are only written when the style replaces all of the
+    /// element's, since those from `define_style!` add to the ones
+    /// already set. Sub-styles, such as those for hovered elements, are
+    /// left out, since their selectors can't be written.
+    pub fn to_css_string(self) -> String {
+        let apply = |values: &mut ComputedValues| {
+            (self.0.attributes.apply)(values);
+            if let Some(css) = self.0.css {
+                css.apply(values);
+            }
+        };
+        let mut defaults = ComputedValues::default();
+        let mut sentinels = sentinel_values();
+        illicit::Layer::new()
+            .offer(ComputedValues::default())
+            .enter(|| {
+                apply(&mut defaults);
+                apply(&mut sentinels);
+            });
+        PROPERTIES
+            .iter()
+            .filter_map(|property| {
+                let value = (property.write)(&defaults)?;
+                if (property.write)(&sentinels).as_ref() != Some(&value) {
+                    return None;
+                }
+                Some(format!("{}: {}", property.name, value))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParseCssError;
+    use crate::layout::{LogicalLength, LogicalSideOffsets};
+    use crate::style::{
+        test_style, AnimatedProperty, Border, BorderStyle, ComputedValues, DisplayType, Edges,
+        Overflow, Style, TextShadow,
+    };
+    use crate::Color;
+    use std::time::Duration;
+
+    fn computed(style: Style) -> ComputedValues {
+        let mut values = ComputedValues::default();
+        (style.0.attributes.apply)(&mut values);
+        if let Some(css) = style.0.css {
+            css.apply(&mut values);
+        }
+        values
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "direction: horizontal; width: calc(100% - 20px); flex_grow: 2; \
+                    align_items: baseline; align_self: start; \
+                    margin: 1px 2px; padding: 0.35px; text_color: rgba(10, 20, 30, 128); \
+                    text_shadow: 1px -2px 3px #336; background_clip: padding_box; \
+                    border_top: 1px solid rgb(160, 160, 160); corner_radius: 1px 2px 3px 4px; \
+                    outline: 2px dashed rgb(0, 0, 255); outline_offset: 3px; overflow_x: hidden; \
+                    clip: bounds; clip_path: inset(1px 2px 3px 4px round 5px); cache: contents; \
+                    backdrop_filter: blur(4px); selection_background: rgb(1, 2, 3)";
+        let style = Style::from_css_string(text);
+        let written = style.to_css_string();
+        let parsed = Style::from_css_string(&written);
+        assert_eq!(parsed.to_css_string(), written);
+        assert_eq!(computed(parsed), computed(style));
+        assert!(written.starts_with("direction: horizontal; width: calc(100% - 20px); "));
+        assert!(written.contains("; border_top: 1px solid #a0a0a0; corner_radius: "));
+
+        // Styles from code are written by what they set.
+        let compiled = test_style("compiled", |values| {
+            values.background_color = Color::new(50, 180, 200, 255);
+            values.overflow_y = Overflow::Hidden;
+            values.border = Edges::new_all_same(Border {
+                width: LogicalLength::new(1.5),
+                style: BorderStyle::Solid,
+                color: Color::white(),
+            });
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.padding = LogicalSideOffsets::new(1.0, 2.0, 3.0, 4.0);
+            }
+        });
+        assert_eq!(
+            compiled.to_css_string(),
+            "padding: 1px 2px 3px 4px; background_color: #32b4c8; \
+             border: 1.5px solid #ffffff; overflow_y: hidden"
+        );
+        let parsed = Style::from_css_string(&compiled.to_css_string());
+        assert_eq!(computed(parsed), computed(compiled));
+    }
+
+    #[test]
+    fn skips_unknown_and_invalid() {
+        let style = Style::from_css_string(
+            "font_family: serif; text-color: rgb(255, 0, 0); width: 10em; height; \
+             text_size: 20px; text_size: 24px;",
+        );
+        assert_eq!(
+            style.to_css_string(),
+            "text_size: 24px; text_color: #ff0000"
+        );
+        assert_eq!(Style::from_css_string("").to_css_string(), "");
+        // Lengths relative to the root are kept until they're applied to
+        // an element.
+        assert_eq!(
+            Style::from_css_string("width: calc(50% - 1rem); text_size: 1.5rem").to_css_string(),
+            "width: calc(50% - 1rem); text_size: 1.5rem"
+        );
+    }
+
+    #[test]
+    fn parses_text_shadow() {
+        let style = Style::from_css_string("text_shadow: 1 1 2 #000");
+        assert_eq!(style.to_css_string(), "text_shadow: 1px 1px 2px #000000");
+        assert_eq!(
+            computed(style).text_shadow,
+            Some(TextShadow {
+                offset_x: LogicalLength::new(1.0),
+                offset_y: LogicalLength::new(1.0),
+                blur: LogicalLength::new(2.0),
+                color: Color::black(),
+            })
+        );
+        let sharp = Style::from_css_string("text_shadow: 0 -1px rgba(255, 255, 255, 128)");
+        assert_eq!(
+            computed(sharp).text_shadow,
+            Some(TextShadow {
+                offset_x: LogicalLength::new(0.0),
+                offset_y: LogicalLength::new(-1.0),
+                blur: LogicalLength::new(0.0),
+                color: Color::new(255, 255, 255, 128),
+            })
+        );
+        assert_eq!(
+            Style::from_css_string("text_shadow: none").to_css_string(),
+            "text_shadow: none"
+        );
+        for invalid in &["1 1", "1 1 -2 #000", "1 1 2 #00", "1 x 2 #000"] {
+            let style = Style::from_css_string(&format!("text_shadow: {}", invalid));
+            assert_eq!(style.to_css_string(), "", "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reports_unknown_and_invalid() {
+        assert_eq!(
+            "font_family: serif; width: 10px".parse::<Style>(),
+            Err(ParseCssError::UnknownProperty("font_family".to_owned()))
+        );
+        assert_eq!(
+            "width: 10em".parse::<Style>(),
+            Err(ParseCssError::InvalidValue(
+                "width".to_owned(),
+                "10em".to_owned()
+            ))
+        );
+        assert_eq!(
+            "height".parse::<Style>(),
+            Err(ParseCssError::MissingValue("height".to_owned()))
+        );
+        let style: Style = "width: 10px; overflow: hidden".parse().unwrap();
+        assert_eq!(
+            style.to_css_string(),
+            "width: 10px; overflow_x: hidden; overflow_y: hidden"
+        );
+    }
+
+    #[test]
+    fn reuses_parsed_styles() {
+        let text = "padding: 3px; text_color: #123";
+        let style = Style::from_css_string(text);
+        assert_eq!(Style::from_css_string(text), style);
+        assert_eq!(text.parse::<Style>(), Ok(style));
+        assert_ne!(Style::from_css_string("padding: 4px"), style);
+    }
+
+    #[test]
+    fn parses_transitions() {
+        let style = Style::from_css_string("transition: width 200ms, padding-bottom 1s");
+        assert_eq!(style.to_css_string(), "");
+
+        let style = Style::from_css_string("transition: width 200ms, corner-radius 0.5s");
+        let transitions = computed(style).transitions;
+        assert_eq!(
+            transitions.duration(AnimatedProperty::Width),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            transitions.duration(AnimatedProperty::CornerRadius),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(transitions.duration(AnimatedProperty::Height), None);
+        assert_eq!(
+            style.to_css_string(),
+            "transition: width 200ms, corner_radius 500ms"
+        );
+        assert_eq!(
+            Style::from_css_string("transition: none").to_css_string(),
+            "transition: none"
+        );
+        for invalid in &["width", "width -1s", "width 200", "opacity 1s"] {
+            let text = format!("transition: {}", invalid);
+            assert!(text.parse::<Style>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -1,7 +1,23 @@
 use crate::dom::element::ElementState;
-use crate::layout::{LogicalLength, LogicalSideOffsets};
+use crate::layout::{LogicalLength, LogicalRect, LogicalSideOffsets};
 use crate::Color;
+use euclid::{size2, vec2};
 use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+
+mod animation;
+mod calc;
+mod css;
+mod paint;
+mod user_agent;
+
+pub(crate) use animation::Animator;
+pub use animation::{AnimatedProperty, PropertyTransition, Transitions};
+pub use calc::Dimension;
+pub use css::{CssDeclarations, ParseCssError};
+pub use paint::PaintDetails;
+pub use user_agent::default_style;
 
 /// Specifies which direction layout should be performed in.
 #[derive(Clone, PartialEq, Copy, Debug)]
@@ -10,6 +26,20 @@ pub(crate) enum Direction {
     Horizontal,
 }
 
+/// Where the children of a row are placed across it, set for all of
+/// them with `align_items` on the row, or for one child with
+/// `align_self`.
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub(crate) enum Alignment {
+    /// At the top of the row.
+    Start,
+    /// Shifted down so that the first lines of their text share a
+    /// baseline, which is as low as the lowest of them. A child without
+    /// any text uses the bottom edge of its margin instead. In a column
+    /// this is the same as `Start`.
+    Baseline,
+}
+
 #[derive(Default, PartialEq, Clone, Copy, Debug)]
 pub(crate) struct InlineValues {}
 
@@ -18,12 +48,17 @@ pub(crate) struct BlockValues {
     pub direction: Direction,
     pub margin: LogicalSideOffsets,
     pub padding: LogicalSideOffsets,
-    pub width: Option<LogicalLength>,
-    pub height: Option<LogicalLength>,
-    pub min_width: Option<LogicalLength>,
-    pub min_height: Option<LogicalLength>,
-    pub max_width: Option<LogicalLength>,
-    pub max_height: Option<LogicalLength>,
+    pub width: Option<Dimension>,
+    pub height: Option<Dimension>,
+    pub min_width: Option<Dimension>,
+    pub min_height: Option<Dimension>,
+    pub max_width: Option<Dimension>,
+    pub max_height: Option<Dimension>,
+    /// The share of any space left over in the parent, along its
+    /// direction, which this box grows by.
+    pub flex_grow: f32,
+    /// How the children are placed across a row.
+    pub align_items: Alignment,
 }
 
 impl Default for BlockValues {
@@ -38,6 +73,8 @@ impl Default for BlockValues {
             min_height: None,
             max_width: None,
             max_height: None,
+            flex_grow: 0.0,
+            align_items: Alignment::Start,
         }
     }
 }
@@ -55,7 +92,7 @@ pub(crate) enum DisplayType {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
-pub(crate) struct Edges<Value> {
+pub struct Edges<Value> {
     pub left: Value,
     pub right: Value,
     pub top: Value,
@@ -87,6 +124,181 @@ impl<Value> Edges<Value> {
     }
 }
 
+/// Whether children are painted outside of an element's box, set for
+/// each axis with `overflow_x` and `overflow_y`, or for both with
+/// `overflow`.
+///
+/// When only one axis is hidden, children are cut off at the element's
+/// inner border edges on that axis, and can still extend past the
+/// element on the other. The element's rounded corners only clip when
+/// both axes are hidden.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    Visible,
+    /// Children are clipped to the inside of the element's border,
+    /// including its rounded corners.
+    Hidden,
+}
+
+/// Which box of an element its background color fills, set with
+/// `background_clip`. The default, `BorderBox`, fills under the border
+/// too, which shows through borders that are dashed or translucent.
+///
+/// The background keeps to the rounded corners of the box it fills.
+/// Inside of the border, each radius is reduced by the border and
+/// padding next to it, the same way the inside of a rounded border is
+/// curved, so a `PaddingBox` background meets the border without gaps.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BackgroundClip {
+    BorderBox,
+    /// Fills inside of the border.
+    PaddingBox,
+    /// Fills inside of the padding, leaving it unpainted.
+    ContentBox,
+}
+
+/// Whether an element is painted. Unlike `display: none`, a hidden
+/// element keeps its place in the layout. It's inherited, and children
+/// can be made visible again inside a hidden parent.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Visibility {
+    Visible,
+    /// The element isn't painted, and isn't hit by the cursor, so
+    /// anything behind it gets the clicks instead.
+    Hidden,
+}
+
+/// A shape which an element and everything it contains is clipped to.
+/// This only affects painting: the element is still hit tested as its
+/// whole rectangular box.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ClipPath {
+    None,
+    /// A circle in the middle of the border box.
+    Circle(ClipRadius),
+    /// The border box shrunk by `offsets`, with each corner rounded by
+    /// `radius`.
+    Inset {
+        offsets: LogicalSideOffsets,
+        radius: LogicalLength,
+    },
+}
+
+/// The radius of a `ClipPath::Circle`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ClipRadius {
+    Length(LogicalLength),
+    /// A fraction of the shorter side of the border box, so 0.5 gives
+    /// the largest circle which fits.
+    Fraction(f32),
+}
+
+/// A clip path was given a negative or non-finite size.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct InvalidClipPath;
+
+impl fmt::Display for InvalidClipPath {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "clip path sizes must be finite and not negative")
+    }
+}
+
+impl std::error::Error for InvalidClipPath {}
+
+fn valid_size(value: f32) -> Result<f32, InvalidClipPath> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(InvalidClipPath)
+    }
+}
+
+impl ClipPath {
+    pub fn circle(radius: ClipRadius) -> Result<ClipPath, InvalidClipPath> {
+        match radius {
+            ClipRadius::Length(length) => valid_size(length.get())?,
+            ClipRadius::Fraction(fraction) => valid_size(fraction)?,
+        };
+        Ok(ClipPath::Circle(radius))
+    }
+
+    pub fn inset(
+        offsets: LogicalSideOffsets,
+        radius: LogicalLength,
+    ) -> Result<ClipPath, InvalidClipPath> {
+        for value in &[
+            offsets.top,
+            offsets.right,
+            offsets.bottom,
+            offsets.left,
+            radius.get(),
+        ] {
+            valid_size(*value)?;
+        }
+        Ok(ClipPath::Inset { offsets, radius })
+    }
+
+    /// The rounded rectangle this clips a box at `rect` to, or `None`
+    /// if it doesn't clip. Both shapes are rounded rectangles: a circle
+    /// is a square with its corners rounded by the whole radius.
+    pub(crate) fn shape(&self, rect: LogicalRect) -> Option<(LogicalRect, Corners<LogicalLength>)> {
+        match *self {
+            ClipPath::None => None,
+            ClipPath::Circle(radius) => {
+                let radius = match radius {
+                    ClipRadius::Length(length) => length.get(),
+                    ClipRadius::Fraction(fraction) => {
+                        fraction * rect.size.width.min(rect.size.height)
+                    }
+                };
+                let center = rect.center();
+                let bounds = LogicalRect::new(
+                    center - vec2(radius, radius),
+                    size2(radius * 2.0, radius * 2.0),
+                );
+                Some((bounds, Corners::new_all_same(LogicalLength::new(radius))))
+            }
+            ClipPath::Inset { offsets, radius } => {
+                let width = (rect.size.width - offsets.left - offsets.right).max(0.0);
+                let height = (rect.size.height - offsets.top - offsets.bottom).max(0.0);
+                let bounds = LogicalRect::new(
+                    rect.origin + vec2(offsets.left, offsets.top),
+                    size2(width, height),
+                );
+                let radius = radius.get().min(width / 2.0).min(height / 2.0);
+                Some((bounds, Corners::new_all_same(LogicalLength::new(radius))))
+            }
+        }
+    }
+}
+
+/// An effect applied to whatever is painted behind an element, within
+/// its box, before the element itself is painted.
+///
+/// Backends which don't support this paint the element without it, so
+/// its background should be semi-opaque for it to stand out from the
+/// content behind it either way.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BackdropFilter {
+    None,
+    /// Blurs the backdrop with the given radius, for a frosted glass
+    /// effect.
+    Blur(LogicalLength),
+}
+
+/// A size which follows the content of the box, up to the given
+/// maximum, like `fit-content()` in CSS. Text inside the box wraps at
+/// the maximum once it's reached.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FitContent(pub LogicalLength);
+
+/// A size given as an expression, like `calc()` in CSS, for example
+/// `width: calc("100% - 20px")`. See `Dimension::parse()` for what's
+/// accepted. Invalid expressions are `None`, and are ignored like
+/// invalid values in CSS.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Calc(pub Option<Dimension>);
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum BorderStyle {
     None,
@@ -114,6 +326,95 @@ impl Border {
     }
 }
 
+/// A line around the outside of an element's border box, like a border
+/// which doesn't take up any space in the layout. This is what focus
+/// rings are usually drawn with.
+///
+/// Outlines aren't clipped by the element's own clip path or overflow,
+/// but they are by its ancestors', like the rest of its content.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Outline {
+    pub width: LogicalLength,
+    pub style: BorderStyle,
+    pub color: Color,
+    /// The gap between the border box and the outline. Negative offsets
+    /// move the outline inside of the box.
+    pub offset: LogicalLength,
+}
+
+impl Outline {
+    pub fn none() -> Outline {
+        Outline {
+            width: LogicalLength::new(0.0),
+            style: BorderStyle::None,
+            color: Color::clear(),
+            offset: LogicalLength::new(0.0),
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.line().visible()
+    }
+
+    pub(crate) fn line(&self) -> Border {
+        Border {
+            width: self.width,
+            style: self.style,
+            color: self.color,
+        }
+    }
+
+    /// The outer edge of the outline around a box at `rect` with the
+    /// given corners. The corners are pushed out with it, so they stay
+    /// rounded the same way, and square corners stay square.
+    pub(crate) fn shape(
+        &self,
+        rect: LogicalRect,
+        radius: &Corners<LogicalLength>,
+    ) -> (LogicalRect, Corners<LogicalLength>) {
+        let spread = self.offset.get() + self.width.get();
+        let grow = |radius: LogicalLength| {
+            if radius.get() > 0.0 {
+                LogicalLength::new((radius.get() + spread).max(0.0))
+            } else {
+                radius
+            }
+        };
+        let bounds = rect.inflate(spread, spread);
+        let radius = Corners {
+            top_left: grow(radius.top_left),
+            top_right: grow(radius.top_right),
+            bottom_left: grow(radius.bottom_left),
+            bottom_right: grow(radius.bottom_right),
+        };
+        (bounds, radius)
+    }
+}
+
+/// A copy of text drawn behind its glyphs, set with `text_shadow` and
+/// inherited like the text color. Shadows are only painted, so they
+/// don't affect layout, and they can spill out of the text's box, where
+/// they're clipped by the ancestors' clips like the rest of the content.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextShadow {
+    /// How far the shadow is moved to the right of the text.
+    pub offset_x: LogicalLength,
+    /// How far the shadow is moved below the text.
+    pub offset_y: LogicalLength,
+    /// The blur radius, where zero draws a sharp copy.
+    pub blur: LogicalLength,
+    pub color: Color,
+}
+
+impl TextShadow {
+    /// What the shadow of text in `rect` covers, including its blur.
+    pub(crate) fn bounds(&self, rect: LogicalRect) -> LogicalRect {
+        let blur = self.blur.get().max(0.0);
+        rect.translate(vec2(self.offset_x.get(), self.offset_y.get()))
+            .inflate(blur, blur)
+    }
+}
+
 impl Edges<Border> {
     pub fn visible(&self) -> bool {
         self.left.visible() || self.right.visible() || self.top.visible() || self.bottom.visible()
@@ -121,7 +422,7 @@ impl Edges<Border> {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
-pub(crate) struct Corners<Value> {
+pub struct Corners<Value> {
     pub top_left: Value,
     pub top_right: Value,
     pub bottom_left: Value,
@@ -142,24 +443,91 @@ where
     }
 }
 
+/// Colors for the parts of the interface which aren't boxes of their
+/// own, such as text selection, so they can follow the app's theme.
+/// They're inherited, so setting them on the theme or on an element
+/// applies to everything inside of it.
+///
+/// Colors which aren't set fall back to the defaults documented on
+/// each getter.
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+pub struct ThemeColors {
+    pub(crate) selection_background: Option<Color>,
+    pub(crate) selection_color: Option<Color>,
+}
+
+impl ThemeColors {
+    /// Defaults to a light blue.
+    pub fn selection_background(&self) -> Color {
+        self.selection_background
+            .unwrap_or_else(|| Color::new(51, 142, 255, 255))
+    }
+
+    /// The color of selected text, which defaults to the color the text
+    /// already has.
+    pub fn selection_color(&self, text_color: Color) -> Color {
+        self.selection_color.unwrap_or(text_color)
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct ComputedValues {
     pub(crate) display: DisplayType,
+    /// Takes the element and everything inside of it out of the
+    /// layout, as if it wasn't in the tree, while keeping its state.
+    pub(crate) display_none: bool,
+    pub(crate) visibility: Visibility,
+    /// Overrides the parent's `align_items` for this element, when set.
+    pub(crate) align_self: Option<Alignment>,
     pub(crate) text_size: LogicalLength,
+    /// A `text_size` given in `rem` by CSS, which is only known in
+    /// pixels once the style is applied to an element. It's resolved
+    /// into `text_size` then, so it's only set in parsed declarations.
+    pub(crate) text_size_rems: Option<f32>,
     pub(crate) text_color: Color,
+    pub(crate) text_shadow: Option<TextShadow>,
     pub(crate) background_color: Color,
+    pub(crate) background_clip: BackgroundClip,
     pub(crate) border: Edges<Border>,
     pub(crate) corner_radius: Corners<LogicalLength>,
+    pub(crate) overflow_x: Overflow,
+    pub(crate) overflow_y: Overflow,
+    /// Clips the element's children to its border box, see
+    /// `PaintDetails::clip`.
+    pub(crate) clip: bool,
+    pub(crate) clip_path: ClipPath,
+    /// Caches the painted element and its children, see
+    /// `PaintDetails::cache`.
+    pub(crate) cache: bool,
+    pub(crate) backdrop_filter: BackdropFilter,
+    pub(crate) theme_colors: ThemeColors,
+    pub(crate) outline: Outline,
+    pub(crate) transitions: Transitions,
 }
 
 impl Default for ComputedValues {
     fn default() -> Self {
         ComputedValues {
             display: DisplayType::Block(BlockValues::default()),
+            display_none: false,
+            visibility: Visibility::Visible,
+            align_self: None,
             text_size: LogicalLength::new(16.0),
+            text_size_rems: None,
             text_color: Color::black(),
+            text_shadow: None,
             background_color: Color::clear(),
+            background_clip: BackgroundClip::BorderBox,
             corner_radius: Corners::new_all_same(LogicalLength::new(0.0)),
+            overflow_x: Overflow::Visible,
+            overflow_y: Overflow::Visible,
+            clip: false,
+            clip_path: ClipPath::None,
+            cache: false,
+            backdrop_filter: BackdropFilter::None,
+            theme_colors: ThemeColors::default(),
+            outline: Outline::none(),
+            transitions: Transitions::default(),
             border: Edges::new_all_same(Border {
                 width: LogicalLength::new(0.0),
                 style: BorderStyle::None,
@@ -169,6 +537,20 @@ impl Default for ComputedValues {
     }
 }
 
+/// The text size of the root element, usually the window, which `rem`
+/// lengths are multiples of. It's offered while styles are applied, and
+/// the root's own `rem` lengths are multiples of the default text size.
+/// Changing the root's text size restyles and lays out every element
+/// with lengths in `rem` again.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RootTextSize(pub LogicalLength);
+
+/// The root's text size while a style is applied to an element, or
+/// `None` when it isn't.
+pub(crate) fn root_text_size() -> Option<f32> {
+    illicit::get::<RootTextSize>().ok().map(|size| size.0.get())
+}
+
 pub trait NodeSelect {
     fn has_type(&self, ty: TypeId) -> bool;
     fn has_state(&self, state: ElementState) -> bool;
@@ -211,6 +593,9 @@ impl std::fmt::Debug for SubStyle {
 #[derive(Debug)]
 pub struct StyleData {
     pub attributes: Attributes,
+    /// Set on styles parsed at runtime, which apply these after
+    /// `attributes`. See `Style::from_css_string()`.
+    pub css: Option<&'static CssDeclarations>,
     pub sub_styles: &'static [SubStyle],
     pub name: &'static str,
     pub file: &'static str,
@@ -230,6 +615,53 @@ impl Style {
     }
 }
 
+/// Styles shared between elements, which are applied after the default
+/// stylesheet and before each element's own style.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stylesheet {
+    /// Applied to every element.
+    pub theme: Option<Style>,
+    /// Applied to the elements which list the class in their `class`
+    /// attribute, after the theme.
+    pub classes: HashMap<String, Style>,
+}
+
+/// Creates a style from a function, for tests that can't use the
+/// `define_style!` macro from inside this crate.
+#[cfg(test)]
+pub(crate) fn test_style(name: &'static str, apply: ApplyFunc) -> Style {
+    Style(Box::leak(Box::new(StyleData {
+        attributes: Attributes {
+            apply,
+            get_attributes: || vec![],
+        },
+        css: None,
+        sub_styles: &[],
+        name,
+        file: file!(),
+        line: line!(),
+    })))
+}
+
+/// Creates a block style with a `width` and `height` in pixels, for the
+/// tests which only need boxes of a known size.
+#[cfg(test)]
+pub(crate) fn sized_box(name: &'static str, width: f32, height: f32) -> Style {
+    // `apply` can't capture the sizes, so they're declared as CSS.
+    let sizes = Style::from_css_string(&format!("width: {}px; height: {}px", width, height));
+    Style(Box::leak(Box::new(StyleData {
+        attributes: Attributes {
+            apply: |_| {},
+            get_attributes: || vec![],
+        },
+        css: sizes.0.css,
+        sub_styles: &[],
+        name,
+        file: file!(),
+        line: line!(),
+    })))
+}
+
 impl PartialEq for Style {
     fn eq(&self, other: &Style) -> bool {
         std::ptr::eq(self.0 as *const StyleData, other.0 as *const StyleData)
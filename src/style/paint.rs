@@ -0,0 +1,186 @@
+use crate::layout::LogicalLength;
+use crate::style::{
+    BackdropFilter, BackgroundClip, Border, ClipPath, ComputedValues, Corners, Edges, Outline,
+    TextShadow,
+};
+use crate::Color;
+
+/// Describes how the box of an element is painted. The renderer reads
+/// this instead of the element's `ComputedValues`, so elements defined
+/// outside of this crate can control how they're drawn by overriding
+/// `Element::paint()`.
+///
+/// New fields may be added in the future, so this can only be created
+/// through `PaintDetails::new()` or `PaintDetails::from_values()`
+/// followed by the builder methods.
+///
+/// ```
+/// use moxie_native::dom::element::{Element, NoChildren};
+/// use moxie_native::style::{ComputedValues, PaintDetails, Style};
+/// use moxie_native::Color;
+///
+/// #[derive(Default, Clone, Debug, PartialEq)]
+/// struct Swatch;
+///
+/// impl Element for Swatch {
+///     type Child = NoChildren;
+///     type Handlers = ();
+///
+///     const ELEMENT_NAME: &'static str = "swatch";
+///
+///     fn style(&self) -> Option<Style> {
+///         None
+///     }
+///
+///     fn paint(&self, values: &ComputedValues) -> PaintDetails {
+///         PaintDetails::from_values(values)
+///             .background_color(Color::new(255, 0, 0, 255))
+///             .corner_radius(4.0)
+///             .build()
+///     }
+/// }
+///
+/// let details = Swatch.paint(&ComputedValues::default());
+/// assert_eq!(details.background_color, Color::new(255, 0, 0, 255));
+/// ```
+#[non_exhaustive]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PaintDetails {
+    /// Fill of the element's border box, or of the box chosen by
+    /// `background_clip`.
+    pub background_color: Color,
+    /// Which box the background fills.
+    pub background_clip: BackgroundClip,
+    /// Color of text parented to the element.
+    pub text_color: Color,
+    /// Shadow drawn behind the glyphs of text parented to the element,
+    /// without affecting layout.
+    pub text_shadow: Option<TextShadow>,
+    /// Borders along each edge of the element.
+    pub border: Edges<Border>,
+    /// Radius of each corner, affecting the background and borders.
+    pub corner_radius: Corners<LogicalLength>,
+    /// Clips the children to the element's border box, as a rectangle,
+    /// like `overflow: hidden` but independent of it, for content which
+    /// is animated outside of the element. Either one clips, and when
+    /// both are set, the children are clipped by both, so the tighter
+    /// overflow clip inside the border and rounded corners wins.
+    pub clip: bool,
+    /// Shape the element and its children are clipped to.
+    pub clip_path: ClipPath,
+    /// Effect applied to the content behind the element.
+    pub backdrop_filter: BackdropFilter,
+    /// Line drawn outside of the border box, without affecting layout.
+    pub outline: Outline,
+    /// Hints that the element and its children are expensive to paint
+    /// but rarely change, like a detailed chart. The backend then keeps
+    /// them rasterized in a texture, which is composited each frame,
+    /// and only painted again when any element in the subtree, its style
+    /// or its layout changes, when an image in it is decoded or moves on
+    /// to another frame, or when the element changes size. Custom
+    /// drawing and overlays which read state from outside of the tree
+    /// have to change the element to be painted again. Moving the element
+    /// doesn't repaint it. The texture covers the border box, so the
+    /// outline and anything overflowing it are cut off, and portals
+    /// inside of it are painted on their own as usual, while cached
+    /// elements inside of it are cached as part of it. Backends bound
+    /// how many subtrees and how large an area they cache, and paint
+    /// the rest in place.
+    pub cache: bool,
+}
+
+impl Default for PaintDetails {
+    fn default() -> Self {
+        PaintDetails::from_values(&ComputedValues::default())
+    }
+}
+
+impl PaintDetails {
+    /// Creates paint details which draw nothing.
+    pub fn new() -> PaintDetails {
+        PaintDetails::default()
+    }
+
+    /// Creates paint details from the style values of an element.
+    pub fn from_values(values: &ComputedValues) -> PaintDetails {
+        PaintDetails {
+            background_color: values.background_color,
+            background_clip: values.background_clip,
+            text_color: values.text_color,
+            text_shadow: values.text_shadow,
+            border: values.border,
+            corner_radius: values.corner_radius,
+            clip: values.clip,
+            clip_path: values.clip_path,
+            backdrop_filter: values.backdrop_filter,
+            outline: values.outline,
+            cache: values.cache,
+        }
+    }
+
+    pub fn background_color(mut self, color: Color) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    pub fn background_clip(mut self, clip: BackgroundClip) -> Self {
+        self.background_clip = clip;
+        self
+    }
+
+    pub fn text_color(mut self, color: Color) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    pub fn text_shadow(mut self, shadow: Option<TextShadow>) -> Self {
+        self.text_shadow = shadow;
+        self
+    }
+
+    /// Sets the same border on every edge.
+    pub fn border(mut self, border: Border) -> Self {
+        self.border = Edges::new_all_same(border);
+        self
+    }
+
+    pub fn borders(mut self, borders: Edges<Border>) -> Self {
+        self.border = borders;
+        self
+    }
+
+    /// Sets the same radius, in logical pixels, on every corner.
+    pub fn corner_radius(mut self, radius: f32) -> Self {
+        self.corner_radius = Corners::new_all_same(LogicalLength::new(radius));
+        self
+    }
+
+    pub fn clip(mut self, clip: bool) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    pub fn clip_path(mut self, clip_path: ClipPath) -> Self {
+        self.clip_path = clip_path;
+        self
+    }
+
+    pub fn backdrop_filter(mut self, filter: BackdropFilter) -> Self {
+        self.backdrop_filter = filter;
+        self
+    }
+
+    pub fn outline(mut self, outline: Outline) -> Self {
+        self.outline = outline;
+        self
+    }
+
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,86 @@
+//! The default stylesheet, which gives the built-in elements their
+//! baseline appearance. It's applied before the app's theme and the
+//! element's own style, so either can override it.
+
+use super::{Attributes, Border, BorderStyle, ComputedValues, DisplayType, Edges, NodeSelect};
+use super::{Style, StyleData, SubStyle};
+use crate::dom::{TextArea, View};
+use crate::layout::{LogicalLength, LogicalSideOffsets};
+use crate::Color;
+
+fn no_attributes() -> Vec<(&'static str, String)> {
+    vec![]
+}
+
+fn is_view(node: &dyn NodeSelect) -> bool {
+    node.is_type::<View>()
+}
+
+fn view(values: &mut ComputedValues) {
+    values.background_color = Color::new(50, 180, 200, 255);
+}
+
+fn view_attributes() -> Vec<(&'static str, String)> {
+    vec![("background_color", "rgb(50, 180, 200)".to_owned())]
+}
+
+fn is_text_area(node: &dyn NodeSelect) -> bool {
+    node.is_type::<TextArea>()
+}
+
+fn text_area(values: &mut ComputedValues) {
+    if let DisplayType::Block(ref mut block) = values.display {
+        block.padding = LogicalSideOffsets::new_all_same(2.0);
+    }
+    values.background_color = Color::white();
+    values.border = Edges::new_all_same(Border {
+        width: LogicalLength::new(1.0),
+        style: BorderStyle::Solid,
+        color: Color::new(160, 160, 160, 255),
+    });
+}
+
+fn text_area_attributes() -> Vec<(&'static str, String)> {
+    vec![
+        ("padding", "2 px".to_owned()),
+        ("background_color", "rgb(255, 255, 255)".to_owned()),
+        (
+            "border",
+            "border(1 px, solid, rgb(160, 160, 160))".to_owned(),
+        ),
+    ]
+}
+
+static SUB_STYLES: [SubStyle; 2] = [
+    SubStyle {
+        selector: is_view,
+        attributes: Attributes {
+            apply: view,
+            get_attributes: view_attributes,
+        },
+    },
+    SubStyle {
+        selector: is_text_area,
+        attributes: Attributes {
+            apply: text_area,
+            get_attributes: text_area_attributes,
+        },
+    },
+];
+
+static DEFAULT_STYLE: StyleData = StyleData {
+    attributes: Attributes {
+        apply: |_| {},
+        get_attributes: no_attributes,
+    },
+    css: None,
+    sub_styles: &SUB_STYLES,
+    name: "DEFAULT_STYLE",
+    file: file!(),
+    line: line!(),
+};
+
+/// The stylesheet applied to every element before its theme.
+pub fn default_style() -> Style {
+    Style(&DEFAULT_STYLE)
+}
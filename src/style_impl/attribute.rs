@@ -1,7 +1,9 @@
 use super::{keyword, types::Corners, types::Edges, Attribute, AttributeHasValue, Length};
 use crate::layout::{LogicalLength, LogicalSideOffsets};
 use crate::style::{
-    Border, ComputedValues, Corners as StyleCorners, Direction, DisplayType, Edges as StyleEdges,
+    BackdropFilter, BackgroundClip, Border, Calc, ClipPath, ComputedValues,
+    Corners as StyleCorners, Direction, DisplayType, Edges as StyleEdges, FitContent, Outline,
+    Overflow, PropertyTransition, TextShadow, Visibility,
 };
 use crate::Color;
 
@@ -35,7 +37,8 @@ macro_rules! define_attribute {
 define_attribute! {
     text_size(TextSize) {
         Length => |values, value| {
-            values.text_size = value.into()
+            values.text_size = value.into();
+            values.text_size_rems = None;
         }
     }
 }
@@ -48,6 +51,17 @@ define_attribute! {
     }
 }
 
+define_attribute! {
+    text_shadow(TextShadowAttr) {
+        TextShadow => |values, value| {
+            values.text_shadow = Some(value)
+        }
+        keyword::None => |values, _value| {
+            values.text_shadow = None
+        }
+    }
+}
+
 define_attribute! {
     background_color(BackgroundColor) {
         Color => |values, value| {
@@ -56,6 +70,22 @@ define_attribute! {
     }
 }
 
+define_attribute! {
+    selection_background(SelectionBackground) {
+        Color => |values, value| {
+            values.theme_colors.selection_background = Some(value)
+        }
+    }
+}
+
+define_attribute! {
+    selection_color(SelectionColor) {
+        Color => |values, value| {
+            values.theme_colors.selection_color = Some(value)
+        }
+    }
+}
+
 define_attribute! {
     width(Width) {
         Length => |values, value| {
@@ -63,6 +93,19 @@ define_attribute! {
                 block.width = Some(value.into());
             }
         }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.width = Some(value);
+                }
+            }
+        }
+        FitContent => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.width = None;
+                block.max_width = Some(value.0.into());
+            }
+        }
     }
 }
 
@@ -73,13 +116,159 @@ define_attribute! {
                 block.height = Some(value.into());
             }
         }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.height = Some(value);
+                }
+            }
+        }
+        FitContent => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.height = None;
+                block.max_height = Some(value.0.into());
+            }
+        }
+    }
+}
+
+define_attribute! {
+    min_width(MinWidth) {
+        Length => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.min_width = Some(value.into());
+            }
+        }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.min_width = Some(value);
+                }
+            }
+        }
+    }
+}
+
+define_attribute! {
+    min_height(MinHeight) {
+        Length => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.min_height = Some(value.into());
+            }
+        }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.min_height = Some(value);
+                }
+            }
+        }
+    }
+}
+
+define_attribute! {
+    max_width(MaxWidth) {
+        Length => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.max_width = Some(value.into());
+            }
+        }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.max_width = Some(value);
+                }
+            }
+        }
+    }
+}
+
+define_attribute! {
+    max_height(MaxHeight) {
+        Length => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.max_height = Some(value.into());
+            }
+        }
+        Calc => |values, value| {
+            if let Some(value) = value.0 {
+                if let DisplayType::Block(ref mut block) = values.display {
+                    block.max_height = Some(value);
+                }
+            }
+        }
+    }
+}
+
+define_attribute! {
+    align_items(AlignItems) {
+        keyword::Start => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.align_items = value.into();
+            }
+        }
+        keyword::Baseline => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.align_items = value.into();
+            }
+        }
+    }
+}
+
+define_attribute! {
+    align_self(AlignSelf) {
+        keyword::Start => |values, value| {
+            values.align_self = Some(value.into())
+        }
+        keyword::Baseline => |values, value| {
+            values.align_self = Some(value.into())
+        }
+    }
+}
+
+define_attribute! {
+    flex_grow(FlexGrow) {
+        f64 => |values, value| {
+            if let DisplayType::Block(ref mut block) = values.display {
+                block.flex_grow = value.max(0.0) as f32;
+            }
+        }
+    }
+}
+
+define_attribute! {
+    background_clip(BackgroundClipAttr) {
+        keyword::BorderBox => |values, value| {
+            values.background_clip = value.into()
+        }
+        keyword::PaddingBox => |values, value| {
+            values.background_clip = value.into()
+        }
+        keyword::ContentBox => |values, value| {
+            values.background_clip = value.into()
+        }
     }
 }
 
 define_attribute! {
     display(DisplayAttr) {
         keyword::Block => |values, _value| {
-            values.display = DisplayType::Block(Default::default())
+            values.display = DisplayType::Block(Default::default());
+            values.display_none = false;
+        }
+        keyword::None => |values, _value| {
+            values.display_none = true
+        }
+    }
+}
+
+define_attribute! {
+    visibility(VisibilityAttr) {
+        keyword::Visible => |values, _value| {
+            values.visibility = Visibility::Visible
+        }
+        keyword::Hidden => |values, _value| {
+            values.visibility = Visibility::Hidden
         }
     }
 }
@@ -165,6 +354,32 @@ define_attribute! {
     }
 }
 
+define_attribute! {
+    outline(OutlineAttr) {
+        Outline => |values, value| {
+            // The offset is set separately, so it's kept.
+            values.outline = Outline {
+                offset: values.outline.offset,
+                ..value
+            };
+        }
+        keyword::None => |values, _value| {
+            values.outline = Outline {
+                offset: values.outline.offset,
+                ..Outline::none()
+            };
+        }
+    }
+}
+
+define_attribute! {
+    outline_offset(OutlineOffset) {
+        Length => |values, value| {
+            values.outline.offset = value.into();
+        }
+    }
+}
+
 define_attribute! {
     corner_radius(CornerRadiusAttr) {
         Length => |values, value| {
@@ -181,6 +396,96 @@ define_attribute! {
     }
 }
 
+define_attribute! {
+    overflow(OverflowAttr) {
+        keyword::Visible => |values, _value| {
+            values.overflow_x = Overflow::Visible;
+            values.overflow_y = Overflow::Visible;
+        }
+        keyword::Hidden => |values, _value| {
+            values.overflow_x = Overflow::Hidden;
+            values.overflow_y = Overflow::Hidden;
+        }
+    }
+}
+
+define_attribute! {
+    overflow_x(OverflowXAttr) {
+        keyword::Visible => |values, _value| {
+            values.overflow_x = Overflow::Visible
+        }
+        keyword::Hidden => |values, _value| {
+            values.overflow_x = Overflow::Hidden
+        }
+    }
+}
+
+define_attribute! {
+    overflow_y(OverflowYAttr) {
+        keyword::Visible => |values, _value| {
+            values.overflow_y = Overflow::Visible
+        }
+        keyword::Hidden => |values, _value| {
+            values.overflow_y = Overflow::Hidden
+        }
+    }
+}
+
+define_attribute! {
+    clip(ClipAttr) {
+        keyword::Bounds => |values, _value| {
+            values.clip = true
+        }
+        keyword::None => |values, _value| {
+            values.clip = false
+        }
+    }
+}
+
+define_attribute! {
+    cache(CacheAttr) {
+        keyword::Contents => |values, _value| {
+            values.cache = true
+        }
+        keyword::None => |values, _value| {
+            values.cache = false
+        }
+    }
+}
+
+define_attribute! {
+    clip_path(ClipPathAttr) {
+        ClipPath => |values, value| {
+            values.clip_path = value
+        }
+        keyword::None => |values, _value| {
+            values.clip_path = ClipPath::None
+        }
+    }
+}
+
+define_attribute! {
+    backdrop_filter(BackdropFilterAttr) {
+        BackdropFilter => |values, value| {
+            values.backdrop_filter = value
+        }
+        keyword::None => |values, _value| {
+            values.backdrop_filter = BackdropFilter::None
+        }
+    }
+}
+
+define_attribute! {
+    transition(TransitionAttr) {
+        PropertyTransition => |values, value| {
+            values.transitions.set(value)
+        }
+        keyword::None => |values, _value| {
+            values.transitions.clear()
+        }
+    }
+}
+
 /*
 display
 direction
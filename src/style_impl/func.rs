@@ -1,13 +1,38 @@
 use super::Length;
-use crate::style::{Border, BorderStyle};
+use crate::layout::{LogicalLength, LogicalSideOffsets};
+use crate::style::{
+    root_text_size, AnimatedProperty, BackdropFilter, Border, BorderStyle, Calc, ClipPath,
+    ClipRadius, ComputedValues, Dimension, FitContent, InvalidClipPath, Outline,
+    PropertyTransition, TextShadow,
+};
 use crate::Color;
+use log::warn;
+use std::time::Duration;
+
+/// Channels are rounded to the nearest byte, and clamped to `[0, 255]`.
+fn channel(value: f64) -> u8 {
+    value.round().max(0.0).min(255.0) as u8
+}
 
 pub fn rgb(red: f64, green: f64, blue: f64) -> Color {
-    Color::new(red as u8, green as u8, blue as u8, 255)
+    Color::new(channel(red), channel(green), channel(blue), 255)
 }
 
 pub fn rgba(red: f64, green: f64, blue: f64, alpha: f64) -> Color {
-    Color::new(red as u8, green as u8, blue as u8, alpha as u8)
+    Color::new(channel(red), channel(green), channel(blue), channel(alpha))
+}
+
+/// Takes the hue in degrees, and the saturation and lightness in
+/// `[0, 1]`, which is what percentages evaluate to.
+pub fn hsl(hue: f64, saturation: f64, lightness: f64) -> Color {
+    Color::from_hsl(hue as f32, saturation as f32, lightness as f32)
+}
+
+pub fn hsla(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Color {
+    Color {
+        alpha: channel(alpha),
+        ..hsl(hue, saturation, lightness)
+    }
 }
 
 pub fn border(width: Length, style: impl Into<BorderStyle>, color: Color) -> Border {
@@ -19,3 +44,102 @@ pub fn border(width: Length, style: impl Into<BorderStyle>, color: Color) -> Bor
         color,
     }
 }
+
+/// An outline right outside of the border box. Use `outline_offset`
+/// to move it further out.
+pub fn outline(width: Length, style: impl Into<BorderStyle>, color: Color) -> Outline {
+    Outline {
+        width: width.into(),
+        style: style.into(),
+        color,
+        offset: LogicalLength::new(0.0),
+    }
+}
+
+/// A shadow behind text, moved right and down by the offsets, and
+/// blurred by `blur`, which is zero for a sharp shadow.
+pub fn text_shadow(offset_x: Length, offset_y: Length, blur: Length, color: Color) -> TextShadow {
+    TextShadow {
+        offset_x: offset_x.into(),
+        offset_y: offset_y.into(),
+        blur: blur.into(),
+        color,
+    }
+}
+
+impl From<Length> for ClipRadius {
+    fn from(length: Length) -> Self {
+        ClipRadius::Length(length.into())
+    }
+}
+
+/// Percentages are of the shorter side of the box.
+impl From<f64> for ClipRadius {
+    fn from(fraction: f64) -> Self {
+        ClipRadius::Fraction(fraction as f32)
+    }
+}
+
+/// Invalid clip paths are ignored, like invalid values in CSS.
+fn checked_clip_path(clip_path: Result<ClipPath, InvalidClipPath>) -> ClipPath {
+    clip_path.unwrap_or_else(|err| {
+        warn!("Ignoring clip path: {}", err);
+        ClipPath::None
+    })
+}
+
+pub fn circle(radius: impl Into<ClipRadius>) -> ClipPath {
+    checked_clip_path(ClipPath::circle(radius.into()))
+}
+
+pub fn inset(offset: Length) -> ClipPath {
+    inset_round(offset, super::pixels(0.0))
+}
+
+pub fn inset_round(offset: Length, radius: Length) -> ClipPath {
+    let offset: LogicalLength = offset.into();
+    checked_clip_path(ClipPath::inset(
+        LogicalSideOffsets::from_length_all_same(offset),
+        radius.into(),
+    ))
+}
+
+/// A negative radius is ignored.
+pub fn blur(radius: Length) -> BackdropFilter {
+    let radius: LogicalLength = radius.into();
+    if radius.get() < 0.0 {
+        warn!("Ignoring negative blur radius: {}", radius.get());
+        return BackdropFilter::None;
+    }
+    BackdropFilter::Blur(radius)
+}
+
+/// Sizes a box to its content, but no larger than `max`. Boxes are
+/// sized to their content when they don't have a size anyway, so this
+/// is the same as setting the maximum size only.
+pub fn fit_content(max: Length) -> FitContent {
+    FitContent(max.into())
+}
+
+/// A size given by an expression over pixels, percentages of the
+/// parent's size and multiples of the root's text size, such as
+/// `calc("100% - 20px")` or `calc("2rem + 4px")`. Invalid expressions
+/// are ignored.
+pub fn calc(expr: &str) -> Calc {
+    let dimension = Dimension::parse(expr);
+    if dimension.is_none() {
+        warn!("Ignoring invalid calc() expression: {}", expr);
+    }
+    let root = root_text_size().unwrap_or_else(|| ComputedValues::default().text_size.get());
+    Calc(dimension.map(|dimension| dimension.resolve_rems(root)))
+}
+
+/// Animates `property` over the given number of milliseconds whenever
+/// its value changes, for example `transition: tween(width, 200)`.
+/// Negative durations are treated as zero.
+pub fn tween(property: impl Into<AnimatedProperty>, milliseconds: f64) -> PropertyTransition {
+    PropertyTransition {
+        property: property.into(),
+        duration: Duration::from_secs_f64(milliseconds.max(0.0) / 1000.0),
+    }
+}
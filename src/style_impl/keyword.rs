@@ -1,4 +1,6 @@
-use crate::style::{BorderStyle, Direction, Display};
+use crate::style::{
+    Alignment, AnimatedProperty, BackgroundClip, BorderStyle, Direction, Display, Overflow,
+};
 
 macro_rules! keyword {
     ($name:ident : $class:ident => $enum:ty as $variant:ident) => {
@@ -20,6 +22,9 @@ keyword!(block: Block => Display as Block);
 keyword!(inline: Inline => Display as Inline);
 keyword!(horizontal: Horizontal => Direction as Horizontal);
 keyword!(vertical: Vertical => Direction as Vertical);
+keyword!(start: Start => Alignment as Start);
+keyword!(baseline: Baseline => Alignment as Baseline);
+keyword!(visible: Visible => Overflow as Visible);
 keyword!(none: None => BorderStyle as None);
 keyword!(solid: Solid => BorderStyle as Solid);
 keyword!(double: Double => BorderStyle as Double);
@@ -30,3 +35,28 @@ keyword!(groove: Groove => BorderStyle as Groove);
 keyword!(ridge: Ridge => BorderStyle as Ridge);
 keyword!(inset: Inset => BorderStyle as Inset);
 keyword!(outset: Outset => BorderStyle as Outset);
+keyword!(border_box: BorderBox => BackgroundClip as BorderBox);
+keyword!(padding_box: PaddingBox => BackgroundClip as PaddingBox);
+keyword!(content_box: ContentBox => BackgroundClip as ContentBox);
+keyword!(width: Width => AnimatedProperty as Width);
+keyword!(height: Height => AnimatedProperty as Height);
+keyword!(text_size: TextSize => AnimatedProperty as TextSize);
+keyword!(corner_radius: CornerRadius => AnimatedProperty as CornerRadius);
+keyword!(padding: Padding => AnimatedProperty as Padding);
+keyword!(margin: Margin => AnimatedProperty as Margin);
+keyword!(border_width: BorderWidth => AnimatedProperty as BorderWidth);
+keyword!(flex_grow: FlexGrow => AnimatedProperty as FlexGrow);
+
+/// `clip: bounds`, which doesn't correspond to an enum.
+pub struct Bounds;
+
+pub fn bounds() -> Bounds {
+    Bounds
+}
+
+/// `cache: contents`, which doesn't correspond to an enum.
+pub struct Contents;
+
+pub fn contents() -> Contents {
+    Contents
+}
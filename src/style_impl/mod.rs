@@ -1,5 +1,5 @@
 use crate::layout::{LogicalLength, LogicalSize};
-use crate::style::ComputedValues;
+use crate::style::{root_text_size, ComputedValues, Dimension};
 use std::ops;
 
 pub mod attribute;
@@ -33,6 +33,12 @@ impl Into<LogicalLength> for Length {
     }
 }
 
+impl Into<Dimension> for Length {
+    fn into(self) -> Dimension {
+        Dimension::pixels(self.0 as f32 / 60.0)
+    }
+}
+
 impl ops::Add for Length {
     type Output = Self;
 
@@ -57,6 +63,19 @@ pub fn ems(_value: f64) -> Length {
     unimplemented!()
 }
 
+/// A multiple of the root element's text size, which is that of the
+/// window unless its style sets another, so that lengths scale with it
+/// however deeply they're nested. Outside of a style update, this is a
+/// multiple of the default text size.
+pub fn rems(value: f64) -> Length {
+    let root = root_text_size().unwrap_or_else(|| ComputedValues::default().text_size.get());
+    Length((value * root as f64 * 60.0) as isize)
+}
+
+pub fn percent(value: f64) -> f64 {
+    value / 100.0
+}
+
 #[illicit::from_env(viewport_size: &LogicalSize)]
 pub fn view_width(value: f64) -> Length {
     Length((value / 100.0 * viewport_size.width as f64 * 60.0) as isize)
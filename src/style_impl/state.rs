@@ -7,3 +7,15 @@ pub fn press() -> ElementState {
 pub fn hover() -> ElementState {
     ElementState::Hover
 }
+
+pub fn focus() -> ElementState {
+    ElementState::Focus
+}
+
+pub fn focus_visible() -> ElementState {
+    ElementState::FocusVisible
+}
+
+pub fn focus_within() -> ElementState {
+    ElementState::FocusWithin
+}
@@ -1,8 +1,10 @@
 use std::fmt;
 use webrender::api::ColorF;
 
-/// Represents an 8-bit RGBA color in sRGB color space.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// Represents an 8-bit RGBA color in sRGB color space. Channels given
+/// as floats, such as from hue and saturation or by `rgb()` in styles,
+/// are rounded to the nearest byte.
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
@@ -32,8 +34,177 @@ impl Color {
         Color::new(0, 0, 0, 255)
     }
 
-    // Parse a color from `R,G,B` or `R,G,B,A` format.
+    /// Creates an opaque color from hue, saturation and lightness. The
+    /// hue is in degrees and wraps around, saturation and lightness are
+    /// in `[0, 1]`.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+        let saturation = saturation.max(0.0).min(1.0);
+        let lightness = lightness.max(0.0).min(1.0);
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// Creates an opaque color from hue, saturation and value. The hue
+    /// is in degrees and wraps around, saturation and value are in
+    /// `[0, 1]`.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let saturation = saturation.max(0.0).min(1.0);
+        let value = value.max(0.0).min(1.0);
+        let chroma = value * saturation;
+        Color::from_chroma(hue, chroma, value - chroma)
+    }
+
+    fn from_chroma(hue: f32, chroma: f32, min: f32) -> Color {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (red, green, blue) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |value: f32| ((value + min) * 255.0).round() as u8;
+        Color::new(channel(red), channel(green), channel(blue), 255)
+    }
+
+    /// Returns the hue in degrees, along with the saturation and
+    /// lightness in `[0, 1]`. Alpha is ignored.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let red = self.red as f32 / 255.0;
+        let green = self.green as f32 / 255.0;
+        let blue = self.blue as f32 / 255.0;
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let chroma = max - min;
+        let lightness = (max + min) / 2.0;
+        if chroma == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+
+        let hue = if max == red {
+            ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            (blue - red) / chroma + 2.0
+        } else {
+            (red - green) / chroma + 4.0
+        };
+        let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+        (hue * 60.0, saturation, lightness)
+    }
+
+    /// Composites this color over `background`. Blending happens in
+    /// linear light with premultiplied alpha, which avoids the dark
+    /// fringes that blending sRGB values directly produces.
+    pub fn over(&self, background: Color) -> Color {
+        self.composite(background, srgb_to_linear, linear_to_srgb)
+    }
+
+    /// Composites this color over `background` by blending the sRGB
+    /// values directly. This is cheaper than `over` and matches what
+    /// most other software does, but midtones come out too dark.
+    pub fn over_fast(&self, background: Color) -> Color {
+        self.composite(background, |value| value, |value| value)
+    }
+
+    fn composite(
+        &self,
+        background: Color,
+        decode: impl Fn(f32) -> f32,
+        encode: impl Fn(f32) -> f32,
+    ) -> Color {
+        let alpha = self.alpha as f32 / 255.0;
+        let background_alpha = background.alpha as f32 / 255.0 * (1.0 - alpha);
+        let out_alpha = alpha + background_alpha;
+        if out_alpha == 0.0 {
+            return Color::clear();
+        }
+
+        let channel = |fg: u8, bg: u8| {
+            let fg = decode(fg as f32 / 255.0) * alpha;
+            let bg = decode(bg as f32 / 255.0) * background_alpha;
+            (encode((fg + bg) / out_alpha) * 255.0).round() as u8
+        };
+        Color::new(
+            channel(self.red, background.red),
+            channel(self.green, background.green),
+            channel(self.blue, background.blue),
+            (out_alpha * 255.0).round() as u8,
+        )
+    }
+
+    /// Interpolates from this color, at `t = 0`, to `other`, at `t = 1`.
+    /// Like `over()`, this happens in linear light with premultiplied
+    /// alpha, so midpoints don't come out too dark, and fading into a
+    /// clear color doesn't fade through black. `t` is clamped to
+    /// `[0, 1]`.
+    pub fn mix(&self, other: Color, t: f32) -> Color {
+        let t = t.max(0.0).min(1.0);
+        let alpha = |color: &Color| color.alpha as f32 / 255.0;
+        let (from_alpha, to_alpha) = (alpha(self), alpha(&other));
+        let out_alpha = from_alpha + (to_alpha - from_alpha) * t;
+        if out_alpha == 0.0 {
+            return Color::clear();
+        }
+
+        let channel = |from: u8, to: u8| {
+            let from = srgb_to_linear(from as f32 / 255.0) * from_alpha;
+            let to = srgb_to_linear(to as f32 / 255.0) * to_alpha;
+            (linear_to_srgb((from + (to - from) * t) / out_alpha) * 255.0).round() as u8
+        };
+        Color::new(
+            channel(self.red, other.red),
+            channel(self.green, other.green),
+            channel(self.blue, other.blue),
+            (out_alpha * 255.0).round() as u8,
+        )
+    }
+
+    /// Parses a color from `#rrggbb` or `#rrggbbaa` format, where the
+    /// alpha defaults to opaque. The `#` is optional.
+    pub fn from_hex(string: &str) -> Result<Color, ()> {
+        let hex = string.strip_prefix('#').unwrap_or(string);
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(());
+        }
+        let channel = |index: usize| {
+            hex.get(index * 2..index * 2 + 2)
+                .map_or(Ok(255), |digits| u8::from_str_radix(digits, 16))
+                .map_err(|_| ())
+        };
+        Ok(Color::new(
+            channel(0)?,
+            channel(1)?,
+            channel(2)?,
+            channel(3)?,
+        ))
+    }
+
+    /// Writes the color in `#rrggbb` format, or `#rrggbbaa` when it
+    /// isn't opaque, which `from_hex()` parses back.
+    pub fn to_hex_string(&self) -> String {
+        if self.alpha == 255 {
+            format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+        } else {
+            format!(
+                "#{:02x}{:02x}{:02x}{:02x}",
+                self.red, self.green, self.blue, self.alpha
+            )
+        }
+    }
+
+    // Parse a color from `#rrggbb`, `#rrggbbaa`, `R,G,B`, `R,G,B,A` or
+    // `hsl(H, S%, L%)` format.
     pub fn parse(string: &str) -> Result<Color, ()> {
+        let string = string.trim();
+        if string.starts_with('#') {
+            return Color::from_hex(string);
+        }
+        if string.starts_with("hsl(") && string.ends_with(')') {
+            return Color::parse_hsl(&string[4..string.len() - 1]);
+        }
+
         let components = string
             .split(',')
             .map(|s| s.parse::<u8>().map_err(|_| ()))
@@ -51,6 +222,132 @@ impl Color {
             Err(())
         }
     }
+
+    fn parse_hsl(string: &str) -> Result<Color, ()> {
+        let components = string.split(',').map(str::trim).collect::<Vec<_>>();
+        if components.len() != 3 {
+            return Err(());
+        }
+        let percent = |string: &str| {
+            if string.ends_with('%') {
+                string[..string.len() - 1]
+                    .trim()
+                    .parse::<f32>()
+                    .map(|value| value / 100.0)
+                    .map_err(|_| ())
+            } else {
+                Err(())
+            }
+        };
+        let hue = components[0].parse::<f32>().map_err(|_| ())?;
+        Ok(Color::from_hsl(
+            hue,
+            percent(components[1])?,
+            percent(components[2])?,
+        ))
+    }
+}
+
+/// A ramp of colors, for mapping values to colors such as in charts and
+/// heat maps. Each stop places a color at a position in `[0, 1]`, and
+/// `sample()` blends between the stops on either side with
+/// `Color::mix()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// Sorted by position.
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    /// Creates a gradient from stops of a position and a color, in any
+    /// order. Two stops at the same position make a hard edge, which
+    /// samples as the one given later from that position on.
+    pub fn new(stops: impl IntoIterator<Item = (f32, Color)>) -> Gradient {
+        let mut stops = stops
+            .into_iter()
+            .filter(|(position, _)| !position.is_nan())
+            .collect::<Vec<_>>();
+        // The sort is stable, so stops at the same position keep their
+        // order.
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Gradient { stops }
+    }
+
+    /// Creates a gradient with `colors` spread evenly from 0 to 1.
+    pub fn even(colors: &[Color]) -> Gradient {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        Gradient::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(index, color)| (index as f32 / last, *color)),
+        )
+    }
+
+    /// From black to white.
+    pub fn grayscale() -> Gradient {
+        Gradient::even(&[Color::black(), Color::white()])
+    }
+
+    /// Matplotlib's viridis, from dark purple through blue and green to
+    /// yellow, which keeps increasing in lightness so it reads in
+    /// grayscale and to most color blind viewers.
+    pub fn viridis() -> Gradient {
+        Gradient::even(&[
+            Color::new(68, 1, 84, 255),
+            Color::new(59, 82, 139, 255),
+            Color::new(33, 145, 140, 255),
+            Color::new(94, 201, 98, 255),
+            Color::new(253, 231, 37, 255),
+        ])
+    }
+
+    /// From blue through light gray to red, for values which diverge
+    /// from a midpoint at 0.5.
+    pub fn cool_warm() -> Gradient {
+        Gradient::even(&[
+            Color::new(59, 76, 192, 255),
+            Color::new(221, 221, 221, 255),
+            Color::new(180, 4, 38, 255),
+        ])
+    }
+
+    pub fn stops(&self) -> &[(f32, Color)] {
+        &self.stops
+    }
+
+    /// The color at `t`, which is clamped to `[0, 1]`. Before the first
+    /// stop, it's the first stop's color, and after the last, it's the
+    /// last's. A gradient with a single stop is that color everywhere,
+    /// and one without any stops is clear.
+    pub fn sample(&self, t: f32) -> Color {
+        let t = t.max(0.0).min(1.0);
+        let next = self.stops.iter().position(|(position, _)| *position > t);
+        let (start, end) = match next {
+            Some(0) => return self.stops[0].1,
+            Some(index) => (self.stops[index - 1], self.stops[index]),
+            None => return self.stops.last().map_or(Color::clear(), |stop| stop.1),
+        };
+        start.1.mix(end.1, (t - start.0) / (end.0 - start.0))
+    }
+}
+
+/// Converts an sRGB channel in `[0, 1]` to linear light.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light channel in `[0, 1]` back to sRGB.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 impl Into<ColorF> for Color {
@@ -64,18 +361,172 @@ impl Into<ColorF> for Color {
     }
 }
 
-/// Displays the color using either `rgb(R, G, B)` or `rgba(R, G, B, A)`
-/// format.
+/// Displays the color like `to_hex_string()`.
 impl fmt::Display for Color {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        if self.alpha == 255 {
-            write!(fmt, "rgb({}, {}, {})", self.red, self.green, self.blue)
-        } else {
-            write!(
-                fmt,
-                "rgba({}, {}, {}, {})",
-                self.red, self.green, self.blue, self.alpha
-            )
+        fmt.write_str(&self.to_hex_string())
+    }
+}
+
+impl fmt::Debug for Color {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Color({})", self.to_hex_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Color, Gradient};
+
+    #[test]
+    fn from_hsl() {
+        assert_eq!(Color::from_hsl(0.0, 1.0, 0.5), Color::new(255, 0, 0, 255));
+        assert_eq!(Color::from_hsl(120.0, 1.0, 0.5), Color::new(0, 255, 0, 255));
+        assert_eq!(Color::from_hsl(240.0, 1.0, 0.5), Color::new(0, 0, 255, 255));
+        assert_eq!(
+            Color::from_hsl(210.0, 0.5, 0.4),
+            Color::new(51, 102, 153, 255)
+        );
+        assert_eq!(
+            Color::from_hsl(-120.0, 1.0, 0.5),
+            Color::from_hsl(240.0, 1.0, 0.5)
+        );
+        assert_eq!(
+            Color::from_hsl(480.0, 1.0, 0.5),
+            Color::from_hsl(120.0, 1.0, 0.5)
+        );
+        assert_eq!(
+            Color::from_hsl(42.0, 0.0, 0.5),
+            Color::new(128, 128, 128, 255)
+        );
+    }
+
+    #[test]
+    fn from_hsv() {
+        assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::new(255, 0, 0, 255));
+        assert_eq!(
+            Color::from_hsv(60.0, 1.0, 1.0),
+            Color::new(255, 255, 0, 255)
+        );
+        assert_eq!(
+            Color::from_hsv(300.0, 0.5, 0.5),
+            Color::new(128, 64, 128, 255)
+        );
+        assert_eq!(Color::from_hsv(0.0, 0.0, 1.0), Color::white());
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for &color in &[
+            Color::new(51, 102, 153, 255),
+            Color::new(250, 128, 114, 255),
+            Color::new(12, 200, 77, 255),
+            Color::new(90, 90, 90, 255),
+        ] {
+            let (hue, saturation, lightness) = color.to_hsl();
+            let result = Color::from_hsl(hue, saturation, lightness);
+            for &(a, b) in &[
+                (color.red, result.red),
+                (color.green, result.green),
+                (color.blue, result.blue),
+            ] {
+                assert!((a as i32 - b as i32).abs() <= 1, "{} != {}", color, result);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_hsl() {
+        assert_eq!(
+            Color::parse("hsl(210, 50%, 40%)"),
+            Ok(Color::new(51, 102, 153, 255))
+        );
+        assert_eq!(Color::parse("hsl(210, 50, 40)"), Err(()));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for &color in &[
+            Color::new(51, 102, 153, 255),
+            Color::new(0, 10, 255, 128),
+            Color::clear(),
+            Color::from_hsl(210.0, 0.35, 0.6),
+        ] {
+            assert_eq!(Color::from_hex(&color.to_hex_string()), Ok(color));
+            assert_eq!(Color::parse(&color.to_string()), Ok(color));
+        }
+        assert_eq!(Color::new(51, 102, 153, 255).to_hex_string(), "#336699");
+        assert_eq!(Color::new(0, 10, 255, 128).to_string(), "#000aff80");
+        assert_eq!(format!("{:?}", Color::white()), "Color(#ffffff)");
+        assert_eq!(Color::from_hex("336699"), Ok(Color::new(51, 102, 153, 255)));
+        for text in &["#336", "#33669", "#3366zz", "#33669é"] {
+            assert_eq!(Color::from_hex(text), Err(()), "{}", text);
         }
     }
+
+    #[test]
+    fn over_linear() {
+        let red = Color::new(255, 0, 0, 128);
+        let blue = Color::new(0, 0, 255, 255);
+        // 0.502 in linear light is 188 in sRGB.
+        assert_eq!(red.over(blue), Color::new(188, 0, 187, 255));
+        assert_eq!(red.over_fast(blue), Color::new(128, 0, 127, 255));
+        assert_eq!(
+            Color::new(255, 255, 255, 128).over(Color::new(0, 0, 0, 128)),
+            Color::new(213, 213, 213, 192)
+        );
+        assert_eq!(Color::clear().over(Color::clear()), Color::clear());
+        assert_eq!(Color::white().over(blue), Color::white());
+    }
+
+    #[test]
+    fn mix_linear() {
+        let black = Color::black();
+        let white = Color::white();
+        assert_eq!(black.mix(white, 0.0), black);
+        assert_eq!(black.mix(white, 1.0), white);
+        // Halfway in linear light, like `over()`.
+        assert_eq!(black.mix(white, 0.5), Color::new(188, 188, 188, 255));
+        assert_eq!(black.mix(white, 2.0), white);
+        // Fading out keeps the color instead of darkening it.
+        let red = Color::new(255, 0, 0, 255);
+        assert_eq!(red.mix(Color::clear(), 0.5), Color::new(255, 0, 0, 128));
+        assert_eq!(red.mix(Color::clear(), 1.0), Color::clear());
+    }
+
+    #[test]
+    fn gradient_sampling() {
+        let red = Color::new(255, 0, 0, 255);
+        let green = Color::new(0, 255, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        let gradient = Gradient::new(vec![(1.0, blue), (0.0, red), (0.5, green)]);
+
+        // At the stops.
+        assert_eq!(gradient.sample(0.0), red);
+        assert_eq!(gradient.sample(0.5), green);
+        assert_eq!(gradient.sample(1.0), blue);
+        // Halfway between adjacent stops.
+        assert_eq!(gradient.sample(0.25), red.mix(green, 0.5));
+        assert_eq!(gradient.sample(0.75), Color::new(0, 188, 188, 255));
+        // Clamped outside of [0, 1].
+        assert_eq!(gradient.sample(-1.0), red);
+        assert_eq!(gradient.sample(7.0), blue);
+        assert_eq!(gradient.sample(std::f32::NAN), red);
+
+        let single = Gradient::new(vec![(0.3, green)]);
+        assert_eq!(single.sample(0.0), green);
+        assert_eq!(single.sample(1.0), green);
+        assert_eq!(Gradient::new(vec![]).sample(0.5), Color::clear());
+
+        // A hard edge switches to the later stop at its position.
+        let edge = Gradient::new(vec![(0.0, red), (0.5, red), (0.5, blue), (1.0, blue)]);
+        assert_eq!(edge.sample(0.49), red);
+        assert_eq!(edge.sample(0.5), blue);
+
+        let viridis = Gradient::viridis();
+        assert_eq!(viridis.stops().len(), 5);
+        assert_eq!(viridis.sample(0.0), Color::new(68, 1, 84, 255));
+        assert_eq!(viridis.sample(1.0), Color::new(253, 231, 37, 255));
+        assert_eq!(Gradient::even(&[green]).sample(0.9), green);
+    }
 }
@@ -27,12 +27,16 @@ pub fn generate_expr(attr_name: &Ident, expr: Expr) -> SynExpr {
             match expr.unit {
                 LengthUnit::Pixels(kw) => parse_quote_spanned!(kw.span => style_impl::pixels(#res)),
                 LengthUnit::Ems(kw) => parse_quote_spanned!(kw.span => style_impl::ems(#res)),
+                LengthUnit::Rems(kw) => parse_quote_spanned!(kw.span => style_impl::rems(#res)),
                 LengthUnit::ViewWidth(kw) => {
                     parse_quote_spanned!(kw.span => style_impl::view_width(#res))
                 }
                 LengthUnit::ViewHeight(kw) => {
                     parse_quote_spanned!(kw.span => style_impl::view_height(#res))
                 }
+                LengthUnit::Percent(symbol) => {
+                    parse_quote_spanned!(symbol.spans[0] => style_impl::percent(#res))
+                }
             }
         }
         Expr::BinaryExpr(exp) => {
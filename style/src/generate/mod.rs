@@ -82,6 +82,7 @@ pub fn generate_style(style: Style) -> ItemStatic {
                 file: ::std::file!(),
                 line: ::std::line!(),
                 attributes: #attributes,
+                css: ::std::option::Option::None,
                 sub_styles: &[
                     #(#sub_styles),*
                 ],
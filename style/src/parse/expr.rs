@@ -12,6 +12,7 @@ use syn::{
 mod kw {
     syn::custom_keyword!(px);
     syn::custom_keyword!(em);
+    syn::custom_keyword!(rem);
     syn::custom_keyword!(vw);
     syn::custom_keyword!(vh);
     syn::custom_keyword!(inherit);
@@ -20,8 +21,10 @@ mod kw {
 pub enum LengthUnit {
     Pixels(kw::px),
     Ems(kw::em),
+    Rems(kw::rem),
     ViewWidth(kw::vw),
     ViewHeight(kw::vh),
+    Percent(Token![%]),
 }
 
 impl ToTokens for LengthUnit {
@@ -29,8 +32,10 @@ impl ToTokens for LengthUnit {
         match self {
             LengthUnit::Pixels(kw) => kw.to_tokens(tokens),
             LengthUnit::Ems(kw) => kw.to_tokens(tokens),
+            LengthUnit::Rems(kw) => kw.to_tokens(tokens),
             LengthUnit::ViewWidth(kw) => kw.to_tokens(tokens),
             LengthUnit::ViewHeight(kw) => kw.to_tokens(tokens),
+            LengthUnit::Percent(symbol) => symbol.to_tokens(tokens),
         }
     }
 }
@@ -202,12 +207,18 @@ impl Expr {
         } else if input.peek(kw::em) {
             let kw = input.parse::<kw::em>()?;
             Ok(Expr::new_length(LengthUnit::Ems(kw), left))
+        } else if input.peek(kw::rem) {
+            let kw = input.parse::<kw::rem>()?;
+            Ok(Expr::new_length(LengthUnit::Rems(kw), left))
         } else if input.peek(kw::vw) {
             let kw = input.parse::<kw::vw>()?;
             Ok(Expr::new_length(LengthUnit::ViewWidth(kw), left))
         } else if input.peek(kw::vh) {
             let kw = input.parse::<kw::vh>()?;
             Ok(Expr::new_length(LengthUnit::ViewHeight(kw), left))
+        } else if input.peek(Token![%]) {
+            let symbol = input.parse::<Token![%]>()?;
+            Ok(Expr::new_length(LengthUnit::Percent(symbol), left))
         } else {
             Ok(left)
         }